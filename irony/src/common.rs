@@ -1,18 +1,71 @@
-use crate::{EntityId, OpId};
+use crate::{EntityId, FxHashMap, OpId};
 
 pub trait ReducerTrait {
     fn reduce_entity(&mut self, id: EntityId) -> usize;
     fn reduce_op(&mut self, id: OpId) -> usize;
 }
 
+#[derive(Default)]
+pub struct IdReducer {
+    entity_set: FxHashMap<EntityId, usize>,
+    op_set: FxHashMap<OpId, usize>,
+}
+
+impl ReducerTrait for IdReducer {
+    fn reduce_entity(&mut self, id: EntityId) -> usize {
+        let len = self.entity_set.len();
+        match self.entity_set.entry(id) {
+            std::collections::hash_map::Entry::Occupied(entry) => {
+                *entry.get()
+            },
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let new_id = len;
+                entry.insert(new_id);
+                new_id
+            }
+        }
+    }
+
+    fn reduce_op(&mut self, id: OpId) -> usize {
+        let len = self.op_set.len();
+        match self.op_set.entry(id) {
+            std::collections::hash_map::Entry::Occupied(entry) => {
+                *entry.get()
+            },
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let new_id = len;
+                entry.insert(new_id);
+                new_id
+            }
+        }
+    }
+}
+
 
-#[derive(Clone, Debug, PartialEq, Hash)]
+/// Name under which an op is found in its symbol table, printed as `@name`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Symbol {
-    name: Option<String>,
+    name: String,
 }
 
 impl Symbol {
-    pub fn new(name: String) -> Self { Self { name: Some(name) } }
+    pub fn new(name: String) -> Self { Self { name } }
+
+    pub fn get_name(&self) -> &str { &self.name }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}", self.name)
+    }
+}
+
+/// Attributes which may refer to symbols
+pub trait SymbolRefTrait: Sized {
+    /// names of the symbols referred to, nested attributes included
+    fn get_symbol_refs(&self) -> Vec<String>;
+    /// the attribute with every reference to `old` replaced by `new`
+    fn replace_symbol_ref(&self, old: &str, new: &str) -> Self;
 }
 
 pub trait Id {
@@ -66,6 +119,26 @@ macro_rules! data_type_enum {
                 }
             }
         )*
+
+        impl irony::IrSerde for $enum_name {
+            fn to_ir_value(&self) -> irony::IrValue {
+                match self {
+                    $($enum_name::$variant(inner) => irony::IrValue::Map(vec![
+                        (stringify!($variant).to_owned(), irony::IrSerde::to_ir_value(inner)),
+                    ])),*
+                }
+            }
+
+            fn from_ir_value(value: &irony::IrValue) -> Result<Self, irony::SerializeError> {
+                let [(variant, inner)] = &value.as_map()?[..] else {
+                    return Err(irony::SerializeError::Malformed(format!("expect a single {} variant", stringify!($enum_name))));
+                };
+                match variant.as_str() {
+                    $(stringify!($variant) => Ok($enum_name::$variant(<$variant_ty as irony::IrSerde>::from_ir_value(inner)?)),)*
+                    _ => Err(irony::SerializeError::Malformed(format!("unknown {} variant `{}`", stringify!($enum_name), variant))),
+                }
+            }
+        }
     };
 }
 
@@ -110,6 +183,30 @@ macro_rules! attribute_enum {
                 }
             }
         }
+
+        impl irony::IrSerde for $name {
+            fn to_ir_value(&self) -> irony::IrValue {
+                match self {
+                    $name::None => irony::IrValue::Null,
+                    $($name::$variant(x) => irony::IrValue::Map(vec![
+                        (stringify!($variant).to_owned(), irony::IrSerde::to_ir_value(x)),
+                    ])),*
+                }
+            }
+
+            fn from_ir_value(value: &irony::IrValue) -> Result<Self, irony::SerializeError> {
+                if let irony::IrValue::Null = value {
+                    return Ok($name::None);
+                }
+                let [(variant, inner)] = &value.as_map()?[..] else {
+                    return Err(irony::SerializeError::Malformed(format!("expect a single {} variant", stringify!($name))));
+                };
+                match variant.as_str() {
+                    $(stringify!($variant) => Ok($name::$variant(<$variant_ty as irony::IrSerde>::from_ir_value(inner)?)),)*
+                    _ => Err(irony::SerializeError::Malformed(format!("unknown {} variant `{}`", stringify!($name), variant))),
+                }
+            }
+        }
     };
 
 
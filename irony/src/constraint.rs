@@ -110,6 +110,17 @@ impl<D, A> SameTypeOperandConstraint<D, A> {
     pub fn new() -> Self { Self { _marker: PhantomData } }
 }
 
+/// The constraint an op trait implies, added to the constraints of the ops declaring it
+#[macro_export]
+macro_rules! trait_constraint {
+    (SameOperandsAndResultType) => {
+        Some(irony::SameTypeConstraint::new().into())
+    };
+    ($op_trait:ident) => {
+        None
+    };
+}
+
 #[macro_export]
 macro_rules! constraint_def {
     (
@@ -0,0 +1,48 @@
+use crate::{EntityId, OpId, RegionId};
+
+/// A broken link between the tables, see [`crate::Environ::find_dangling_refs`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DanglingRef {
+    /// `op` uses a deleted entity
+    Use { op: OpId, entity: EntityId },
+    /// `op` defines a deleted entity
+    Def { op: OpId, entity: EntityId },
+    /// `op` holds a deleted region
+    Region { op: OpId, region: RegionId },
+    /// the parent of `op` is deleted or does not list it
+    OpParent { op: OpId, region: RegionId },
+    /// the parent of `entity` is deleted or does not list it
+    EntityParent { entity: EntityId, region: RegionId },
+    /// `region` lists a deleted op
+    OpChild { region: RegionId, op: OpId },
+    /// `region` lists a deleted entity
+    EntityChild { region: RegionId, entity: EntityId },
+}
+
+impl std::fmt::Display for DanglingRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DanglingRef::Use { op, entity } => {
+                write!(f, "op {} uses deleted entity {}", op.0, entity.0)
+            },
+            DanglingRef::Def { op, entity } => {
+                write!(f, "op {} defines deleted entity {}", op.0, entity.0)
+            },
+            DanglingRef::Region { op, region } => {
+                write!(f, "op {} holds deleted region {}", op.0, region.0)
+            },
+            DanglingRef::OpParent { op, region } => {
+                write!(f, "op {} is not a child of its parent region {}", op.0, region.0)
+            },
+            DanglingRef::EntityParent { entity, region } => {
+                write!(f, "entity {} is not a child of its parent region {}", entity.0, region.0)
+            },
+            DanglingRef::OpChild { region, op } => {
+                write!(f, "region {} lists deleted op {}", region.0, op.0)
+            },
+            DanglingRef::EntityChild { region, entity } => {
+                write!(f, "region {} lists deleted entity {}", region.0, entity.0)
+            },
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::Loc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A message about the IR, pointing to where the offending op comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub loc: Option<Loc>,
+    pub notes: Vec<Diagnostic>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self { severity, message: message.into(), loc: None, notes: vec![] }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self { Self::new(Severity::Error, message) }
+
+    pub fn warning(message: impl Into<String>) -> Self { Self::new(Severity::Warning, message) }
+
+    pub fn at(mut self, loc: Option<Loc>) -> Self {
+        self.loc = loc;
+        self
+    }
+
+    pub fn with_note(mut self, message: impl Into<String>, loc: Option<Loc>) -> Self {
+        self.notes.push(Diagnostic::new(Severity::Note, message).at(loc));
+        self
+    }
+}
+
+/// `file:line:col: error: message`, then one indented line per note
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(loc) = &self.loc {
+            write!(f, "{}: ", loc)?;
+        }
+        write!(f, "{}: {}", self.severity, self.message)?;
+        for note in self.notes.iter() {
+            write!(f, "\n  {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HandlerId(usize);
+
+type Handler = Box<dyn FnMut(&Diagnostic) -> bool>;
+
+/// Dispatches diagnostics to the registered handlers, the most recent first, until one
+/// of them returns `true`. Diagnostics no handler takes are kept in the engine.
+#[derive(Default)]
+pub struct DiagnosticEngine {
+    handlers: Vec<(HandlerId, Handler)>,
+    next_id: usize,
+    unhandled: Vec<Diagnostic>,
+    error_count: usize,
+}
+
+impl std::fmt::Debug for DiagnosticEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiagnosticEngine")
+            .field("handlers", &self.handlers.len())
+            .field("unhandled", &self.unhandled)
+            .field("error_count", &self.error_count)
+            .finish()
+    }
+}
+
+impl DiagnosticEngine {
+    pub fn add_handler<F>(&mut self, handler: F) -> HandlerId
+    where F: FnMut(&Diagnostic) -> bool + 'static {
+        let id = HandlerId(self.next_id);
+        self.next_id += 1;
+        self.handlers.push((id, Box::new(handler)));
+        id
+    }
+
+    pub fn remove_handler(&mut self, id: HandlerId) {
+        self.handlers.retain(|(handler_id, _)| *handler_id != id);
+    }
+
+    /// Print every diagnostic to stderr
+    pub fn add_stderr_handler(&mut self) -> HandlerId {
+        self.add_handler(|diagnostic| {
+            eprintln!("{}", diagnostic);
+            true
+        })
+    }
+
+    pub fn emit(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.severity == Severity::Error {
+            self.error_count += 1;
+        }
+        for (_, handler) in self.handlers.iter_mut().rev() {
+            if handler(&diagnostic) {
+                return;
+            }
+        }
+        self.unhandled.push(diagnostic);
+    }
+
+    pub fn error_count(&self) -> usize { self.error_count }
+
+    pub fn has_errors(&self) -> bool { self.error_count > 0 }
+
+    pub fn get_unhandled(&self) -> &[Diagnostic] { &self.unhandled }
+
+    /// Take the diagnostics no handler took and reset the error count
+    pub fn take_unhandled(&mut self) -> Vec<Diagnostic> {
+        self.error_count = 0;
+        std::mem::take(&mut self.unhandled)
+    }
+}
@@ -0,0 +1,178 @@
+use crate::Op;
+
+/// The name an op is registered under
+#[derive(Clone, PartialEq, Debug, Hash, Eq)]
+pub struct OpInfo {
+    /// the dialect of the op, if it belongs to one
+    pub dialect: Option<&'static str>,
+    /// the name of the Rust type of the op
+    pub type_name: &'static str,
+    /// the qualified name, e.g. `comb.variadic`, or the type name outside of a dialect
+    pub name: String,
+}
+
+impl OpInfo {
+    /// The op is named by its `mnemonic` if given, else by the snake case of its type name
+    /// without the leading word naming the dialect, e.g. `hw.struct_extract` for
+    /// `HwStructExtract` in `hw`.
+    pub fn new(
+        dialect: Option<&'static str>, type_name: &'static str, mnemonic: Option<&'static str>,
+    ) -> Self {
+        let name = match (dialect, mnemonic) {
+            (Some(dialect), Some(mnemonic)) => format!("{}.{}", dialect, mnemonic),
+            (Some(dialect), None) => format!("{}.{}", dialect, default_mnemonic(dialect, type_name)),
+            (None, Some(mnemonic)) => mnemonic.to_owned(),
+            (None, None) => type_name.to_owned(),
+        };
+        Self { dialect, type_name, name }
+    }
+}
+
+fn default_mnemonic(dialect: &str, type_name: &str) -> String {
+    let mut words = vec![];
+    for c in type_name.chars() {
+        if c.is_uppercase() || words.is_empty() {
+            words.push(String::new());
+        }
+        words.last_mut().unwrap().extend(c.to_lowercase());
+    }
+    if words.len() > 1 && words[0] == dialect.to_lowercase() {
+        words.remove(0);
+    }
+    words.join("_")
+}
+
+/// A dialect: a namespace and the ops defined in it, see `dialect_def!`
+pub trait Dialect {
+    fn get_name() -> &'static str;
+    fn get_ops() -> Vec<OpInfo>;
+}
+
+/// The dialects and ops an op enum is composed of
+#[derive(Clone, PartialEq, Debug)]
+pub struct DialectRegistry {
+    ops: Vec<OpInfo>,
+}
+
+impl DialectRegistry {
+    pub fn of<O: Op>() -> Self { Self { ops: O::get_registered_ops() } }
+
+    /// the dialect names, in the order their first op was registered
+    pub fn get_dialects(&self) -> Vec<&'static str> {
+        let mut dialects = vec![];
+        for dialect in self.ops.iter().filter_map(|info| info.dialect) {
+            if !dialects.contains(&dialect) {
+                dialects.push(dialect);
+            }
+        }
+        dialects
+    }
+
+    /// the ops of `dialect`, or the ops outside of any dialect if `None`
+    pub fn get_ops(&self, dialect: Option<&str>) -> Vec<&OpInfo> {
+        self.ops.iter().filter(|info| info.dialect == dialect).collect()
+    }
+
+    pub fn get_all_ops(&self) -> &[OpInfo] { &self.ops }
+
+    /// the op registered under the qualified `name`
+    pub fn get_op(&self, name: &str) -> Option<&OpInfo> {
+        self.ops.iter().find(|info| info.name == name)
+    }
+
+    /// the qualified name of the op whose type is `type_name`
+    pub fn qualify(&self, type_name: &str) -> Option<&str> {
+        self.ops.iter().find(|info| info.type_name == type_name).map(|info| info.name.as_str())
+    }
+}
+
+/// Define the ops of a dialect, each one as with `op_def_one!`, named `dialect.mnemonic`
+/// (see [`OpInfo::new`]), and a unit struct implementing [`Dialect`] listing them. A macro of
+/// the same name lists them to `dialect_enum!`; it is in textual scope, so the modules between
+/// the definition and the composition need `#[macro_use]`.
+///
+/// ```ignore
+/// irony::dialect_def! {
+///     [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+///     Comb = "comb" {
+///         CombICmp("icmp"): { .. },
+///         CombParity: { .. },
+///     }
+/// }
+/// ```
+#[cfg(feature = "macro-rules")]
+#[macro_export]
+macro_rules! dialect_def {
+    (
+        [data_type = $data_ty:ty, attr = $attr_ty:ty, constraint = $constraint_ty:ty]
+        $dialect:ident = $dialect_name:literal {
+            $($name:ident $(($mnemonic:literal))? : { $($body:tt)* }),* $(,)?
+        }
+    ) => {
+        $(
+            irony::op_def_one! {
+                [data_type = $data_ty, attr = $attr_ty, constraint = $constraint_ty, dialect = $dialect_name]
+                $name $(($mnemonic))? : { $($body)* }
+            }
+        )*
+
+        pub struct $dialect;
+
+        impl irony::Dialect for $dialect {
+            fn get_name() -> &'static str { $dialect_name }
+
+            fn get_ops() -> Vec<irony::OpInfo> { vec![$($name::op_info().clone()),*] }
+        }
+
+        #[allow(unused_macros)]
+        macro_rules! $dialect {
+            ([$$($$header:tt)*] $$enum:ident [$$($$op:ident),*] $$($$rest:ident)*) => {
+                irony::dialect_enum! {
+                    @compose [$$($$header)*] $$enum [$$($$op,)* $($name),*] $$($$rest)*
+                }
+            };
+        }
+    };
+}
+
+/// Compose the dialects defined by `dialect_def!` into an op enum and its printer, as
+/// `op_enum!` and `op_printer!` do for a list of ops.
+///
+/// ```ignore
+/// irony::dialect_enum! {
+///     [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+///     OpEnum = HwDialect, CombDialect
+/// }
+/// ```
+#[cfg(feature = "macro-rules")]
+#[macro_export]
+macro_rules! dialect_enum {
+    (
+        [data_type = $data_ty:ty, attr = $attr_ty:ty, constraint = $constraint_ty:ty]
+        $name:ident = $($dialect:ident),* $(,)?
+    ) => {
+        irony::dialect_enum! {
+            @compose [data_type = $data_ty, attr = $attr_ty, constraint = $constraint_ty]
+            $name [] $($dialect)*
+        }
+    };
+    (
+        @compose [$($header:tt)*] $name:ident [$($op:ident),* $(,)?] $dialect:ident $($rest:ident)*
+    ) => {
+        $dialect! { [$($header)*] $name [$($op),*] $($rest)* }
+    };
+    (
+        @compose [data_type = $data_ty:ty, attr = $attr_ty:ty, constraint = $constraint_ty:ty]
+        $name:ident [$($op:ident),* $(,)?]
+    ) => {
+        irony::op_enum! {
+            [data_type = $data_ty, attr = $attr_ty, constraint = $constraint_ty]
+            $name = $($op),*
+        }
+
+        irony::op_printer! {
+            [data_type = $data_ty, attr = $attr_ty]
+            $name = $($op),*
+        }
+    };
+}
@@ -0,0 +1,213 @@
+use crate::{Entity, EntityId, Environ, FxHashMap, FxHashSet, Op, OpId, RegionId};
+
+/// What [`export_dot`] draws
+#[derive(Clone, Debug, Default)]
+pub struct DotOptions {
+    /// fill ops with a color picked by the dialect prefix of their name
+    pub color_by_dialect: bool,
+    /// only draw the ops in the fan-in and fan-out cone of this entity
+    pub cone: Option<EntityId>,
+}
+
+const PALETTE: [&str; 8] = [
+    "#a6cee3", "#b2df8a", "#fb9a99", "#fdbf6f", "#cab2d6", "#ffff99", "#8dd3c7", "#d9d9d9",
+];
+
+/// The dialect of an op name: what comes before the first `.`, or the leading
+/// capitalized word, e.g. `Comb` for `CombVariadic`.
+pub fn dialect_prefix(op_name: &str) -> &str {
+    if let Some((prefix, _)) = op_name.split_once('.') {
+        return prefix;
+    }
+    let end = op_name
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_uppercase())
+        .map_or(op_name.len(), |(i, _)| i);
+    &op_name[..end]
+}
+
+fn escape(label: &str) -> String { label.replace('\\', "\\\\").replace('"', "\\\"") }
+
+struct DotWriter<'env, E: Environ> {
+    env: &'env E,
+    options: &'env DotOptions,
+    /// ops to draw, every op if `None`
+    selected: Option<FxHashSet<OpId>>,
+    def_of: FxHashMap<EntityId, OpId>,
+    dialects: Vec<String>,
+    out: String,
+}
+
+impl<'env, E> DotWriter<'env, E>
+where
+    E: Environ,
+    E::DataTypeT: std::fmt::Display,
+{
+    fn is_selected(&self, op: OpId) -> bool {
+        self.selected.as_ref().is_none_or(|selected| selected.contains(&op))
+    }
+
+    fn walk(&self, ops: Vec<OpId>, all: &mut Vec<OpId>) {
+        for op in ops {
+            all.push(op);
+            for region in self.env.get_op(op).get_regions().into_iter().flat_map(|(_, r)| r) {
+                self.walk(self.env.get_region(region).get_op_children(), all);
+            }
+        }
+    }
+
+    fn region_has_selected(&self, region: RegionId) -> bool {
+        self.env.get_region(region).get_op_children().into_iter().any(|op| {
+            self.is_selected(op)
+                || self
+                    .env
+                    .get_op(op)
+                    .get_regions()
+                    .into_iter()
+                    .flat_map(|(_, regions)| regions)
+                    .any(|region| self.region_has_selected(region))
+        })
+    }
+
+    fn entity_label(&self, entity: EntityId) -> String {
+        match self.env.get_entity(entity).get_dtype() {
+            Some(dtype) => format!("{}: {}", self.env.print_entity(entity), dtype),
+            None => self.env.print_entity(entity),
+        }
+    }
+
+    fn op_label(&self, op_id: OpId) -> String {
+        let op = self.env.get_op(op_id);
+        match op.get_defs().into_iter().flat_map(|(_, defs)| defs).flatten().next() {
+            Some(def) if !op.get_regions().is_empty() => {
+                format!("{} {}", op.get_op_name(), self.env.print_entity(def))
+            },
+            _ => op.get_op_name(),
+        }
+    }
+
+    fn write_ops(&mut self, ops: Vec<OpId>, indent: usize) {
+        let pad = "  ".repeat(indent);
+        for op_id in ops {
+            if self.is_selected(op_id) {
+                let name = self.env.get_op(op_id).get_op_name();
+                let mut attrs = format!("label=\"{}\"", escape(&self.op_label(op_id)));
+                if self.options.color_by_dialect {
+                    let dialect = dialect_prefix(&name).to_owned();
+                    let index = match self.dialects.iter().position(|d| *d == dialect) {
+                        Some(index) => index,
+                        None => {
+                            self.dialects.push(dialect);
+                            self.dialects.len() - 1
+                        },
+                    };
+                    attrs += &format!(", fillcolor=\"{}\"", PALETTE[index % PALETTE.len()]);
+                }
+                self.out += &format!("{}op{} [{}];\n", pad, op_id.0, attrs);
+            }
+
+            let regions = self.env.get_op(op_id).get_regions();
+            for (region_name, regions) in regions {
+                for region in regions {
+                    if !self.region_has_selected(region) {
+                        continue;
+                    }
+                    let label = format!("{}: {}", self.op_label(op_id), region_name);
+                    self.out += &format!("{}subgraph cluster_region{} {{\n", pad, region.0);
+                    self.out += &format!("{}  label=\"{}\";\n", pad, escape(&label));
+                    self.write_ops(self.env.get_region(region).get_op_children(), indent + 1);
+                    self.out += &format!("{}}}\n", pad);
+                }
+            }
+        }
+    }
+
+    fn write_edges(&mut self, all: &[OpId]) {
+        let mut sources = FxHashSet::default();
+        let drawn = all.iter().filter(|&&op| self.is_selected(op)).copied().collect::<Vec<_>>();
+        for op_id in drawn {
+            for entity in self.env.get_op(op_id).get_uses().into_iter().flat_map(|(_, u)| u).flatten()
+            {
+                let label = escape(&self.entity_label(entity));
+                let source = match self.def_of.get(&entity).copied() {
+                    Some(def) if self.is_selected(def) => format!("op{}", def.0),
+                    _ => {
+                        if sources.insert(entity) {
+                            self.out += &format!(
+                                "  entity{} [shape=ellipse, style=dashed, label=\"{}\"];\n",
+                                entity.0, label
+                            );
+                        }
+                        format!("entity{}", entity.0)
+                    },
+                };
+                self.out += &format!("  {} -> op{} [label=\"{}\"];\n", source, op_id.0, label);
+            }
+        }
+    }
+}
+
+/// The dataflow of `env` in the DOT language: ops are nodes and entities are edges from
+/// their def to their uses, with the ops of every region grouped in a cluster.
+pub fn export_dot<E>(env: &E, options: &DotOptions) -> String
+where
+    E: Environ,
+    E::DataTypeT: std::fmt::Display,
+{
+    let mut writer = DotWriter {
+        env,
+        options,
+        selected: None,
+        def_of: FxHashMap::default(),
+        dialects: vec![],
+        out: String::new(),
+    };
+
+    let mut all = vec![];
+    writer.walk(env.get_ops_with_parent(None), &mut all);
+    let mut users_of: FxHashMap<EntityId, Vec<OpId>> = FxHashMap::default();
+    for &op_id in all.iter() {
+        let op = env.get_op(op_id);
+        for def in op.get_defs().into_iter().flat_map(|(_, defs)| defs).flatten() {
+            writer.def_of.insert(def, op_id);
+        }
+        for used in op.get_uses().into_iter().flat_map(|(_, uses)| uses).flatten() {
+            users_of.entry(used).or_default().push(op_id);
+        }
+    }
+
+    if let Some(entity) = options.cone {
+        let mut selected = FxHashSet::default();
+        // fan-in: walk back through the defs
+        let mut worklist = vec![entity];
+        while let Some(entity) = worklist.pop() {
+            if let Some(&def) = writer.def_of.get(&entity) {
+                if selected.insert(def) {
+                    let op = env.get_op(def);
+                    worklist.extend(op.get_uses().into_iter().flat_map(|(_, u)| u).flatten());
+                }
+            }
+        }
+        // fan-out: walk forward through the uses
+        let mut visited = FxHashSet::default();
+        let mut worklist = vec![entity];
+        while let Some(entity) = worklist.pop() {
+            for &user in users_of.get(&entity).into_iter().flatten() {
+                if visited.insert(user) {
+                    selected.insert(user);
+                    let op = env.get_op(user);
+                    worklist.extend(op.get_defs().into_iter().flat_map(|(_, d)| d).flatten());
+                }
+            }
+        }
+        writer.selected = Some(selected);
+    }
+
+    writer.out += "digraph irony {\n";
+    writer.out += "  node [shape=box, style=filled, fillcolor=white];\n";
+    writer.write_ops(env.get_ops_with_parent(None), 1);
+    writer.write_edges(&all);
+    writer.out += "}\n";
+    writer.out
+}
@@ -6,6 +6,14 @@ pub trait Entity: Id {
     type DataTypeT;
     type AttributeT: Clone + PartialEq + std::fmt::Display;
     fn get_dtype(&self) -> Option<Self::DataTypeT>;
+    fn set_dtype(&mut self, dtype: Option<Self::DataTypeT>);
+    fn get_entity_name(&self) -> String;
+
+    /// Build the entity called `name` from its dtype and attributes
+    fn from_parts(
+        name: &str, dtype: Option<Self::DataTypeT>, attrs: Vec<(String, Self::AttributeT)>,
+    ) -> Option<Self>
+    where Self: Sized;
 
     fn get_defs<E: Environ>(&self, env: &E) -> Vec<OpId>;
     fn get_uses<E: Environ>(&self, env: &E) -> Vec<OpId>;
@@ -60,6 +68,10 @@ pub struct Region {
     pub isolated: bool,
     pub op_children: Vec<OpId>,
     pub entity_children: Vec<EntityId>,
+    /// entities defined on entry of the region, also listed in `entity_children`
+    pub arguments: Vec<EntityId>,
+    /// whether the symbols declared by the ops of the region are looked up in it
+    pub symbol_table: bool,
 }
 
 impl Id for Region {
@@ -89,9 +101,16 @@ impl Region {
             isolated,
             op_children: vec![],
             entity_children: vec![],
+            arguments: vec![],
+            symbol_table: false,
         }
     }
 
+    pub fn with_symbol_table(mut self) -> Self {
+        self.symbol_table = true;
+        self
+    }
+
     pub fn add_op_child(&mut self, op: OpId) {
         if let Some(_) =
             self.op_children.iter().find(|&op_exist| op_exist.id() == op.id())
@@ -118,10 +137,27 @@ impl Region {
         }
     }
 
+    pub fn add_argument(&mut self, entity: EntityId) {
+        if self.arguments.contains(&entity) {
+            panic!("{} has already been an argument of {}", entity.id(), self.id())
+        } else {
+            self.arguments.push(entity)
+        }
+    }
+
+    pub fn remove_op_child(&mut self, op: OpId) { self.op_children.retain(|&child| child != op) }
+
+    /// remove `entity` from the children, and from the arguments if it is one
+    pub fn remove_entity_child(&mut self, entity: EntityId) {
+        self.entity_children.retain(|&child| child != entity);
+        self.arguments.retain(|&argument| argument != entity);
+    }
+
     pub fn get_op_children(&self) -> Vec<OpId> { self.op_children.to_owned() }
     pub fn get_entity_children(&self) -> Vec<EntityId> {
         self.entity_children.to_owned()
     }
+    pub fn get_arguments(&self) -> Vec<EntityId> { self.arguments.to_owned() }
 }
 
 #[macro_export]
@@ -173,6 +209,23 @@ macro_rules! entity_def_one {
                 self.dtype.to_owned()
             }
 
+            fn set_dtype(&mut self, dtype: Option<Self::DataTypeT>) {
+                self.dtype = dtype
+            }
+
+            fn get_entity_name(&self) -> String {
+                stringify!($name).to_owned()
+            }
+
+            fn from_parts(name: &str, dtype: Option<Self::DataTypeT>, attrs: Vec<(String, Self::AttributeT)>) -> Option<Self> {
+                if name != stringify!($name) {
+                    return None;
+                }
+                let mut entity = Self::const_new(dtype);
+                entity.set_attrs(attrs);
+                Some(entity)
+            }
+
             fn as_id(&self) -> irony::EntityId {
                 irony::EntityId(self.id)
             }
@@ -232,6 +285,20 @@ macro_rules! entity_def_one {
                     $($($attr: $attr.map(|x| x.into())),*)?
                 }
             }
+
+            $(
+                $(
+                    pub fn $attr(&self) -> Option<&$attr_inner_ty> {
+                        self.$attr.as_ref()
+                    }
+
+                    irony::paste! {
+                        pub fn [< set_ $attr >](&mut self, $attr: Option<$attr_inner_ty>) {
+                            self.$attr = $attr;
+                        }
+                    }
+                )*
+            )?
         }
     };
 
@@ -268,6 +335,25 @@ macro_rules! entity_enum {
                 }
             }
 
+            fn set_dtype(&mut self, dtype: Option<Self::DataTypeT>) {
+                match self {
+                    $($name::$variant(inner) => inner.set_dtype(dtype),)*
+                }
+            }
+
+            fn get_entity_name(&self) -> String {
+                match self {
+                    $($name::$variant(inner) => inner.get_entity_name(),)*
+                }
+            }
+
+            fn from_parts(name: &str, dtype: Option<Self::DataTypeT>, attrs: Vec<(String, Self::AttributeT)>) -> Option<Self> {
+                match name {
+                    $(stringify!($variant) => $variant::from_parts(name, dtype, attrs).map(|entity| entity.into()),)*
+                    _ => None,
+                }
+            }
+
             fn as_id(&self) -> irony::EntityId {
                 match self {
                     $($name::$variant(inner) => inner.as_id(), )*
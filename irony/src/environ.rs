@@ -3,10 +3,22 @@ use std::cell::RefMut;
 use super::constraint::ConstraintTrait;
 use super::entity::{Entity, EntityId};
 use super::operation::{Op, OpId};
-use crate::{Id, OpPrinterTrait, ReducerTrait, Region, RegionId};
+use crate::mapping::IrSnapshot;
+use crate::{
+    DanglingRef, Diagnostic, DialectRegistry, DiagnosticEngine, FxHashMap, Id, IronyError, IronyResult, Loc, IrMapping, OpPrinterTrait, OpTrait, ReducerTrait, Region, RegionId,
+    Symbol, SymbolRefTrait, Transaction,
+};
+
+/// What [`Environ::verify_with`] checks besides the constraints and traits of the ops
+#[derive(Clone, Debug, Default)]
+pub struct VerifyOptions {
+    /// every op follows the definitions of the entities it uses, see
+    /// [`Environ::verify_def_before_use`]
+    pub def_before_use: bool,
+}
 
 pub trait Environ: Sized {
-    type DataTypeT;
+    type DataTypeT: PartialEq;
     type AttributeT: Clone + PartialEq + std::fmt::Display;
 
     type OpT: Op<DataTypeT = Self::DataTypeT, AttributeT = Self::AttributeT>;
@@ -18,7 +30,11 @@ pub trait Environ: Sized {
 
     fn get_defs(&self, id: EntityId) -> Vec<OpId>;
     fn get_uses(&self, id: EntityId) -> Vec<OpId>;
-    fn get_entity(&self, id: EntityId) -> &Self::EntityT;
+    fn try_get_entity(&self, id: EntityId) -> IronyResult<&Self::EntityT>;
+    #[track_caller]
+    fn get_entity(&self, id: EntityId) -> &Self::EntityT {
+        self.try_get_entity(id).unwrap_or_else(|err| panic!("{}", err))
+    }
     fn get_entities(&self, ids: &[EntityId]) -> Vec<&Self::EntityT>;
     fn get_entities_with_parent(&self, id: Option<RegionId>) -> Vec<EntityId>;
     fn get_entity_entry(
@@ -33,12 +49,33 @@ pub trait Environ: Sized {
             .and_modify(|entity| entity.update_attrs(field_name, f));
     }
 
-    fn get_op(&self, id: OpId) -> &Self::OpT;
+    fn try_get_op(&self, id: OpId) -> IronyResult<&Self::OpT>;
+    #[track_caller]
+    fn get_op(&self, id: OpId) -> &Self::OpT {
+        self.try_get_op(id).unwrap_or_else(|err| panic!("{}", err))
+    }
+    fn get_ops_with_parent(&self, parent: Option<RegionId>) -> Vec<OpId>;
     fn get_op_entry(&mut self, op_id: OpId) -> indexmap::map::Entry<usize, Self::OpT>;
 
     fn get_ops(&self, ids: &[OpId]) -> Vec<&Self::OpT>;
+    fn contains_op(&self, id: OpId) -> bool { self.try_get_op(id).is_ok() }
+    fn contains_entity(&self, id: EntityId) -> bool { self.try_get_entity(id).is_ok() }
+    fn contains_region(&self, id: RegionId) -> bool { self.try_get_region(id).is_ok() }
+    /// the dialects and ops the environment is composed of
+    fn get_registry(&self) -> DialectRegistry { DialectRegistry::of::<Self::OpT>() }
+    /// every op in the table, in table order
+    fn get_op_ids(&self) -> Vec<OpId>;
+    fn get_entity_ids(&self) -> Vec<EntityId>;
+    fn get_region_ids(&self) -> Vec<RegionId>;
     fn add_entity(&mut self, entity: Self::EntityT) -> EntityId;
-    fn get_region(&self, id: RegionId) -> &Region;
+    fn try_get_region(&self, id: RegionId) -> IronyResult<&Region>;
+    #[track_caller]
+    fn get_region(&self, id: RegionId) -> &Region {
+        self.try_get_region(id).unwrap_or_else(|err| panic!("{}", err))
+    }
+    fn get_region_entry(
+        &mut self, region_id: RegionId,
+    ) -> indexmap::map::Entry<'_, usize, Region>;
     fn add_region(&mut self, region: Region) -> RegionId;
     fn add_op(&mut self, op: Self::OpT) -> OpId;
     fn set_entity_parent(&mut self, id: EntityId);
@@ -47,27 +84,405 @@ pub trait Environ: Sized {
     fn begin_region(&mut self, region: Option<RegionId>);
     fn end_region(&mut self) -> Option<Option<RegionId>>;
 
+    /// Start recording the changes to the op, entity and region tables. Transactions nest.
+    fn begin_transaction(&mut self);
+    /// keep the changes made since the matching [`Environ::begin_transaction`]
+    fn commit_transaction(&mut self);
+    /// undo the changes made since the matching [`Environ::begin_transaction`]
+    fn rollback_transaction(&mut self);
+
+    /// Open a transaction, rolled back unless the returned guard is committed
+    fn transaction(&mut self) -> Transaction<'_, Self> {
+        self.begin_transaction();
+        Transaction::new(self)
+    }
+
+    /// Run `f` in a transaction, rolled back if it fails
+    fn try_transaction<T, Err, F>(&mut self, f: F) -> Result<T, Err>
+    where F: FnOnce(&mut Self) -> Result<T, Err> {
+        self.begin_transaction();
+        let result = f(self);
+        match result {
+            Ok(_) => self.commit_transaction(),
+            Err(_) => self.rollback_transaction(),
+        }
+        result
+    }
+
+    /// Add `entity` as the last argument of `region`, owned by the region
+    fn add_region_argument(&mut self, region: RegionId, entity: Self::EntityT) -> EntityId {
+        self.begin_region(Some(region));
+        let argument = self.add_entity(entity);
+        self.end_region();
+        self.get_region_entry(region).and_modify(|region| region.add_argument(argument));
+        argument
+    }
+
+    fn get_region_arguments(&self, region: RegionId) -> Vec<EntityId> {
+        self.get_region(region).get_arguments()
+    }
+
+    /// the region that `entity` is an argument of, if it is one
+    fn get_argument_owner(&self, entity: EntityId) -> Option<RegionId> {
+        self.get_entity(entity)
+            .get_parent()
+            .filter(|&region| self.get_region(region).arguments.contains(&entity))
+    }
+
+    fn print_region_arguments(&self, region: RegionId) -> String {
+        self.get_region_arguments(region)
+            .into_iter()
+            .map(|argument| self.print_entity(argument))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Ops in `region` and its nested regions in pre-order, every op if `region` is `None`
+    fn walk_ops(&self, region: Option<RegionId>) -> Vec<OpId> {
+        let mut worklist = match region {
+            Some(region) => self.get_region(region).get_op_children(),
+            None => self.get_ops_with_parent(None),
+        };
+        worklist.reverse();
+        let mut ops = vec![];
+        while let Some(op) = worklist.pop() {
+            ops.push(op);
+            for (_, regions) in self.get_op(op).get_regions().into_iter().rev() {
+                for region in regions.into_iter().rev() {
+                    worklist.extend(self.get_region(region).get_op_children().into_iter().rev());
+                }
+            }
+        }
+        ops
+    }
+
+    /// The symbol table `op` is placed in: its nearest enclosing region declared as a
+    /// symbol table, or the top level (`None`).
+    fn get_symbol_scope(&self, op: OpId) -> Option<RegionId> {
+        let mut region = self.get_op(op).get_parent();
+        while let Some(current) = region {
+            if self.get_region(current).symbol_table {
+                return Some(current);
+            }
+            region = self.get_region_use(current).and_then(|owner| self.get_op(owner).get_parent());
+        }
+        None
+    }
+
+    /// The op placed in `region` which is `op` or encloses it
+    fn get_ancestor_in(&self, region: RegionId, op: OpId) -> Option<OpId> {
+        let mut current = op;
+        loop {
+            let parent = self.get_op(current).get_parent()?;
+            if parent == region {
+                return Some(current);
+            }
+            current = self.get_region_use(parent)?;
+        }
+    }
+
+    /// the symbols declared by the ops placed directly in `scope`
+    fn get_symbols(&self, scope: Option<RegionId>) -> Vec<(Symbol, OpId)> {
+        let ops = match scope {
+            Some(region) => self.get_region(region).get_op_children(),
+            None => self.get_ops_with_parent(None),
+        };
+        ops.into_iter()
+            .filter_map(|op| self.get_op(op).get_symbol().map(|symbol| (symbol, op)))
+            .collect()
+    }
+
+    fn lookup_symbol(&self, scope: Option<RegionId>, name: &str) -> Option<OpId> {
+        self.get_symbols(scope)
+            .into_iter()
+            .find(|(symbol, _)| symbol.get_name() == name)
+            .map(|(_, op)| op)
+    }
+
+    /// Resolve `name` as seen from `from`, searching the enclosing symbol tables from the
+    /// innermost one out to the top level.
+    fn resolve_symbol(&self, from: OpId, name: &str) -> Option<OpId> {
+        let mut region = self.get_op(from).get_parent();
+        while let Some(current) = region {
+            if self.get_region(current).symbol_table {
+                if let Some(op) = self.lookup_symbol(Some(current), name) {
+                    return Some(op);
+                }
+            }
+            region = self.get_region_use(current).and_then(|owner| self.get_op(owner).get_parent());
+        }
+        self.lookup_symbol(None, name)
+    }
+
+    /// names declared more than once in `scope`
+    fn find_duplicate_symbols(&self, scope: Option<RegionId>) -> Vec<String> {
+        let mut seen = crate::FxHashSet::default();
+        let mut duplicates = vec![];
+        for (symbol, _) in self.get_symbols(scope) {
+            let name = symbol.get_name().to_owned();
+            if !seen.insert(name.to_owned()) && !duplicates.contains(&name) {
+                duplicates.push(name);
+            }
+        }
+        duplicates
+    }
+
+    /// the symbols referred to by the attributes of `op` that resolve to no op
+    fn find_unresolved_symbols(&self, op: OpId) -> Vec<String>
+    where Self::AttributeT: SymbolRefTrait {
+        let mut unresolved = vec![];
+        for (_, attr) in self.get_op(op).get_attrs().iter() {
+            for name in attr.get_symbol_refs() {
+                if self.resolve_symbol(op, &name).is_none() && !unresolved.contains(&name) {
+                    unresolved.push(name);
+                }
+            }
+        }
+        unresolved
+    }
+
+    /// the ops holding an attribute that refers to the symbol of `op`
+    fn get_symbol_uses(&self, op: OpId) -> Vec<OpId>
+    where Self::AttributeT: SymbolRefTrait {
+        let Some(symbol) = self.get_op(op).get_symbol() else {
+            return vec![];
+        };
+        self.walk_ops(None)
+            .into_iter()
+            .filter(|&user| {
+                self.get_op(user).get_attrs().iter().any(|(_, attr)| {
+                    attr.get_symbol_refs().iter().any(|name| name == symbol.get_name())
+                }) && self.resolve_symbol(user, symbol.get_name()) == Some(op)
+            })
+            .collect()
+    }
+
+    /// Rename the symbol of `op` and every reference to it. Fails if `op` declares no
+    /// symbol or `name` is already taken in its symbol table.
+    fn rename_symbol(&mut self, op: OpId, name: &str) -> IronyResult<()>
+    where Self::AttributeT: SymbolRefTrait {
+        let Some(old) = self.get_op(op).get_symbol() else {
+            return Err(IronyError::NoSymbol(op));
+        };
+        if self.lookup_symbol(self.get_symbol_scope(op), name).is_some() {
+            return Err(IronyError::DuplicateSymbol(name.to_owned()));
+        }
+        let users = self.get_symbol_uses(op);
+        self.get_op_entry(op).and_modify(|op| op.set_symbol(Symbol::new(name.to_owned())));
+        for user in users {
+            self.get_op_entry(user).and_modify(|user| {
+                let attrs = user
+                    .get_attrs()
+                    .into_iter()
+                    .map(|(attr_name, attr)| (attr_name, attr.replace_symbol_ref(old.get_name(), name)))
+                    .collect();
+                user.set_attrs(attrs)
+            });
+        }
+        Ok(())
+    }
+
+    /// make every op using `old` use `new` instead
+    fn replace_all_uses(&mut self, old: EntityId, new: EntityId) {
+        for op in self.get_uses(old) {
+            self.get_op_entry(op).and_modify(|op| {
+                let uses = op
+                    .get_uses()
+                    .into_iter()
+                    .map(|(name, uses)| {
+                        let uses = uses
+                            .into_iter()
+                            .map(|used| if used == Some(old) { Some(new) } else { used })
+                            .collect();
+                        (name, uses)
+                    })
+                    .collect();
+                op.set_uses(uses)
+            });
+        }
+    }
+
+    /// Deep-copy `ops`, with the regions nested in them, to the end of `target`.
+    ///
+    /// Entities defined in the copied IR are cloned through `add_entity` unless `mapping`
+    /// already maps them; uses of entities defined outside are kept as they are.
+    fn clone_ops_with(
+        &mut self, ops: &[OpId], target: Option<RegionId>, mapping: &mut IrMapping,
+    ) -> Vec<OpId>
+    where
+        Self::OpT: Clone,
+        Self::EntityT: Clone,
+    {
+        IrSnapshot::take(self, ops, &[]).restore(self, target, mapping)
+    }
+
+    fn clone_op_with(
+        &mut self, op: OpId, target: Option<RegionId>, mapping: &mut IrMapping,
+    ) -> OpId
+    where
+        Self::OpT: Clone,
+        Self::EntityT: Clone,
+    {
+        self.clone_ops_with(&[op], target, mapping)[0]
+    }
+
+    fn clone_op(&mut self, op: OpId, target: Option<RegionId>) -> IrMapping
+    where
+        Self::OpT: Clone,
+        Self::EntityT: Clone,
+    {
+        let mut mapping = IrMapping::new();
+        self.clone_op_with(op, target, &mut mapping);
+        mapping
+    }
+
+    /// Deep-copy the ops and entities of `region` to the end of `target`.
+    fn clone_region_with(
+        &mut self, region: RegionId, target: RegionId, mapping: &mut IrMapping,
+    ) -> Vec<OpId>
+    where
+        Self::OpT: Clone,
+        Self::EntityT: Clone,
+    {
+        let region = self.get_region(region);
+        IrSnapshot::take(self, &region.get_op_children(), &region.get_entity_children())
+            .restore(self, Some(target), mapping)
+    }
+
+    fn clone_region(&mut self, region: RegionId, target: RegionId) -> IrMapping
+    where
+        Self::OpT: Clone,
+        Self::EntityT: Clone,
+    {
+        let mut mapping = IrMapping::new();
+        self.clone_region_with(region, target, &mut mapping);
+        mapping
+    }
+
+    /// Deep-copy `op` into `target` of another environment of the same dialect.
+    fn clone_op_into<D>(&self, op: OpId, dst: &mut D, target: Option<RegionId>) -> IrMapping
+    where
+        D: Environ<OpT = Self::OpT, EntityT = Self::EntityT>,
+        Self::OpT: Clone,
+        Self::EntityT: Clone,
+    {
+        let mut mapping = IrMapping::new();
+        IrSnapshot::take(self, &[op], &[]).restore(dst, target, &mut mapping);
+        mapping
+    }
+
+    /// Deep-copy the ops and entities of `region` into `target` of another environment
+    /// of the same dialect.
+    fn clone_region_into<D>(&self, region: RegionId, dst: &mut D, target: RegionId) -> IrMapping
+    where
+        D: Environ<OpT = Self::OpT, EntityT = Self::EntityT>,
+        Self::OpT: Clone,
+        Self::EntityT: Clone,
+    {
+        let mut mapping = IrMapping::new();
+        let region = self.get_region(region);
+        IrSnapshot::take(self, &region.get_op_children(), &region.get_entity_children())
+            .restore(dst, Some(target), &mut mapping);
+        mapping
+    }
+
     fn with_region<F: for<'a> Fn(&mut Self) -> ()>(
         &mut self, parent: Option<RegionId>, f: F,
     );
 
-    fn verify_op(&self, op: OpId) -> bool {
-        let op = self.get_op(op);
+    /// whether `op` satisfies its constraints and its traits
+    fn verify_op(&self, op_id: OpId) -> bool {
+        self.verify_op_constraints(op_id) && self.verify_op_traits(op_id).is_ok()
+    }
+
+    /// whether `op` satisfies its constraints, those its traits imply included
+    fn verify_op_constraints(&self, op_id: OpId) -> bool {
+        let op = self.get_op(op_id);
         let constraints = op.get_constraints();
         let attributes = op.get_attrs();
         let uses = op.get_uses();
         let defs = op.get_defs();
         let regions = op.get_regions();
-        let all_true = constraints.into_iter().all(|constraint| {
+        constraints.into_iter().all(|constraint| {
             constraint.verify(
                 self,
                 attributes.to_owned(),
-                defs.to_owned(),
                 uses.to_owned(),
+                defs.to_owned(),
                 regions.to_owned(),
             )
-        });
-        all_true
+        })
+    }
+
+    /// Check what the traits of `op` imply, returning the first one it breaks
+    fn verify_op_traits(&self, op_id: OpId) -> Result<(), OpTrait> {
+        let op = self.get_op(op_id);
+        if op.has_trait(OpTrait::Pure) && op.has_trait(OpTrait::HasSideEffects) {
+            return Err(OpTrait::Pure);
+        }
+        if op.is_terminator() {
+            if let Some(parent) = op.get_parent() {
+                if self.get_region(parent).op_children.last() != Some(&op_id) {
+                    return Err(OpTrait::Terminator);
+                }
+            }
+        }
+        if op.is_isolated_from_above() {
+            let (ops, _) = self.get_op_subtree(op_id);
+            let regions = ops
+                .iter()
+                .flat_map(|&op| self.get_op(op).get_regions())
+                .flat_map(|(_, regions)| regions)
+                .collect::<Vec<_>>();
+            let isolated = ops.iter().skip(1).all(|&op| {
+                self.get_op(op).get_uses().into_iter().flat_map(|(_, uses)| uses).flatten().all(
+                    |entity| {
+                        self.get_entity(entity).get_parent().is_some_and(|parent| regions.contains(&parent))
+                    },
+                )
+            });
+            if !isolated {
+                return Err(OpTrait::IsolatedFromAbove);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the entities used by `op` are defined before it, by an earlier op of its
+    /// region or of an enclosing one. Sequential ops may use entities defined later.
+    /// Returns the first entity used too early.
+    fn verify_def_before_use(&self, op_id: OpId) -> Result<(), EntityId> {
+        self.verify_def_before_use_in(op_id, &mut FxHashMap::default())
+    }
+
+    /// [`Environ::verify_def_before_use`], with the position of each op in its region kept
+    /// in `positions` and computed once per region, to verify many ops in linear time
+    fn verify_def_before_use_in(
+        &self, op_id: OpId, positions: &mut FxHashMap<RegionId, FxHashMap<OpId, usize>>,
+    ) -> Result<(), EntityId> {
+        let op = self.get_op(op_id);
+        if op.has_trait(OpTrait::Sequential) {
+            return Ok(());
+        }
+        for used in op.get_uses().into_iter().flat_map(|(_, uses)| uses).flatten() {
+            for def in self.get_defs(used) {
+                let Some(region) = self.get_op(def).get_parent() else {
+                    continue;
+                };
+                let Some(user) = self.get_ancestor_in(region, op_id) else {
+                    continue;
+                };
+                let positions = positions.entry(region).or_insert_with(|| {
+                    let children = self.get_region(region).op_children.iter();
+                    children.enumerate().map(|(position, &child)| (child, position)).collect()
+                });
+                let position = |op: OpId| positions.get(&op).copied();
+                if def == op_id || position(def) > position(user) {
+                    return Err(used);
+                }
+            }
+        }
+        Ok(())
     }
 
     fn print_op(&self, op: OpId) -> String {
@@ -127,25 +542,237 @@ pub trait Environ: Sized {
         format!("{}", crate::utils::print::tab(ops.join("\n")))
     }
 
+    /// Remove the entity and drop it from the children of its parent region, whether it
+    /// is used or not. See [`Environ::try_delete_entity`] for the checked version.
     fn delete_entity(&mut self, entity_id: EntityId);
-    
+
+    /// Remove the op with the entities it defines and its regions, whether the entities
+    /// are used or not. See [`Environ::try_delete_op`] for the checked version.
     fn delete_op(&mut self, op_id: OpId) -> ();
 
-    fn delete_region(&mut self, region_id: RegionId) -> () {
-        for op in self.get_region(region_id).get_op_children() {
-            self.delete_op(op);
+    /// Remove the region with everything in it. The op holding it is left as is.
+    fn delete_region(&mut self, region_id: RegionId) -> ();
+
+    /// The ops and entities deleted along with `op`: itself, the ops nested in its regions,
+    /// the entities they define and the entities of its regions.
+    fn get_op_subtree(&self, op: OpId) -> (Vec<OpId>, Vec<EntityId>) {
+        let mut ops = vec![op];
+        for (_, regions) in self.get_op(op).get_regions() {
+            for region in regions {
+                ops.extend(self.walk_ops(Some(region)));
+            }
+        }
+        let mut entities = vec![];
+        for &op in ops.iter() {
+            entities.extend(self.get_op(op).get_defs().into_iter().flat_map(|(_, defs)| defs).flatten());
+            for (_, regions) in self.get_op(op).get_regions() {
+                for region in regions {
+                    entities.extend(self.get_region(region).get_entity_children());
+                }
+            }
+        }
+        let mut seen = crate::FxHashSet::default();
+        entities.retain(|&entity| seen.insert(entity));
+        (ops, entities)
+    }
+
+    /// The entities deleted along with `op` which are still used by ops that are not, with
+    /// those users
+    fn get_external_uses(&self, op: OpId) -> Vec<(EntityId, Vec<OpId>)> {
+        let (ops, entities) = self.get_op_subtree(op);
+        entities
+            .into_iter()
+            .filter_map(|entity| {
+                let users = self
+                    .get_uses(entity)
+                    .into_iter()
+                    .filter(|user| !ops.contains(user))
+                    .collect::<Vec<_>>();
+                (!users.is_empty()).then_some((entity, users))
+            })
+            .collect()
+    }
+
+    /// Delete `entity` unless it is still used
+    fn try_delete_entity(&mut self, entity: EntityId) -> IronyResult<()> {
+        let users = self.get_uses(entity);
+        if !users.is_empty() {
+            return Err(IronyError::InUse { entity, users });
+        }
+        self.delete_entity(entity);
+        Ok(())
+    }
+
+    /// Delete `op` unless an entity deleted with it is still used outside of it
+    fn try_delete_op(&mut self, op: OpId) -> IronyResult<()> {
+        if let Some((entity, users)) = self.get_external_uses(op).into_iter().next() {
+            return Err(IronyError::InUse { entity, users });
+        }
+        self.delete_op(op);
+        Ok(())
+    }
+
+    /// Delete `op` together with every op that transitively uses what it defines, and
+    /// return the deleted ops in deletion order
+    fn delete_op_cascade(&mut self, op: OpId) -> Vec<OpId> {
+        let mut doomed = vec![op];
+        let mut index = 0;
+        while index < doomed.len() {
+            for (_, users) in self.get_external_uses(doomed[index]) {
+                for user in users {
+                    if !doomed.contains(&user) {
+                        doomed.push(user);
+                    }
+                }
+            }
+            index += 1;
+        }
+        // ops nested in a doomed op are already gone with it
+        doomed.retain(|&op| {
+            let exists = self.contains_op(op);
+            if exists {
+                self.delete_op(op);
+            }
+            exists
+        });
+        doomed
+    }
+
+    /// Every reference to an op, entity or region missing from the tables, and every
+    /// child missing from its parent region
+    fn find_dangling_refs(&self) -> Vec<DanglingRef> {
+        let mut dangling = vec![];
+        for op in self.get_op_ids() {
+            let op_ref = self.get_op(op);
+            for entity in op_ref.get_uses().into_iter().flat_map(|(_, uses)| uses).flatten() {
+                if !self.contains_entity(entity) {
+                    dangling.push(DanglingRef::Use { op, entity });
+                }
+            }
+            for entity in op_ref.get_defs().into_iter().flat_map(|(_, defs)| defs).flatten() {
+                if !self.contains_entity(entity) {
+                    dangling.push(DanglingRef::Def { op, entity });
+                }
+            }
+            for region in op_ref.get_regions().into_iter().flat_map(|(_, regions)| regions) {
+                if !self.contains_region(region) {
+                    dangling.push(DanglingRef::Region { op, region });
+                }
+            }
+            if let Some(region) = op_ref.get_parent() {
+                if !self.contains_region(region)
+                    || !self.get_region(region).op_children.contains(&op)
+                {
+                    dangling.push(DanglingRef::OpParent { op, region });
+                }
+            }
         }
-        for entity in self.get_region(region_id).get_entity_children() {
-            self.delete_entity(entity);
+        for entity in self.get_entity_ids() {
+            if let Some(region) = self.get_entity(entity).get_parent() {
+                if !self.contains_region(region)
+                    || !self.get_region(region).entity_children.contains(&entity)
+                {
+                    dangling.push(DanglingRef::EntityParent { entity, region });
+                }
+            }
+        }
+        for region in self.get_region_ids() {
+            for op in self.get_region(region).get_op_children() {
+                if !self.contains_op(op) {
+                    dangling.push(DanglingRef::OpChild { region, op });
+                }
+            }
+            for entity in self.get_region(region).get_entity_children() {
+                if !self.contains_entity(entity) {
+                    dangling.push(DanglingRef::EntityChild { region, entity });
+                }
+            }
         }
+        dangling
     }
 
     fn dump(&self) -> String;
 
-    fn run_passes(&mut self) -> Result<(), ()>; // -> ???
+    fn run_passes(&mut self) -> IronyResult<()>;
 
     #[track_caller]
-    fn get_hasher(&self) -> RefMut<crate::FxHasher>;
+    fn get_hasher(&self) -> RefMut<'_, crate::FxHasher>;
+
+    #[track_caller]
+    fn get_diagnostics(&self) -> RefMut<'_, DiagnosticEngine>;
+
+    fn get_op_loc(&self, op: OpId) -> Option<Loc> { self.get_op(op).get_loc() }
+
+    fn set_op_loc(&mut self, op: OpId, loc: Option<Loc>) {
+        self.get_op_entry(op).and_modify(|op| op.set_loc(loc));
+    }
+
+    fn emit_diagnostic(&self, diagnostic: Diagnostic) { self.get_diagnostics().emit(diagnostic) }
+
+    /// Report an error at the location of `op`
+    fn emit_error(&self, op: OpId, message: impl Into<String>) {
+        self.emit_diagnostic(Diagnostic::error(message).at(self.get_op_loc(op)))
+    }
+
+    /// Report a warning at the location of `op`
+    fn emit_warning(&self, op: OpId, message: impl Into<String>) {
+        self.emit_diagnostic(Diagnostic::warning(message).at(self.get_op_loc(op)))
+    }
+
+    /// Verify every op, reporting an error for each one that breaks its constraints or traits
+    /// or refers to an unresolved symbol, and for each symbol declared again in a symbol table
+    fn verify(&self) -> bool
+    where Self::AttributeT: SymbolRefTrait {
+        self.verify_with(&VerifyOptions::default())
+    }
+
+    /// Verify every op as [`Environ::verify`] does, and run the checks enabled in `options`
+    fn verify_with(&self, options: &VerifyOptions) -> bool
+    where Self::AttributeT: SymbolRefTrait {
+        let mut all_true = true;
+        let mut positions = FxHashMap::default();
+        for op in self.get_op_ids() {
+            let name = self.get_op(op).get_op_name();
+            let unresolved = self.find_unresolved_symbols(op);
+            if let Err(op_trait) = self.verify_op_traits(op) {
+                self.emit_error(op, format!("`{}` breaks its `{}` trait", name, op_trait));
+                all_true = false;
+            } else if !unresolved.is_empty() {
+                // the constraints of the op may need its symbols resolved
+                for symbol in unresolved {
+                    self.emit_error(op, format!("unresolved symbol `@{}`", symbol));
+                }
+                all_true = false;
+            } else if !self.verify_op_constraints(op) {
+                self.emit_error(op, format!("`{}` does not satisfy its constraints", name));
+                all_true = false;
+            }
+            if !options.def_before_use {
+                continue;
+            }
+            if let Err(entity) = self.verify_def_before_use_in(op, &mut positions) {
+                let entity = self.print_entity(entity);
+                self.emit_error(op, format!("`{}` uses {} before its definition", name, entity));
+                all_true = false;
+            }
+        }
+        // a symbol is declared once in each symbol table
+        let tables = self.get_region_ids().into_iter().filter(|&region| {
+            self.get_region(region).symbol_table
+        });
+        for scope in std::iter::once(None).chain(tables.map(Some)) {
+            let duplicates = self.find_duplicate_symbols(scope);
+            let mut seen = crate::FxHashSet::default();
+            for (symbol, op) in self.get_symbols(scope) {
+                let name = symbol.get_name().to_owned();
+                if duplicates.contains(&name) && !seen.insert(name.to_owned()) {
+                    self.emit_error(op, format!("the symbol `{}` is already declared", name));
+                    all_true = false;
+                }
+            }
+        }
+        all_true
+    }
 
     fn hash_region(&self, region: RegionId, reducer: &mut impl ReducerTrait) {
         let region = self.get_region(region);
@@ -223,6 +850,7 @@ macro_rules! environ_def {
 
             hasher: Rc<RefCell<irony::FxHasher>>,
             op_hash_table: FxHashMap<OpHashT, irony::OpId>,
+            diagnostics: RefCell<irony::DiagnosticEngine>,
 
             $($field_vis $field_name: $field_ty,)*
         }
@@ -259,15 +887,8 @@ macro_rules! environ_def {
                 v
             }
 
-            fn get_entity(&self, id: irony::EntityId) -> &Self::EntityT {
-                match self.entity_table.get(&id.id()) {
-                    Some(entity) => entity,
-                    None => panic!(
-                        "get entity not in the table by id \ntable: {:#?}\nentity: {:#?}",
-                        self.entity_table.get_map(),
-                        id.id()
-                    ),
-                }
+            fn try_get_entity(&self, id: irony::EntityId) -> irony::IronyResult<&Self::EntityT> {
+                self.entity_table.get(&id.id()).ok_or(irony::IronyError::EntityNotFound(id))
             }
 
             fn get_entities(&self, ids: &[irony::EntityId]) -> Vec<&Self::EntityT> {
@@ -290,15 +911,18 @@ macro_rules! environ_def {
                 self.entity_table.entry(entity_id.id())
             }
 
-            fn get_op(&self, id: irony::OpId) -> &Self::OpT {
-                match self.op_table.get(&id.id()) {
-                    Some(op) =>op,
-                    None => panic!(
-                        "get op not in the table by id \ntable: {:#?}\nop: {:#?}",
-                        self.op_table.get_map(),
-                        id.id()
-                    ),
-                }
+            fn try_get_op(&self, id: irony::OpId) -> irony::IronyResult<&Self::OpT> {
+                self.op_table.get(&id.id()).ok_or(irony::IronyError::OpNotFound(id))
+            }
+
+            fn get_ops_with_parent(&self, parent: Option<RegionId>) -> Vec<OpId> {
+                self.op_table.iter().filter_map(|(id, op)| {
+                    if op.get_parent() == parent {
+                        Some(OpId(*id))
+                    } else {
+                        None
+                    }
+                }).collect()
             }
 
             fn get_op_entry(&mut self, op_id: irony::OpId) -> indexmap::map::Entry<usize, Self::OpT> {
@@ -311,21 +935,30 @@ macro_rules! environ_def {
                 .collect()
             }
 
+            fn get_op_ids(&self) -> Vec<irony::OpId> {
+                self.op_table.keys().map(|&id| irony::OpId(id)).collect()
+            }
+
+            fn get_entity_ids(&self) -> Vec<irony::EntityId> {
+                self.entity_table.keys().map(|&id| irony::EntityId(id)).collect()
+            }
+
+            fn get_region_ids(&self) -> Vec<irony::RegionId> {
+                self.region_table.keys().map(|&id| irony::RegionId(id)).collect()
+            }
+
             fn add_entity(&mut self, entity: Self::EntityT) -> irony::EntityId {
                 let (id, _) = self.entity_table.insert_with_id(entity);
                 self.set_entity_parent(irony::EntityId::from(id));
                 irony::EntityId(id)
             }
 
-            fn get_region(&self, id: irony::RegionId) -> &irony::Region {
-                match self.region_table.get(&id.id()) {
-                    Some(region) => region,
-                    None => panic!(
-                        "get region not in the table by id \ntable: {:#?}\nregion: {:#?}",
-                        self.region_table.get_map(),
-                        id.id()
-                    ),
-                }
+            fn try_get_region(&self, id: irony::RegionId) -> irony::IronyResult<&irony::Region> {
+                self.region_table.get(&id.id()).ok_or(irony::IronyError::RegionNotFound(id))
+            }
+
+            fn get_region_entry(&mut self, region_id: irony::RegionId) -> indexmap::map::Entry<'_, usize, irony::Region> {
+                self.region_table.entry(region_id.id())
             }
 
             fn add_region(&mut self, region: irony::Region) -> irony::RegionId {
@@ -333,7 +966,12 @@ macro_rules! environ_def {
                 irony::RegionId(id)
             }
 
-            fn add_op(&mut self, op: Self::OpT) -> irony::OpId {
+            /// Ops without a location get the one of the caller
+            #[track_caller]
+            fn add_op(&mut self, mut op: Self::OpT) -> irony::OpId {
+                if op.get_loc().is_none() {
+                    op.set_loc(Some(irony::Loc::caller()));
+                }
                 let (id, op) = self.op_table.insert_with_id(op);
                 self.set_op_parent(irony::OpId::from(id));
                 irony::OpId(id)
@@ -387,46 +1025,144 @@ macro_rules! environ_def {
                 format!("entity table: {:#?}\nregion table: {:#?}\nop table: {:#?}", self.entity_table.get_map(), self.region_table.get_map(), self.op_table.get_map())
             }
 
-            fn run_passes(&mut self) -> Result<(), ()>{
+            fn run_passes(&mut self) -> irony::IronyResult<()> {
                 let pass_manager = self.pass_manager.clone();
                 pass_manager.run_passes(self)?;
                 Ok(())
             }
 
-            fn get_hasher(&self) -> RefMut<irony::FxHasher> {
+            fn get_hasher(&self) -> RefMut<'_, irony::FxHasher> {
                 self.hasher.borrow_mut()
             }
 
-            fn delete_entity(&mut self, entity_id: EntityId) {
-                // if !self.get_entity(entity_id).get_uses(self).is_empty() {
-                //     panic!("The op to be deleted defines an entity that has been used.\n\t{} has been used by\n\t{}\n", self.print_entity(entity_id), self.get_entity(entity_id).get_uses(self).iter().map(|op| self.print_op(*op)).collect::<Vec<String>>().join("\n\t"));
-                // } else {
-                //     self.entity_table.remove(&entity_id.id());
-                // }
-
-                // TODO: turn the "delete entity being used" panic into some checking
+            fn get_diagnostics(&self) -> RefMut<'_, irony::DiagnosticEngine> {
+                self.diagnostics.borrow_mut()
+            }
 
-                self.entity_table.remove(&entity_id.id());
+            fn delete_entity(&mut self, entity_id: EntityId) {
+                if let Some(parent) = self.get_entity(entity_id).get_parent() {
+                    if self.region_table.contains_key(&parent.id()) {
+                        self.get_region_entry(parent)
+                            .and_modify(|region| region.remove_entity_child(entity_id));
+                    }
+                }
+                self.entity_table.remove_with_id(entity_id.id());
             }
 
             fn delete_op(&mut self, op_id: OpId) -> () {
                 for (_, def_field) in self.get_op(op_id).get_defs() {
                     for def in def_field {
                         if let Some(entity_id) = def {
-                            {
+                            if self.entity_table.contains_key(&entity_id.id()) {
                                 self.delete_entity(entity_id);
                             }
                         }
                     }
                 }
-        
+
                 for (_, region_field) in self.get_op(op_id).get_regions() {
                     for region in region_field {
                         self.delete_region(region);
                     }
                 }
-        
-                self.op_table.remove(&op_id.id());
+
+                if let Some(parent) = self.get_op(op_id).get_parent() {
+                    if self.region_table.contains_key(&parent.id()) {
+                        self.get_region_entry(parent)
+                            .and_modify(|region| region.remove_op_child(op_id));
+                    }
+                }
+                self.op_table.remove_with_id(op_id.id());
+            }
+
+            fn delete_region(&mut self, region_id: RegionId) -> () {
+                for op in self.get_region(region_id).get_op_children() {
+                    self.delete_op(op);
+                }
+                for entity in self.get_region(region_id).get_entity_children() {
+                    self.delete_entity(entity);
+                }
+                self.region_table.remove_with_id(region_id.id());
+            }
+
+            fn begin_transaction(&mut self) {
+                self.op_table.begin_journal();
+                self.entity_table.begin_journal();
+                self.region_table.begin_journal();
+            }
+
+            fn commit_transaction(&mut self) {
+                self.op_table.commit_journal();
+                self.entity_table.commit_journal();
+                self.region_table.commit_journal();
+            }
+
+            fn rollback_transaction(&mut self) {
+                self.op_table.rollback_journal();
+                self.entity_table.rollback_journal();
+                self.region_table.rollback_journal();
+                let op_table = &self.op_table;
+                self.op_hash_table.retain(|_, op| op_table.contains_key(&op.id()));
+            }
+        }
+
+        impl $name {
+            /// The key under which `op` is hash-consed: its parent region and the hash of
+            /// the op with its ids reduced by [`irony::IdReducer`]
+            pub fn op_hash_key(&self, op: irony::OpId) -> OpHashT {
+                self.hasher.replace(irony::FxHasherBuilder::default().build_hasher());
+                self.get_op(op).hash_with_reducer(self, &mut irony::IdReducer::default());
+                let hash_value = self.hasher.borrow_mut().finish();
+                OpHashT(self.get_op(op).get_parent(), hash_value)
+            }
+
+            /// The op, entity and region tables with ids and child order kept
+            pub fn to_ir_value(&self) -> irony::IrValue {
+                irony::IrTables::<$op_ty, $entity_ty>::to_ir_value(
+                    self.op_table.iter(),
+                    self.entity_table.iter(),
+                    self.region_table.iter(),
+                )
+            }
+
+            /// Rebuild an environment from [`Self::to_ir_value`]. New ops and entities
+            /// are added at the top level, and hash-consed against the loaded ops.
+            pub fn from_ir_value(value: &irony::IrValue) -> irony::IronyResult<Self> {
+                let tables = irony::IrTables::<$op_ty, $entity_ty>::from_ir_value(value)?;
+                let mut env = Self::default();
+                for (id, entity) in tables.entities {
+                    env.entity_table.insert_with_given_id(id, entity)?;
+                }
+                for (id, region) in tables.regions {
+                    env.region_table.insert_with_given_id(id, region)?;
+                }
+                for (id, op) in tables.ops {
+                    env.op_table.insert_with_given_id(id, op)?;
+                }
+                // the first of equal ops absorbs the ones hash-consed later
+                let ops = env.op_table.keys().map(|&id| irony::OpId(id)).collect::<Vec<_>>();
+                for op in ops {
+                    let key = env.op_hash_key(op);
+                    env.op_hash_table.entry(key).or_insert(op);
+                }
+                env.begin_region(None);
+                Ok(env)
+            }
+
+            pub fn to_json(&self) -> String {
+                self.to_ir_value().to_json()
+            }
+
+            pub fn from_json(input: &str) -> irony::IronyResult<Self> {
+                Self::from_ir_value(&irony::IrValue::from_json(input)?)
+            }
+
+            pub fn to_binary(&self) -> Vec<u8> {
+                irony::encode_binary(&self.to_ir_value())
+            }
+
+            pub fn from_binary(input: &[u8]) -> irony::IronyResult<Self> {
+                Self::from_ir_value(&irony::decode_binary(input)?)
             }
         }
     };
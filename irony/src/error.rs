@@ -0,0 +1,54 @@
+use crate::{EntityId, OpId, RegionId, SerializeError};
+
+/// Errors of the fallible [`crate::Environ`] methods
+#[derive(Clone, Debug, PartialEq)]
+pub enum IronyError {
+    OpNotFound(OpId),
+    EntityNotFound(EntityId),
+    RegionNotFound(RegionId),
+    /// `entity` would be deleted while `users` still use it
+    InUse { entity: EntityId, users: Vec<OpId> },
+    /// the op declares no symbol
+    NoSymbol(OpId),
+    /// the symbol is already declared in the symbol table
+    DuplicateSymbol(String),
+    Serialize(SerializeError),
+    /// a pass or transformation could not handle `op`
+    Failed { op: Option<OpId>, message: String },
+}
+
+pub type IronyResult<T> = Result<T, IronyError>;
+
+impl std::fmt::Display for IronyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IronyError::OpNotFound(op) => write!(f, "op {} is not in the table", op.0),
+            IronyError::EntityNotFound(entity) => {
+                write!(f, "entity {} is not in the table", entity.0)
+            },
+            IronyError::RegionNotFound(region) => {
+                write!(f, "region {} is not in the table", region.0)
+            },
+            IronyError::InUse { entity, users } => {
+                let users = users.iter().map(|user| user.0.to_string()).collect::<Vec<_>>();
+                write!(f, "entity {} is still used by op {}", entity.0, users.join(", "))
+            },
+            IronyError::NoSymbol(op) => write!(f, "op {} declares no symbol", op.0),
+            IronyError::DuplicateSymbol(name) => write!(f, "symbol @{} already exists", name),
+            IronyError::Serialize(err) => write!(f, "{}", err),
+            IronyError::Failed { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl IronyError {
+    pub fn failed(op: Option<OpId>, message: impl Into<String>) -> Self {
+        IronyError::Failed { op, message: message.into() }
+    }
+}
+
+impl std::error::Error for IronyError {}
+
+impl From<SerializeError> for IronyError {
+    fn from(err: SerializeError) -> Self { IronyError::Serialize(err) }
+}
@@ -1,5 +1,7 @@
 use std::fmt::Debug;
-use std::ops::{Deref, DerefMut};
+use std::ops::Deref;
+
+use crate::SerializeError;
 
 pub type FxHasher = rustc_hash::FxHasher;
 pub type FxHasherBuilder = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
@@ -12,6 +14,19 @@ pub type FxHashMap<K, V> = std::collections::HashMap<K, V, FxHasherBuilder>;
 pub struct FxMapWithUniqueId<V> {
     indexmap: FxIndexMap<usize, V>,
     next_id: usize,
+    /// one journal per open transaction, innermost last
+    journals: Vec<Journal<V>>,
+}
+
+/// The state of the entries touched since a transaction began
+#[derive(Debug)]
+struct Journal<V> {
+    next_id: usize,
+    /// id and value before the first touch, `None` for new entries
+    old: Vec<(usize, Option<V>)>,
+    touched: FxHashSet<usize>,
+    /// order of the ids before the first removal shifted them
+    order: Option<Vec<usize>>,
 }
 
 impl<V> Default for FxMapWithUniqueId<V> {
@@ -19,6 +34,7 @@ impl<V> Default for FxMapWithUniqueId<V> {
         Self {
             indexmap: Default::default(),
             next_id: Default::default(),
+            journals: Default::default(),
         }
     }
 }
@@ -29,20 +45,92 @@ impl<V> Deref for FxMapWithUniqueId<V> {
     fn deref(&self) -> &Self::Target { &self.indexmap }
 }
 
-impl<V> DerefMut for FxMapWithUniqueId<V> {
-    fn deref_mut(&mut self) -> &mut Self::Target { &mut self.indexmap }
-}
-
 impl<V> FxMapWithUniqueId<V>
-where V: PartialEq + Debug + super::Id
+where V: PartialEq + Debug + Clone + super::Id
 {
+    /// Remember the entry `id` as it is now, if it is the first time it is touched in the
+    /// innermost open transaction. Must be called before any modification of the entry.
+    pub fn touch(&mut self, id: usize) {
+        let Some(journal) = self.journals.last_mut() else {
+            return;
+        };
+        if journal.touched.insert(id) {
+            journal.old.push((id, self.indexmap.get(&id).cloned()));
+        }
+    }
+
+    pub fn begin_journal(&mut self) {
+        self.journals.push(Journal {
+            next_id: self.next_id,
+            old: vec![],
+            touched: FxHashSet::default(),
+            order: None,
+        });
+    }
+
+    /// Keep the changes, handing the old entries over to the enclosing transaction
+    pub fn commit_journal(&mut self) {
+        let journal = self.journals.pop().expect("no open transaction to commit");
+        if let Some(outer) = self.journals.last_mut() {
+            if outer.order.is_none() {
+                outer.order = journal.order;
+            }
+            for (id, old) in journal.old {
+                if outer.touched.insert(id) {
+                    outer.old.push((id, old));
+                }
+            }
+        }
+    }
+
+    /// Put every touched entry back as it was, at its old position
+    pub fn rollback_journal(&mut self) {
+        let journal = self.journals.pop().expect("no open transaction to roll back");
+        for (id, old) in journal.old {
+            match old {
+                None => {
+                    self.indexmap.shift_remove(&id);
+                },
+                Some(value) => {
+                    self.indexmap.insert(id, value);
+                },
+            }
+        }
+        if let Some(order) = journal.order {
+            let position: FxHashMap<usize, usize> =
+                order.into_iter().enumerate().map(|(index, id)| (id, index)).collect();
+            // entries added since keep their current order after the others
+            let position = |id: &usize| position.get(id).copied().unwrap_or(usize::MAX);
+            self.indexmap.sort_by(|a, _, b, _| position(a).cmp(&position(b)));
+        }
+        self.next_id = journal.next_id;
+    }
+
+    /// remove the entry `id`, keeping the order of the others
+    pub fn remove_with_id(&mut self, id: usize) -> Option<V> {
+        self.touch(id);
+        if let Some(journal) = self.journals.last_mut() {
+            if journal.order.is_none() {
+                journal.order = Some(self.indexmap.keys().copied().collect());
+            }
+        }
+        self.indexmap.shift_remove(&id)
+    }
+
     pub fn get_map(&self) -> &FxIndexMap<usize, V> { &self.indexmap }
 
-    pub fn get_map_mut(&mut self) -> &mut FxIndexMap<usize, V> { &mut self.indexmap }
+    /// The entry `id`, touched first so that rolling back undoes what is done with it.
+    /// Entries are only changed through here, [`Self::insert_with_id`] and
+    /// [`Self::remove_with_id`].
+    pub fn entry(&mut self, id: usize) -> indexmap::map::Entry<'_, usize, V> {
+        self.touch(id);
+        self.indexmap.entry(id)
+    }
 
     pub fn insert_with_id<'a, 't: 'a>(&'t mut self, mut value: V) -> (usize, &'a V) {
         let cur_id = self.next_id;
         self.next_id += 1;
+        self.touch(cur_id);
 
         value.set_id(cur_id);
 
@@ -51,4 +139,20 @@ where V: PartialEq + Debug + super::Id
 
         (cur_id, self.indexmap.get(&cur_id).unwrap())
     }
+
+    /// Insert `value` under a known `id`, e.g. when loading a serialized table. Ids given
+    /// out afterwards stay unique.
+    pub fn insert_with_given_id(&mut self, id: usize, mut value: V) -> Result<(), SerializeError> {
+        let Some(next_id) = id.checked_add(1) else {
+            return Err(SerializeError::Malformed(format!("invalid id {}", id)));
+        };
+        if self.indexmap.contains_key(&id) {
+            return Err(SerializeError::Malformed(format!("duplicate id {}", id)));
+        }
+        self.touch(id);
+        value.set_id(id);
+        self.indexmap.insert(id, value);
+        self.next_id = self.next_id.max(next_id);
+        Ok(())
+    }
 }
@@ -1,12 +1,22 @@
-#![feature(macro_metavar_expr)]
+#![cfg_attr(feature = "macro-rules", feature(macro_metavar_expr))]
 
 mod common;
 mod constraint;
+mod delete;
+mod diagnostic;
+mod dialect;
+mod dot;
 mod entity;
 mod environ;
+mod error;
+mod location;
+mod mapping;
 mod operation;
 mod pass;
 mod printer;
+mod serialize;
+mod transaction;
+mod transforms;
 
 mod hash;
 
@@ -14,12 +24,22 @@ pub mod utils;
 
 pub use common::*;
 pub use constraint::*;
+pub use delete::*;
+pub use diagnostic::*;
+pub use dialect::*;
+pub use dot::*;
 pub use entity::*;
 pub use environ::*;
+pub use error::*;
 pub use hash::*;
+pub use location::Loc;
+pub use mapping::IrMapping;
 pub use operation::*;
 pub use pass::*;
 pub use printer::*;
+pub use serialize::*;
+pub use transaction::Transaction;
+pub use transforms::*;
 
 
 pub mod preclude {
@@ -31,4 +51,5 @@ pub mod preclude {
 }
 
 pub use indexmap;
+pub use irony_macros;
 pub use visible::StructFields;
@@ -0,0 +1,134 @@
+use crate::{IrSerde, IrValue, SerializeError};
+
+/// Where an op comes from
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Loc {
+    /// a position in a source file, of a frontend or of the Rust code building the IR
+    FileLineCol { file: String, line: u32, column: u32 },
+    /// a named location, e.g. the instance path of an inlined op
+    Name { name: String, child: Option<Box<Loc>> },
+    /// the op comes from the combination of several ops, e.g. after CSE
+    Fused(Vec<Loc>),
+    /// `callee` was inlined at `caller`
+    CallSite { callee: Box<Loc>, caller: Box<Loc> },
+}
+
+impl Loc {
+    pub fn file_line_col(file: impl Into<String>, line: u32, column: u32) -> Self {
+        Loc::FileLineCol { file: file.into(), line, column }
+    }
+
+    pub fn name(name: impl Into<String>, child: Option<Loc>) -> Self {
+        Loc::Name { name: name.into(), child: child.map(Box::new) }
+    }
+
+    pub fn call_site(callee: Loc, caller: Loc) -> Self {
+        Loc::CallSite { callee: Box::new(callee), caller: Box::new(caller) }
+    }
+
+    /// The Rust source location of the caller
+    #[track_caller]
+    pub fn caller() -> Self { std::panic::Location::caller().into() }
+
+    /// Fuse the known locations, flattening nested fused ones and dropping duplicates.
+    /// `None` if none is known.
+    pub fn fuse(locs: impl IntoIterator<Item = Option<Loc>>) -> Option<Loc> {
+        let mut fused: Vec<Loc> = vec![];
+        for loc in locs.into_iter().flatten() {
+            let parts = match loc {
+                Loc::Fused(parts) => parts,
+                loc => vec![loc],
+            };
+            for part in parts {
+                if !fused.contains(&part) {
+                    fused.push(part);
+                }
+            }
+        }
+        match fused.len() {
+            0 => None,
+            1 => fused.pop(),
+            _ => Some(Loc::Fused(fused)),
+        }
+    }
+}
+
+impl From<&std::panic::Location<'static>> for Loc {
+    fn from(location: &std::panic::Location<'static>) -> Self {
+        Loc::file_line_col(location.file(), location.line(), location.column())
+    }
+}
+
+impl std::fmt::Display for Loc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Loc::FileLineCol { file, line, column } => write!(f, "{}:{}:{}", file, line, column),
+            Loc::Name { name, child: None } => write!(f, "\"{}\"", name),
+            Loc::Name { name, child: Some(child) } => write!(f, "\"{}\"({})", name, child),
+            Loc::Fused(locs) => {
+                let locs = locs.iter().map(|loc| loc.to_string()).collect::<Vec<_>>();
+                write!(f, "fused[{}]", locs.join(", "))
+            },
+            Loc::CallSite { callee, caller } => write!(f, "{} at {}", callee, caller),
+        }
+    }
+}
+
+impl IrSerde for Loc {
+    fn to_ir_value(&self) -> IrValue {
+        let (kind, value) = match self {
+            Loc::FileLineCol { file, line, column } => (
+                "file",
+                IrValue::Map(vec![
+                    ("file".to_owned(), IrValue::Str(file.to_owned())),
+                    ("line".to_owned(), IrValue::UInt(*line as u64)),
+                    ("column".to_owned(), IrValue::UInt(*column as u64)),
+                ]),
+            ),
+            Loc::Name { name, child } => (
+                "name",
+                IrValue::Map(vec![
+                    ("name".to_owned(), IrValue::Str(name.to_owned())),
+                    ("child".to_owned(), child.as_ref().map_or(IrValue::Null, |c| c.to_ir_value())),
+                ]),
+            ),
+            Loc::Fused(locs) => ("fused", IrValue::List(locs.iter().map(Loc::to_ir_value).collect())),
+            Loc::CallSite { callee, caller } => (
+                "callsite",
+                IrValue::Map(vec![
+                    ("callee".to_owned(), callee.to_ir_value()),
+                    ("caller".to_owned(), caller.to_ir_value()),
+                ]),
+            ),
+        };
+        IrValue::Map(vec![(kind.to_owned(), value)])
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        let [(kind, value)] = &value.as_map()?[..] else {
+            return Err(SerializeError::Malformed(format!("expect a location, found {:?}", value)));
+        };
+        match kind.as_str() {
+            "file" => Ok(Loc::file_line_col(
+                value.field("file")?.as_str()?,
+                value.field("line")?.as_uint()? as u32,
+                value.field("column")?.as_uint()? as u32,
+            )),
+            "name" => {
+                let child = match value.field("child")? {
+                    IrValue::Null => None,
+                    child => Some(Loc::from_ir_value(child)?),
+                };
+                Ok(Loc::name(value.field("name")?.as_str()?, child))
+            },
+            "fused" => Ok(Loc::Fused(
+                value.as_list()?.iter().map(Loc::from_ir_value).collect::<Result<_, _>>()?,
+            )),
+            "callsite" => Ok(Loc::call_site(
+                Loc::from_ir_value(value.field("callee")?)?,
+                Loc::from_ir_value(value.field("caller")?)?,
+            )),
+            _ => Err(SerializeError::Malformed(format!("unknown location kind `{}`", kind))),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{Entity, EntityId, Environ, FxHashMap, FxHashSet, Op, OpId, Region, RegionId};
+
+/// Correspondence from old to new ids built up while cloning IR.
+///
+/// Entities mapped before cloning are reused instead of being cloned, which is how the
+/// values flowing into a cloned region are substituted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IrMapping {
+    entities: FxHashMap<EntityId, EntityId>,
+    ops: FxHashMap<OpId, OpId>,
+    regions: FxHashMap<RegionId, RegionId>,
+}
+
+impl IrMapping {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn map_entity(&mut self, from: EntityId, to: EntityId) { self.entities.insert(from, to); }
+
+    pub fn map_op(&mut self, from: OpId, to: OpId) { self.ops.insert(from, to); }
+
+    pub fn map_region(&mut self, from: RegionId, to: RegionId) { self.regions.insert(from, to); }
+
+    pub fn lookup_entity(&self, from: EntityId) -> Option<EntityId> {
+        self.entities.get(&from).copied()
+    }
+
+    pub fn lookup_op(&self, from: OpId) -> Option<OpId> { self.ops.get(&from).copied() }
+
+    pub fn lookup_region(&self, from: RegionId) -> Option<RegionId> {
+        self.regions.get(&from).copied()
+    }
+
+    /// the mapped entity, or `from` itself if it is not mapped
+    pub fn lookup_entity_or_same(&self, from: EntityId) -> EntityId {
+        self.lookup_entity(from).unwrap_or(from)
+    }
+
+    pub fn get_entity_map(&self) -> &FxHashMap<EntityId, EntityId> { &self.entities }
+
+    pub fn get_op_map(&self) -> &FxHashMap<OpId, OpId> { &self.ops }
+
+    pub fn get_region_map(&self) -> &FxHashMap<RegionId, RegionId> { &self.regions }
+
+    fn remap(
+        &self, fields: Vec<(String, Vec<Option<EntityId>>)>,
+    ) -> Vec<(String, Vec<Option<EntityId>>)> {
+        fields
+            .into_iter()
+            .map(|(name, ids)| {
+                (name, ids.into_iter().map(|id| id.map(|id| self.lookup_entity_or_same(id))).collect())
+            })
+            .collect()
+    }
+}
+
+/// Owned copy of some ops with everything nested in them, taken before inserting the
+/// copy so that the source and the destination may be the same environment.
+pub(crate) struct IrSnapshot<O, T> {
+    /// old id and value of every nested region, outer regions first
+    regions: Vec<(RegionId, Region)>,
+    /// old id, old parent and value of every entity to clone
+    entities: Vec<(EntityId, Option<RegionId>, T)>,
+    /// the ids in `entities`, each entity being taken once
+    taken: FxHashSet<EntityId>,
+    /// old id and value of every op, in pre-order
+    ops: Vec<(OpId, O)>,
+    roots: Vec<OpId>,
+}
+
+impl<O: Op + Clone, T: Entity + Clone> IrSnapshot<O, T> {
+    /// Copy `roots` and the `entities` living next to them, which are cloned even if no
+    /// root defines them.
+    pub(crate) fn take<E>(env: &E, roots: &[OpId], entities: &[EntityId]) -> Self
+    where E: Environ<OpT = O, EntityT = T> {
+        let mut snapshot = Self {
+            regions: vec![],
+            entities: vec![],
+            taken: FxHashSet::default(),
+            ops: vec![],
+            roots: roots.to_vec(),
+        };
+        for &entity in entities {
+            snapshot.take_entity(env, entity);
+        }
+        for &op in roots {
+            snapshot.take_op(env, op);
+        }
+        snapshot
+    }
+
+    fn take_entity<E>(&mut self, env: &E, entity: EntityId)
+    where E: Environ<OpT = O, EntityT = T> {
+        if !self.taken.insert(entity) {
+            return;
+        }
+        let value = env.get_entity(entity);
+        self.entities.push((entity, value.get_parent(), value.to_owned()));
+    }
+
+    fn take_op<E>(&mut self, env: &E, op_id: OpId)
+    where E: Environ<OpT = O, EntityT = T> {
+        let op = env.get_op(op_id);
+        self.ops.push((op_id, op.to_owned()));
+        for def in op.get_defs().into_iter().flat_map(|(_, defs)| defs).flatten() {
+            self.take_entity(env, def);
+        }
+        for region_id in op.get_regions().into_iter().flat_map(|(_, regions)| regions) {
+            let region = env.get_region(region_id);
+            self.regions.push((region_id, region.to_owned()));
+            for entity in region.get_entity_children() {
+                self.take_entity(env, entity);
+            }
+            for child in region.get_op_children() {
+                self.take_op(env, child);
+            }
+        }
+    }
+
+    /// Insert the copy into `env`, the roots going to `target`. Returns the new roots.
+    pub(crate) fn restore<E>(
+        self, env: &mut E, target: Option<RegionId>, mapping: &mut IrMapping,
+    ) -> Vec<OpId>
+    where E: Environ<OpT = O, EntityT = T> {
+        for (old, region) in self.regions.iter() {
+            let mut new = Region::new(region.isolated);
+            new.symbol_table = region.symbol_table;
+            let new = env.add_region(new);
+            mapping.map_region(*old, new);
+        }
+
+        for (old, parent, entity) in self.entities {
+            if mapping.lookup_entity(old).is_some() {
+                continue;
+            }
+            let parent = parent.and_then(|parent| mapping.lookup_region(parent)).or(target);
+            env.begin_region(parent);
+            let new = env.add_entity(entity);
+            env.end_region();
+            mapping.map_entity(old, new);
+        }
+
+        for (old, region) in self.regions {
+            let new = mapping.lookup_region(old).unwrap();
+            env.get_region_entry(new).and_modify(|new| {
+                new.arguments = region
+                    .arguments
+                    .iter()
+                    .map(|&argument| mapping.lookup_entity_or_same(argument))
+                    .collect()
+            });
+        }
+
+        for (old, mut op) in self.ops {
+            let parent = if self.roots.contains(&old) {
+                target
+            } else {
+                op.get_parent().and_then(|parent| mapping.lookup_region(parent))
+            };
+            op.set_defs(mapping.remap(op.get_defs()));
+            op.set_uses(mapping.remap(op.get_uses()));
+            op.set_regions(
+                op.get_regions()
+                    .into_iter()
+                    .map(|(name, regions)| {
+                        let regions = regions
+                            .into_iter()
+                            .map(|region| mapping.lookup_region(region).unwrap())
+                            .collect();
+                        (name, regions)
+                    })
+                    .collect(),
+            );
+            op.set_parent(None);
+            env.begin_region(parent);
+            let new = env.add_op(op);
+            env.end_region();
+            mapping.map_op(old, new);
+        }
+
+        self.roots.iter().map(|&root| mapping.lookup_op(root).unwrap()).collect()
+    }
+}
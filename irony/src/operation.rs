@@ -5,7 +5,7 @@ pub use paste::paste;
 use super::common::Id;
 use super::entity::EntityId;
 use crate::printer::OpPrinterTrait;
-use crate::{ConstraintTrait, RegionId, ReducerTrait, Environ};
+use crate::{ConstraintTrait, RegionId, ReducerTrait, Environ, Loc, OpInfo, Symbol};
 
 pub trait Op: Id + Debug {
     type DataTypeT;
@@ -20,7 +20,9 @@ pub trait Op: Id + Debug {
     >;
 
     fn get_defs(&self) -> Vec<(String, Vec<Option<EntityId>>)>;
+    fn set_defs(&mut self, defs: Vec<(String, Vec<Option<EntityId>>)>) -> ();
     fn get_uses(&self) -> Vec<(String, Vec<Option<EntityId>>)>;
+    fn set_uses(&mut self, uses: Vec<(String, Vec<Option<EntityId>>)>) -> ();
 
     fn get_attrs(&self) -> Vec<(String, Self::AttributeT)>;
     fn set_attrs(&mut self, attrs: Vec<(String, Self::AttributeT)>) -> ();
@@ -33,16 +35,80 @@ pub trait Op: Id + Debug {
     fn set_parent(&mut self, parent: Option<RegionId>);
 
     fn get_regions(&self) -> Vec<(String, Vec<RegionId>)>;
+    fn set_regions(&mut self, regions: Vec<(String, Vec<RegionId>)>) -> ();
 
     fn use_region(&self, region: RegionId) -> bool;
 
+    /// the qualified name of the op, e.g. `comb.variadic`
     fn get_op_name(&self) -> String;
+    /// the dialect of the op, the prefix of its qualified name
+    fn get_dialect(&self) -> Option<&'static str>;
+    /// the ops the type may hold: itself for a single op, all the variants for an op enum
+    fn get_registered_ops() -> Vec<OpInfo>
+    where Self: Sized;
+
+    /// the symbol declared by the op, held by the attribute named in its `symbol` clause
+    fn get_symbol(&self) -> Option<Symbol>;
+    fn set_symbol(&mut self, symbol: Symbol);
+
+    fn get_loc(&self) -> Option<Loc>;
+    fn set_loc(&mut self, loc: Option<Loc>);
+
+    /// the traits declared in the `traits` clause of the op
+    fn get_traits(&self) -> Vec<OpTrait>;
+    fn has_trait(&self, op_trait: OpTrait) -> bool { self.get_traits().contains(&op_trait) }
+    fn is_pure(&self) -> bool {
+        self.has_trait(OpTrait::Pure) && !self.has_trait(OpTrait::HasSideEffects)
+    }
+    fn has_side_effects(&self) -> bool { self.has_trait(OpTrait::HasSideEffects) }
+    fn is_commutative(&self) -> bool { self.has_trait(OpTrait::Commutative) }
+    fn is_terminator(&self) -> bool { self.has_trait(OpTrait::Terminator) }
+    fn is_isolated_from_above(&self) -> bool { self.has_trait(OpTrait::IsolatedFromAbove) }
+    fn with_loc(mut self, loc: Loc) -> Self
+    where Self: Sized {
+        self.set_loc(Some(loc));
+        self
+    }
+
+    /// Build the op called `name` from its defs, uses, attributes and regions
+    fn from_parts(
+        name: &str, defs: Vec<(String, Vec<Option<EntityId>>)>,
+        uses: Vec<(String, Vec<Option<EntityId>>)>, attrs: Vec<(String, Self::AttributeT)>,
+        regions: Vec<(String, Vec<RegionId>)>,
+    ) -> Option<Self>
+    where Self: Sized;
 
     fn get_printer(&self) -> Self::PrinterT;
 
     fn hash_with_reducer(&self, env: &impl Environ, reducer: &mut impl ReducerTrait); 
 }
 
+/// Properties of an op which generic passes and the verifier rely on
+#[derive(Clone, Copy, PartialEq, Debug, Hash, Eq)]
+pub enum OpTrait {
+    /// no effect besides its defs, so it may be erased when they are unused or merged
+    /// with an equal op
+    Pure,
+    /// the order of its uses does not matter
+    Commutative,
+    /// the last op of its region
+    Terminator,
+    /// the ops in its regions use nothing defined outside of them
+    IsolatedFromAbove,
+    /// never erased or merged, even if declared `Pure`
+    HasSideEffects,
+    /// all the defs and uses share one dtype, checked by the `SameTypeConstraint` it adds
+    /// to the constraints of the op
+    SameOperandsAndResultType,
+    /// its defs change on a clock edge, so its uses may be defined after it, as in the
+    /// feedback of a register
+    Sequential,
+}
+
+impl std::fmt::Display for OpTrait {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "{:?}", self) }
+}
+
 #[derive(Clone, Copy, PartialEq, Debug, Hash, Eq)]
 pub struct OpId(pub usize);
 impl From<usize> for OpId {
@@ -68,14 +134,16 @@ macro_rules! reduce_then_hash {
 #[macro_export]
 macro_rules! op_def {
     (
-        [data_type = $data_ty:ty, attr = $attr_ty:ty, constraint = $constraint_ty:ty]
+        [data_type = $data_ty:ty, attr = $attr_ty:ty, constraint = $constraint_ty:ty$(, dialect = $dialect:literal)?]
         $name_enum:ident  = {
             $(
-                $name:ident : {
+                $name:ident $(($mnemonic:literal))? : {
                     defs: [$($def:ident),*$(;$($variadic_def:ident),*)?],
                     uses: [$($use:ident),*$(;$($variadic_use:ident),*)?],
                     $(attrs: [$($attr:ident:$attr_variant:ident($attr_inner_ty:ty)$(($attr_hash:tt))?),*],)?
                     $(regions: [$($region:ident),*$(;$($variadic_region:ident),+)?],)?
+                    $(symbol: $symbol:ident,)?
+                    $(traits: [$($op_trait:ident),*],)?
                     $(constraints: [$($constraint:expr),*],)?
                     print: ($($print_tt:tt)*)$(,)?
                 }
@@ -86,12 +154,14 @@ macro_rules! op_def {
 
         $(
             irony::op_def_one! {
-                [data_type = $data_ty, attr = $attr_ty, constraint = $constraint_ty]
-                $name: {
+                [data_type = $data_ty, attr = $attr_ty, constraint = $constraint_ty$(, dialect = $dialect)?]
+                $name $(($mnemonic))? : {
                     defs : [$($def),*$(;$($variadic_def),+)?],
                     uses : [$($use),*$(;$($variadic_use),+)?],
                     $(attrs : [$($attr : $attr_variant($attr_inner_ty)$(($attr_hash))?),*],)?
                     $(regions: [$($region),*$(;$($variadic_region),+)?],)?
+                    $(symbol: $symbol,)?
+                    $(traits: [$($op_trait),*],)?
                     $(constraints : [$($constraint),*],)?
                     print: ($($print_tt)*)
                 }
@@ -111,279 +181,56 @@ macro_rules! op_def {
 
     };
 }
+/// Define one op; the op itself is generated by `irony_macros::op`, so an op reads the same
+/// whichever of the two macros defines it.
+#[cfg(feature = "macro-rules")]
 #[macro_export]
 macro_rules! op_def_one {
     (
-        [data_type = $data_ty:ty, attr = $attr_ty:ty, constraint = $constraint_ty:ty]
-        $name:ident : {
+        [data_type = $data_ty:ty, attr = $attr_ty:ty, constraint = $constraint_ty:ty$(, dialect = $dialect:literal)?]
+        $name:ident $(($mnemonic:literal))? : {
             defs: [$($def:ident),*$(;$($variadic_def:ident),+)?],
             uses: [$($use:ident),*$(;$($variadic_use:ident),+)?],
             $(attrs: [$($attr:ident:$attr_variant:ident($attr_inner_ty:ty)$(($attr_hash:tt))?),*],)?
             $(regions: [$($region:ident),*$(;$($variadic_region:ident),+)?],)?
+            $(symbol: $symbol:ident,)?
+            $(traits: [$($op_trait:ident),*],)?
             $(constraints: [$($constraint:expr),*],)?
             print: ($($print_tt:tt)*)$(,)?
         }
     ) => {
-        #[StructFields(pub)]
-        #[derive(PartialEq, Debug)]
-        pub struct $name  {
-            id: usize,
-            op_name: String,
-            $($def: Option<irony::EntityId>,)*
-            $($($variadic_def: Vec<irony::EntityId>,)*)?
-            $($use:Option<irony::EntityId>,)*
-            $($($variadic_use: Vec<irony::EntityId>,)*)?
-            $($($attr: Option<$attr_inner_ty>,)*)?
+        #[irony::irony_macros::op(
+            data_type = $data_ty,
+            attr = $attr_ty,
+            constraint = $constraint_ty,
+            $(constraints = [$($constraint),*],)?
+            $(symbol = $symbol,)?
+            $(traits = [$($op_trait),*],)?
+            $(dialect = $dialect,)?
+            $(name = $mnemonic,)?
+            print = ($($print_tt)*)
+        )]
+        pub struct $name {
+            $(#[def] $def: irony::EntityId,)*
+            $($(#[def] $variadic_def: Vec<irony::EntityId>,)*)?
+            $(#[uses] $use: irony::EntityId,)*
+            $($(#[uses] $variadic_use: Vec<irony::EntityId>,)*)?
+            $($(
+                #[attr$((hash ${ignore(attr_hash)}))?]
+                $attr: $attr_inner_ty,
+            )*)?
             $(
-                $($region: Option<irony::RegionId>,)*
-                $(
-                    $($variadic_region: Vec<irony::RegionId>,)*
-                )?
+                $(#[region] $region: irony::RegionId,)*
+                $($(#[region] $variadic_region: Vec<irony::RegionId>,)*)?
             )?
-
-            constraints: Vec<$constraint_ty>,
-            parent: Option<irony::RegionId>,
-            printer: paste!([< $name Printer >]),
         }
-
-        impl irony::Id for $name {
-            fn id(&self) -> usize {
-                self.id
-            }
-
-            fn set_id(&mut self, id: usize) {
-                self.id = id;
-            }
-        }
-
-        impl irony::Op for $name {
-            type DataTypeT = $data_ty;
-            type ConstraintT = $constraint_ty;
-            type AttributeT = $attr_ty;
-            type PrinterT = paste!([< $name Printer >]);
-
-            fn get_defs(&self) -> Vec<(String, Vec<Option<irony::EntityId>>)> {
-                vec![
-                    $((format!("{}", stringify!($def)), vec![self.$def.to_owned()])),*
-                    $($((format!("{}", stringify!($variadic_def)), self.$variadic_def.to_owned().into_iter().map(|x| Some(x)).collect()))*)?
-                ]
-            }
-
-            fn get_uses(&self) -> Vec<(String, Vec<Option<irony::EntityId>>)> {
-                vec![
-                    $((format!("{}", stringify!($use)), vec![self.$use.to_owned()]),)*
-                    $($((format!("{}", stringify!($variadic_use)), self.$variadic_use.to_owned().into_iter().map(|x| Some(x)).collect())),*)?
-                ]
-
-            }
-
-            fn get_attrs(&self) -> Vec<(String, Self::AttributeT)> {
-                vec![
-                    $(
-                        $(
-                            if self.$attr.is_some() {
-                                (format!("{}", stringify!($attr)), self.$attr.to_owned().unwrap().into())
-                            } else {
-                                (format!("none"), Self::AttributeT::None)
-                            }
-                        ),*
-                    )?
-                ]
-            }
-
-            fn set_attrs(&mut self, attrs: Vec<(String, Self::AttributeT)>) ->() {
-                $(
-                    $(
-                        self.$attr = attrs.iter().find(|(k, _)| k == &format!("{}", stringify!($attr))).map(|(_, v)| v.clone().into());
-                    )*
-                )?
-            }
-
-            fn get_constraints(&self) -> Vec<Self::ConstraintT> {
-                self.constraints.clone()
-            }
-
-            fn uses(&self, entity: irony::EntityId) -> bool {
-                self.get_uses().iter().flat_map(|(_, v)| v.iter()).any(|&x| {
-                    if let Some(x) = x {
-                        x.id() == entity.id()
-                    } else {
-                        false
-                    }}
-                )
-            }
-
-            fn defs(&self, entity: irony::EntityId) -> bool {
-                self.get_defs().iter().flat_map(|(_, v)| v.iter()).any(|&x| {
-                    if let Some(x) = x {
-                        x.id() == entity.id()
-                    } else {
-                        false
-                    }}
-                )
-            }
-
-
-            fn get_parent(&self) -> Option<irony::RegionId> {
-                self.parent
-            }
-            fn set_parent(&mut self, parent: Option<irony::RegionId>) {
-                self.parent = parent;
-            }
-
-            fn get_regions(&self) -> Vec<(String, Vec<irony::RegionId>)> {
-                vec![
-                    $(
-                        $((format!("{}", stringify!($region)), vec![self.$region.unwrap()]),)*
-                        $(
-                            $((format!("{}", stringify!($variadic_region)), self.$variadic_region.to_owned()),)*
-                        )?
-                    )?
-                ]
-            }
-
-            fn use_region(&self, region: irony::RegionId) -> bool{
-                self.get_regions().iter().any(|(_, v)| v.contains(&region))
-            }
-
-            fn get_op_name(&self) -> String {
-                self.op_name.clone()
-            }
-
-            fn get_printer(&self) -> Self::PrinterT {
-                self.printer.clone()
-            }
-            
-
-            fn hash_with_reducer(&self, env: &impl Environ, reducer: &mut impl ReducerTrait) {
-
-                // println!("hashing op: {}", self.op_name);
-
-                {
-                   self.op_name.hash(env.get_hasher().deref_mut());
-                //    println!("\thash {}", self.op_name);
-                }
-                $(
-                    if self.$def.is_some() {
-                        reduce_then_hash!(reducer, self.$def.to_owned().unwrap(), env.get_hasher().deref_mut());
-
-                    }
-                )*
-                $(
-                    $(
-                        for def in self.$variadic_def.to_owned() {
-                            reduce_then_hash!(reducer, def, env.get_hasher().deref_mut());
-                        }
-                    )*
-                )?
-
-                $(
-                    if self.$use.is_some() {
-                        reduce_then_hash!(reducer, self.$use.to_owned().unwrap(), env.get_hasher().deref_mut());
-                    }
-                )*
-                $(
-                    $(
-                        for used in self.$variadic_use.to_owned() {
-                            reduce_then_hash!(reducer, used, env.get_hasher().deref_mut());
-                        }
-                    )*
-                )?
-
-                $(
-                    $(
-                        $(
-                            ${ignore(attr_hash)}
-                            self.$attr.hash(env.get_hasher().deref_mut());
-                            // println!("\thash {:?}", self.$attr);
-                        )?
-                    )*
-                )?
-
-                $(
-                    $(
-                        // println!("hash region {:?}", self.$region.unwrap());
-                        env.hash_region(self.$region.unwrap(), reducer);
-                    )*
-                    $(
-                        $(
-                            for region in self.$variadic_region.to_owned() {
-                                // println!("hash region {:?}", region);
-                                env.hash_region(region, reducer);
-                            }
-                        )*
-                    )?
-                )?
-
-            }
-        }
-
-
-        impl $name {
-            pub fn new(
-                $($def: Option<irony::EntityId>,)*
-                $($($variadic_def: Vec<irony::EntityId>,)*)?
-                $($use: Option<irony::EntityId>,)*
-                $($($variadic_use: Vec<irony::EntityId>,)*)?
-                $($($attr: Option<$attr_inner_ty>,)*)?
-                $($($region: Option<irony::RegionId>,)*)?
-                $($($($variadic_region: Vec<irony::RegionId>,)*)?)?
-            ) -> Self {
-
-                Self {
-                    id: 0,
-                    op_name: stringify!($name).to_owned(),
-                    $($def,)*
-                    $($($variadic_def,)*)?
-                    $($use,)*
-                    $($($variadic_use,)*)?
-                    $($($attr,)*)?
-                    $($($region,)*)?
-                    $($($($variadic_region,)*)?)?
-
-                    constraints: vec![
-                        $($($constraint),*)?
-                    ],
-                    parent: None,
-                    printer: paste!([< $name Printer >]),
-                }
-
-            }
-        }
-
-        paste! {
-            #[derive(Clone, Debug, PartialEq, Hash)]
-            pub struct [< $name Printer >];
-
-            impl OpPrinterTrait for [< $name Printer >] {
-                type DataTypeT = $data_ty;
-                type AttributeT = $attr_ty;
-
-                fn print<'env, E, EntityT: Entity>(
-                    &self,
-                    env: &'env E,
-                    attrs: Vec<(String, Self::AttributeT)>,
-                    uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
-                    defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
-                    regions: Vec<(String, Vec<irony::RegionId>)>,
-                ) -> String
-                where
-                    E: Environ<EntityT = EntityT, AttributeT = Self::AttributeT>,
-                    EntityT: Entity<DataTypeT = Self::DataTypeT, AttributeT = Self::AttributeT> {
-                        let f = $($print_tt)*;
-                        f(env, attrs, uses, defs, regions)
-                    }
-            }
-
-        }
-
     };
 }
 
 #[macro_export]
 macro_rules! op_enum {
     ([data_type = $data_ty:ty, attr = $attr:ty, constraint = $constraint:ty] $name:ident = $($variant:ident),*) => {
-        #[derive(PartialEq, Debug)]
+        #[derive(PartialEq, Debug, Clone)]
         pub enum $name {
             $($variant($variant)),*
         }
@@ -420,11 +267,21 @@ macro_rules! op_enum {
                     $($name::$variant(inner) => inner.get_defs()),*
                 }
             }
+            fn set_defs(&mut self, defs: Vec<(String, Vec<Option<irony::EntityId>>)>) -> () {
+                match self {
+                    $($name::$variant(inner) => inner.set_defs(defs)),*
+                }
+            }
             fn get_uses(&self) -> Vec<(String, Vec<Option<irony::EntityId>>)> {
                 match self {
                     $($name::$variant(inner) => inner.get_uses()),*
                 }
             }
+            fn set_uses(&mut self, uses: Vec<(String, Vec<Option<irony::EntityId>>)>) -> () {
+                match self {
+                    $($name::$variant(inner) => inner.set_uses(uses)),*
+                }
+            }
 
             fn get_attrs(&self) -> Vec<(String, Self::AttributeT)> {
                 match self {
@@ -473,6 +330,12 @@ macro_rules! op_enum {
                 }
             }
 
+            fn set_regions(&mut self, regions: Vec<(String, Vec<irony::RegionId>)>) -> () {
+                match self {
+                    $($name::$variant(inner) => inner.set_regions(regions)),*
+                }
+            }
+
 
 
             fn use_region(&self, region: irony::RegionId) -> bool{
@@ -487,6 +350,63 @@ macro_rules! op_enum {
                 }
             }
 
+            fn get_symbol(&self) -> Option<irony::Symbol> {
+                match self {
+                    $($name::$variant(inner) => inner.get_symbol()),*
+                }
+            }
+
+            fn set_symbol(&mut self, symbol: irony::Symbol) {
+                match self {
+                    $($name::$variant(inner) => inner.set_symbol(symbol)),*
+                }
+            }
+
+            fn get_loc(&self) -> Option<irony::Loc> {
+                match self {
+                    $($name::$variant(inner) => inner.get_loc()),*
+                }
+            }
+
+            fn set_loc(&mut self, loc: Option<irony::Loc>) {
+                match self {
+                    $($name::$variant(inner) => inner.set_loc(loc)),*
+                }
+            }
+
+            fn get_traits(&self) -> Vec<irony::OpTrait> {
+                match self {
+                    $($name::$variant(inner) => inner.get_traits()),*
+                }
+            }
+
+            fn get_dialect(&self) -> Option<&'static str> {
+                match self {
+                    $($name::$variant(inner) => inner.get_dialect()),*
+                }
+            }
+
+            fn get_registered_ops() -> Vec<irony::OpInfo> {
+                let mut ops = vec![];
+                $(ops.extend(<$variant as irony::Op>::get_registered_ops());)*
+                ops
+            }
+
+            fn from_parts(
+                name: &str,
+                defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                attrs: Vec<(String, Self::AttributeT)>,
+                regions: Vec<(String, Vec<irony::RegionId>)>,
+            ) -> Option<Self> {
+                static OPS: std::sync::OnceLock<Vec<irony::OpInfo>> = std::sync::OnceLock::new();
+                let ops = OPS.get_or_init(<Self as irony::Op>::get_registered_ops);
+                match ops.iter().find(|info| info.name == name)?.type_name {
+                    $(stringify!($variant) => $variant::from_parts(name, defs, uses, attrs, regions).map(|op| op.into()),)*
+                    _ => None,
+                }
+            }
+
             fn get_printer(&self) -> Self::PrinterT {
                 match self {
                     $($name::$variant(inner) => inner.get_printer().into()),*
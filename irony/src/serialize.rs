@@ -0,0 +1,736 @@
+use crate::{DialectRegistry, Entity, EntityId, Loc, Op, OpId, Region, RegionId};
+
+/// Version of the serialized format, bumped whenever the layout changes. Newer versions
+/// can read all the older ones.
+///
+/// 2: region arguments
+/// 3: symbol table regions
+/// 4: op locations
+/// 5: ops named by their qualified name
+/// 6: the `attrs` of `hw.aggregate_constant` renamed to `values`
+pub const FORMAT_VERSION: u64 = 6;
+
+/// Op attributes renamed after they were written, as the op, the old and new names and
+/// the first version using the new name
+const RENAMED_ATTRS: &[(&str, &str, &str, u64)] =
+    &[("hw.aggregate_constant", "attrs", "values", 6)];
+
+const BINARY_MAGIC: &[u8; 4] = b"IRNY";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SerializeError {
+    /// the input is not valid JSON or binary
+    Syntax(String),
+    /// the input was written by a newer, unknown version of the format
+    Version(u64),
+    /// the input is well-formed but does not describe an environment
+    Malformed(String),
+}
+
+impl std::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SerializeError::Syntax(msg) => write!(f, "syntax error: {}", msg),
+            SerializeError::Version(version) => write!(
+                f,
+                "format version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            ),
+            SerializeError::Malformed(msg) => write!(f, "malformed input: {}", msg),
+        }
+    }
+}
+
+fn malformed<T>(msg: impl Into<String>) -> Result<T, SerializeError> {
+    Err(SerializeError::Malformed(msg.into()))
+}
+
+/// Self-describing tree that both the JSON and the binary formats encode
+#[derive(Clone, Debug, PartialEq)]
+pub enum IrValue {
+    Null,
+    Bool(bool),
+    UInt(u64),
+    Str(String),
+    List(Vec<IrValue>),
+    Map(Vec<(String, IrValue)>),
+}
+
+impl IrValue {
+    pub fn get(&self, key: &str) -> Option<&IrValue> {
+        match self {
+            IrValue::Map(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn field(&self, key: &str) -> Result<&IrValue, SerializeError> {
+        self.get(key)
+            .ok_or_else(|| SerializeError::Malformed(format!("missing field `{}`", key)))
+    }
+
+    pub fn as_bool(&self) -> Result<bool, SerializeError> {
+        match self {
+            IrValue::Bool(b) => Ok(*b),
+            _ => malformed(format!("expect a bool, found {:?}", self)),
+        }
+    }
+
+    pub fn as_uint(&self) -> Result<u64, SerializeError> {
+        match self {
+            IrValue::UInt(x) => Ok(*x),
+            _ => malformed(format!("expect an unsigned integer, found {:?}", self)),
+        }
+    }
+
+    pub fn as_usize(&self) -> Result<usize, SerializeError> { Ok(self.as_uint()? as usize) }
+
+    pub fn as_str(&self) -> Result<&str, SerializeError> {
+        match self {
+            IrValue::Str(s) => Ok(s),
+            _ => malformed(format!("expect a string, found {:?}", self)),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&Vec<IrValue>, SerializeError> {
+        match self {
+            IrValue::List(l) => Ok(l),
+            _ => malformed(format!("expect a list, found {:?}", self)),
+        }
+    }
+
+    pub fn as_map(&self) -> Result<&Vec<(String, IrValue)>, SerializeError> {
+        match self {
+            IrValue::Map(m) => Ok(m),
+            _ => malformed(format!("expect a map, found {:?}", self)),
+        }
+    }
+
+    /// Pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out, 0);
+        out
+    }
+
+    fn write_json(&self, out: &mut String, indent: usize) {
+        let pad = |out: &mut String, indent: usize| out.push_str(&"  ".repeat(indent));
+        match self {
+            IrValue::Null => out.push_str("null"),
+            IrValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            IrValue::UInt(x) => out.push_str(&x.to_string()),
+            IrValue::Str(s) => write_json_str(out, s),
+            IrValue::List(items) if items.is_empty() => out.push_str("[]"),
+            IrValue::List(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    pad(out, indent + 1);
+                    item.write_json(out, indent + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                pad(out, indent);
+                out.push(']');
+            },
+            IrValue::Map(fields) if fields.is_empty() => out.push_str("{}"),
+            IrValue::Map(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    pad(out, indent + 1);
+                    write_json_str(out, key);
+                    out.push_str(": ");
+                    value.write_json(out, indent + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                pad(out, indent);
+                out.push('}');
+            },
+        }
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, SerializeError> {
+        let mut parser = JsonParser { chars: input.chars().collect(), pos: 0, depth: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.chars.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// Compact binary encoding: a tag byte per value, LEB128 lengths and integers
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.write_bytes(&mut out);
+        out
+    }
+
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        match self {
+            IrValue::Null => out.push(0),
+            IrValue::Bool(false) => out.push(1),
+            IrValue::Bool(true) => out.push(2),
+            IrValue::UInt(x) => {
+                out.push(3);
+                write_varint(out, *x);
+            },
+            IrValue::Str(s) => {
+                out.push(4);
+                write_varint(out, s.len() as u64);
+                out.extend_from_slice(s.as_bytes());
+            },
+            IrValue::List(items) => {
+                out.push(5);
+                write_varint(out, items.len() as u64);
+                for item in items {
+                    item.write_bytes(out);
+                }
+            },
+            IrValue::Map(fields) => {
+                out.push(6);
+                write_varint(out, fields.len() as u64);
+                for (key, value) in fields {
+                    write_varint(out, key.len() as u64);
+                    out.extend_from_slice(key.as_bytes());
+                    value.write_bytes(out);
+                }
+            },
+        }
+    }
+
+    pub fn from_bytes(input: &[u8]) -> Result<Self, SerializeError> {
+        let mut reader = ByteReader { bytes: input, pos: 0, depth: 0 };
+        let value = reader.read_value()?;
+        if reader.pos != input.len() {
+            return Err(SerializeError::Syntax("trailing bytes".to_owned()));
+        }
+        Ok(value)
+    }
+}
+
+fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How deep lists and maps may nest in a loaded value, so that a hostile file cannot
+/// overflow the stack
+const MAX_DEPTH: usize = 256;
+
+fn too_deep() -> SerializeError { SerializeError::Syntax("nesting too deep".to_owned()) }
+
+struct JsonParser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl JsonParser {
+    fn error(&self, msg: &str) -> SerializeError {
+        SerializeError::Syntax(format!("{} at character {}", msg, self.pos))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SerializeError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expect `{}`", c)))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: IrValue) -> Result<IrValue, SerializeError> {
+        let end = self.pos + word.len();
+        if end <= self.chars.len() && self.chars[self.pos..end].iter().copied().eq(word.chars()) {
+            self.pos = end;
+            Ok(value)
+        } else {
+            Err(self.error("unknown keyword"))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<IrValue, SerializeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(too_deep());
+        }
+        self.depth += 1;
+        let value = self.parse_nested_value();
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_nested_value(&mut self) -> Result<IrValue, SerializeError> {
+        match self.peek() {
+            Some('n') => self.keyword("null", IrValue::Null),
+            Some('t') => self.keyword("true", IrValue::Bool(true)),
+            Some('f') => self.keyword("false", IrValue::Bool(false)),
+            Some('"') => Ok(IrValue::Str(self.parse_str()?)),
+            Some(c) if c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() {
+                    self.pos += 1;
+                }
+                let digits = self.chars[start..self.pos].iter().collect::<String>();
+                digits.parse().map(IrValue::UInt).map_err(|_| self.error("integer overflow"))
+            },
+            Some('[') => {
+                self.pos += 1;
+                let mut items = vec![];
+                if self.peek() == Some(']') {
+                    self.pos += 1;
+                    return Ok(IrValue::List(items));
+                }
+                loop {
+                    items.push(self.parse_value()?);
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {
+                            self.pos += 1;
+                            return Ok(IrValue::List(items));
+                        },
+                        _ => return Err(self.error("expect `,` or `]`")),
+                    }
+                }
+            },
+            Some('{') => {
+                self.pos += 1;
+                let mut fields = vec![];
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(IrValue::Map(fields));
+                }
+                loop {
+                    if self.peek() != Some('"') {
+                        return Err(self.error("expect a key"));
+                    }
+                    let key = self.parse_str()?;
+                    self.expect(':')?;
+                    fields.push((key, self.parse_value()?));
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => {
+                            self.pos += 1;
+                            return Ok(IrValue::Map(fields));
+                        },
+                        _ => return Err(self.error("expect `,` or `}`")),
+                    }
+                }
+            },
+            _ => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn parse_str(&mut self) -> Result<String, SerializeError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let Some(&c) = self.chars.get(self.pos) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let Some(&escaped) = self.chars.get(self.pos) else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => s.push(escaped),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let hex = self
+                                .chars
+                                .get(self.pos..self.pos + 4)
+                                .map(|hex| hex.iter().collect::<String>())
+                                .ok_or_else(|| self.error("bad unicode escape"))?;
+                            self.pos += 4;
+                            let c = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("bad unicode escape"))?;
+                            s.push(c);
+                        },
+                        _ => return Err(self.error("bad escape")),
+                    }
+                },
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut x: u64) {
+    loop {
+        let byte = (x & 0x7f) as u8;
+        x >>= 7;
+        if x == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn eof() -> SerializeError { SerializeError::Syntax("unexpected end of input".to_owned()) }
+
+    fn read_byte(&mut self) -> Result<u8, SerializeError> {
+        let byte = *self.bytes.get(self.pos).ok_or_else(Self::eof)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, SerializeError> {
+        let mut x = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            x |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(x);
+            }
+        }
+        Err(SerializeError::Syntax("varint overflow".to_owned()))
+    }
+
+    fn read_str(&mut self) -> Result<String, SerializeError> {
+        let len = self.read_varint()? as usize;
+        let end = self.pos.checked_add(len).ok_or_else(Self::eof)?;
+        let bytes = self.bytes.get(self.pos..end).ok_or_else(Self::eof)?;
+        self.pos += len;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| SerializeError::Syntax("invalid utf-8".to_owned()))
+    }
+
+    fn read_value(&mut self) -> Result<IrValue, SerializeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(too_deep());
+        }
+        self.depth += 1;
+        let value = self.read_nested_value();
+        self.depth -= 1;
+        value
+    }
+
+    fn read_nested_value(&mut self) -> Result<IrValue, SerializeError> {
+        match self.read_byte()? {
+            0 => Ok(IrValue::Null),
+            1 => Ok(IrValue::Bool(false)),
+            2 => Ok(IrValue::Bool(true)),
+            3 => Ok(IrValue::UInt(self.read_varint()?)),
+            4 => Ok(IrValue::Str(self.read_str()?)),
+            5 => {
+                let len = self.read_varint()?;
+                (0..len).map(|_| self.read_value()).collect::<Result<_, _>>().map(IrValue::List)
+            },
+            6 => {
+                let len = self.read_varint()?;
+                (0..len)
+                    .map(|_| Ok((self.read_str()?, self.read_value()?)))
+                    .collect::<Result<_, _>>()
+                    .map(IrValue::Map)
+            },
+            tag => Err(SerializeError::Syntax(format!("unknown tag {}", tag))),
+        }
+    }
+}
+
+/// Conversion of data types and attributes from and into [`IrValue`]
+pub trait IrSerde: Sized {
+    fn to_ir_value(&self) -> IrValue;
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError>;
+}
+
+fn ids_to_value(fields: Vec<(String, Vec<Option<EntityId>>)>) -> IrValue {
+    IrValue::Map(
+        fields
+            .into_iter()
+            .map(|(name, ids)| {
+                let ids = ids
+                    .into_iter()
+                    .map(|id| id.map_or(IrValue::Null, |id| IrValue::UInt(id.0 as u64)))
+                    .collect();
+                (name, IrValue::List(ids))
+            })
+            .collect(),
+    )
+}
+
+/// Defs or uses of an op, grouped by the name of their field
+type NamedIds = Vec<(String, Vec<Option<EntityId>>)>;
+
+fn ids_from_value(value: &IrValue) -> Result<NamedIds, SerializeError> {
+    value
+        .as_map()?
+        .iter()
+        .map(|(name, ids)| {
+            let ids = ids
+                .as_list()?
+                .iter()
+                .map(|id| match id {
+                    IrValue::Null => Ok(None),
+                    id => Ok(Some(EntityId(id.as_usize()?))),
+                })
+                .collect::<Result<_, SerializeError>>()?;
+            Ok((name.to_owned(), ids))
+        })
+        .collect()
+}
+
+fn attrs_to_value<A: IrSerde>(attrs: Vec<(String, A)>) -> IrValue {
+    IrValue::Map(
+        attrs
+            .into_iter()
+            .filter(|(name, _)| name != "none")
+            .map(|(name, attr)| (name, attr.to_ir_value()))
+            .collect(),
+    )
+}
+
+fn attrs_from_value<A: IrSerde>(value: &IrValue) -> Result<Vec<(String, A)>, SerializeError> {
+    value
+        .as_map()?
+        .iter()
+        .map(|(name, attr)| Ok((name.to_owned(), A::from_ir_value(attr)?)))
+        .collect()
+}
+
+fn parent_to_value(parent: Option<RegionId>) -> IrValue {
+    parent.map_or(IrValue::Null, |parent| IrValue::UInt(parent.0 as u64))
+}
+
+fn parent_from_value(value: Option<&IrValue>) -> Result<Option<RegionId>, SerializeError> {
+    match value {
+        None | Some(IrValue::Null) => Ok(None),
+        Some(parent) => Ok(Some(RegionId(parent.as_usize()?))),
+    }
+}
+
+fn uints<T>(ids: &[T], f: impl Fn(&T) -> usize) -> IrValue {
+    IrValue::List(ids.iter().map(|id| IrValue::UInt(f(id) as u64)).collect())
+}
+
+/// The three tables of an environment, as stored in the serialized format
+pub struct IrTables<O, T> {
+    pub ops: Vec<(usize, O)>,
+    pub entities: Vec<(usize, T)>,
+    pub regions: Vec<(usize, Region)>,
+}
+
+impl<O, T> IrTables<O, T>
+where
+    O: Op,
+    T: Entity,
+    O::AttributeT: IrSerde,
+    T::DataTypeT: IrSerde,
+    T::AttributeT: IrSerde,
+{
+    pub fn to_ir_value<'a>(
+        ops: impl Iterator<Item = (&'a usize, &'a O)>,
+        entities: impl Iterator<Item = (&'a usize, &'a T)>,
+        regions: impl Iterator<Item = (&'a usize, &'a Region)>,
+    ) -> IrValue
+    where
+        O: 'a,
+        T: 'a,
+    {
+        let entities = entities
+            .map(|(id, entity)| {
+                IrValue::Map(vec![
+                    ("id".to_owned(), IrValue::UInt(*id as u64)),
+                    ("kind".to_owned(), IrValue::Str(entity.get_entity_name())),
+                    ("parent".to_owned(), parent_to_value(entity.get_parent())),
+                    (
+                        "dtype".to_owned(),
+                        entity.get_dtype().map_or(IrValue::Null, |dtype| dtype.to_ir_value()),
+                    ),
+                    ("attrs".to_owned(), attrs_to_value(entity.get_attrs())),
+                ])
+            })
+            .collect();
+
+        let regions = regions
+            .map(|(id, region)| {
+                IrValue::Map(vec![
+                    ("id".to_owned(), IrValue::UInt(*id as u64)),
+                    ("isolated".to_owned(), IrValue::Bool(region.isolated)),
+                    ("ops".to_owned(), uints(&region.op_children, |op| op.0)),
+                    ("entities".to_owned(), uints(&region.entity_children, |entity| entity.0)),
+                    ("arguments".to_owned(), uints(&region.arguments, |entity| entity.0)),
+                    ("symbol_table".to_owned(), IrValue::Bool(region.symbol_table)),
+                ])
+            })
+            .collect();
+
+        let ops = ops
+            .map(|(id, op)| {
+                let regions = op
+                    .get_regions()
+                    .into_iter()
+                    .map(|(name, regions)| (name, uints(&regions, |region| region.0)))
+                    .collect();
+                IrValue::Map(vec![
+                    ("id".to_owned(), IrValue::UInt(*id as u64)),
+                    ("name".to_owned(), IrValue::Str(op.get_op_name())),
+                    ("parent".to_owned(), parent_to_value(op.get_parent())),
+                    ("defs".to_owned(), ids_to_value(op.get_defs())),
+                    ("uses".to_owned(), ids_to_value(op.get_uses())),
+                    ("attrs".to_owned(), attrs_to_value(op.get_attrs())),
+                    ("regions".to_owned(), IrValue::Map(regions)),
+                    ("loc".to_owned(), op.get_loc().map_or(IrValue::Null, |loc| loc.to_ir_value())),
+                ])
+            })
+            .collect();
+
+        IrValue::Map(vec![
+            ("format".to_owned(), IrValue::Str("irony".to_owned())),
+            ("version".to_owned(), IrValue::UInt(FORMAT_VERSION)),
+            ("entities".to_owned(), IrValue::List(entities)),
+            ("regions".to_owned(), IrValue::List(regions)),
+            ("ops".to_owned(), IrValue::List(ops)),
+        ])
+    }
+
+    pub fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        if value.field("format")?.as_str()? != "irony" {
+            return malformed("not an irony environment");
+        }
+        let version = value.field("version")?.as_uint()?;
+        if version > FORMAT_VERSION {
+            return Err(SerializeError::Version(version));
+        }
+
+        let mut tables = Self { ops: vec![], entities: vec![], regions: vec![] };
+
+        for entity in value.field("entities")?.as_list()? {
+            let kind = entity.field("kind")?.as_str()?;
+            let dtype = match entity.get("dtype") {
+                None | Some(IrValue::Null) => None,
+                Some(dtype) => Some(T::DataTypeT::from_ir_value(dtype)?),
+            };
+            let attrs = attrs_from_value(entity.field("attrs")?)?;
+            let Some(mut parsed) = T::from_parts(kind, dtype, attrs) else {
+                return malformed(format!("unknown entity kind `{}`", kind));
+            };
+            parsed.set_parent(parent_from_value(entity.get("parent"))?);
+            tables.entities.push((entity.field("id")?.as_usize()?, parsed));
+        }
+
+        for region in value.field("regions")?.as_list()? {
+            let mut parsed = Region::new(region.field("isolated")?.as_bool()?);
+            for op in region.field("ops")?.as_list()? {
+                parsed.add_op_child(OpId(op.as_usize()?));
+            }
+            for entity in region.field("entities")?.as_list()? {
+                parsed.add_entity_child(EntityId(entity.as_usize()?));
+            }
+            // absent before version 2
+            if let Some(arguments) = region.get("arguments") {
+                for argument in arguments.as_list()? {
+                    parsed.add_argument(EntityId(argument.as_usize()?));
+                }
+            }
+            // absent before version 3
+            if let Some(symbol_table) = region.get("symbol_table") {
+                parsed.symbol_table = symbol_table.as_bool()?;
+            }
+            tables.regions.push((region.field("id")?.as_usize()?, parsed));
+        }
+
+        let registry = DialectRegistry::of::<O>();
+        for op in value.field("ops")?.as_list()? {
+            let mut name = op.field("name")?.as_str()?;
+            // named by their type before version 5
+            if version < 5 {
+                name = registry.qualify(name).unwrap_or(name);
+            }
+            let regions = op
+                .field("regions")?
+                .as_map()?
+                .iter()
+                .map(|(name, regions)| {
+                    let regions = regions
+                        .as_list()?
+                        .iter()
+                        .map(|region| Ok(RegionId(region.as_usize()?)))
+                        .collect::<Result<_, SerializeError>>()?;
+                    Ok((name.to_owned(), regions))
+                })
+                .collect::<Result<_, SerializeError>>()?;
+            let mut attrs = attrs_from_value(op.field("attrs")?)?;
+            for &(_, old, new, since) in RENAMED_ATTRS.iter().filter(|(op, ..)| *op == name) {
+                if version < since && attrs.iter().all(|(attr, _)| attr != new) {
+                    attrs.iter_mut().filter(|(attr, _)| attr == old).for_each(|(attr, _)| {
+                        *attr = new.to_owned();
+                    });
+                }
+            }
+            let Some(mut parsed) = O::from_parts(
+                name,
+                ids_from_value(op.field("defs")?)?,
+                ids_from_value(op.field("uses")?)?,
+                attrs,
+                regions,
+            ) else {
+                return malformed(format!("unknown op `{}`", name));
+            };
+            parsed.set_parent(parent_from_value(op.get("parent"))?);
+            // absent before version 4
+            if let Some(loc) = op.get("loc").filter(|loc| **loc != IrValue::Null) {
+                parsed.set_loc(Some(Loc::from_ir_value(loc)?));
+            }
+            tables.ops.push((op.field("id")?.as_usize()?, parsed));
+        }
+
+        Ok(tables)
+    }
+}
+
+/// Binary files start with a magic number and the format version
+pub fn encode_binary(value: &IrValue) -> Vec<u8> {
+    let mut out = BINARY_MAGIC.to_vec();
+    write_varint(&mut out, FORMAT_VERSION);
+    value.write_bytes(&mut out);
+    out
+}
+
+pub fn decode_binary(input: &[u8]) -> Result<IrValue, SerializeError> {
+    if !input.starts_with(BINARY_MAGIC) {
+        return Err(SerializeError::Syntax("not an irony binary".to_owned()));
+    }
+    let mut reader = ByteReader { bytes: input, pos: BINARY_MAGIC.len(), depth: 0 };
+    let version = reader.read_varint()?;
+    if version > FORMAT_VERSION {
+        return Err(SerializeError::Version(version));
+    }
+    IrValue::from_bytes(&input[reader.pos..])
+}
@@ -0,0 +1,44 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::Environ;
+
+/// Guard of an open transaction, see [`Environ::transaction`].
+///
+/// The environment is reached through the guard. Dropping it without calling
+/// [`Transaction::commit`] rolls the changes back.
+pub struct Transaction<'env, E: Environ> {
+    env: &'env mut E,
+    open: bool,
+}
+
+impl<'env, E: Environ> Transaction<'env, E> {
+    pub(crate) fn new(env: &'env mut E) -> Self { Self { env, open: true } }
+
+    pub fn commit(mut self) {
+        self.open = false;
+        self.env.commit_transaction();
+    }
+
+    pub fn rollback(mut self) {
+        self.open = false;
+        self.env.rollback_transaction();
+    }
+}
+
+impl<'env, E: Environ> Deref for Transaction<'env, E> {
+    type Target = E;
+
+    fn deref(&self) -> &Self::Target { self.env }
+}
+
+impl<'env, E: Environ> DerefMut for Transaction<'env, E> {
+    fn deref_mut(&mut self) -> &mut Self::Target { self.env }
+}
+
+impl<'env, E: Environ> Drop for Transaction<'env, E> {
+    fn drop(&mut self) {
+        if self.open {
+            self.env.rollback_transaction();
+        }
+    }
+}
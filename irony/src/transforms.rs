@@ -0,0 +1,223 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::{Hash, Hasher};
+
+use crate::{
+    Entity, EntityId, Environ, FxHashMap, FxHashSet, FxHasher, Loc, Op, OpId, OpTrait, RegionId,
+};
+
+fn flat_defs(op: &impl Op) -> Vec<Option<EntityId>> {
+    op.get_defs().into_iter().flat_map(|(_, defs)| defs).collect()
+}
+
+/// whether `op` can stand in for `other`: the same name, attributes and uses (in any order
+/// if commutative), and defs of the same dtypes
+fn equivalent<E: Environ>(env: &E, op: OpId, other: OpId) -> bool {
+    let (op, other) = (env.get_op(op), env.get_op(other));
+    if op.get_op_name() != other.get_op_name() || op.get_attrs() != other.get_attrs() {
+        return false;
+    }
+    let same_uses = if op.is_commutative() {
+        let sorted = |op: &E::OpT| {
+            let mut uses = op.get_uses().into_iter().flat_map(|(_, uses)| uses).collect::<Vec<_>>();
+            uses.sort_by_key(|used| used.map(|entity| entity.0));
+            uses
+        };
+        sorted(op) == sorted(other)
+    } else {
+        op.get_uses() == other.get_uses()
+    };
+    let (defs, other_defs) = (flat_defs(op), flat_defs(other));
+    same_uses
+        && defs.len() == other_defs.len()
+        && defs.iter().zip(other_defs.iter()).all(|pair| match pair {
+            (Some(def), Some(other)) => {
+                env.get_entity(*def).get_dtype() == env.get_entity(*other).get_dtype()
+            },
+            (def, other) => def == other,
+        })
+}
+
+/// the hash of the name and uses of `op`, the uses sorted if it is commutative, under which
+/// the ops it may be equivalent to are found
+fn cse_key<E: Environ>(env: &E, op: OpId) -> u64 {
+    let op = env.get_op(op);
+    let mut uses = op.get_uses().into_iter().flat_map(|(_, uses)| uses).collect::<Vec<_>>();
+    if op.is_commutative() {
+        uses.sort_by_key(|used| used.map(|entity| entity.0));
+    }
+    let mut hasher = FxHasher::default();
+    (op.get_op_name(), uses).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn cse_region<E: Environ>(
+    env: &mut E, region: RegionId, mut known: FxHashMap<u64, Vec<OpId>>,
+) -> usize {
+    let mut erased = 0;
+    for op in env.get_region(region).get_op_children() {
+        let regions = env.get_op(op).get_regions();
+        if !regions.is_empty() {
+            let outer = if env.get_op(op).is_isolated_from_above() {
+                FxHashMap::default()
+            } else {
+                known.clone()
+            };
+            for region in regions.into_iter().flat_map(|(_, regions)| regions) {
+                erased += cse_region(env, region, outer.clone());
+            }
+            continue;
+        }
+        if !env.get_op(op).is_pure() {
+            continue;
+        }
+        let candidates = known.entry(cse_key(env, op)).or_default();
+        let Some(kept) = candidates.iter().copied().find(|&kept| equivalent(env, kept, op)) else {
+            candidates.push(op);
+            continue;
+        };
+        let defs = flat_defs(env.get_op(op)).into_iter().zip(flat_defs(env.get_op(kept)));
+        for (def, kept_def) in defs {
+            if let (Some(def), Some(kept_def)) = (def, kept_def) {
+                env.replace_all_uses(def, kept_def);
+            }
+        }
+        let loc = Loc::fuse([env.get_op_loc(kept), env.get_op_loc(op)]);
+        env.set_op_loc(kept, loc);
+        env.delete_op(op);
+        erased += 1;
+    }
+    erased
+}
+
+/// Merge each pure op of `region` into an equivalent one before it, in the same region or
+/// an enclosing one up to the closest op isolated from above. Returns the number of ops
+/// erased.
+pub fn cse<E: Environ>(env: &mut E, region: RegionId) -> usize {
+    cse_region(env, region, FxHashMap::default())
+}
+
+fn is_dead<E: Environ>(env: &E, op: OpId) -> bool {
+    env.get_op(op).is_pure()
+        && flat_defs(env.get_op(op)).into_iter().flatten().all(|def| env.get_uses(def).is_empty())
+}
+
+/// Erase the pure ops of `region`, nested regions included, whose defs are all unused,
+/// until none is left. Returns the number of ops erased.
+pub fn dce<E: Environ>(env: &mut E, region: RegionId) -> usize {
+    let mut erased = 0;
+    let mut worklist = env.walk_ops(Some(region));
+    let inside = worklist.iter().copied().collect::<FxHashSet<_>>();
+    while let Some(op) = worklist.pop() {
+        if !env.contains_op(op) || !is_dead(env, op) {
+            continue;
+        }
+        let uses = env.get_op(op).get_uses().into_iter().flat_map(|(_, uses)| uses);
+        let used = uses.flatten().collect::<Vec<_>>();
+        env.delete_op(op);
+        erased += 1;
+        // the defs of what it used may have lost their last user
+        for entity in used {
+            worklist.extend(env.get_defs(entity).into_iter().filter(|def| inside.contains(def)));
+        }
+    }
+    erased
+}
+
+/// Bring `region` to a canonical form: the uses of commutative ops are sorted, then `cse`
+/// and `dce` run until nothing changes. Returns the number of ops rewritten or erased.
+pub fn canonicalize<E: Environ>(env: &mut E, region: RegionId) -> usize {
+    let mut changed = 0;
+    for op in env.walk_ops(Some(region)) {
+        if !env.get_op(op).is_commutative() {
+            continue;
+        }
+        let uses = env.get_op(op).get_uses();
+        let sorted = uses
+            .iter()
+            .map(|(name, uses)| {
+                let mut uses = uses.to_owned();
+                uses.sort_by_key(|used| used.map(|entity| entity.0));
+                (name.to_owned(), uses)
+            })
+            .collect::<Vec<_>>();
+        if sorted != uses {
+            env.get_op_entry(op).and_modify(|op| op.set_uses(sorted));
+            changed += 1;
+        }
+    }
+    loop {
+        let erased = cse(env, region) + dce(env, region);
+        if erased == 0 {
+            return changed;
+        }
+        changed += erased;
+    }
+}
+
+/// the positions in `region` of the ops each op of it waits for: the ops defining what it
+/// or its nested ops use, except for sequential users, and every other op for a terminator
+fn region_deps<E: Environ>(env: &E, region: RegionId, ops: &[OpId]) -> Vec<Vec<usize>> {
+    let positions =
+        ops.iter().enumerate().map(|(index, &op)| (op, index)).collect::<FxHashMap<_, _>>();
+    let position = |op: OpId| positions.get(&op).copied();
+    ops.iter()
+        .enumerate()
+        .map(|(index, &op)| {
+            if env.get_op(op).is_terminator() {
+                return (0..ops.len()).filter(|&other| other != index).collect();
+            }
+            let nested = env.get_op(op).get_regions().into_iter().flat_map(|(_, regions)| regions);
+            let nested = nested.flat_map(|region| env.walk_ops(Some(region)));
+            let users = std::iter::once(op).chain(nested);
+            let mut deps = vec![];
+            for user in users.filter(|&user| !env.get_op(user).has_trait(OpTrait::Sequential)) {
+                let uses = env.get_op(user).get_uses().into_iter().flat_map(|(_, uses)| uses);
+                for def in uses.flatten().flat_map(|used| env.get_defs(used)) {
+                    let dep = env.get_ancestor_in(region, def).and_then(position);
+                    if let Some(dep) = dep.filter(|&dep| dep != index && !deps.contains(&dep)) {
+                        deps.push(dep);
+                    }
+                }
+            }
+            deps
+        })
+        .collect()
+}
+
+/// Reorder the ops of `region` so that each one follows the ops defining the entities it
+/// uses, keeping the current order where possible. Uses by sequential ops, such as the
+/// feedback of a register, are not ordered. On a dependency cycle the region is left as
+/// is and the ops which could not be placed are returned.
+pub fn sort_topologically<E: Environ>(env: &mut E, region: RegionId) -> Result<(), Vec<OpId>> {
+    let ops = env.get_region(region).get_op_children();
+    let deps = region_deps(env, region, &ops);
+    let mut waiting = deps.iter().map(|deps| deps.len()).collect::<Vec<_>>();
+    let mut users = vec![vec![]; ops.len()];
+    for (index, deps) in deps.iter().enumerate() {
+        for &dep in deps {
+            users[dep].push(index);
+        }
+    }
+
+    let mut ready =
+        (0..ops.len()).filter(|&index| waiting[index] == 0).map(Reverse).collect::<BinaryHeap<_>>();
+    let mut sorted = vec![];
+    while let Some(Reverse(index)) = ready.pop() {
+        sorted.push(ops[index]);
+        for &user in users[index].iter() {
+            waiting[user] -= 1;
+            if waiting[user] == 0 {
+                ready.push(Reverse(user));
+            }
+        }
+    }
+    if sorted.len() < ops.len() {
+        let unplaced = (0..ops.len()).filter(|&index| waiting[index] > 0);
+        return Err(unplaced.map(|index| ops[index]).collect());
+    }
+    if sorted != ops {
+        env.get_region_entry(region).and_modify(|region| region.op_children = sorted);
+    }
+    Ok(())
+}
@@ -0,0 +1,334 @@
+use irony::{EntityId, FxHashMap};
+
+/// A literal of an [`Aig`]: twice the variable, plus one when inverted. Variable 0 is the
+/// constant false.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AigLit(pub u32);
+
+impl AigLit {
+    pub const FALSE: AigLit = AigLit(0);
+    pub const TRUE: AigLit = AigLit(1);
+
+    pub fn var(&self) -> u32 { self.0 >> 1 }
+
+    pub fn is_inverted(&self) -> bool { self.0 & 1 == 1 }
+
+    pub fn is_const(&self) -> bool { self.var() == 0 }
+}
+
+impl std::ops::Not for AigLit {
+    type Output = AigLit;
+
+    fn not(self) -> AigLit { AigLit(self.0 ^ 1) }
+}
+
+impl From<bool> for AigLit {
+    fn from(value: bool) -> Self { if value { AigLit::TRUE } else { AigLit::FALSE } }
+}
+
+/// A bit of an entity: the entity and the index of the bit, the least significant first
+pub type AigSource = (EntityId, usize);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AigInput {
+    pub lit: AigLit,
+    pub name: String,
+    pub source: Option<AigSource>,
+}
+
+/// A latch holds a bit of a register, set to `next` at every clock edge
+#[derive(Clone, Debug, PartialEq)]
+pub struct AigLatch {
+    pub lit: AigLit,
+    pub next: AigLit,
+    pub init: bool,
+    pub name: String,
+    pub source: Option<AigSource>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AigOutput {
+    pub lit: AigLit,
+    pub name: String,
+    pub source: Option<AigSource>,
+}
+
+/// A copy of an [`Aig`] made by [`Aig::instantiate`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AigInstance(Vec<AigLit>);
+
+impl AigInstance {
+    /// the literal standing for `lit` of the copied graph
+    pub fn get(&self, lit: AigLit) -> AigLit {
+        let copy = self.0[lit.var() as usize];
+        if lit.is_inverted() { !copy } else { copy }
+    }
+}
+
+/// an and gate as numbered in AIGER: its variable and its two inputs
+type AigAnd = (u32, AigLit, AigLit);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AigNode {
+    Input,
+    Latch,
+    And(AigLit, AigLit),
+}
+
+/// An And-Inverter Graph: inputs and latches combined by two-input and gates with
+/// optionally inverted edges. Gates are hashed, so the same gate is never built twice.
+#[derive(Clone, Debug, Default)]
+pub struct Aig {
+    /// node `i` is variable `i + 1`
+    nodes: Vec<AigNode>,
+    strash: FxHashMap<(AigLit, AigLit), AigLit>,
+    pub inputs: Vec<AigInput>,
+    pub latches: Vec<AigLatch>,
+    pub outputs: Vec<AigOutput>,
+}
+
+impl Aig {
+    pub fn new() -> Self { Self::default() }
+
+    fn add_node(&mut self, node: AigNode) -> AigLit {
+        self.nodes.push(node);
+        AigLit(self.nodes.len() as u32 * 2)
+    }
+
+    pub fn add_input(&mut self, name: impl Into<String>, source: Option<AigSource>) -> AigLit {
+        let lit = self.add_node(AigNode::Input);
+        self.inputs.push(AigInput { lit, name: name.into(), source });
+        lit
+    }
+
+    /// A latch initialized to `init`, whose next state is set later by `set_latch_next`
+    pub fn add_latch(
+        &mut self, name: impl Into<String>, init: bool, source: Option<AigSource>,
+    ) -> AigLit {
+        let lit = self.add_node(AigNode::Latch);
+        let name = name.into();
+        self.latches.push(AigLatch { lit, next: AigLit::FALSE, init, name, source });
+        lit
+    }
+
+    pub fn set_latch_next(&mut self, latch: AigLit, next: AigLit) {
+        let latch = self.latches.iter_mut().find(|l| l.lit == latch).expect("not a latch");
+        latch.next = next;
+    }
+
+    pub fn add_output(&mut self, lit: AigLit, name: impl Into<String>, source: Option<AigSource>) {
+        self.outputs.push(AigOutput { lit, name: name.into(), source });
+    }
+
+    /// the inputs of the and gate `lit` is the output of, possibly inverted
+    pub fn get_and(&self, lit: AigLit) -> Option<(AigLit, AigLit)> {
+        match lit.is_const() {
+            true => None,
+            false => match self.nodes[lit.var() as usize - 1] {
+                AigNode::And(a, b) => Some((a, b)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Copy the gates into `into`, the inputs and latches replaced by `inputs` and
+    /// `latches`, in the order they were added. The result gives the literal of `into`
+    /// standing for each literal of `self`.
+    pub fn instantiate(
+        &self, into: &mut Aig, inputs: &[AigLit], latches: &[AigLit],
+    ) -> AigInstance {
+        let mut vars = vec![AigLit::FALSE; self.nodes.len() + 1];
+        for (input, &lit) in self.inputs.iter().zip(inputs) {
+            vars[input.lit.var() as usize] = lit;
+        }
+        for (latch, &lit) in self.latches.iter().zip(latches) {
+            vars[latch.lit.var() as usize] = lit;
+        }
+        let mut instance = AigInstance(vars);
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = node {
+                let (a, b) = (instance.get(*a), instance.get(*b));
+                instance.0[index + 1] = into.and(a, b);
+            }
+        }
+        instance
+    }
+
+    /// the number of variables, the constant included
+    pub fn var_count(&self) -> usize { self.nodes.len() + 1 }
+
+    /// the number of and gates
+    pub fn and_count(&self) -> usize {
+        self.nodes.iter().filter(|node| matches!(node, AigNode::And(..))).count()
+    }
+
+    pub fn and(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        if a == AigLit::FALSE || a == !b {
+            return AigLit::FALSE;
+        }
+        if a == AigLit::TRUE || a == b {
+            return b;
+        }
+        if let Some(&lit) = self.strash.get(&(a, b)) {
+            return lit;
+        }
+        let lit = self.add_node(AigNode::And(a, b));
+        self.strash.insert((a, b), lit);
+        lit
+    }
+
+    pub fn or(&mut self, a: AigLit, b: AigLit) -> AigLit { !self.and(!a, !b) }
+
+    pub fn xor(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        let (x, y) = (self.and(a, !b), self.and(!a, b));
+        self.or(x, y)
+    }
+
+    /// `cond ? then : other`
+    pub fn mux(&mut self, cond: AigLit, then: AigLit, other: AigLit) -> AigLit {
+        let (x, y) = (self.and(cond, then), self.and(!cond, other));
+        self.or(x, y)
+    }
+
+    /// Evaluate one clock cycle: the outputs and the next latch values from the input and
+    /// latch values, in the order they were added.
+    pub fn simulate(&self, inputs: &[bool], state: &[bool]) -> (Vec<bool>, Vec<bool>) {
+        let mut values = vec![false; self.nodes.len() + 1];
+        for (input, &value) in self.inputs.iter().zip(inputs) {
+            values[input.lit.var() as usize] = value;
+        }
+        for (latch, &value) in self.latches.iter().zip(state) {
+            values[latch.lit.var() as usize] = value;
+        }
+        let value = |values: &Vec<bool>, lit: AigLit| {
+            values[lit.var() as usize] ^ lit.is_inverted()
+        };
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = node {
+                values[index + 1] = value(&values, *a) && value(&values, *b);
+            }
+        }
+        let outputs = self.outputs.iter().map(|output| value(&values, output.lit)).collect();
+        let next = self.latches.iter().map(|latch| value(&values, latch.next)).collect();
+        (outputs, next)
+    }
+
+    /// the and gates reachable from the outputs and latches, numbered after the inputs and
+    /// latches as AIGER requires
+    fn numbering(&self) -> (FxHashMap<u32, u32>, Vec<AigAnd>) {
+        let mut vars = FxHashMap::default();
+        vars.insert(0, 0);
+        for (index, input) in self.inputs.iter().enumerate() {
+            vars.insert(input.lit.var(), index as u32 + 1);
+        }
+        for (index, latch) in self.latches.iter().enumerate() {
+            vars.insert(latch.lit.var(), (self.inputs.len() + index) as u32 + 1);
+        }
+
+        let mut used = vec![false; self.nodes.len() + 1];
+        let mut worklist = self.outputs.iter().map(|output| output.lit).collect::<Vec<_>>();
+        worklist.extend(self.latches.iter().map(|latch| latch.next));
+        while let Some(lit) = worklist.pop() {
+            if std::mem::replace(&mut used[lit.var() as usize], true) || lit.is_const() {
+                continue;
+            }
+            if let AigNode::And(a, b) = self.nodes[lit.var() as usize - 1] {
+                worklist.extend([a, b]);
+            }
+        }
+
+        let mut ands = vec![];
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = node {
+                if used[index + 1] {
+                    let var = (self.inputs.len() + self.latches.len() + ands.len()) as u32 + 1;
+                    vars.insert(index as u32 + 1, var);
+                    ands.push((var, *a, *b));
+                }
+            }
+        }
+        (vars, ands)
+    }
+
+    fn header(&self, format: &str, ands: usize) -> String {
+        let (i, l, o) = (self.inputs.len(), self.latches.len(), self.outputs.len());
+        format!("{} {} {} {} {} {}\n", format, i + l + ands, i, l, o, ands)
+    }
+
+    fn latch_line(&self, latch: &AigLatch, map: &impl Fn(AigLit) -> u32) -> String {
+        if latch.init {
+            format!("{} 1", map(latch.next))
+        } else {
+            map(latch.next).to_string()
+        }
+    }
+
+    fn symbols(&self) -> String {
+        let mut symbols = String::new();
+        for (index, input) in self.inputs.iter().enumerate() {
+            symbols.push_str(&format!("i{} {}\n", index, input.name));
+        }
+        for (index, latch) in self.latches.iter().enumerate() {
+            symbols.push_str(&format!("l{} {}\n", index, latch.name));
+        }
+        for (index, output) in self.outputs.iter().enumerate() {
+            symbols.push_str(&format!("o{} {}\n", index, output.name));
+        }
+        symbols
+    }
+
+    /// The AIGER ascii format (`aag`), with the names in its symbol table. Gates no output
+    /// or latch depends on are left out.
+    pub fn to_aiger_ascii(&self) -> String {
+        let (vars, ands) = self.numbering();
+        let map = |lit: AigLit| vars[&lit.var()] * 2 + lit.is_inverted() as u32;
+
+        let mut out = self.header("aag", ands.len());
+        for index in 0..self.inputs.len() {
+            out.push_str(&format!("{}\n", (index + 1) * 2));
+        }
+        for (index, latch) in self.latches.iter().enumerate() {
+            let lit = (self.inputs.len() + index + 1) * 2;
+            out.push_str(&format!("{} {}\n", lit, self.latch_line(latch, &map)));
+        }
+        for output in self.outputs.iter() {
+            out.push_str(&format!("{}\n", map(output.lit)));
+        }
+        for (var, a, b) in ands.iter() {
+            out.push_str(&format!("{} {} {}\n", var * 2, map(*a), map(*b)));
+        }
+        out.push_str(&self.symbols());
+        out
+    }
+
+    /// The AIGER binary format (`aig`): inputs and latches are implicit and the gates are
+    /// stored as deltas between literals
+    pub fn to_aiger_binary(&self) -> Vec<u8> {
+        let (vars, ands) = self.numbering();
+        let map = |lit: AigLit| vars[&lit.var()] * 2 + lit.is_inverted() as u32;
+
+        let mut out = self.header("aig", ands.len()).into_bytes();
+        for latch in self.latches.iter() {
+            out.extend(format!("{}\n", self.latch_line(latch, &map)).into_bytes());
+        }
+        for output in self.outputs.iter() {
+            out.extend(format!("{}\n", map(output.lit)).into_bytes());
+        }
+        let write_delta = |out: &mut Vec<u8>, mut delta: u32| {
+            while delta >= 0x80 {
+                out.push((delta & 0x7f) as u8 | 0x80);
+                delta >>= 7;
+            }
+            out.push(delta as u8);
+        };
+        for (var, a, b) in ands.iter() {
+            let lhs = var * 2;
+            let (rhs0, rhs1) = (map(*a).max(map(*b)), map(*a).min(map(*b)));
+            write_delta(&mut out, lhs - rhs0);
+            write_delta(&mut out, rhs0 - rhs1);
+        }
+        out.extend(self.symbols().into_bytes());
+        out
+    }
+}
@@ -0,0 +1,450 @@
+use irony::{Entity, EntityId, Environ, FxHashMap, FxHashSet, IronyError, Op, OpId};
+
+use crate::{
+    utils, Aig, AigLit, AttributeEnum, CombBinaryPredicate, CombICmpPredicate,
+    CombUnaryPredicate, CombVariadicPredicate, ConstantAttr, EntityEnum, OpEnum, StringAttr,
+};
+
+/// An op or entity of a module that cannot be turned into gates
+#[derive(Clone, Debug, PartialEq)]
+pub struct AigError {
+    pub op: Option<OpId>,
+    pub message: String,
+}
+
+impl std::fmt::Display for AigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<AigError> for IronyError {
+    fn from(err: AigError) -> Self { IronyError::Failed { op: err.op, message: err.message } }
+}
+
+type Bits = Vec<AigLit>;
+
+fn add(aig: &mut Aig, a: &[AigLit], b: &[AigLit], carry_in: AigLit) -> (Bits, AigLit) {
+    let mut carry = carry_in;
+    let mut sum = vec![];
+    for (&x, &y) in a.iter().zip(b) {
+        let half = aig.xor(x, y);
+        sum.push(aig.xor(half, carry));
+        let (both, propagated) = (aig.and(x, y), aig.and(half, carry));
+        carry = aig.or(both, propagated);
+    }
+    (sum, carry)
+}
+
+fn not(bits: &[AigLit]) -> Bits { bits.iter().map(|&bit| !bit).collect() }
+
+fn sub(aig: &mut Aig, a: &[AigLit], b: &[AigLit]) -> Bits { add(aig, a, &not(b), AigLit::TRUE).0 }
+
+fn neg(aig: &mut Aig, a: &[AigLit]) -> Bits { sub(aig, &vec![AigLit::FALSE; a.len()], a) }
+
+fn mul(aig: &mut Aig, a: &[AigLit], b: &[AigLit]) -> Bits {
+    let mut product = vec![AigLit::FALSE; a.len()];
+    // bits of `b` past the width of `a` only shift partial products out
+    for (shift, &y) in b.iter().take(a.len()).enumerate() {
+        let mut partial = vec![AigLit::FALSE; shift];
+        partial.extend(a.iter().take(a.len() - shift).map(|&x| aig.and(x, y)));
+        product = add(aig, &product, &partial, AigLit::FALSE).0;
+    }
+    product
+}
+
+fn ult(aig: &mut Aig, a: &[AigLit], b: &[AigLit]) -> AigLit {
+    // a - b borrows exactly when a < b
+    !add(aig, a, &not(b), AigLit::TRUE).1
+}
+
+fn slt(aig: &mut Aig, a: &[AigLit], b: &[AigLit]) -> AigLit {
+    let flip = |bits: &[AigLit]| {
+        let mut bits = bits.to_vec();
+        if let Some(sign) = bits.last_mut() {
+            *sign = !*sign;
+        }
+        bits
+    };
+    ult(aig, &flip(a), &flip(b))
+}
+
+fn eq(aig: &mut Aig, a: &[AigLit], b: &[AigLit]) -> AigLit {
+    let mut all = AigLit::TRUE;
+    for (&x, &y) in a.iter().zip(b) {
+        let same = !aig.xor(x, y);
+        all = aig.and(all, same);
+    }
+    all
+}
+
+fn mux_bits(aig: &mut Aig, cond: AigLit, then: &[AigLit], other: &[AigLit]) -> Bits {
+    then.iter().zip(other).map(|(&x, &y)| aig.mux(cond, x, y)).collect()
+}
+
+/// Shift `a` by the amount `b` with a barrel shifter, filling with `fill`
+fn shift(aig: &mut Aig, a: &[AigLit], b: &[AigLit], left: bool, fill: AigLit) -> Bits {
+    let width = a.len();
+    let mut result = a.to_vec();
+    let mut overflow = AigLit::FALSE;
+    for (stage, &bit) in b.iter().enumerate() {
+        if stage >= usize::BITS as usize || 1usize << stage >= width {
+            overflow = aig.or(overflow, bit);
+            continue;
+        }
+        let amount = 1 << stage;
+        let shifted = (0..width)
+            .map(|i| match left {
+                true if i >= amount => result[i - amount],
+                false if i + amount < width => result[i + amount],
+                _ => fill,
+            })
+            .collect::<Vec<_>>();
+        result = mux_bits(aig, bit, &shifted, &result);
+    }
+    mux_bits(aig, overflow, &vec![fill; width], &result)
+}
+
+/// Restoring division of unsigned numbers. Dividing by zero gives all ones and leaves `a`
+/// as the remainder.
+fn divmod_u(aig: &mut Aig, a: &[AigLit], b: &[AigLit]) -> (Bits, Bits) {
+    let width = a.len();
+    let mut divisor = b.to_vec();
+    divisor.push(AigLit::FALSE);
+    let mut remainder = vec![AigLit::FALSE; width + 1];
+    let mut quotient = vec![AigLit::FALSE; width];
+    for i in (0..width).rev() {
+        remainder.pop();
+        remainder.insert(0, a[i]);
+        let fits = !ult(aig, &remainder, &divisor);
+        let reduced = sub(aig, &remainder, &divisor);
+        remainder = mux_bits(aig, fits, &reduced, &remainder);
+        quotient[i] = fits;
+    }
+    remainder.pop();
+    (quotient, remainder)
+}
+
+/// Signed division rounding toward zero; the remainder takes the sign of `a`
+fn divmod_s(aig: &mut Aig, a: &[AigLit], b: &[AigLit]) -> (Bits, Bits) {
+    let (Some(&sign_a), Some(&sign_b)) = (a.last(), b.last()) else {
+        return (vec![], vec![]);
+    };
+    let neg_a = neg(aig, a);
+    let neg_b = neg(aig, b);
+    let abs_a = mux_bits(aig, sign_a, &neg_a, a);
+    let abs_b = mux_bits(aig, sign_b, &neg_b, b);
+    let (quotient, remainder) = divmod_u(aig, &abs_a, &abs_b);
+    let negative = aig.xor(sign_a, sign_b);
+    let neg_quotient = neg(aig, &quotient);
+    let neg_remainder = neg(aig, &remainder);
+    (
+        mux_bits(aig, negative, &neg_quotient, &quotient),
+        mux_bits(aig, sign_a, &neg_remainder, &remainder),
+    )
+}
+
+pub(crate) struct BitBlaster<'env, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> {
+    env: &'env E,
+    pub(crate) aig: Aig,
+    bits: FxHashMap<EntityId, Bits>,
+    /// entities whose bits are being built, to catch combinational loops
+    pending: FxHashSet<EntityId>,
+}
+
+impl<E: Environ<EntityT = EntityEnum, OpT = OpEnum>> BitBlaster<'_, E> {
+    fn error<T>(&self, op: Option<OpId>, message: String) -> Result<T, AigError> {
+        Err(AigError { op, message })
+    }
+
+    /// the width of a value, an event being a single bit telling whether it happens
+    pub(crate) fn width(&self, entity: EntityId) -> Result<usize, AigError> {
+        match self.env.get_entity(entity).get_dtype() {
+            Some(dtype) => match dtype.try_width() {
+                Some(width) => Ok(width),
+                None => {
+                    let name = self.env.print_entity(entity);
+                    self.error(None, format!("{} has no bit width", name))
+                },
+            },
+            None if matches!(self.env.get_entity(entity), EntityEnum::Event(_)) => Ok(1),
+            None => self.error(None, format!("{} has no dtype", self.env.print_entity(entity))),
+        }
+    }
+
+    fn bit_name(&self, entity: EntityId, bit: usize, width: usize) -> String {
+        let name = match self.env.get_entity(entity).get_attr("name") {
+            Some(AttributeEnum::StringAttr(StringAttr(name))) => name,
+            _ => format!("_{}", entity.0),
+        };
+        if width == 1 {
+            name
+        } else {
+            format!("{}[{}]", name, bit)
+        }
+    }
+
+    fn add_inputs(&mut self, entity: EntityId) -> Result<(), AigError> {
+        let width = self.width(entity)?;
+        let bits = (0..width)
+            .map(|bit| {
+                let name = self.bit_name(entity, bit, width);
+                self.aig.add_input(name, Some((entity, bit)))
+            })
+            .collect();
+        self.bits.insert(entity, bits);
+        Ok(())
+    }
+
+    pub(crate) fn get(&mut self, entity: Option<EntityId>, op: OpId) -> Result<Bits, AigError> {
+        let Some(entity) = entity else {
+            return self.error(Some(op), format!("an operand of op {} is missing", op.0));
+        };
+        if let Some(bits) = self.bits.get(&entity) {
+            return Ok(bits.to_owned());
+        }
+        let bits = self.build(entity, op);
+        if bits.is_err() {
+            // nothing is left pending to be taken for a loop by a later call
+            self.pending.clear();
+        }
+        bits
+    }
+
+    /// Lower the defs of the cone of `entity` used by `op`, each after its operands, with
+    /// an explicit stack
+    fn build(&mut self, entity: EntityId, op: OpId) -> Result<Bits, AigError> {
+        let env = self.env;
+        let mut stack = vec![(entity, op, false)];
+        while let Some((entity, user, ready)) = stack.pop() {
+            if ready {
+                let bits = self.lower(env.get_defs(entity)[0])?;
+                self.pending.remove(&entity);
+                self.bits.insert(entity, bits);
+                continue;
+            }
+            if self.bits.contains_key(&entity) {
+                continue;
+            }
+            if !self.pending.insert(entity) {
+                let name = env.print_entity(entity);
+                return self.error(Some(user), format!("{} is on a combinational loop", name));
+            }
+            let Some(&def) = env.get_defs(entity).first() else {
+                let name = env.print_entity(entity);
+                return self.error(Some(user), format!("{} is not driven", name));
+            };
+            stack.push((entity, user, true));
+            let operands = env.get_op(def).get_uses().into_iter().flat_map(|(_, uses)| uses);
+            stack.extend(operands.flatten().map(|operand| (operand, def, false)));
+        }
+        Ok(self.bits[&entity].to_owned())
+    }
+
+    fn get_all(&mut self, entities: &[EntityId], op: OpId) -> Result<Vec<Bits>, AigError> {
+        entities.iter().map(|&entity| self.get(Some(entity), op)).collect()
+    }
+
+    /// the bits of the single def of `op`
+    fn lower(&mut self, op_id: OpId) -> Result<Bits, AigError> {
+        let env = self.env;
+        let op = env.get_op(op_id);
+        let Some(Some(lhs)) = op.get_defs().first().and_then(|(_, defs)| defs.first().copied())
+        else {
+            return self.error(Some(op_id), format!("op {} defines nothing", op_id.0));
+        };
+        let width = self.width(lhs)?;
+        let bits = match op {
+            OpEnum::HwConstant(op) => {
+                let value = match op.value() {
+                    Some(ConstantAttr(value)) => value.to_owned(),
+                    None => vec![],
+                };
+                (0..width).map(|i| value.get(i).copied().unwrap_or(false).into()).collect()
+            },
+            OpEnum::EventDef(_) => {
+                let name = self.bit_name(lhs, 0, 1);
+                vec![self.aig.add_input(name, Some((lhs, 0)))]
+            },
+            OpEnum::EventFrom(op) => self.get(op.rhs(), op_id)?,
+            OpEnum::EventEval(op) => self.get(op.rhs(), op_id)?,
+            OpEnum::Assign(op) => self.get(op.rhs(), op_id)?,
+            OpEnum::HwBitCast(op) => self.get(op.rhs(), op_id)?,
+            OpEnum::CombUnary(op) => {
+                let operand = self.get(op.op(), op_id)?;
+                match op.predicate() {
+                    Some(CombUnaryPredicate::Neg) => neg(&mut self.aig, &operand),
+                    _ => not(&operand),
+                }
+            },
+            OpEnum::CombVariadic(op) => {
+                let mut operands = self.get_all(op.operands(), op_id)?.into_iter();
+                let aig = &mut self.aig;
+                use CombVariadicPredicate::*;
+                let mut result = operands.next().unwrap_or_default();
+                for bits in operands {
+                    let pairs = result.iter().copied().zip(bits.iter().copied());
+                    result = match op.predicate() {
+                        Some(Add) => add(aig, &result, &bits, AigLit::FALSE).0,
+                        Some(Mul) => mul(aig, &result, &bits),
+                        Some(And) => pairs.map(|(x, y)| aig.and(x, y)).collect(),
+                        Some(Or) => pairs.map(|(x, y)| aig.or(x, y)).collect(),
+                        Some(Xor) | None => pairs.map(|(x, y)| aig.xor(x, y)).collect(),
+                    };
+                }
+                result
+            },
+            OpEnum::CombBinary(op) => {
+                let (a, b) = (self.get(op.op0(), op_id)?, self.get(op.op1(), op_id)?);
+                let aig = &mut self.aig;
+                match op.predicate() {
+                    Some(CombBinaryPredicate::Sub) => sub(aig, &a, &b),
+                    Some(CombBinaryPredicate::Shl) => shift(aig, &a, &b, true, AigLit::FALSE),
+                    Some(CombBinaryPredicate::ShrU) => shift(aig, &a, &b, false, AigLit::FALSE),
+                    Some(CombBinaryPredicate::ShrS) => {
+                        let sign = a.last().copied().unwrap_or(AigLit::FALSE);
+                        shift(aig, &a, &b, false, sign)
+                    },
+                    Some(CombBinaryPredicate::DivU) => divmod_u(aig, &a, &b).0,
+                    Some(CombBinaryPredicate::ModU) => divmod_u(aig, &a, &b).1,
+                    Some(CombBinaryPredicate::DivS) => divmod_s(aig, &a, &b).0,
+                    Some(CombBinaryPredicate::ModS) => divmod_s(aig, &a, &b).1,
+                    None => return self.error(Some(op_id), "a binary op needs a predicate".into()),
+                }
+            },
+            OpEnum::CombICmp(op) => {
+                let (a, b) = (self.get(op.op0(), op_id)?, self.get(op.op1(), op_id)?);
+                let aig = &mut self.aig;
+                use CombICmpPredicate::*;
+                let bit = match op.predicate() {
+                    Some(EQ | CEQ | WEQ) => eq(aig, &a, &b),
+                    Some(NE | CNE | WNE) => !eq(aig, &a, &b),
+                    Some(ULT) => ult(aig, &a, &b),
+                    Some(UGT) => ult(aig, &b, &a),
+                    Some(ULE) => !ult(aig, &b, &a),
+                    Some(UGE) => !ult(aig, &a, &b),
+                    Some(SLT) => slt(aig, &a, &b),
+                    Some(SGT) => slt(aig, &b, &a),
+                    Some(SLE) => !slt(aig, &b, &a),
+                    Some(SGE) => !slt(aig, &a, &b),
+                    None => return self.error(Some(op_id), "a comparison needs a predicate".into()),
+                };
+                vec![bit]
+            },
+            OpEnum::CombMux2(op) => {
+                let cond = self.get(op.cond(), op_id)?;
+                let (then, other) = (self.get(op.op0(), op_id)?, self.get(op.op1(), op_id)?);
+                mux_bits(&mut self.aig, cond[0], &then, &other)
+            },
+            OpEnum::CombExtract(op) => {
+                let (input, low) = (self.get(op.input(), op_id)?, self.get(op.low(), op_id)?);
+                let shifted = shift(&mut self.aig, &input, &low, false, AigLit::FALSE);
+                shifted.into_iter().take(width).collect()
+            },
+            OpEnum::CombConcat(op) => {
+                // the first operand holds the most significant bits
+                let operands = self.get_all(op.operands(), op_id)?;
+                operands.into_iter().rev().flatten().collect()
+            },
+            OpEnum::CombReplicate(op) => {
+                let operand = self.get(op.rhs(), op_id)?;
+                operand.iter().copied().cycle().take(width).collect()
+            },
+            OpEnum::CombParity(op) => {
+                let operand = self.get(op.rhs(), op_id)?;
+                let aig = &mut self.aig;
+                vec![operand.into_iter().fold(AigLit::FALSE, |acc, bit| aig.xor(acc, bit))]
+            },
+            OpEnum::SeqCompReg(_) | OpEnum::HwInput(_) => {
+                unreachable!("registers and inputs are set up before lowering")
+            },
+            OpEnum::HwInstance(_) => {
+                return self.error(Some(op_id), "instances must be flattened first".into())
+            },
+            op => {
+                let name = op.get_op_name();
+                return self.error(Some(op_id), format!("`{}` cannot be turned into gates", name));
+            },
+        };
+        if bits.len() != width {
+            let name = env.print_entity(lhs);
+            return self.error(Some(op_id), format!("{} is not {} bits wide", name, width));
+        }
+        Ok(bits)
+    }
+}
+
+/// Turn the body of an `HwModule` into an AIG.
+///
+/// Ports and `HwInput` defs become inputs, the bits of each `SeqCompReg` become latches
+/// initialized to zero whose next state takes the synchronous reset into account, and the
+/// operands of `HwOutput` become outputs. An event is a bit set in the cycles it happens
+/// in, a free input for `event.define`. The module must be flattened and single-clocked.
+pub fn bitblast_module<E>(env: &E, module: OpId) -> Result<Aig, AigError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    blast_module(env, module).map(|blaster| blaster.aig)
+}
+
+/// the blaster of `bitblast_module`, to get the bits of further entities
+pub(crate) fn blast_module<E>(env: &E, module: OpId) -> Result<BitBlaster<'_, E>, AigError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let OpEnum::HwModule(op) = env.get_op(module) else {
+        let message = "only modules can be bit-blasted".into();
+        return Err(AigError { op: Some(module), message });
+    };
+    let Some(body) = op.body() else {
+        let message = "the module has no body".into();
+        return Err(AigError { op: Some(module), message });
+    };
+    let (bits, pending) = (FxHashMap::default(), FxHashSet::default());
+    let mut blaster = BitBlaster { env, aig: Aig::new(), bits, pending };
+
+    for port in utils::get_input_ports(env, body) {
+        blaster.add_inputs(port)?;
+    }
+    let ops = utils::walk_region_ops(env, body);
+    let mut registers = vec![];
+    for &op_id in ops.iter() {
+        if let OpEnum::SeqCompReg(reg) = env.get_op(op_id) {
+            let Some(output) = reg.output() else {
+                return blaster.error(Some(op_id), "a register defines nothing".into());
+            };
+            let width = blaster.width(output)?;
+            let bits = (0..width)
+                .map(|bit| {
+                    let name = blaster.bit_name(output, bit, width);
+                    blaster.aig.add_latch(name, false, Some((output, bit)))
+                })
+                .collect::<Vec<_>>();
+            blaster.bits.insert(output, bits.to_owned());
+            registers.push((op_id, bits));
+        }
+    }
+
+    for (op_id, latches) in registers {
+        let OpEnum::SeqCompReg(reg) = env.get_op(op_id) else { unreachable!() };
+        let mut next = blaster.get(reg.input(), op_id)?;
+        if let (Some(reset), Some(reset_val)) = (reg.reset(), reg.reset_val()) {
+            let reset = blaster.get(Some(reset), op_id)?[0];
+            let reset_val = blaster.get(Some(reset_val), op_id)?;
+            next = mux_bits(&mut blaster.aig, reset, &reset_val, &next);
+        }
+        for (latch, next) in latches.into_iter().zip(next) {
+            blaster.aig.set_latch_next(latch, next);
+        }
+    }
+
+    for &op_id in ops.iter() {
+        let OpEnum::HwOutput(output) = env.get_op(op_id) else {
+            continue;
+        };
+        for &entity in output.outputs() {
+            let bits = blaster.get(Some(entity), op_id)?;
+            for (bit, lit) in bits.iter().enumerate() {
+                let name = blaster.bit_name(entity, bit, bits.len());
+                blaster.aig.add_output(*lit, name, Some((entity, bit)));
+            }
+        }
+    }
+    Ok(blaster)
+}
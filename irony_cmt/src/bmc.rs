@@ -0,0 +1,436 @@
+use irony::{Entity, EntityId, Environ, FxHashMap, Op, OpId};
+
+use crate::{
+    blast_module, Aig, AigError, AigInstance, AigLit, AttributeEnum, EntityEnum, IdAttr, OpEnum,
+    SatLit, SatSolver, StringAttr,
+};
+
+/// A named wire of a counterexample trace
+#[derive(Clone, Debug, PartialEq)]
+pub struct BmcSignal {
+    pub entity: EntityId,
+    pub name: String,
+    /// the value in each cycle, the least significant bit first
+    pub values: Vec<Vec<bool>>,
+}
+
+impl BmcSignal {
+    /// the value in `cycle`, for signals of at most 64 bits
+    pub fn get_value(&self, cycle: usize) -> Option<u64> {
+        let bits = self.values.get(cycle).filter(|bits| bits.len() <= 64)?;
+        Some(bits.iter().rev().fold(0, |acc, &bit| acc << 1 | bit as u64))
+    }
+}
+
+/// The values of the named wires from the initial state up to the cycle where a property
+/// is violated
+#[derive(Clone, Debug, PartialEq)]
+pub struct BmcTrace {
+    pub signals: Vec<BmcSignal>,
+}
+
+impl BmcTrace {
+    pub fn get_signal(&self, name: &str) -> Option<&BmcSignal> {
+        self.signals.iter().find(|signal| signal.name == name)
+    }
+}
+
+impl std::fmt::Display for BmcTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.signals.iter().map(|signal| signal.name.len()).max().unwrap_or(0);
+        for signal in self.signals.iter() {
+            let values = signal.values.iter().map(|bits| {
+                bits.iter().rev().map(|&bit| if bit { '1' } else { '0' }).collect::<String>()
+            });
+            let values = values.collect::<Vec<_>>().join(" ");
+            writeln!(f, "{:width$} {}", signal.name, values, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BmcResult {
+    /// no violation within `bound` cycles after the initial one
+    Pass { bound: usize },
+    /// violated in the cycle `depth`, the shortest counterexample
+    Fail { depth: usize, trace: BmcTrace },
+}
+
+/// The outcome for one `property.synthesize`
+#[derive(Clone, Debug, PartialEq)]
+pub struct BmcReport {
+    pub target: OpId,
+    pub property: EntityId,
+    pub result: BmcResult,
+}
+
+/// The module unrolled over the cycles `0..=bound`, the violations of the properties on
+/// it, and a solver holding the gates of the unrolling encoded so far. Going one cycle
+/// deeper adds a frame and the new gates rather than starting over.
+struct Unrolling<'a, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> {
+    env: &'a E,
+    events: &'a FxHashMap<EntityId, AigLit>,
+    frame: &'a Aig,
+    aig: Aig,
+    frames: Vec<AigInstance>,
+    inputs: Vec<Vec<AigLit>>,
+    /// the latches entering the next frame
+    state: Vec<AigLit>,
+    bound: usize,
+    /// whether a sequence matching from a cycle ends in each of the following ones
+    matches: FxHashMap<(EntityId, usize), Vec<AigLit>>,
+    fails: FxHashMap<(EntityId, usize), AigLit>,
+    solver: SatSolver,
+    /// the solver variable of each encoded variable of `aig`
+    vars: FxHashMap<u32, u32>,
+}
+
+impl<'a, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> Unrolling<'a, E> {
+    fn new(env: &'a E, frame: &'a Aig, events: &'a FxHashMap<EntityId, AigLit>) -> Self {
+        let state = frame.latches.iter().map(|latch| latch.init.into()).collect();
+        Self {
+            env,
+            events,
+            frame,
+            aig: Aig::new(),
+            frames: vec![],
+            inputs: vec![],
+            state,
+            bound: 0,
+            matches: FxHashMap::default(),
+            fails: FxHashMap::default(),
+            solver: SatSolver::new(),
+            vars: FxHashMap::default(),
+        }
+    }
+
+    /// unroll up to `bound`, the violations being those seen by the cycles up to it
+    fn unroll(&mut self, bound: usize) {
+        while self.frames.len() <= bound {
+            let cycle = self.frames.len();
+            let aig = &mut self.aig;
+            let inputs = self
+                .frame
+                .inputs
+                .iter()
+                .map(|input| aig.add_input(format!("{}@{}", input.name, cycle), input.source))
+                .collect::<Vec<_>>();
+            let instance = self.frame.instantiate(aig, &inputs, &self.state);
+            self.state = self.frame.latches.iter().map(|latch| instance.get(latch.next)).collect();
+            self.frames.push(instance);
+            self.inputs.push(inputs);
+        }
+        if bound != self.bound {
+            self.bound = bound;
+            self.matches.clear();
+            self.fails.clear();
+        }
+    }
+
+    fn error<T>(&self, op: Option<OpId>, message: String) -> Result<T, AigError> {
+        Err(AigError { op, message })
+    }
+
+    fn def(&self, entity: Option<EntityId>) -> Result<(EntityId, OpId), AigError> {
+        let Some(entity) = entity else {
+            return self.error(None, "an operand of a property is missing".into());
+        };
+        match self.env.get_defs(entity).first() {
+            Some(&def) => Ok((entity, def)),
+            None => self.error(None, format!("{} is not driven", self.env.print_entity(entity))),
+        }
+    }
+
+    fn any(&mut self, lits: impl IntoIterator<Item = AigLit>) -> AigLit {
+        lits.into_iter().fold(AigLit::FALSE, |acc, lit| self.aig.or(acc, lit))
+    }
+
+    /// the most cycles a match of `sequence` spans, `None` if unbounded
+    fn length(&self, sequence: EntityId) -> Result<Option<usize>, AigError> {
+        let (_, def) = self.def(Some(sequence))?;
+        match self.env.get_op(def) {
+            OpEnum::SqnFromEvent(_) => Ok(Some(1)),
+            OpEnum::SqnDelay(op) => {
+                let rhs = self.length(self.def(op.rhs())?.0)?;
+                Ok(op.ub().zip(rhs).map(|(IdAttr(ub), rhs)| ub + rhs))
+            },
+            OpEnum::SqnConcat(op) => {
+                let s0 = self.length(self.def(op.s0())?.0)?;
+                let s1 = self.length(self.def(op.s1())?.0)?;
+                Ok(s0.zip(s1).map(|(s0, s1)| s0 + s1 - 1))
+            },
+            op => self.error(Some(def), format!("`{}` is not a sequence", op.get_op_name())),
+        }
+    }
+
+    /// whether a match of `sequence` from `start` ends in `start + i`, for each `i`
+    fn matches(&mut self, sequence: EntityId, start: usize) -> Result<Vec<AigLit>, AigError> {
+        if let Some(lits) = self.matches.get(&(sequence, start)) {
+            return Ok(lits.to_owned());
+        }
+        let (_, def) = self.def(Some(sequence))?;
+        let mut lits = vec![AigLit::FALSE; self.bound + 1 - start];
+        match self.env.get_op(def) {
+            OpEnum::SqnFromEvent(op) => {
+                let (event, _) = self.def(op.rhs())?;
+                let Some(&lit) = self.events.get(&event) else {
+                    let name = self.env.print_entity(event);
+                    return self.error(Some(def), format!("{} is not an event", name));
+                };
+                lits[0] = self.frames[start].get(lit);
+            },
+            OpEnum::SqnDelay(op) => {
+                let (rhs, _) = self.def(op.rhs())?;
+                let lb = op.lb().map_or(0, |IdAttr(lb)| *lb);
+                let ub = op.ub().map_or(usize::MAX, |IdAttr(ub)| *ub);
+                for delay in lb..=ub.min(self.bound - start) {
+                    let delayed = self.matches(rhs, start + delay)?;
+                    for (end, lit) in delayed.into_iter().enumerate() {
+                        lits[delay + end] = self.aig.or(lits[delay + end], lit);
+                    }
+                }
+            },
+            OpEnum::SqnConcat(op) => {
+                let ((s0, _), (s1, _)) = (self.def(op.s0())?, self.def(op.s1())?);
+                // the second sequence starts in the cycle the first one ends
+                for (middle, first) in self.matches(s0, start)?.into_iter().enumerate() {
+                    let second = self.matches(s1, start + middle)?;
+                    for (end, lit) in second.into_iter().enumerate() {
+                        let both = self.aig.and(first, lit);
+                        lits[middle + end] = self.aig.or(lits[middle + end], both);
+                    }
+                }
+            },
+            op => {
+                let name = op.get_op_name();
+                return self.error(Some(def), format!("`{}` is not a sequence", name));
+            },
+        }
+        self.matches.insert((sequence, start), lits.to_owned());
+        Ok(lits)
+    }
+
+    /// Whether the cycles up to the bound violate `property` checked from `cycle`, whatever
+    /// comes after them. Obligations that can always be met later, as for `eventually`,
+    /// are never violated.
+    fn fail(&mut self, property: EntityId, cycle: usize) -> Result<AigLit, AigError> {
+        if let Some(&lit) = self.fails.get(&(property, cycle)) {
+            return Ok(lit);
+        }
+        let (_, def) = self.def(Some(property))?;
+        let lit = match self.env.get_op(def) {
+            OpEnum::PrptFromSqn(op) => self.sequence_fail(self.def(op.rhs())?.0, cycle)?,
+            OpEnum::SqnFromEvent(_) | OpEnum::SqnDelay(_) | OpEnum::SqnConcat(_) => {
+                self.sequence_fail(property, cycle)?
+            },
+            OpEnum::PrptNexttime(op) => match cycle < self.bound {
+                true => self.fail(self.def(op.rhs())?.0, cycle + 1)?,
+                false => AigLit::FALSE,
+            },
+            OpEnum::PrptAlways(op) => {
+                let (rhs, _) = self.def(op.rhs())?;
+                let fails = (cycle..=self.bound)
+                    .map(|later| self.fail(rhs, later))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.any(fails)
+            },
+            OpEnum::PrptEventually(_) => AigLit::FALSE,
+            OpEnum::PrptUntil(op) => {
+                // `a` is violated before `b` holds
+                let ((a, _), (b, _)) = (self.def(op.a())?, self.def(op.b())?);
+                let (mut waiting, mut fails) = (AigLit::TRUE, vec![]);
+                for later in cycle..=self.bound {
+                    let b_fails = self.fail(b, later)?;
+                    waiting = self.aig.and(waiting, b_fails);
+                    let a_fails = self.fail(a, later)?;
+                    fails.push(self.aig.and(waiting, a_fails));
+                }
+                self.any(fails)
+            },
+            OpEnum::PrptConjunction(op) => {
+                let ((a, _), (b, _)) = (self.def(op.a())?, self.def(op.b())?);
+                let (a, b) = (self.fail(a, cycle)?, self.fail(b, cycle)?);
+                self.aig.or(a, b)
+            },
+            OpEnum::PrptImplica(op) => {
+                // overlapping: `b` is checked from the cycle each match of `a` ends in
+                let (mut a, a_def) = self.def(op.a())?;
+                if let OpEnum::PrptFromSqn(from) = self.env.get_op(a_def) {
+                    a = self.def(from.rhs())?.0;
+                }
+                let (b, _) = self.def(op.b())?;
+                let mut fails = vec![];
+                for (end, matched) in self.matches(a, cycle)?.into_iter().enumerate() {
+                    let b_fails = self.fail(b, cycle + end)?;
+                    fails.push(self.aig.and(matched, b_fails));
+                }
+                self.any(fails)
+            },
+            op => {
+                let name = op.get_op_name();
+                return self.error(Some(def), format!("`{}` is not a property", name));
+            },
+        };
+        self.fails.insert((property, cycle), lit);
+        Ok(lit)
+    }
+
+    /// a sequence as a property fails when it has no match and no match can end later
+    fn sequence_fail(&mut self, sequence: EntityId, cycle: usize) -> Result<AigLit, AigError> {
+        match self.length(sequence)? {
+            Some(length) if cycle + length <= self.bound + 1 => {
+                let matches = self.matches(sequence, cycle)?;
+                Ok(!self.any(matches))
+            },
+            _ => Ok(AigLit::FALSE),
+        }
+    }
+
+    /// The values of the variables of `aig` making `root` true, if any. Only the gates
+    /// which are not in the solver yet are encoded, and `root` is assumed rather than
+    /// asserted; when it cannot hold, its negation is kept as a lemma for the next calls.
+    fn solve(&mut self, root: AigLit) -> Option<Vec<bool>> {
+        let mut encoded = vec![];
+        let mut worklist = vec![root];
+        while let Some(lit) = worklist.pop() {
+            if lit.is_const() || self.vars.contains_key(&lit.var()) {
+                continue;
+            }
+            self.vars.insert(lit.var(), self.solver.new_var());
+            if let Some((a, b)) = self.aig.get_and(lit) {
+                encoded.push(lit.var());
+                worklist.extend([a, b]);
+            }
+        }
+        let vars = &self.vars;
+        let sat_lit = |lit: AigLit| SatLit::new(vars[&lit.var()], lit.is_inverted());
+        for var in encoded {
+            let (a, b) = self.aig.get_and(AigLit(var * 2)).unwrap();
+            let out = SatLit::new(vars[&var], false);
+            // the constants were folded away when the gates were built
+            let (a, b) = (sat_lit(a), sat_lit(b));
+            self.solver.add_clause(&[!out, a]);
+            self.solver.add_clause(&[!out, b]);
+            self.solver.add_clause(&[out, !a, !b]);
+        }
+        let model = match root {
+            AigLit::FALSE => return None,
+            AigLit::TRUE => self.solver.solve()?,
+            _ => {
+                let root = sat_lit(root);
+                let Some(model) = self.solver.solve_with(&[root]) else {
+                    self.solver.add_clause(&[!root]);
+                    return None;
+                };
+                model
+            },
+        };
+        let mut values = vec![false; self.aig.var_count()];
+        for (&var, &sat_var) in self.vars.iter() {
+            values[var as usize] = model[sat_var as usize];
+        }
+        Some(values)
+    }
+}
+
+/// Bounded model checking of the `property.synthesize` targets of an `HwModule`.
+///
+/// The module is bit-blasted as by [`bitblast_module`](crate::bitblast_module), registers
+/// starting at zero, and unrolled for up to `bound` cycles after the initial one. Each
+/// target is asserted in every cycle; a property is violated when the cycles seen so far
+/// already rule it out, so liveness obligations never fail. Sequences follow the `ltl`
+/// dialect of CIRCT: `sequence.delay s [lb:ub]` is `##[lb:ub] s`, and the second operand
+/// of `sequence.concat` starts in the cycle the first one ends. The search goes one cycle
+/// deeper at a time on a single unrolling and [`SatSolver`], so a counterexample is a
+/// shortest one.
+pub fn check_properties<E>(env: &E, module: OpId, bound: usize) -> Result<Vec<BmcReport>, AigError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    // only a module with a body is bit-blasted
+    let mut blaster = blast_module(env, module)?;
+    let OpEnum::HwModule(op) = env.get_op(module) else { unreachable!() };
+    let Some(body_id) = op.body() else { unreachable!() };
+    let body = env.get_region(body_id);
+    let ops = crate::utils::walk_region_ops(env, body_id);
+
+    let mut events = FxHashMap::default();
+    let mut targets = vec![];
+    for &op_id in ops.iter() {
+        match env.get_op(op_id) {
+            OpEnum::SqnFromEvent(op) => {
+                if let Some(event) = op.rhs() {
+                    events.insert(event, blaster.get(Some(event), op_id)?[0]);
+                }
+            },
+            OpEnum::PrptSynth(op) => match op.property() {
+                Some(property) => targets.push((op_id, property)),
+                None => return Err(AigError { op: Some(op_id), message: "no property".into() }),
+            },
+            _ => {},
+        }
+    }
+
+    // the named wires the bit-blaster can compute are traced as extra outputs
+    let first_output = blaster.aig.outputs.len();
+    let mut signals = vec![];
+    for entity in body.get_arguments().into_iter().chain(body.get_entity_children()) {
+        let name = match env.get_entity(entity) {
+            EntityEnum::Wire(wire) => match wire.get_attr("name") {
+                Some(AttributeEnum::StringAttr(StringAttr(name))) => name,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if signals.iter().any(|(other, _, _)| *other == entity) {
+            continue;
+        }
+        if let Ok(bits) = blaster.get(Some(entity), module) {
+            for (bit, &lit) in bits.iter().enumerate() {
+                blaster.aig.add_output(lit, format!("{}[{}]", name, bit), Some((entity, bit)));
+            }
+            signals.push((entity, name, bits.len()));
+        }
+    }
+    let frame = blaster.aig;
+
+    let mut unrolling = Unrolling::new(env, &frame, &events);
+    let mut reports = vec![];
+    for (target, property) in targets {
+        let mut result = BmcResult::Pass { bound };
+        for depth in 0..=bound {
+            unrolling.unroll(depth);
+            let fails = (0..=depth)
+                .map(|cycle| unrolling.fail(property, cycle))
+                .collect::<Result<Vec<_>, _>>()?;
+            let violated = unrolling.any(fails);
+            let Some(values) = unrolling.solve(violated) else {
+                continue;
+            };
+
+            // replay the inputs of the model on the module
+            let mut traced = signals
+                .iter()
+                .map(|(entity, name, _)| BmcSignal {
+                    entity: *entity,
+                    name: name.to_owned(),
+                    values: vec![],
+                })
+                .collect::<Vec<_>>();
+            let mut state = frame.latches.iter().map(|latch| latch.init).collect::<Vec<_>>();
+            for inputs in unrolling.inputs[..=depth].iter() {
+                let inputs = inputs.iter().map(|lit| values[lit.var() as usize]);
+                let inputs = inputs.collect::<Vec<_>>();
+                let (outputs, next) = frame.simulate(&inputs, &state);
+                let mut outputs = outputs[first_output..].iter().copied();
+                for (signal, (_, _, width)) in traced.iter_mut().zip(signals.iter()) {
+                    signal.values.push(outputs.by_ref().take(*width).collect());
+                }
+                state = next;
+            }
+            result = BmcResult::Fail { depth, trace: BmcTrace { signals: traced } };
+            break;
+        }
+        reports.push(BmcReport { target, property, result });
+    }
+    Ok(reports)
+}
@@ -0,0 +1,297 @@
+use irony::{EntityId, Environ, FxHashMap, IronyError, IronyResult, Op, OpId, RegionId};
+
+use crate::{utils, EntityEnum, InstanceGraph, OpEnum, StringAttr};
+
+/// A set of entities depending on each other combinationally, reported by one of its
+/// cycles: each entity drives the next one without a register in between
+#[derive(Clone, Debug, PartialEq)]
+pub struct CombLoop {
+    pub module: OpId,
+    pub module_name: String,
+    /// every entity of the set
+    pub component: Vec<EntityId>,
+    /// the shortest cycle through the first entity of the set, repeated at the end
+    pub entities: Vec<EntityId>,
+    /// the printed names of the entities, e.g. `%a`
+    pub names: Vec<String>,
+}
+
+impl std::fmt::Display for CombLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "combinational loop in `{}`: {}", self.module_name, self.names.join(" -> "))
+    }
+}
+
+/// The combinational dependencies of a module body: an edge from each entity to the
+/// entities it drives
+#[derive(Default)]
+struct DepGraph {
+    nodes: Vec<EntityId>,
+    index: FxHashMap<EntityId, usize>,
+    succs: Vec<Vec<usize>>,
+}
+
+impl DepGraph {
+    fn node(&mut self, entity: EntityId) -> usize {
+        if let Some(&index) = self.index.get(&entity) {
+            return index;
+        }
+        self.nodes.push(entity);
+        self.succs.push(vec![]);
+        self.index.insert(entity, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn add_edge(&mut self, from: EntityId, to: EntityId) {
+        let (from, to) = (self.node(from), self.node(to));
+        if !self.succs[from].contains(&to) {
+            self.succs[from].push(to);
+        }
+    }
+
+    /// the strongly connected components with a cycle, found by Tarjan's algorithm with an
+    /// explicit stack, as chains of entities can be long
+    fn cyclic_components(&self) -> Vec<Vec<usize>> {
+        let count = self.nodes.len();
+        let mut next = 0;
+        let mut order: Vec<Option<usize>> = vec![None; count];
+        let mut low = vec![0; count];
+        let mut stack = vec![];
+        let mut on_stack = vec![false; count];
+        let mut components = vec![];
+
+        for root in 0..count {
+            if order[root].is_some() {
+                continue;
+            }
+            // the nodes being visited, each with the index of its next successor
+            let mut visiting = vec![(root, 0)];
+            while let Some(&mut (node, ref mut edge)) = visiting.last_mut() {
+                if *edge == 0 && order[node].is_none() {
+                    order[node] = Some(next);
+                    low[node] = next;
+                    next += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                }
+                if let Some(&succ) = self.succs[node].get(*edge) {
+                    *edge += 1;
+                    match order[succ] {
+                        None => visiting.push((succ, 0)),
+                        Some(succ_order) if on_stack[succ] => low[node] = low[node].min(succ_order),
+                        Some(_) => {},
+                    }
+                    continue;
+                }
+
+                visiting.pop();
+                if let Some(&(parent, _)) = visiting.last() {
+                    low[parent] = low[parent].min(low[node]);
+                }
+                if Some(low[node]) != order[node] {
+                    continue;
+                }
+                let mut component = vec![];
+                while let Some(top) = stack.pop() {
+                    on_stack[top] = false;
+                    component.push(top);
+                    if top == node {
+                        break;
+                    }
+                }
+                if component.len() > 1 || self.succs[node].contains(&node) {
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+        components.sort();
+        components
+    }
+
+    /// the shortest cycle through the first node of `component`
+    fn cycle_in(&self, component: &[usize]) -> Vec<EntityId> {
+        let start = component[0];
+        let mut inside = vec![false; self.nodes.len()];
+        component.iter().for_each(|&node| inside[node] = true);
+        let mut pred: FxHashMap<usize, usize> = FxHashMap::default();
+        let mut queue = std::collections::VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            for &succ in self.succs[node].iter() {
+                if !inside[succ] || pred.contains_key(&succ) {
+                    continue;
+                }
+                pred.insert(succ, node);
+                if succ == start {
+                    queue.clear();
+                    break;
+                }
+                queue.push_back(succ);
+            }
+        }
+        let mut cycle = vec![start];
+        let mut node = pred[&start];
+        while node != start {
+            cycle.push(node);
+            node = pred[&node];
+        }
+        cycle.push(start);
+        cycle.reverse();
+        cycle.into_iter().map(|node| self.nodes[node]).collect()
+    }
+
+    /// the nodes from which `to` can be reached
+    fn reaching(&self, to: EntityId) -> Vec<EntityId> {
+        let Some(&to) = self.index.get(&to) else {
+            return vec![];
+        };
+        let mut preds = vec![vec![]; self.nodes.len()];
+        for (node, succs) in self.succs.iter().enumerate() {
+            for &succ in succs {
+                preds[succ].push(node);
+            }
+        }
+        let mut seen = vec![false; self.nodes.len()];
+        let mut worklist = vec![to];
+        while let Some(node) = worklist.pop() {
+            if !std::mem::replace(&mut seen[node], true) {
+                worklist.extend(preds[node].iter().copied());
+            }
+        }
+        (0..self.nodes.len()).filter(|&node| seen[node]).map(|node| self.nodes[node]).collect()
+    }
+}
+
+/// Combinational loop detection over `HwModule` bodies.
+///
+/// Registers and memory ports break the dependencies; an `HwInstance` output depends on
+/// the instance inputs the module output depends on, so loops through instances are found.
+#[derive(Clone, Debug, Default)]
+pub struct CombLoopAnalysis {
+    /// for each module, the input ports each output port depends on
+    port_deps: FxHashMap<OpId, Vec<Vec<usize>>>,
+}
+
+impl CombLoopAnalysis {
+    /// Fails if the module hierarchy cannot be built
+    pub fn new<E>(env: &E) -> IronyResult<Self>
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        let mut analysis = Self::default();
+        // callees first, so that instances see the port dependencies of their module
+        for module in InstanceGraph::new(env)?.post_order() {
+            if !matches!(env.get_op(module), OpEnum::HwModule(_)) {
+                continue;
+            }
+            let body = Self::get_body(env, module)?;
+            let graph = analysis.build_graph(env, body);
+            let inputs = utils::get_input_ports(env, body);
+            let deps = Self::get_output_ports(env, body)
+                .into_iter()
+                .map(|output| {
+                    let reaching = graph.reaching(output);
+                    (0..inputs.len()).filter(|&i| reaching.contains(&inputs[i])).collect()
+                })
+                .collect();
+            analysis.port_deps.insert(module, deps);
+        }
+        Ok(analysis)
+    }
+
+    fn get_body<E>(env: &E, module: OpId) -> IronyResult<RegionId>
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        let OpEnum::HwModule(op) = env.get_op(module) else {
+            let message = "combinational loops are looked for in an HwModule";
+            return Err(IronyError::failed(Some(module), message));
+        };
+        op.body().ok_or_else(|| IronyError::failed(Some(module), "the module has no body"))
+    }
+
+    fn get_output_ports<E>(env: &E, body: RegionId) -> Vec<EntityId>
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        env.get_region(body)
+            .get_op_children()
+            .into_iter()
+            .find_map(|op| match env.get_op(op) {
+                OpEnum::HwOutput(output) => Some(output.outputs().to_vec()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// the input ports each output port of `module` depends on combinationally
+    pub fn get_port_deps(&self, module: OpId) -> Option<&Vec<Vec<usize>>> {
+        self.port_deps.get(&module)
+    }
+
+    fn build_graph<E>(&self, env: &E, body: RegionId) -> DepGraph
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        let mut graph = DepGraph::default();
+        for op_id in utils::walk_region_ops(env, body) {
+            match env.get_op(op_id) {
+                OpEnum::SeqCompReg(_)
+                | OpEnum::SeqHlmem(_)
+                | OpEnum::SeqRead(_)
+                | OpEnum::SeqWrite(_) => {},
+                OpEnum::HwInstance(inst) => {
+                    let target = utils::get_instance_target(env, op_id);
+                    // modules on a recursive cycle are not analyzed yet: every output
+                    // depends on every input
+                    let deps = target.and_then(|target| self.port_deps.get(&target));
+                    for (i, &output) in inst.outputs().iter().enumerate() {
+                        for (j, &input) in inst.inputs().iter().enumerate() {
+                            let depends = deps.is_none_or(|deps| deps.get(i).is_some_and(|d| d.contains(&j)));
+                            if depends {
+                                graph.add_edge(input, output);
+                            }
+                        }
+                    }
+                },
+                op => {
+                    let defs = op.get_defs().into_iter().flat_map(|(_, defs)| defs).flatten();
+                    let uses = op.get_uses().into_iter().flat_map(|(_, uses)| uses).flatten();
+                    let uses = uses.collect::<Vec<_>>();
+                    for def in defs {
+                        graph.node(def);
+                        for &used in uses.iter() {
+                            graph.add_edge(used, def);
+                        }
+                    }
+                },
+            }
+        }
+        graph
+    }
+
+    /// Each set of entities depending on each other combinationally in the body of
+    /// `module`, with one of its cycles. A set may hold more cycles, which are not listed.
+    /// Fails if `module` is not a module with a body.
+    pub fn find_cyclic_components<E>(&self, env: &E, module: OpId) -> IronyResult<Vec<CombLoop>>
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        let graph = self.build_graph(env, Self::get_body(env, module)?);
+        let module_name = match env.get_op(module) {
+            OpEnum::HwModule(op) => op.name().map(|StringAttr(name)| name.to_owned()),
+            _ => None,
+        };
+        Ok(graph
+            .cyclic_components()
+            .iter()
+            .map(|component| {
+                let entities = graph.cycle_in(component);
+                let names = entities.iter().map(|&entity| env.print_entity(entity)).collect();
+                let module_name = module_name.to_owned().unwrap_or_default();
+                let component = component.iter().map(|&node| graph.nodes[node]).collect();
+                CombLoop { module, module_name, component, entities, names }
+            })
+            .collect())
+    }
+
+    /// the cyclic components of every module
+    pub fn find_all_cyclic_components<E>(&self, env: &E) -> IronyResult<Vec<CombLoop>>
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        let mut loops = vec![];
+        for module in InstanceGraph::new(env)?.get_modules() {
+            loops.extend(self.find_cyclic_components(env, module)?);
+        }
+        Ok(loops)
+    }
+}
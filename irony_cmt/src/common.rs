@@ -54,9 +54,11 @@ impl std::fmt::Display for UArrayType {
 #[derive(Clone, Debug, PartialEq, Hash)]
 pub struct SeqHlmemType(pub Box<DataTypeEnum>, pub Vec<usize>);
 
-// TODO: fix this
 impl std::fmt::Display for SeqHlmemType {
-    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { todo!() }
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let dims = self.1.iter().map(|dim| format!("{}x", dim)).collect::<String>();
+        write!(f, "!seq.hlmem<{}{}>", dims, self.0)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Hash)]
@@ -323,6 +325,31 @@ impl AttributeEnum {
     }
 }
 
+impl irony::SymbolRefTrait for AttributeEnum {
+    fn get_symbol_refs(&self) -> Vec<String> {
+        match self {
+            AttributeEnum::SymbolRefAttr(SymbolRefAttr(name)) => vec![name.to_owned()],
+            AttributeEnum::ArrayAttr(ArrayAttr(attrs)) => {
+                attrs.iter().flat_map(|attr| attr.get_symbol_refs()).collect()
+            },
+            _ => vec![],
+        }
+    }
+
+    fn replace_symbol_ref(&self, old: &str, new: &str) -> Self {
+        match self {
+            AttributeEnum::SymbolRefAttr(SymbolRefAttr(name)) if name == old => {
+                SymbolRefAttr(new.to_owned()).into()
+            },
+            AttributeEnum::ArrayAttr(ArrayAttr(attrs)) => ArrayAttr(
+                attrs.iter().map(|attr| attr.replace_symbol_ref(old, new)).collect(),
+            )
+            .into(),
+            attr => attr.to_owned(),
+        }
+    }
+}
+
 impl<I: Into<AttributeEnum>> Into<ArrayAttr> for Vec<I> {
     fn into(self) -> ArrayAttr { ArrayAttr(self.into_iter().map(|x| x.into()).collect()) }
 }
@@ -331,16 +358,47 @@ impl Into<ArrayAttr> for () {
     fn into(self) -> ArrayAttr { ArrayAttr(Vec::<AttributeEnum>::new()) }
 }
 
+/// Reference to the op declaring the symbol `@name`
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct SymbolRefAttr(pub String);
+
+impl Into<SymbolRefAttr> for &str {
+    fn into(self) -> SymbolRefAttr { SymbolRefAttr(self.to_string()) }
+}
+
+impl std::fmt::Display for SymbolRefAttr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}", self.0)
+    }
+}
+
+/// Source location owning its file name, so that it survives serialization
 #[derive(Clone, Debug, PartialEq, Hash)]
-pub struct LocationAttr(pub Location<'static>);
+pub struct LocationAttr {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
 
 impl From<&Location<'static>> for LocationAttr {
-    fn from(location: &Location<'static>) -> Self { LocationAttr(location.to_owned()) }
+    fn from(location: &Location<'static>) -> Self {
+        LocationAttr {
+            file: location.file().to_owned(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
 }
 
 impl std::fmt::Display for LocationAttr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+impl From<LocationAttr> for irony::Loc {
+    fn from(location: LocationAttr) -> Self {
+        irony::Loc::file_line_col(location.file, location.line, location.column)
     }
 }
 
@@ -362,6 +420,7 @@ irony::attribute_enum! {
         LocationAttr(LocationAttr),
         IdAttr(IdAttr),
         StringAttr(StringAttr),
+        SymbolRefAttr(SymbolRefAttr),
         TypeAttr(TypeAttr),
         ArrayAttr(ArrayAttr),
         CombVariadicPredicate(CombVariadicPredicate),
@@ -373,13 +432,21 @@ irony::attribute_enum! {
 
 impl DataTypeEnum {
     pub fn width(&self) -> usize {
+        match self.try_width() {
+            Some(width) => width,
+            None => unimplemented!("{} has no bit width", self),
+        }
+    }
+
+    /// the number of bits of the dtype, `None` for events, memories and unpacked arrays
+    pub fn try_width(&self) -> Option<usize> {
         match self {
-            DataTypeEnum::UInt(UIntType(width)) => *width,
-            DataTypeEnum::Array(ArrayType(boxed, size)) => boxed.width() * size,
+            DataTypeEnum::UInt(UIntType(width)) => Some(*width),
+            DataTypeEnum::Array(ArrayType(boxed, size)) => Some(boxed.try_width()? * size),
             DataTypeEnum::Struct(StructType(v_field_type)) => {
-                v_field_type.iter().map(|(_, dtype)| dtype.width()).sum()
+                v_field_type.iter().map(|(_, dtype)| dtype.try_width()).sum()
             },
-            _ => unimplemented!(),
+            _ => None,
         }
     }
 }
@@ -25,14 +25,9 @@ irony::constraint_def! {
         }),
         InstanceConstraint(InstanceConstraint ,
             |env: &E, attrs, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
-            let target_id = irony::utils::extract_vec(&attrs, "target_id");
-            let Some(AttributeEnum::IdAttr(target_id)) = target_id else {
-                panic!("target_id must be a IdAttr")
+            let Some(target_def) = super::utils::lookup_instance_target(env, &attrs) else {
+                return false;
             };
-
-            let target = env.get_entity(EntityId(target_id.0 as usize));
-            assert!(target.get_defs(env).len() == 1);
-            let target_def = target.get_defs(env)[0];
             let target_region= env.get_op(target_def).get_regions()[0].1[0];
 
             super::utils::extract_input_types(env, target_region) == super::utils::extract_types(env, uses[0].1.to_owned())
@@ -41,6 +36,33 @@ irony::constraint_def! {
 
         }),
 
+        EventUnionConstraint(EventUnionConstraint,
+            |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, _, _| {
+            // the unions form a forest: a son has a single father and is not an ancestor of it
+            let (Some(father), Some(son)) = (uses[0].1[0], uses[1].1[0]) else {
+                return false;
+            };
+            super::events::is_event(env, father) && super::events::is_event(env, son) &&
+            father != son &&
+            super::events::get_union_fathers(env, son).len() == 1 &&
+            !super::events::get_event_ancestors(env, father).contains(&son)
+        }),
+        EventElseOfConstraint(EventElseOfConstraint,
+            |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, _, _| {
+            let (Some(e), Some(t)) = (uses[0].1[0], uses[1].1[0]) else {
+                return false;
+            };
+            super::events::is_event(env, e) && super::events::is_event(env, t) && e != t
+        }),
+        EventBlockConstraint(EventBlockConstraint,
+            |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, _, regions: Vec<(String, Vec<irony::RegionId>)>| {
+            let Some(event) = uses[0].1[0] else {
+                return false;
+            };
+            super::events::is_event(env, event) &&
+            super::events::are_block_events_in_scope(env, regions[0].1[0])
+        }),
+
         SameTypeConstant(SameTypeConstant,
             |_, _, _, _, _|  {
                 true
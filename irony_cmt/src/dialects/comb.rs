@@ -0,0 +1,118 @@
+use crate::*;
+
+irony::dialect_def! {
+    [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+
+    CombDialect = "comb" {
+        // TODO: Add more constraints for safer usage
+        CombVariadic: {
+            defs: [lhs],
+            uses: [; operands],
+            attrs: [predicate: CombVariadicPredicate(CombVariadicPredicate)(*)],
+            traits: [Pure, Commutative, SameOperandsAndResultType],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>,  defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let def = env.print_entity(defs[0].1[0].unwrap());
+                    let uses = uses[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let AttributeEnum::CombVariadicPredicate(predicate) = irony::utils::extract_vec(&attrs, "predicate").unwrap() else { panic!("")};
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = comb.{} {} : {}", def, predicate, uses, typ)
+                }
+            )
+        },
+        CombBinary: {
+            defs: [lhs],
+            uses: [op0, op1],
+            attrs: [predicate: CombBinaryPredicate(CombBinaryPredicate)(*)],
+            traits: [Pure, SameOperandsAndResultType],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let def = env.print_entity(defs[0].1[0].unwrap());
+                    let uses = vec![env.print_entity(uses[0].1[0].unwrap()), env.print_entity(uses[1].1[0].unwrap())].join(", ");
+                    let AttributeEnum::CombBinaryPredicate(predicate) = irony::utils::extract_vec(&attrs, "predicate").unwrap() else { panic!("")};
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = comb.{} {} : {}", def, predicate, uses, typ)
+                }
+            )
+        },
+
+        CombICmp("icmp"): {
+            defs: [lhs],
+            uses: [op0, op1],
+            attrs: [predicate: CombICmpPredicate(CombICmpPredicate)(*)],
+            traits: [Pure],
+            constraints: [SameTypeOperands::new().into()],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String,Vec<Option<EntityId>>)>, _| {
+                    let def = env.print_entity(defs[0].1[0].unwrap());
+                    let inputs = vec![env.print_entity(uses[0].1[0].unwrap()), env.print_entity(uses[1].1[0].unwrap())].join(", ");
+                    let AttributeEnum::CombICmpPredicate(predicate) = irony::utils::extract_vec(&attrs, "predicate").unwrap() else { panic!("")};
+                    let typ = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = comb.icmp {} {} : {}", def, predicate, inputs, typ)
+                }
+            )
+        },
+        CombParity: {
+            defs: [lhs],
+            uses: [rhs],
+            traits: [Pure],
+            constraints: [/* TODO: fill this */],
+            print: (
+                |_, _, _, _, _| {
+                    unimplemented!()
+                }
+            )
+        },
+        CombExtract: {
+            defs: [lhs],
+            uses: [input, low],
+            traits: [Pure],
+            constraints: [/* TODO: fill this */],
+            print: (
+                |_, _, _, _, _| {
+                    unimplemented!()
+                }
+            )
+        },
+        CombConcat: {
+            defs: [lhs],
+            uses: [; operands],
+            traits: [Pure],
+            constraints: [/* TODO: fill this */],
+            print: (
+                |_, _, _, _, _| {
+                    unimplemented!()
+                }
+            )
+        },
+        CombReplicate: {
+            defs: [lhs],
+            uses: [rhs],
+            traits: [Pure],
+            constraints: [/* TODO: fill this */],
+            print: (
+                |_, _, _, _, _| {
+                    unimplemented!()
+                }
+            )
+        },
+        CombMux2("mux"): {
+            defs: [lhs],
+            uses: [cond, op0, op1],
+            traits: [Pure],
+            constraints: [/* TODO: fill this */],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let def = env.print_entity(defs[0].1[0].unwrap());
+                    let uses = uses.iter().map(|(_, ids)| {
+                        format!("{}", env.print_entity(ids[0].unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = comb.mux {} : {}", def, uses, typ)
+                }
+            )
+        },
+    }
+}
@@ -0,0 +1,99 @@
+use crate::*;
+
+irony::dialect_def! {
+    [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+
+    EventDialect = "event" {
+        // declares an event; with no wire, son or else of its own it is a free event, fired
+        // from outside the module
+        EventDef("define"): {
+            defs: [lhs],
+            uses: [],
+            print: (
+                |env: &E, _, _, def: Vec<(String, Vec<Option<EntityId>>)>, _|  {
+                    let lhs = env.print_entity(def[0].1[0].unwrap());
+                    format!("{} = event.define", lhs)
+                }
+            )
+        },
+        
+        // the event firing in the cycles the 1-bit `rhs` is high
+        EventFrom: {
+            defs: [lhs],
+            uses: [rhs],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _|  {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+                    format!("{} = event.from {}", lhs, rhs)
+                }
+            )
+        },
+
+        // the 1-bit wire high in the cycles the event `rhs` fires
+        EventEval: {
+            defs: [lhs],
+            uses: [rhs],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _|  {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+                    format!("{} = event.eval {}", lhs, rhs)
+                }
+            )
+        },
+
+        // the effects of the ops in `body` only take place in the cycles `event` fires, and
+        // the body only refers to the events in scope
+        EventBlockDef("block"): {
+            defs: [],
+            uses: [event],
+            regions: [body],
+            constraints: [EventBlockConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, _,  regions: Vec<(String, Vec<RegionId>)>| {
+                    let event = env.print_entity(uses[0].1[0].unwrap());
+
+                    let args = env.print_region_arguments(regions[0].1[0]);
+                    let args = if args.is_empty() { args } else { format!("({})", args) };
+
+                    let body = env.print_region(regions[0].1[0]);
+
+                    format!("event.block {}{} {{\n{}\n}}",  event, args, body)
+                }
+            )
+        },
+
+        // `son` is a sub-event of `father`, which fires whenever `son` does; the unions
+        // form a forest
+        EventUnion: {
+            defs: [],
+            uses: [father, son],
+            constraints: [EventUnionConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, _, _| {
+                    let father = env.print_entity(uses[0].1[0].unwrap());
+                    let son = env.print_entity(uses[1].1[0].unwrap());
+
+                    format!("event.union {} <- {}", father, son)
+                }
+            )
+        },
+
+        // `e` fires in the cycles the father of `t`, or any cycle if `t` is a root, fires
+        // but `t` does not
+        EventElseOf: {
+            defs: [],
+            uses: [e, t],
+            constraints: [EventElseOfConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, _, _| {
+                    let e = env.print_entity(uses[0].1[0].unwrap());
+                    let t = env.print_entity(uses[1].1[0].unwrap());
+
+                    format!("event.else_of {} <- {}", e, t)
+                }
+            )
+        },
+    }
+}
@@ -0,0 +1,310 @@
+use crate::*;
+
+irony::dialect_def! {
+    [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+
+    HwDialect = "hw" {
+        Assign("wire"): {
+            defs: [lhs],
+            uses: [rhs],
+            traits: [Pure, SameOperandsAndResultType],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>,  defs:Vec<(String, Vec<Option<EntityId>>)>, _ | {
+                    // format!("{} = {}", env.print_entity(defs[0].1[0].unwrap()), env.print_entity(uses[0].1[0].unwrap()))
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = hw.wire {} : {}", lhs, rhs, typ)
+                }
+            )
+        },
+
+        HwModule: {
+            defs: [lhs],
+            uses: [],
+            attrs: [name: StringAttr(StringAttr), arg_names: ArrayAttr(ArrayAttr), arg_types: ArrayAttr(ArrayAttr)(*), output_names: ArrayAttr(ArrayAttr), output_types: ArrayAttr(ArrayAttr)(*)],
+            regions: [body],
+            symbol: name,
+            traits: [IsolatedFromAbove],
+            constraints: [ModuleConstraint::default().into()],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, _ , _, regions: Vec<(String, Vec<RegionId>)>| {
+                    let AttributeEnum::ArrayAttr(arg_names) = irony::utils::extract_vec(&attrs, "arg_names").unwrap() else { panic!("")};
+                    let AttributeEnum::ArrayAttr(arg_types) = irony::utils::extract_vec(&attrs, "arg_types").unwrap() else { panic!("")};
+
+                    let AttributeEnum::ArrayAttr(output_names) = irony::utils::extract_vec(&attrs, "output_names").unwrap() else { panic!("")};
+                    let AttributeEnum::ArrayAttr(output_types) = irony::utils::extract_vec(&attrs, "output_types").unwrap() else { panic!("")};
+                    let name = irony::utils::extract_vec(&attrs, "name").unwrap();
+
+                    let args = arg_names.0.iter().zip(arg_types.0.iter()).map(|(name, ty)| {
+                        format!("%{}: {}", name, ty)
+                    }).collect::<Vec<_>>().join(", ");
+
+                    let outputs = output_names.0.iter().zip(output_types.0.iter()).map(|(name, ty)| {
+                        format!("{}: {}", name, ty)
+                    }).collect::<Vec<_>>().join(", ");
+                    format!("hw.module @{}({}) -> ({}) {{\n{}\n}}", name, args, outputs, env.print_region(regions[0].1[0]))
+                }
+            )
+        },
+
+        // TODO: Support EXT_W_PARAMS ?
+        HwInstance: {
+            defs: [; outputs],
+            uses: [; inputs],
+            attrs: [target_id: IdAttr(IdAttr)(*), target: SymbolRefAttr(SymbolRefAttr)(*), name: StringAttr(StringAttr)],
+            traits: [HasSideEffects],
+            constraints: [InstanceConstraint::default().into()],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let module_attrs = env.get_op(crate::utils::lookup_instance_target(env, &attrs).unwrap()).get_attrs();
+                    let AttributeEnum::StringAttr(instance_name) = irony::utils::extract_vec(&attrs, "name").unwrap() else { panic!("")};
+
+                    let AttributeEnum::ArrayAttr(arg_names) = irony::utils::extract_vec(&module_attrs, "arg_names").unwrap() else { panic!("")};
+                    let AttributeEnum::ArrayAttr(arg_types) = irony::utils::extract_vec(&module_attrs, "arg_types").unwrap() else { panic!("")};
+
+                    let AttributeEnum::ArrayAttr(output_names) = irony::utils::extract_vec(&module_attrs, "output_names").unwrap() else { panic!("")};
+                    let AttributeEnum::ArrayAttr(output_types) = irony::utils::extract_vec(&module_attrs, "output_types").unwrap() else { panic!("")};
+                    let AttributeEnum::StringAttr(mod_name) = irony::utils::extract_vec(&module_attrs, "name").unwrap() else { panic!("")};
+
+                    let outputs = defs[0].1.iter().map(|id| {
+                        env.print_entity((*id).unwrap())
+                    }).collect::<Vec<_>>().join(", ");
+
+                    let output_types = output_names.0.iter().zip(output_types.0.iter()).map(|(name, ty)| {
+                        format!("{}: {}", name, ty)
+                    }).collect::<Vec<_>>().join(", ");
+
+                    let args = arg_names.0.iter().zip(uses[0].1.iter()).zip(arg_types.0.iter()).map(|((name, id), ty)| {
+                        format!("{} : {} : {}", name, env.print_entity((*id).unwrap()), ty)
+                    }).collect::<Vec<_>>().join(", ");
+
+                    format!("{} = hw.instance \"{}\" @{}({}) -> ({})", outputs, instance_name, mod_name, args, output_types)
+                }
+            )
+        },
+
+        HwInput: {
+            defs: [; inputs],
+            uses: [],
+            print: (
+                |_, _, _, _, _| {
+                    format!("")
+                }
+            )
+        },
+
+        HwOutput: {
+            defs: [],
+            uses: [; outputs],
+            traits: [Terminator],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, _, _| {
+                    let outputs = uses[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let output_types = uses[0].1.iter().map(|id| {
+                        format!("{}", env.get_entity((*id).unwrap()).get_dtype().unwrap())
+                    }).collect::<Vec<_>>().join(", ");
+                    format!("hw.output {}: {}", outputs, output_types)
+                }
+            )
+        },
+
+        HwBitCast("bitcast"): {
+            defs: [lhs],
+            uses: [rhs],
+            traits: [Pure],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+                    let rhs_typ = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+                    let lhs_typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+
+                    format!("{} = hw.bitcast {}: ({}) -> {}", lhs, rhs, rhs_typ, lhs_typ)
+                }
+            )
+        },
+
+        // TODO: support super large constant and boolean constant
+        HwConstant: {
+            defs: [lhs],
+            uses: [],
+            attrs: [value: ConstantAttr(ConstantAttr)(*)],
+            traits: [Pure],
+            constraints: [SameTypeConstant::default().into()],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, _, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let AttributeEnum::ConstantAttr(value) = irony::utils::extract_vec(&attrs, "value").unwrap() else { panic!("")};
+                    let value = irony::utils::arith::from_bits_to_u32(value.0);
+                    let names = defs[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let types = defs[0].1.iter().map(|id| {
+                        format!("{}", env.get_entity((*id).unwrap()).get_dtype().unwrap())
+                    }).collect::<Vec<_>>().join(", ");
+                    format!("{} = hw.constant {}: {}", names, value, types)
+                }
+            )
+        },
+
+        HwAggregateConstant: {
+            defs: [lhs],
+            uses: [],
+            attrs: [values: ArrayAttr(ArrayAttr)(*)],
+            traits: [Pure],
+            constraints: [SameTypeAggregate::default().into()],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, _, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let attrs = irony::utils::extract_vec(&attrs, "values").unwrap();
+                    let name = format!("{}", env.print_entity(defs[0].1[0].unwrap()));
+                    let types = format!("{}", env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap());
+                    let values = attrs.print_for_aggregate_constant(env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap());
+                    format!("{} = hw.aggregate_constant {} : {}", name, values, types)
+                }
+            )
+        },
+
+        HwArrayConcat: {
+            defs: [lhs],
+            uses: [; operands],
+            traits: [Pure],
+            constraints: [ArrayConcatConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let rst = env.print_entity(defs[0].1[0].unwrap());
+                    let operands = uses[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let sub_typs = uses[0].1.iter().map(|id| {
+                        format!("{}", env.get_entity((*id).unwrap()).get_dtype().unwrap())
+                    }).collect::<Vec<_>>().join(", ");
+                    format!("{} = hw.array_concat {} : {}", rst, operands, sub_typs)
+                }
+            )
+        },
+
+        HwArrayCreate: {
+            defs: [lhs],
+            uses: [; operands],
+            traits: [Pure],
+            constraints: [ArrayCreateConstraint::default().into(), SameTypeOperands::new().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let rst = env.print_entity(defs[0].1[0].unwrap());
+                    let operands = uses[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let sub_typ = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = hw.array_create {} : {}", rst, operands, sub_typ)
+                }
+            )
+        },
+
+        HwArrayGet: {
+            defs: [lhs],
+            uses: [array, index],
+            traits: [Pure],
+            constraints: [ArrayGetConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let rst = env.print_entity(defs[0].1[0].unwrap());
+                    let array = env.print_entity(uses[0].1[0].unwrap());
+                    let index = env.print_entity(uses[1].1[0].unwrap());
+                    let array_typ = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+                    let index_typ = env.get_entity(uses[1].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = hw.array_get {}[{}] : {}, {}", rst, array, index, array_typ, index_typ)
+                }
+            )
+        },
+
+        HwArraySlice: {
+            defs: [lhs],
+            uses: [array, index],
+            traits: [Pure],
+            constraints: [ArraySliceConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let rst = env.print_entity(defs[0].1[0].unwrap());
+                    let array = env.print_entity(uses[0].1[0].unwrap());
+                    let index = env.print_entity(uses[1].1[0].unwrap());
+                    let old_typ = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+                    let new_typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = hw.array_slice {}[{}] : ({}) -> {}", rst, array, index, old_typ, new_typ)
+                }
+            )
+        },
+
+        HwStructCreate: {
+            defs: [lhs],
+            uses: [; operands],
+            traits: [Pure],
+            constraints: [StructCreateConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let operands = uses[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let lhs_ty = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = hw.struct_create ({}) : {}", lhs, operands, lhs_ty)
+                }
+            )
+        },
+
+        HwStructExtract: {
+            defs: [lhs],
+            uses: [struct_input],
+            attrs: [field: StringAttr(StringAttr)(*)],
+            traits: [Pure],
+            constraints: [StructExtractConstraint::default().into()],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let struct_input = env.print_entity(uses[0].1[0].unwrap());
+                    let field = irony::utils::extract_vec(&attrs, "field").unwrap();
+                    let struct_ty = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = hw.struct_extract {}[\"{}\"] : {}", lhs, struct_input, field, struct_ty)
+                }
+            )
+        },
+
+        HwStructInject: {
+            defs: [lhs],
+            uses: [struct_input, new_value],
+            attrs: [field: StringAttr(StringAttr)(*)],
+            traits: [Pure],
+            constraints: [StructInjectConstraint::default().into()],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let struct_input = env.print_entity(uses[0].1[0].unwrap());
+                    let new_value = env.print_entity(uses[1].1[0].unwrap());
+                    let field = irony::utils::extract_vec(&attrs, "field").unwrap();
+                    let struct_ty = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = hw.struct_inject {}[\"{}\"], {} : {}", lhs, struct_input, field, new_value, struct_ty)
+                }
+            )
+        },
+
+        HwStructExplode: {
+            defs: [; outputs],
+            uses: [struct_input],
+            traits: [Pure],
+            constraints: [StructExplodeConstraint::default().into()],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let outputs = defs[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+                    let struct_input = env.print_entity(uses[0].1[0].unwrap());
+                    let struct_ty = env.get_entity(uses[0].1[0].unwrap()).get_dtype().unwrap();
+
+                    format!("{} = hw.struct_explode {} : {}", outputs, struct_input, struct_ty)
+                }
+            )
+        },
+    }
+}
@@ -0,0 +1,22 @@
+#[macro_use]
+mod event;
+#[macro_use]
+mod sequence;
+#[macro_use]
+mod property;
+#[macro_use]
+mod temporary;
+#[macro_use]
+mod hw;
+#[macro_use]
+mod comb;
+#[macro_use]
+mod seq;
+
+pub use event::*;
+pub use sequence::*;
+pub use property::*;
+pub use temporary::*;
+pub use hw::*;
+pub use comb::*;
+pub use seq::*;
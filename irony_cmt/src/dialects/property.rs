@@ -0,0 +1,116 @@
+use crate::*;
+
+irony::dialect_def! {
+    [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+
+    PropertyDialect = "property" {
+        PrptFromSqn("from_sequence"): {
+            defs: [lhs],
+            uses: [rhs],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+
+                    format!("{} = property.from_sequence {}", lhs, rhs)
+                }
+            )
+        },
+
+        PrptNexttime("nexttime"): {
+            defs: [rst],
+            uses: [rhs],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+
+                    format!("{} = property.nexttime {}", lhs, rhs)
+                }
+            )
+        },
+
+        PrptAlways("always"): {
+            defs: [rst],
+            uses: [rhs],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+
+                    format!("{} = property.always {}", lhs, rhs)
+                }
+            )
+        },
+
+        PrptEventually("eventually"): {
+            defs: [rst],
+            uses: [rhs],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+
+                    format!("{} = property.eventually {}", lhs, rhs)
+                }
+            )
+        },
+
+        PrptUntil("until"): {
+            defs: [rst],
+            uses: [a, b],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let a = env.print_entity(uses[0].1[0].unwrap());
+                    let b = env.print_entity(uses[1].1[0].unwrap());
+
+                    format!("{} = property.until {}, {}", lhs, a, b)
+                }
+            )
+        },
+        
+        PrptConjunction("and"): {
+            defs: [rst],
+            uses: [a, b],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let a = env.print_entity(uses[0].1[0].unwrap());
+                    let b = env.print_entity(uses[1].1[0].unwrap());
+
+                    format!("{} = property.and {}, {}", lhs, a, b)
+                }
+            )         
+        },
+        
+        PrptImplica("implica"): {
+            defs: [rst],
+            uses: [a, b],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let a = env.print_entity(uses[0].1[0].unwrap());
+                    let b = env.print_entity(uses[1].1[0].unwrap());
+
+                    format!("{} = property.implica {}, {}", lhs, a, b)
+                }
+            )  
+        },
+
+        PrptSynth("synthesize"): {
+            defs: [],
+            uses: [property],
+            print: (
+                |env: &E, _attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, _, _| {
+                    let property = env.print_entity(uses[0].1[0].unwrap());
+
+                    let attr = "";
+
+                    format!("property.synthesize {} {}", property, attr)
+                }
+
+            )
+        },
+    }
+}
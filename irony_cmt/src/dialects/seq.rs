@@ -0,0 +1,107 @@
+use crate::*;
+
+irony::dialect_def! {
+    [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+
+    SeqDialect = "seq" {
+        SeqCompReg("compreg"): {
+            defs: [output],
+            uses: [input, clk,reset,reset_val],
+            traits: [Sequential],
+            constraints: [/* TODO: fill this */],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let output_name = env.print_entity(defs[0].1[0].unwrap());
+                    let input_name = match uses.iter().find(|(name, _)| name == "input").and_then(|(_, ids)| Some(env.print_entity(ids[0].unwrap()))) {
+                        Some(name) => name,
+                        None => format!(""),
+                    };
+                    let clk = match uses.iter().find(|(name, _)| name == "clk").and_then(|(_, ids)| Some(env.print_entity(ids[0].unwrap()))) {
+                        Some(name) => name,
+                        None => format!(""),
+                    };
+                    let reset = match uses.iter().find(|(name, _)| name == "reset").and_then(|(_, ids)| {
+                        if let Some(id) = ids[0] {
+                            Some(env.print_entity(id))
+                        } else {
+                            None
+                        }}) {
+                        Some(name) => name,
+                        None => format!(""),
+                    };
+                    let reset_val = match uses.iter().find(|(name, _)| name == "reset_val").and_then(|(_, ids)| {
+                        if let Some(id) = ids[0] {
+                            Some(env.print_entity(id))
+                        } else {
+                            None
+                        }}) {
+                        Some(name) => name,
+                        None => format!(""),
+                    };
+
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+
+                    format!("{} = seq.compreg {} {} {} {} : {}", output_name, input_name, clk, reset, reset_val, typ)
+                }
+            )
+        },
+
+        SeqHlmem: {
+            defs: [handle],
+            uses: [clk, reset],
+            constraints: [/* TODO: fill this */],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let handle = env.print_entity(defs[0].1[0].unwrap());
+                    let uses = uses.iter().filter_map(|(_, ids)| ids[0]).map(|id| env.print_entity(id)).collect::<Vec<_>>().join(", ");
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = seq.hlmem {} : {}", handle, uses, typ)
+                }
+            )
+        },
+
+        SeqRead: {
+            defs: [rdata],
+            uses: [mem, renable; address],
+            attrs: [latency: IdAttr(IdAttr)],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    // a missing operand prints as nothing, as in `seq.compreg`
+                    let print = |id: Option<EntityId>| id.map(|id| env.print_entity(id)).unwrap_or_default();
+                    let rdata = print(defs[0].1[0]);
+                    let mem = print(uses[0].1[0]);
+                    let renable = print(uses[1].1[0]);
+                    let address = uses[2].1.iter().map(|&id| print(id)).collect::<Vec<_>>().join(", ");
+                    let latency = match irony::utils::extract_vec(&attrs, "latency") {
+                        Some(AttributeEnum::IdAttr(IdAttr(latency))) => latency.to_string(),
+                        _ => String::new(),
+                    };
+                    let typ = uses[0].1[0].and_then(|mem| env.get_entity(mem).get_dtype()).map(|typ| typ.to_string()).unwrap_or_default();
+                    format!("{} = seq.read {}[{}] rden {} {{latency = {}}} : {}", rdata, mem, address, renable, latency, typ)
+                }
+            )
+        },
+
+        SeqWrite: {
+            defs: [],
+            uses: [mem, wenable, wdata; address],
+            attrs: [latency: IdAttr(IdAttr)],
+            traits: [HasSideEffects],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, _, _| {
+                    let print = |id: Option<EntityId>| id.map(|id| env.print_entity(id)).unwrap_or_default();
+                    let mem = print(uses[0].1[0]);
+                    let wenable = print(uses[1].1[0]);
+                    let wdata = print(uses[2].1[0]);
+                    let address = uses[3].1.iter().map(|&id| print(id)).collect::<Vec<_>>().join(", ");
+                    let latency = match irony::utils::extract_vec(&attrs, "latency") {
+                        Some(AttributeEnum::IdAttr(IdAttr(latency))) => latency.to_string(),
+                        _ => String::new(),
+                    };
+                    let typ = uses[0].1[0].and_then(|mem| env.get_entity(mem).get_dtype()).map(|typ| typ.to_string()).unwrap_or_default();
+                    format!("seq.write {}[{}] {} wren {} {{latency = {}}} : {}", mem, address, wdata, wenable, latency, typ)
+                }
+            )
+        },
+    }
+}
@@ -0,0 +1,52 @@
+use crate::*;
+
+irony::dialect_def! {
+    [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+
+    SequenceDialect = "sequence" {
+        SqnFromEvent("from_event"): {
+            defs: [lhs],
+            uses: [rhs],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+
+                    format!("{} = sequence.from_event {}", lhs, rhs)
+                }
+            )
+        },
+
+        SqnDelay("delay"): {
+            defs: [lhs],
+            uses: [rhs],
+            attrs: [lb: IdAttr(IdAttr)(*), ub: IdAttr(IdAttr)(*)],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let rhs = env.print_entity(uses[0].1[0].unwrap());
+                    let lb = irony::utils::extract_vec(&attrs, "lb").unwrap();
+                    let ub = match irony::utils::extract_vec(&attrs, "ub") {
+                        Some(x) => format!("{}", x),
+                        None => format!(""),
+                    };
+                    format!("{} = sequence.delay {} [{}:{}]", lhs, rhs, lb, ub)
+                }
+            )
+        },
+
+        SqnConcat("concat"): {
+            defs: [lhs],
+            uses: [s0, s1],
+            print: (
+                |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs:Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let s0 = env.print_entity(uses[0].1[0].unwrap());
+                    let s1 = env.print_entity(uses[1].1[0].unwrap());
+
+                    format!("{} = sequence.concat {}, {}", lhs, s0, s1)
+                }
+            )
+        },
+    }
+}
@@ -0,0 +1,110 @@
+use crate::*;
+
+irony::dialect_def! {
+    [data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum]
+
+    TemporaryDialect = "temporary" {
+        Cases: {
+            defs: [; results],
+            uses: [; conds],
+            attrs: [ onehot: BoolAttr(BoolAttr)(*)],
+            regions: [dflt; bodies],
+            constraints: [/* TODO */],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, regions: Vec<(String, Vec<RegionId>)>| {
+
+                    let results = defs[0].1.iter().map(|id| {
+                        format!("{}", env.print_entity((*id).unwrap()))
+                    }).collect::<Vec<_>>().join(", ");
+
+                    let mode = if let AttributeEnum::BoolAttr(BoolAttr(x)) = irony::utils::extract_vec(&attrs, "onehot").unwrap() {
+                        if x {
+                            "onehot"
+                        } else {
+                            "priority"
+                        }
+                    } else {
+                        "priority"
+                    };
+
+                    let cases =
+                        uses[0].1.iter().zip(regions[1].1.iter()).map(|(cond, body)| {
+                            format!("{} : {{\n{}\n}}", env.print_entity(cond.unwrap()), env.print_region(*body))
+                        }).collect::<Vec<_>>().join(", \n");
+
+                    let default =
+                        format!("default : {{\n{}\n}}", env.print_region(regions[0].1[0]));
+
+                    let res_typs = defs[0].1.iter().map(|id| {
+                        format!("{}", env.get_entity((*id).unwrap()).get_dtype().unwrap())
+                    }).collect::<Vec<_>>().join(", ");
+
+                    format!("{} = cases {} {{\n{}\n{}\n}} : {}", results, mode, irony::utils::print::tab(cases), irony::utils::print::tab(default), res_typs)
+                }
+            )
+        },
+
+        Select: {
+            defs: [lhs],
+            uses: [default; conds, values],
+            attrs: [ onehot: BoolAttr(BoolAttr)(*)],
+            traits: [Pure],
+            constraints: [/* TODO */],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+
+                    let mode = if let AttributeEnum::BoolAttr(BoolAttr(x)) = irony::utils::extract_vec(&attrs, "onehot").unwrap() {
+                        if x {
+                            "onehot"
+                        } else {
+                            "priority"
+                        }
+                    } else {
+                        "priority"
+                    };
+
+                    let candidates = uses[2].1.iter().zip(uses[1].1.iter()).map(|(value, cond)| {
+                        format!("\t{} : {}", env.print_entity(cond.unwrap()), env.print_entity(value.unwrap()))
+                    }).collect::<Vec<_>>().join(", \n");
+
+
+                    let default =  if let Some(default) = uses[0].1[0] {
+                        format!("\tdefault : {}\n", env.print_entity(default))
+                    } else  { String::default() } ;
+
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = ILLEGAL.select {} {{\n{}\n{}}} : {}", lhs, mode, candidates, default, typ)
+                }
+            )
+        },
+
+        CombUnary: {
+            defs: [lhs],
+            uses: [op],
+            attrs: [predicate: CombUnaryPredicate(CombUnaryPredicate)(*)],
+            traits: [Pure, SameOperandsAndResultType],
+            print: (
+                |env: &E, attrs: Vec<(String, AttributeEnum)>, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let def = env.print_entity(defs[0].1[0].unwrap());
+                    let uses = vec![env.print_entity(uses[0].1[0].unwrap())].join(", ");
+                    let AttributeEnum::CombUnaryPredicate(predicate) = irony::utils::extract_vec(&attrs, "predicate").unwrap() else { panic!("")};
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = ILLEGAL.{} {} : {}", def, predicate, uses, typ)
+                }
+            )
+        },
+
+        Invalid: {
+            defs: [lhs],
+            uses: [],
+            print: (
+                |env: &E, _, _, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                    let lhs = env.print_entity(defs[0].1[0].unwrap());
+                    let typ = env.get_entity(defs[0].1[0].unwrap()).get_dtype().unwrap();
+                    format!("{} = ILLEGAL.invalid : {}", lhs, typ)
+                }
+            )
+        },
+    }
+}
@@ -0,0 +1,626 @@
+use irony::{
+    Entity, EntityId, Environ, FxHashMap, FxHashSet, IronyError, Loc, Op, OpId, RegionId,
+};
+
+use crate::{
+    utils, ArrayAttr, Assign, AttributeEnum, CombMux2, CombVariadic, CombVariadicPredicate,
+    ConstantAttr, DataTypeEnum, EntityEnum, EventUnion, HwConstant, HwInput, OpEnum, StringAttr,
+    TypeAttr, Wire,
+};
+
+pub fn is_event<E: Environ>(env: &E, entity: EntityId) -> bool {
+    env.try_get_entity(entity).is_ok_and(|entity| entity.get_entity_name() == "Event")
+}
+
+/// the fathers `son` is united into by `event.union`
+pub fn get_union_fathers<E: Environ>(env: &E, son: EntityId) -> Vec<EntityId> {
+    let union = EventUnion::op_info().name.as_str();
+    env.get_uses(son)
+        .into_iter()
+        .map(|op| env.get_op(op))
+        .filter(|op| op.get_op_name() == union)
+        .filter_map(|op| {
+            let uses = op.get_uses();
+            (uses[1].1[0] == Some(son)).then_some(uses[0].1[0]).flatten()
+        })
+        .collect()
+}
+
+/// the fathers of `event` up to its root, stopping before an event seen twice
+pub fn get_event_ancestors<E: Environ>(env: &E, event: EntityId) -> Vec<EntityId> {
+    let mut ancestors = vec![];
+    let mut current = event;
+    while let Some(&father) = get_union_fathers(env, current).first() {
+        if father == event || ancestors.contains(&father) {
+            break;
+        }
+        ancestors.push(father);
+        current = father;
+    }
+    ancestors
+}
+
+/// whether `event` is created in a region enclosing `op`, or at the top level
+pub fn is_event_in_scope<E: Environ>(env: &E, event: EntityId, op: OpId) -> bool {
+    match env.get_entity(event).get_parent() {
+        Some(region) => env.get_ancestor_in(region, op).is_some(),
+        None => true,
+    }
+}
+
+/// whether the ops in `body` only refer to the events in scope
+pub fn are_block_events_in_scope<E: Environ>(env: &E, body: RegionId) -> bool {
+    env.walk_ops(Some(body)).into_iter().all(|op| {
+        let uses = env.get_op(op).get_uses().into_iter().flat_map(|(_, uses)| uses).flatten();
+        uses.filter(|&used| is_event(env, used)).all(|event| is_event_in_scope(env, event, op))
+    })
+}
+
+/// The events of a module and how they relate. `event.union father <- son` makes `son` a
+/// sub-event of `father`, so the unions form a forest of events, the roots being the
+/// coarsest ones. `event.else_of e <- t` makes `e` fire when the father of `t` does but
+/// `t` does not. `event.block` ties ops to the cycles its event fires in.
+#[derive(Clone, Debug, Default)]
+pub struct EventGraph {
+    events: Vec<EntityId>,
+    /// (father, son)
+    unions: Vec<(EntityId, EntityId)>,
+    /// (e, t)
+    else_ofs: Vec<(EntityId, EntityId)>,
+    blocks: Vec<(EntityId, OpId)>,
+}
+
+impl EventGraph {
+    pub fn new<E>(env: &E, module: OpId) -> Self
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        let mut graph = Self::default();
+        let body = env.get_op(module).get_regions()[0].1[0];
+        for op_id in utils::walk_region_ops(env, body) {
+            let op = env.get_op(op_id);
+            let defs = op.get_defs().into_iter().flat_map(|(_, defs)| defs);
+            let uses = op.get_uses().into_iter().flat_map(|(_, uses)| uses);
+            for entity in defs.chain(uses).flatten() {
+                if is_event(env, entity) && !graph.events.contains(&entity) {
+                    graph.events.push(entity);
+                }
+            }
+            match op {
+                OpEnum::EventUnion(op) => {
+                    if let (Some(father), Some(son)) = (op.father(), op.son()) {
+                        graph.unions.push((father, son));
+                    }
+                },
+                OpEnum::EventElseOf(op) => {
+                    if let (Some(e), Some(t)) = (op.e(), op.t()) {
+                        graph.else_ofs.push((e, t));
+                    }
+                },
+                OpEnum::EventBlockDef(op) => {
+                    if let Some(event) = op.event() {
+                        graph.blocks.push((event, op_id));
+                    }
+                },
+                _ => {},
+            }
+        }
+        graph
+    }
+
+    pub fn get_events(&self) -> Vec<EntityId> { self.events.to_owned() }
+
+    /// the first father of `son`, the only one in a forest
+    pub fn get_father(&self, son: EntityId) -> Option<EntityId> {
+        self.unions.iter().find(|(_, other)| *other == son).map(|(father, _)| *father)
+    }
+
+    pub fn get_sons(&self, father: EntityId) -> Vec<EntityId> {
+        self.unions.iter().filter(|(other, _)| *other == father).map(|(_, son)| *son).collect()
+    }
+
+    pub fn get_roots(&self) -> Vec<EntityId> {
+        self.events.iter().copied().filter(|&event| self.get_father(event).is_none()).collect()
+    }
+
+    /// the fathers of `event` up to its root, stopping before an event seen twice
+    pub fn get_ancestors(&self, event: EntityId) -> Vec<EntityId> {
+        let mut ancestors = vec![];
+        let mut current = event;
+        while let Some(father) = self.get_father(current) {
+            if father == event || ancestors.contains(&father) {
+                break;
+            }
+            ancestors.push(father);
+            current = father;
+        }
+        ancestors
+    }
+
+    /// how many fathers `event` has above it, 0 for a root
+    pub fn get_depth(&self, event: EntityId) -> usize { self.get_ancestors(event).len() }
+
+    /// whether `event` is `ancestor` or fires only when it does
+    pub fn is_sub_event(&self, event: EntityId, ancestor: EntityId) -> bool {
+        event == ancestor || self.get_ancestors(event).contains(&ancestor)
+    }
+
+    /// the events `e` is the else of
+    pub fn get_else_of(&self, e: EntityId) -> Vec<EntityId> {
+        self.else_ofs.iter().filter(|(other, _)| *other == e).map(|(_, t)| *t).collect()
+    }
+
+    /// the `event.block` ops of `event`
+    pub fn get_blocks(&self, event: EntityId) -> Vec<OpId> {
+        self.blocks.iter().filter(|(other, _)| *other == event).map(|(_, op)| *op).collect()
+    }
+
+    /// Find events united into themselves, returning one such cycle from son to father
+    pub fn find_cycle(&self) -> Option<Vec<EntityId>> {
+        self.events.iter().find_map(|&event| {
+            let mut path = vec![event];
+            let mut current = event;
+            while let Some(father) = self.get_father(current) {
+                if father == event {
+                    return Some(path);
+                }
+                if path.contains(&father) {
+                    return None;
+                }
+                path.push(father);
+                current = father;
+            }
+            None
+        })
+    }
+
+    /// whether every event has one father at most and none is its own ancestor
+    pub fn is_forest(&self) -> bool {
+        let sons = self.unions.iter().map(|(_, son)| *son).collect::<Vec<_>>();
+        let unique = sons.iter().collect::<FxHashSet<_>>().len() == sons.len();
+        unique && self.find_cycle().is_none()
+    }
+}
+
+/// An event or an op in an event block that cannot be turned into plain logic
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventError {
+    pub op: Option<OpId>,
+    pub message: String,
+}
+
+impl std::fmt::Display for EventError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<EventError> for IronyError {
+    fn from(err: EventError) -> Self { IronyError::Failed { op: err.op, message: err.message } }
+}
+
+/// the guard, the value and the location of an assignment in an event block
+type Driver = (EntityId, EntityId, Option<Loc>);
+
+struct EventLowering<'env, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> {
+    env: &'env mut E,
+    module: OpId,
+    body: RegionId,
+    /// the 1-bit wire high in the cycles each event fires in
+    fires: FxHashMap<EntityId, EntityId>,
+    pending: FxHashSet<EntityId>,
+    one: Option<EntityId>,
+    /// the wires assigned in event blocks, with their assignments
+    drivers: Vec<(EntityId, Vec<Driver>)>,
+    /// the location of the ops being lowered, given to the new ops
+    loc: Option<Loc>,
+}
+
+impl<E: Environ<EntityT = EntityEnum, OpT = OpEnum>> EventLowering<'_, E> {
+    fn error<T>(&self, op: Option<OpId>, message: String) -> Result<T, EventError> {
+        Err(EventError { op, message })
+    }
+
+    fn add_op(&mut self, op: OpEnum) -> OpId {
+        utils::add_op_at(self.env, op, self.loc.to_owned())
+    }
+
+    fn wire(&mut self, dtype: Option<DataTypeEnum>) -> EntityId {
+        self.env.add_entity(Wire::new(dtype, None, None, None).into())
+    }
+
+    fn constant(&mut self, dtype: Option<DataTypeEnum>, bits: Vec<bool>) -> EntityId {
+        let constant = self.wire(dtype);
+        self.add_op(HwConstant::new(Some(constant), Some(ConstantAttr(bits))).into());
+        constant
+    }
+
+    fn one(&mut self) -> EntityId {
+        if let Some(one) = self.one {
+            utils::fuse_def_loc(self.env, one, self.loc.to_owned());
+            return one;
+        }
+        let one = self.constant(Some(DataTypeEnum::UInt(1.into())), vec![true]);
+        self.one = Some(one);
+        one
+    }
+
+    fn variadic(&mut self, operands: Vec<EntityId>, predicate: CombVariadicPredicate) -> EntityId {
+        match operands[..] {
+            [operand] => operand,
+            _ => {
+                let lhs = self.wire(Some(DataTypeEnum::UInt(1.into())));
+                self.add_op(CombVariadic::new(Some(lhs), operands, Some(predicate)).into());
+                lhs
+            },
+        }
+    }
+
+    fn not(&mut self, operand: EntityId) -> EntityId {
+        let one = self.one();
+        self.variadic(vec![operand, one], CombVariadicPredicate::Xor)
+    }
+
+    fn mux(&mut self, cond: EntityId, then: EntityId, other: EntityId) -> EntityId {
+        let lhs = self.wire(self.env.get_entity(then).get_dtype());
+        self.add_op(CombMux2::new(Some(lhs), Some(cond), Some(then), Some(other)).into());
+        lhs
+    }
+
+    /// delete `op` but not the entities it defines
+    fn detach(&mut self, op: OpId) {
+        self.env.get_op_entry(op).and_modify(|op| {
+            let defs = op.get_defs().into_iter().map(|(name, defs)| (name, vec![None; defs.len()]));
+            op.set_defs(defs.collect());
+        });
+        self.env.delete_op(op);
+    }
+
+    /// a new 1-bit input port standing for the free event `event`
+    fn input(&mut self, event: EntityId) -> Result<EntityId, EventError> {
+        let Some(AttributeEnum::StringAttr(StringAttr(name))) =
+            self.env.get_entity(event).get_attr("name")
+        else {
+            let message = format!("the free event {} needs a name", self.env.print_entity(event));
+            return self.error(None, message);
+        };
+        let dtype = DataTypeEnum::UInt(1.into());
+        let wire = Wire::new(Some(dtype.to_owned()), Some(name.to_owned().into()), None, None);
+        let input_op = self.env.get_region(self.body).get_op_children().into_iter().find(|&op| {
+            matches!(self.env.get_op(op), OpEnum::HwInput(_))
+        });
+        let port = if let Some(input_op) = input_op {
+            let port = self.env.add_entity(wire.into());
+            self.env.get_op_entry(input_op).and_modify(|op| {
+                let (field, mut inputs) = op.get_defs().remove(0);
+                inputs.push(Some(port));
+                op.set_defs(vec![(field, inputs)]);
+            });
+            port
+        } else if !self.env.get_region(self.body).arguments.is_empty() {
+            self.env.add_region_argument(self.body, wire.into())
+        } else {
+            let port = self.env.add_entity(wire.into());
+            let input_op = self.add_op(HwInput::new(vec![port]).into());
+            self.env.get_region_entry(self.body).and_modify(|region| {
+                region.op_children.retain(|&op| op != input_op);
+                region.op_children.insert(0, input_op);
+            });
+            port
+        };
+        self.env.get_op_entry(self.module).and_modify(|op| {
+            let mut attrs = op.get_attrs();
+            for (key, attr) in attrs.iter_mut() {
+                let port = match key.as_str() {
+                    "arg_names" => StringAttr(name.to_owned()).into(),
+                    "arg_types" => TypeAttr(dtype.to_owned()).into(),
+                    _ => continue,
+                };
+                if let AttributeEnum::ArrayAttr(ArrayAttr(ports)) = attr {
+                    ports.push(port);
+                }
+            }
+            op.set_attrs(attrs);
+        });
+        Ok(port)
+    }
+
+    /// The wire high in the cycles `event` fires in: those of the wire it is made from, of
+    /// its sons and of the events it is the else of. An `event.define` with none of these
+    /// is a free event, which becomes an input port.
+    fn fires(&mut self, event: EntityId) -> Result<EntityId, EventError> {
+        if let Some(&fires) = self.fires.get(&event) {
+            return Ok(fires);
+        }
+        let printed = self.env.print_entity(event);
+        if !self.pending.insert(event) {
+            return self.error(None, format!("{} fires depending on itself", printed));
+        }
+        // the new ops are located at the ops the event is made of
+        let ops = self.env.get_defs(event).into_iter().chain(self.env.get_uses(event));
+        let locs = ops.filter(|&op_id| match self.env.get_op(op_id) {
+            OpEnum::EventDef(_) | OpEnum::EventFrom(_) => true,
+            OpEnum::EventUnion(op) => op.father() == Some(event),
+            OpEnum::EventElseOf(op) => op.e() == Some(event),
+            _ => false,
+        });
+        let loc = Loc::fuse(locs.map(|op_id| self.env.get_op_loc(op_id)).collect::<Vec<_>>());
+        let outer = std::mem::replace(&mut self.loc, loc);
+        let mut sources = vec![];
+        let mut free = false;
+        for op_id in self.env.get_defs(event) {
+            match self.env.get_op(op_id) {
+                OpEnum::EventFrom(op) => match op.rhs() {
+                    Some(rhs) => sources.push(rhs),
+                    None => return self.error(Some(op_id), "no wire to make an event from".into()),
+                },
+                OpEnum::EventDef(_) => free = true,
+                _ => {},
+            }
+        }
+        for op_id in self.env.get_uses(event) {
+            match self.env.get_op(op_id).to_owned() {
+                OpEnum::EventUnion(op) if op.father() == Some(event) => {
+                    if let Some(son) = op.son() {
+                        sources.push(self.fires(son)?);
+                    }
+                },
+                OpEnum::EventElseOf(op) if op.e() == Some(event) => {
+                    let Some(t) = op.t() else {
+                        return self.error(Some(op_id), "no event to be the else of".into());
+                    };
+                    let base = match get_union_fathers(self.env, t).first() {
+                        Some(&father) => self.fires(father)?,
+                        None => self.one(),
+                    };
+                    let t = self.fires(t)?;
+                    let not_t = self.not(t);
+                    sources.push(self.variadic(vec![base, not_t], CombVariadicPredicate::And));
+                },
+                _ => {},
+            }
+        }
+        if sources.is_empty() {
+            if !free {
+                return self.error(None, format!("{} is never defined", printed));
+            }
+            sources.push(self.input(event)?);
+        }
+        let fires = self.variadic(sources, CombVariadicPredicate::Or);
+        self.loc = outer;
+        self.pending.remove(&event);
+        self.fires.insert(event, fires);
+        Ok(fires)
+    }
+
+    /// Guard the effects of the ops of `region` by `guard`, if any, and inline the event
+    /// blocks in it
+    fn lower_region(
+        &mut self, region: RegionId, guard: Option<EntityId>,
+    ) -> Result<(), EventError> {
+        for op_id in self.env.get_region(region).get_op_children() {
+            let op = self.env.get_op(op_id).to_owned();
+            if let OpEnum::EventBlockDef(block) = op {
+                let Some(event) = block.event() else {
+                    return self.error(Some(op_id), "an event block has no event".into());
+                };
+                let inner = block.body().unwrap();
+                if !self.env.get_region(inner).arguments.is_empty() {
+                    let message = "event block arguments are not supported".into();
+                    return self.error(Some(op_id), message);
+                }
+                let fires = self.fires(event)?;
+                self.loc = self.env.get_op_loc(op_id);
+                let guard = match guard {
+                    Some(guard) => self.variadic(vec![guard, fires], CombVariadicPredicate::And),
+                    None => fires,
+                };
+                self.lower_region(inner, Some(guard))?;
+                self.inline(op_id, inner, region);
+                continue;
+            }
+            let Some(guard) = guard else {
+                continue;
+            };
+            self.loc = op.get_loc();
+            match op {
+                OpEnum::SeqCompReg(reg) => {
+                    // a clock enable: the register holds its value in the other cycles
+                    let (Some(input), Some(output)) = (reg.input(), reg.output()) else {
+                        return self.error(Some(op_id), "a register is not connected".into());
+                    };
+                    let next = self.mux(guard, input, output);
+                    self.set_use(op_id, "input", next);
+                },
+                OpEnum::SeqRead(op) => {
+                    let enable = self.enable(op.renable(), guard);
+                    self.set_use(op_id, "renable", enable);
+                },
+                OpEnum::SeqWrite(op) => {
+                    let enable = self.enable(op.wenable(), guard);
+                    self.set_use(op_id, "wenable", enable);
+                },
+                OpEnum::Assign(op) => {
+                    let (Some(lhs), Some(rhs)) = (op.lhs(), op.rhs()) else {
+                        return self.error(Some(op_id), "an assignment is not connected".into());
+                    };
+                    let driver = (guard, rhs, self.loc.to_owned());
+                    match self.drivers.iter_mut().find(|(wire, _)| *wire == lhs) {
+                        Some((_, drivers)) => drivers.push(driver),
+                        None => self.drivers.push((lhs, vec![driver])),
+                    }
+                    self.detach(op_id);
+                },
+                OpEnum::HwInstance(_) => {
+                    let message = "an instance in an event block cannot be guarded".into();
+                    return self.error(Some(op_id), message);
+                },
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    fn enable(&mut self, enable: Option<EntityId>, guard: EntityId) -> EntityId {
+        match enable {
+            Some(enable) => self.variadic(vec![enable, guard], CombVariadicPredicate::And),
+            None => guard,
+        }
+    }
+
+    fn set_use(&mut self, op: OpId, field: &str, entity: EntityId) {
+        self.env.get_op_entry(op).and_modify(|op| {
+            let mut uses = op.get_uses();
+            for (name, uses) in uses.iter_mut() {
+                if name == field {
+                    *uses = vec![Some(entity)];
+                }
+            }
+            op.set_uses(uses);
+        });
+    }
+
+    /// move the ops and entities of the body of `block` into `region` in its place
+    fn inline(&mut self, block: OpId, body: RegionId, region: RegionId) {
+        let ops = self.env.get_region(body).get_op_children();
+        let entities = self.env.get_region(body).get_entity_children();
+        for &op in ops.iter() {
+            self.env.get_op_entry(op).and_modify(|op| op.set_parent(Some(region)));
+        }
+        for &entity in entities.iter() {
+            self.env.get_entity_entry(entity).and_modify(|entity| entity.set_parent(Some(region)));
+        }
+        self.env.get_region_entry(body).and_modify(|body| {
+            body.op_children.clear();
+            body.entity_children.clear();
+        });
+        self.env.get_region_entry(region).and_modify(|region| {
+            let position = region.op_children.iter().position(|&op| op == block).unwrap();
+            region.op_children.splice(position..=position, ops);
+            region.entity_children.extend(entities);
+        });
+        self.env.delete_op(block);
+    }
+
+    /// Drive the wires assigned in event blocks by a chain of multiplexers, the last
+    /// assignment winning. In the cycles no guard holds a wire keeps the value it is
+    /// assigned outside of the blocks, or zero.
+    fn drive(&mut self) -> Result<(), EventError> {
+        for (wire, drivers) in std::mem::take(&mut self.drivers) {
+            let defs = self.env.get_defs(wire);
+            // the driver is located at all the assignments to the wire
+            let locs = defs.iter().map(|&def| self.env.get_op_loc(def));
+            let loc = Loc::fuse(locs.chain(drivers.iter().map(|(_, _, loc)| loc.to_owned())));
+            self.loc = loc.to_owned();
+            let mut value = match defs[..] {
+                [] => {
+                    let dtype = self.env.get_entity(wire).get_dtype();
+                    let Some(width) = dtype.as_ref().map_or(Some(0), |dtype| dtype.try_width())
+                    else {
+                        let message = format!("{} has no bit width", self.env.print_entity(wire));
+                        return self.error(None, message);
+                    };
+                    self.constant(dtype, vec![false; width])
+                },
+                [def] => match self.env.get_op(def) {
+                    OpEnum::Assign(op) if op.rhs().is_some() => {
+                        let rhs = op.rhs().unwrap();
+                        self.detach(def);
+                        rhs
+                    },
+                    _ => {
+                        let wire = self.env.print_entity(wire);
+                        let message = format!("{} is driven inside and outside event blocks", wire);
+                        return self.error(Some(def), message);
+                    },
+                },
+                _ => {
+                    let message = format!("{} has several drivers", self.env.print_entity(wire));
+                    return self.error(defs.first().copied(), message);
+                },
+            };
+            for (guard, rhs, driver_loc) in drivers {
+                self.loc = driver_loc;
+                value = self.mux(guard, rhs, value);
+            }
+            self.loc = loc;
+            self.add_op(Assign::new(Some(wire), Some(value)).into());
+        }
+        Ok(())
+    }
+}
+
+/// Turn the events of an `HwModule` into plain logic.
+///
+/// Each event becomes the 1-bit wire high in the cycles it fires in, see [`EventGraph`];
+/// a free `event.define` becomes an input port named after it. `event.eval` reads that
+/// wire, and the body of an `event.block` is inlined with its effects guarded: registers
+/// are enabled and memory ports only act when the event fires, and a wire assigned in
+/// blocks takes the value of the last block whose event fires, or else its value outside
+/// of them. Other ops are left unguarded. The sequence and property ops must have been
+/// lowered beforehand, e.g. by [`lower_monitors`](crate::lower_monitors). Nothing is
+/// changed on error.
+pub fn lower_events<E>(env: &mut E, module: OpId) -> Result<(), EventError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let OpEnum::HwModule(hw_module) = env.get_op(module) else {
+        let message = "only the events of a module can be lowered".into();
+        return Err(EventError { op: Some(module), message });
+    };
+    let Some(body) = hw_module.body() else {
+        return Err(EventError { op: Some(module), message: "the module has no body".into() });
+    };
+    let ops = utils::walk_region_ops(env, body);
+    if let Some(&op) = ops
+        .iter()
+        .find(|&&op| matches!(env.get_op(op).get_dialect(), Some("sequence" | "property")))
+    {
+        let message = "the properties must be lowered before the events".into();
+        return Err(EventError { op: Some(op), message });
+    }
+
+    let mut transaction = env.transaction();
+    transaction.begin_region(Some(body));
+    let mut lowering = EventLowering {
+        env: &mut *transaction,
+        module,
+        body,
+        fires: FxHashMap::default(),
+        pending: FxHashSet::default(),
+        one: None,
+        drivers: vec![],
+        loc: None,
+    };
+    let result = lowering.lower_region(body, None).and_then(|_| lowering.drive()).and_then(|_| {
+        for op_id in utils::walk_region_ops(lowering.env, body) {
+            if let OpEnum::EventEval(op) = lowering.env.get_op(op_id) {
+                let (lhs, event) = (op.lhs(), op.rhs());
+                let Some(event) = event else {
+                    return lowering.error(Some(op_id), "no event to evaluate".into());
+                };
+                let fires = lowering.fires(event)?;
+                lowering.loc = lowering.env.get_op_loc(op_id);
+                lowering.detach(op_id);
+                lowering.add_op(Assign::new(lhs, Some(fires)).into());
+            }
+        }
+        Ok(())
+    });
+    transaction.end_region();
+    result?;
+
+    for op_id in utils::walk_region_ops(&*transaction, body) {
+        if transaction.get_op(op_id).get_dialect() == Some("event") {
+            transaction.delete_op(op_id);
+        }
+    }
+    for entity in transaction.get_region(body).get_entity_children() {
+        if is_event(&*transaction, entity) {
+            transaction.delete_entity(entity);
+        }
+    }
+    if let Err(ops) = irony::sort_topologically(&mut *transaction, body) {
+        let message = "the guards close a combinational cycle".into();
+        return Err(EventError { op: ops.first().copied(), message });
+    }
+    transaction.commit();
+    Ok(())
+}
+
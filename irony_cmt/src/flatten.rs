@@ -0,0 +1,136 @@
+use irony::{Entity, EntityId, Environ, IrMapping, IronyError, IronyResult, Loc, Op, OpId};
+
+use crate::{utils, AttributeEnum, EntityEnum, InstanceGraph, OpEnum, StringAttr};
+
+/// the name of a cloned entity, prefixed with the name of the instance it comes from
+fn prefix_name<E>(env: &mut E, entity: EntityId, prefix: &str)
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let Some(AttributeEnum::StringAttr(StringAttr(name))) = env.get_entity(entity).get_attr("name")
+    else {
+        return;
+    };
+    env.get_entity_entry(entity).and_modify(|entity| {
+        entity.set_attrs(vec![(
+            "name".to_owned(),
+            StringAttr(format!("{}_{}", prefix, name)).into(),
+        )])
+    });
+}
+
+/// Inline the body of the module targeted by `instance` in place of the instance.
+///
+/// Entities cloned from the module are prefixed by the instance name, the module ports
+/// are replaced by the instance inputs and the instance outputs by the `HwOutput` uses.
+pub fn inline_instance<E>(env: &mut E, instance: OpId) -> IronyResult<()>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let error = |message: &str| Err(IronyError::failed(Some(instance), message));
+    let OpEnum::HwInstance(inst) = env.get_op(instance).to_owned() else {
+        return error("only HwInstance can be inlined");
+    };
+    let prefix = inst.name().map(|StringAttr(name)| name.to_owned()).unwrap_or_default();
+    let Some(target) = utils::get_instance_target(env, instance) else {
+        return error("the target of the instance cannot be found");
+    };
+    let OpEnum::HwModule(module) = env.get_op(target) else {
+        return error("the target of an instance must be a module");
+    };
+    let Some(body) = module.body() else {
+        return error("the target of the instance has no body");
+    };
+    let Some(caller) = inst.get_parent() else {
+        return error("an instance must be placed in a region");
+    };
+
+    let mut mapping = IrMapping::new();
+    for (port, operand) in utils::get_input_ports(env, body).into_iter().zip(inst.inputs()) {
+        mapping.map_entity(port, *operand);
+    }
+    let mut outputs = vec![];
+    let mut ops = vec![];
+    for op_id in env.get_region(body).get_op_children() {
+        match env.get_op(op_id) {
+            OpEnum::HwInput(_) => {},
+            OpEnum::HwOutput(output) => outputs = output.outputs().to_vec(),
+            _ => ops.push(op_id),
+        }
+    }
+    let ports = mapping.get_entity_map().keys().copied().collect::<Vec<_>>();
+
+    let before = env.get_region(caller).op_children.len();
+    env.clone_ops_with(&ops, Some(caller), &mut mapping);
+    // the inlined ops are located in the module, called from the instance
+    if let Some(caller_loc) = inst.get_loc() {
+        for (&old, &new) in mapping.get_op_map().iter() {
+            if let Some(callee_loc) = env.get_op_loc(old) {
+                env.set_op_loc(new, Some(Loc::call_site(callee_loc, caller_loc.to_owned())));
+            }
+        }
+    }
+    for (old, new) in mapping.get_entity_map().to_owned() {
+        if !ports.contains(&old) {
+            prefix_name(env, new, &prefix);
+        }
+    }
+
+    // move the cloned ops to where the instance was
+    env.get_region_entry(caller).and_modify(|region| {
+        let cloned = region.op_children.split_off(before);
+        let position = region.op_children.iter().position(|&op| op == instance).unwrap();
+        region.op_children.splice(position..position + 1, cloned);
+    });
+
+    for (result, output) in inst.outputs().iter().zip(outputs.iter()) {
+        let output = mapping.lookup_entity_or_same(*output);
+        env.replace_all_uses(*result, output);
+    }
+
+    env.delete_op(instance);
+    Ok(())
+}
+
+/// Inline instances in `module` until none is left when `recursive`, or only the
+/// instances placed directly in it otherwise. Nothing changes if an instance cannot be
+/// inlined.
+pub fn flatten_module<E>(env: &mut E, module: OpId, recursive: bool) -> IronyResult<()>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    if recursive && InstanceGraph::new(env)?.is_recursive() {
+        let message = "a recursive hierarchy cannot be flattened";
+        return Err(IronyError::failed(Some(module), message));
+    }
+    let OpEnum::HwModule(hw_module) = env.get_op(module) else {
+        return Err(IronyError::failed(Some(module), "only a module can be flattened"));
+    };
+    let body = hw_module.body.unwrap();
+
+    env.try_transaction(|env| loop {
+        let instances = utils::walk_region_ops(env, body)
+            .into_iter()
+            .filter(|&op| matches!(env.get_op(op), OpEnum::HwInstance(_)))
+            .collect::<Vec<_>>();
+        if instances.is_empty() {
+            return Ok(());
+        }
+        for instance in instances {
+            inline_instance(env, instance)?;
+        }
+        if !recursive {
+            return Ok(());
+        }
+    })
+}
+
+/// Flatten the whole hierarchy into the single top module, deleting the modules which
+/// are no longer instantiated.
+pub fn flatten_top<E>(env: &mut E) -> IronyResult<OpId>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let tops = InstanceGraph::new(env)?.get_top_modules();
+    let [top] = tops[..] else {
+        let message = format!("the hierarchy has {} top modules instead of one", tops.len());
+        return Err(IronyError::failed(None, message));
+    };
+    flatten_module(env, top, true)?;
+    for module in InstanceGraph::new(env)?.unused_modules() {
+        env.delete_op(module);
+    }
+    Ok(top)
+}
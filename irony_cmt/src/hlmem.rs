@@ -0,0 +1,574 @@
+use irony::{
+    Entity, EntityId, Environ, FxHashMap, FxHashSet, IronyError, Loc, Op, OpId, Region,
+    RegionId,
+};
+
+use crate::{
+    utils, ArrayAttr, Assign, AttributeEnum, CombConcat, CombExtract, CombICmp, CombICmpPredicate,
+    CombMux2, CombVariadic, CombVariadicPredicate, ConstantAttr, DataTypeEnum, EntityEnum,
+    HwConstant, HwInput, HwInstance, HwModule, HwOutput, IdAttr, OpEnum, SeqCompReg, SeqHlmemType,
+    StringAttr, TypeAttr, Wire,
+};
+
+/// A `seq.hlmem` or one of its ports that cannot be lowered
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryError {
+    pub op: Option<OpId>,
+    pub message: String,
+}
+
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<MemoryError> for IronyError {
+    fn from(err: MemoryError) -> Self { IronyError::Failed { op: err.op, message: err.message } }
+}
+
+/// a `seq.read` or `seq.write` of a memory
+struct Port {
+    op: OpId,
+    enable: EntityId,
+    address: Vec<EntityId>,
+    /// the data written by a write port, or read by a read port
+    data: EntityId,
+    latency: usize,
+}
+
+struct Memory {
+    op: OpId,
+    name: Option<String>,
+    elem: DataTypeEnum,
+    /// the bits of a word
+    word_width: usize,
+    dims: Vec<usize>,
+    clk: Option<EntityId>,
+    reset: Option<EntityId>,
+    reads: Vec<Port>,
+    writes: Vec<Port>,
+}
+
+impl Memory {
+    fn depth(&self) -> usize { self.dims.iter().product() }
+
+    fn bits(&self) -> usize { self.depth() * self.word_width }
+}
+
+fn error<T>(op: OpId, message: String) -> Result<T, MemoryError> {
+    Err(MemoryError { op: Some(op), message })
+}
+
+fn get_name<E>(env: &E, entity: EntityId) -> Option<String>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    match env.get_entity(entity).get_attr("name") {
+        Some(AttributeEnum::StringAttr(StringAttr(name))) => Some(name),
+        _ => None,
+    }
+}
+
+/// the memories defined in `body` with their ports
+fn get_memories<E>(env: &E, body: RegionId) -> Result<Vec<Memory>, MemoryError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let ops = utils::walk_region_ops(env, body);
+    let mut memories = vec![];
+    for &op_id in ops.iter() {
+        let OpEnum::SeqHlmem(op) = env.get_op(op_id) else { continue };
+        let Some(handle) = op.handle() else {
+            return error(op_id, "the memory defines no handle".into());
+        };
+        let Some(DataTypeEnum::SeqHlmem(SeqHlmemType(elem, dims))) =
+            env.get_entity(handle).get_dtype()
+        else {
+            return error(op_id, "the handle is not typed as a memory".into());
+        };
+        let Some(word_width) = elem.try_width() else {
+            return error(op_id, format!("words of type {} cannot be stored", elem));
+        };
+        if dims.is_empty() || dims.contains(&0) {
+            return error(op_id, "the memory holds no words".into());
+        }
+
+        let mut memory = Memory {
+            op: op_id,
+            name: get_name(env, handle),
+            elem: *elem,
+            word_width,
+            dims,
+            clk: op.clk(),
+            reset: op.reset(),
+            reads: vec![],
+            writes: vec![],
+        };
+        for &port in ops.iter() {
+            let (mem, enable, data, address, latency) = match env.get_op(port) {
+                OpEnum::SeqRead(op) => {
+                    (op.mem(), op.renable(), op.rdata(), op.address(), op.latency())
+                },
+                OpEnum::SeqWrite(op) => {
+                    (op.mem(), op.wenable(), op.wdata(), op.address(), op.latency())
+                },
+                _ => continue,
+            };
+            if mem != Some(handle) {
+                continue;
+            }
+            if env.get_op(port).get_parent() != Some(body) {
+                return error(port, "the events must be lowered before the memories".into());
+            }
+            let (Some(enable), Some(data)) = (enable, data) else {
+                return error(port, "the port is missing an operand".into());
+            };
+            let address = address.to_owned();
+            if address.len() != memory.dims.len() {
+                let message = format!("{} dimensions are addressed", memory.dims.len());
+                return error(port, message);
+            }
+            let Some(&IdAttr(latency)) = latency else {
+                return error(port, "the port has no latency".into());
+            };
+            let port = Port { op: port, enable, address, data, latency };
+            match env.get_op(port.op) {
+                OpEnum::SeqRead(_) => memory.reads.push(port),
+                _ if latency == 0 => return error(port.op, "a write takes a cycle at least".into()),
+                _ => memory.writes.push(port),
+            }
+        }
+        memories.push(memory);
+    }
+    Ok(memories)
+}
+
+struct MemoryLowering<'env, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> {
+    env: &'env mut E,
+    /// the region the modules standing for memory macros are defined in
+    scope: Option<RegionId>,
+    constants: FxHashMap<(usize, usize), EntityId>,
+    /// the names of the memories of the module, which the instances are named after
+    names: FxHashSet<String>,
+    /// the location of the ops being lowered, given to the new ops
+    loc: Option<Loc>,
+}
+
+impl<E: Environ<EntityT = EntityEnum, OpT = OpEnum>> MemoryLowering<'_, E> {
+    /// a name for the instance of an unnamed memory, `mem` or `mem_<n>`, unique in the module
+    fn fresh_instance_name(&mut self) -> String {
+        let name = (0..)
+            .map(|n| if n == 0 { "mem".to_owned() } else { format!("mem_{}", n) })
+            .find(|name| !self.names.contains(name))
+            .unwrap();
+        self.names.insert(name.to_owned());
+        name
+    }
+
+    /// the new ops are located at `ops`
+    fn locate(&mut self, ops: impl IntoIterator<Item = OpId>) {
+        self.loc = Loc::fuse(ops.into_iter().map(|op| self.env.get_op_loc(op)).collect::<Vec<_>>());
+    }
+
+    fn add_op(&mut self, op: OpEnum) -> OpId {
+        utils::add_op_at(self.env, op, self.loc.to_owned())
+    }
+
+    fn wire(&mut self, dtype: DataTypeEnum) -> EntityId {
+        self.env.add_entity(Wire::new(Some(dtype), None, None, None).into())
+    }
+
+    fn width(&self, entity: EntityId) -> Result<usize, MemoryError> {
+        match self.env.get_entity(entity).get_dtype() {
+            Some(dtype) => dtype.try_width().ok_or_else(|| {
+                let message = format!("{} has no bit width", self.env.print_entity(entity));
+                MemoryError { op: None, message }
+            }),
+            None => {
+                let message = format!("{} has no type", self.env.print_entity(entity));
+                Err(MemoryError { op: None, message })
+            },
+        }
+    }
+
+    fn constant(&mut self, value: usize, width: usize) -> EntityId {
+        if let Some(&constant) = self.constants.get(&(value, width)) {
+            utils::fuse_def_loc(self.env, constant, self.loc.to_owned());
+            return constant;
+        }
+        let constant = self.wire(DataTypeEnum::UInt(width.into()));
+        let bits = (0..width).map(|bit| bit < usize::BITS as usize && value >> bit & 1 == 1);
+        let attr = Some(ConstantAttr(bits.collect()));
+        self.add_op(HwConstant::new(Some(constant), attr).into());
+        self.constants.insert((value, width), constant);
+        constant
+    }
+
+    /// a word of `memory` with all its bits low
+    fn zero(&mut self, memory: &Memory) -> EntityId {
+        let constant = self.wire(memory.elem.to_owned());
+        let attr = Some(ConstantAttr(vec![false; memory.word_width]));
+        self.add_op(HwConstant::new(Some(constant), attr).into());
+        constant
+    }
+
+    fn variadic(&mut self, operands: Vec<EntityId>, predicate: CombVariadicPredicate) -> EntityId {
+        let dtype = self.env.get_entity(operands[0]).get_dtype().unwrap();
+        let lhs = self.wire(dtype);
+        self.add_op(CombVariadic::new(Some(lhs), operands, Some(predicate)).into());
+        lhs
+    }
+
+    fn and(&mut self, operands: Vec<EntityId>) -> EntityId {
+        match operands[..] {
+            [] => self.constant(1, 1),
+            [operand] => operand,
+            _ => self.variadic(operands, CombVariadicPredicate::And),
+        }
+    }
+
+    fn mux(&mut self, cond: EntityId, then: EntityId, otherwise: EntityId) -> EntityId {
+        let lhs = self.wire(self.env.get_entity(then).get_dtype().unwrap());
+        let mux = CombMux2::new(Some(lhs), Some(cond), Some(then), Some(otherwise));
+        self.add_op(mux.into());
+        lhs
+    }
+
+    /// high when `address` is `value`
+    fn equals(&mut self, address: EntityId, value: usize) -> Result<EntityId, MemoryError> {
+        let width = self.width(address)?;
+        if width < usize::BITS as usize && value >> width != 0 {
+            return Ok(self.constant(0, 1));
+        }
+        let value = self.constant(value, width);
+        let lhs = self.wire(DataTypeEnum::UInt(1.into()));
+        let predicate = Some(CombICmpPredicate::EQ);
+        self.add_op(CombICmp::new(Some(lhs), Some(address), Some(value), predicate).into());
+        Ok(lhs)
+    }
+
+    /// `address` zero-extended or truncated to `width` bits
+    fn resize(&mut self, address: EntityId, width: usize) -> Result<EntityId, MemoryError> {
+        let current = self.width(address)?;
+        if current == width {
+            return Ok(address);
+        }
+        let lhs = self.wire(DataTypeEnum::UInt(width.into()));
+        if current < width {
+            let zeros = self.constant(0, width - current);
+            self.add_op(CombConcat::new(Some(lhs), vec![zeros, address]).into());
+        } else {
+            let low = self.constant(0, 1);
+            self.add_op(CombExtract::new(Some(lhs), Some(address), Some(low)).into());
+        }
+        Ok(lhs)
+    }
+
+    fn reg_to(
+        &mut self, output: EntityId, input: EntityId, reset_val: Option<EntityId>, memory: &Memory,
+    ) {
+        let reg = SeqCompReg::new(Some(output), Some(input), memory.clk, memory.reset, reset_val);
+        self.add_op(reg.into());
+    }
+
+    /// `entity` delayed by `stages` cycles
+    fn delay(&mut self, mut entity: EntityId, stages: usize, clk: Option<EntityId>) -> EntityId {
+        for _ in 0..stages {
+            let output = self.wire(self.env.get_entity(entity).get_dtype().unwrap());
+            self.add_op(SeqCompReg::new(Some(output), Some(entity), clk, None, None).into());
+            entity = output;
+        }
+        entity
+    }
+
+    /// for each dimension, the comparisons of its address against each index
+    fn decode(
+        &mut self, address: &[EntityId], dims: &[usize],
+    ) -> Result<Vec<Vec<EntityId>>, MemoryError> {
+        address
+            .iter()
+            .zip(dims)
+            .map(|(&address, &dim)| (0..dim).map(|index| self.equals(address, index)).collect())
+            .collect()
+    }
+
+    /// the words of `memory` as registers, in row-major order
+    fn registers(&mut self, memory: &Memory) -> Result<(), MemoryError> {
+        let mut writes = vec![];
+        for port in memory.writes.iter() {
+            self.locate([port.op]);
+            let stages = port.latency - 1;
+            let enable = self.delay(port.enable, stages, memory.clk);
+            let address = port
+                .address
+                .iter()
+                .map(|&address| self.delay(address, stages, memory.clk))
+                .collect::<Vec<_>>();
+            let data = self.delay(port.data, stages, memory.clk);
+            writes.push((enable, self.decode(&address, &memory.dims)?, data));
+        }
+        let reads = memory
+            .reads
+            .iter()
+            .map(|port| {
+                self.locate([port.op]);
+                self.decode(&port.address, &memory.dims)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the words are located at the memory and its write ports
+        let writers = memory.writes.iter().map(|port| port.op);
+        self.locate(std::iter::once(memory.op).chain(writers.clone()));
+        let reset_val = memory.reset.map(|_| self.zero(memory));
+        let mut words = vec![];
+        for flat in 0..memory.depth() {
+            let mut rest = flat;
+            let mut index = vec![0; memory.dims.len()];
+            for (dim, &size) in memory.dims.iter().enumerate().rev() {
+                index[dim] = rest % size;
+                rest /= size;
+            }
+            let name = memory.name.as_ref().map(|name| {
+                let index = index.iter().map(|index| format!("_{}", index)).collect::<String>();
+                StringAttr(format!("{}{}", name, index))
+            });
+            let word = Wire::new(Some(memory.elem.to_owned()), name, None, None);
+            let word = self.env.add_entity(word.into());
+            // a later port wins when writing the same word
+            let mut next = word;
+            for ((enable, decoder, data), writer) in writes.iter().zip(writers.clone()) {
+                self.locate([writer]);
+                let mut hits = vec![*enable];
+                hits.extend(index.iter().enumerate().map(|(dim, &index)| decoder[dim][index]));
+                let hit = self.and(hits);
+                next = self.mux(hit, *data, next);
+            }
+            self.locate(std::iter::once(memory.op).chain(writers.clone()));
+            self.reg_to(word, next, reset_val, memory);
+            words.push((index, word));
+        }
+
+        for (port, decoder) in memory.reads.iter().zip(reads) {
+            self.locate([port.op]);
+            let mut data = self.zero(memory);
+            for (index, word) in words.iter() {
+                let hits = index.iter().enumerate().map(|(dim, &index)| decoder[dim][index]);
+                let hit = self.and(hits.collect());
+                data = self.mux(hit, *word, data);
+            }
+            if port.latency > 0 {
+                // the first stage holds the last word read, the others shift it along
+                let held = self.wire(memory.elem.to_owned());
+                let next = self.mux(port.enable, data, held);
+                let reg = SeqCompReg::new(Some(held), Some(next), memory.clk, None, None);
+                self.add_op(reg.into());
+                data = self.delay(held, port.latency - 1, memory.clk);
+            }
+            self.add_op(Assign::new(Some(port.data), Some(data)).into());
+        }
+        Ok(())
+    }
+
+    /// the name of the module standing for the macro of `memory`, defined unless it already is
+    fn macro_module(
+        &mut self, memory: &Memory, address_width: usize,
+    ) -> Result<String, MemoryError> {
+        let ports = memory
+            .reads
+            .iter()
+            .map(|port| format!("_r{}", port.latency))
+            .chain(memory.writes.iter().map(|port| format!("_w{}", port.latency)))
+            .collect::<String>();
+        let reset = if memory.reset.is_some() { "_rst" } else { "" };
+        // the shape and element type as in `!seq.hlmem<2x2xi4>`
+        let shape = memory.dims.iter().map(|size| format!("{}x", size)).collect::<String>();
+        let elem = memory.elem.to_string().replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+        let name = format!("hlmem_{}{}{}{}", shape, elem, ports, reset);
+
+        let bit = DataTypeEnum::UInt(1.into());
+        let address = DataTypeEnum::UInt(address_width.into());
+        let mut inputs = vec![("clk".to_owned(), bit.to_owned())];
+        if memory.reset.is_some() {
+            inputs.push(("rst".into(), bit.to_owned()));
+        }
+        let mut outputs = vec![];
+        for i in 0..memory.reads.len() {
+            inputs.push((format!("r{}_en", i), bit.to_owned()));
+            inputs.push((format!("r{}_addr", i), address.to_owned()));
+            outputs.push((format!("r{}_data", i), memory.elem.to_owned()));
+        }
+        for i in 0..memory.writes.len() {
+            inputs.push((format!("w{}_en", i), bit.to_owned()));
+            inputs.push((format!("w{}_addr", i), address.to_owned()));
+            inputs.push((format!("w{}_data", i), memory.elem.to_owned()));
+        }
+
+        let attrs = |ports: &Vec<(String, DataTypeEnum)>| {
+            let (names, types): (Vec<_>, Vec<_>) = ports
+                .iter()
+                .map(|(name, dtype)| (StringAttr(name.to_owned()), TypeAttr(dtype.to_owned())))
+                .unzip();
+            (Some(names.into()), Some(types.into()))
+        };
+        let (arg_names, arg_types): (Option<ArrayAttr>, Option<ArrayAttr>) = attrs(&inputs);
+        let (output_names, output_types): (Option<ArrayAttr>, Option<ArrayAttr>) = attrs(&outputs);
+        if let Some(existing) = self.env.lookup_symbol(self.scope, &name) {
+            // a macro defined before has the same ports
+            let is_macro = match self.env.get_op(existing) {
+                OpEnum::HwModule(module) => {
+                    module.arg_names() == arg_names.as_ref()
+                        && module.arg_types() == arg_types.as_ref()
+                        && module.output_names() == output_names.as_ref()
+                        && module.output_types() == output_types.as_ref()
+                },
+                _ => false,
+            };
+            if !is_macro {
+                return error(memory.op, format!("`{}` is not a memory macro", name));
+            }
+            return Ok(name);
+        }
+
+        // the body only declares the ports, the outputs are left undriven
+        let body = self.env.add_region(Region::new(true));
+        self.env.begin_region(Some(body));
+        let mut ports = |ports: &Vec<(String, DataTypeEnum)>| {
+            ports
+                .iter()
+                .map(|(name, dtype)| {
+                    let name = Some(name.as_str().into());
+                    let port = Wire::new(Some(dtype.to_owned()), name, None, None);
+                    self.env.add_entity(port.into())
+                })
+                .collect::<Vec<_>>()
+        };
+        let (input_ports, output_ports) = (ports(&inputs), ports(&outputs));
+        self.add_op(HwInput::new(input_ports).into());
+        self.add_op(HwOutput::new(output_ports).into());
+        self.env.end_region();
+
+        let module = HwModule::new(
+            None,
+            Some(StringAttr(name.to_owned())),
+            arg_names,
+            arg_types,
+            output_names,
+            output_types,
+            Some(body),
+        );
+        self.env.begin_region(self.scope);
+        self.add_op(module.into());
+        self.env.end_region();
+        Ok(name)
+    }
+
+    /// an instance of the macro of `memory`, flattening the addresses in row-major order
+    fn instance(&mut self, memory: &Memory) -> Result<(), MemoryError> {
+        let Some(clk) = memory.clk else {
+            return error(memory.op, "a memory macro needs a clock".into());
+        };
+        let address_width = memory.depth().next_power_of_two().trailing_zeros().max(1) as usize;
+        let mut flatten = |port: &Port| -> Result<EntityId, MemoryError> {
+            self.locate([port.op]);
+            let mut flat = self.resize(port.address[0], address_width)?;
+            for (&address, &size) in port.address.iter().zip(memory.dims.iter()).skip(1) {
+                let size = self.constant(size, address_width);
+                let row = self.variadic(vec![flat, size], CombVariadicPredicate::Mul);
+                let address = self.resize(address, address_width)?;
+                flat = self.variadic(vec![row, address], CombVariadicPredicate::Add);
+            }
+            Ok(flat)
+        };
+
+        let mut inputs = vec![clk];
+        inputs.extend(memory.reset);
+        let mut outputs = vec![];
+        for port in memory.reads.iter() {
+            inputs.extend([port.enable, flatten(port)?]);
+            outputs.push(port.data);
+        }
+        for port in memory.writes.iter() {
+            inputs.extend([port.enable, flatten(port)?, port.data]);
+        }
+        // the macro and its instance are located at the memory and all its ports
+        let ports = memory.reads.iter().chain(memory.writes.iter()).map(|port| port.op);
+        self.locate(std::iter::once(memory.op).chain(ports));
+        let target = self.macro_module(memory, address_width)?;
+        let name = match memory.name.to_owned() {
+            Some(name) => name,
+            None => self.fresh_instance_name(),
+        };
+        let instance = HwInstance::new(
+            outputs,
+            inputs,
+            None,
+            Some(target.as_str().into()),
+            Some(StringAttr(name)),
+        );
+        self.add_op(instance.into());
+        Ok(())
+    }
+}
+
+/// Lower the `seq.hlmem` memories of an `HwModule` and their ports, each one chosen by its
+/// size: a memory of at most `max_register_bits` bits becomes an array of `SeqCompReg`,
+/// a larger one an instance of a memory macro.
+///
+/// As registers, each word is named after the memory and its index. A write port of
+/// latency `n` updates the addressed word `n` cycles later, the later port winning on a
+/// conflict. A read port of latency 0 reads combinationally, one of latency `n` holds the
+/// word read when enabled and delivers it `n` cycles later.
+///
+/// A macro is an `HwModule` beside `module` whose body only declares its ports: `clk`,
+/// `rst` for a memory with a reset, then `en`, `addr` and `data` for each read port
+/// `r<i>` and write port `w<i>`. The address is flattened in row-major order, and the
+/// shape, element type and ports are kept in the name of the macro, like
+/// `hlmem_64xi8_r1_w1`. Another module of that name is an error.
+///
+/// The ports must be in the body of `module`, so the events must be lowered first.
+pub fn lower_memories<E>(
+    env: &mut E, module: OpId, max_register_bits: usize,
+) -> Result<(), MemoryError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let OpEnum::HwModule(hw_module) = env.get_op(module) else {
+        return error(module, "only the memories of a module can be lowered".into());
+    };
+    let Some(body) = hw_module.body() else {
+        return error(module, "the module has no body".into());
+    };
+    let memories = get_memories(env, body)?;
+    if memories.is_empty() {
+        return Ok(());
+    }
+
+    let scope = env.get_op(module).get_parent();
+    let mut transaction = env.transaction();
+    transaction.begin_region(Some(body));
+    let names = memories.iter().filter_map(|memory| memory.name.to_owned()).collect();
+    let constants = FxHashMap::default();
+    let mut lowering =
+        MemoryLowering { env: &mut *transaction, scope, constants, names, loc: None };
+    let result = memories.iter().try_for_each(|memory| match memory.bits() <= max_register_bits {
+        true => lowering.registers(memory),
+        false => lowering.instance(memory),
+    });
+    transaction.end_region();
+    result?;
+
+    for memory in memories.iter() {
+        for port in memory.reads.iter() {
+            // the data read is defined by the lowering now
+            transaction.get_op_entry(port.op).and_modify(|op| {
+                op.set_defs(vec![("rdata".into(), vec![None])]);
+            });
+            transaction.delete_op(port.op);
+        }
+        for port in memory.writes.iter() {
+            transaction.delete_op(port.op);
+        }
+        transaction.delete_op(memory.op);
+    }
+    if let Err(ops) = irony::sort_topologically(&mut *transaction, body) {
+        let message = "a read of latency 0 closes a combinational cycle".into();
+        return Err(MemoryError { op: ops.first().copied(), message });
+    }
+    transaction.commit();
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use irony::{Entity, EntityId, Environ, IronyError, Op, OpId, RegionId};
+
+use crate::{
+    utils, ArrayAttr, ArrayType, AttributeEnum, DataTypeEnum, EntityEnum, HwModule, OpEnum,
+    SeqHlmemType, StringAttr, StructType, TypeAttr, UIntType,
+};
+
+/// An inferred dtype disagrees with the dtype an entity already carries
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeConflict {
+    pub op: OpId,
+    pub entity: EntityId,
+    /// `None` when the op needs a dtype with a bit width the declared one lacks
+    pub inferred: Option<DataTypeEnum>,
+    pub declared: DataTypeEnum,
+    pub message: String,
+}
+
+impl std::fmt::Display for TypeConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<TypeConflict> for IronyError {
+    fn from(err: TypeConflict) -> Self { IronyError::failed(Some(err.op), err.message) }
+}
+
+fn i1() -> DataTypeEnum { DataTypeEnum::UInt(UIntType(1)) }
+
+fn dtype_of<E>(env: &E, entity: EntityId) -> Option<DataTypeEnum>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    env.get_entity(entity).get_dtype()
+}
+
+/// all the entities share the first dtype known among them
+fn same_type<E>(
+    env: &E, entities: impl IntoIterator<Item = Option<EntityId>>,
+) -> Vec<(EntityId, DataTypeEnum)>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let entities = entities.into_iter().flatten().collect::<Vec<_>>();
+    match entities.iter().find_map(|&entity| dtype_of(env, entity)) {
+        Some(dtype) => entities.into_iter().map(|entity| (entity, dtype.to_owned())).collect(),
+        None => vec![],
+    }
+}
+
+/// the port types of a module, none if they are not all `TypeAttr`
+fn port_types(types: Option<&ArrayAttr>) -> Vec<DataTypeEnum> {
+    let Some(ArrayAttr(types)) = types else {
+        return vec![];
+    };
+    types
+        .iter()
+        .map(|ty| match ty {
+            AttributeEnum::TypeAttr(TypeAttr(ty)) => Some(ty.to_owned()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .unwrap_or_default()
+}
+
+fn as_module<E>(env: &E, op: Option<OpId>) -> Option<&HwModule>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    match env.get_op(op?) {
+        OpEnum::HwModule(module) => Some(module),
+        _ => None,
+    }
+}
+
+fn zip_types(
+    entities: &[EntityId], types: Vec<DataTypeEnum>,
+) -> Vec<(EntityId, DataTypeEnum)> {
+    entities.iter().copied().zip(types).collect()
+}
+
+fn struct_field(dtype: Option<DataTypeEnum>, field: &Option<StringAttr>) -> Option<DataTypeEnum> {
+    let (Some(DataTypeEnum::Struct(StructType(fields))), Some(StringAttr(field))) = (dtype, field)
+    else {
+        return None;
+    };
+    fields.into_iter().find(|(name, _)| name == field).map(|(_, dtype)| *dtype)
+}
+
+/// The transfer rule of `op`: the dtypes it implies for the entities it defines or uses.
+///
+/// Entities whose dtype cannot be derived yet are left out. Fails if an operand has a
+/// dtype the op cannot take.
+pub fn infer_op_types<E>(
+    env: &E, op_id: OpId,
+) -> Result<Vec<(EntityId, DataTypeEnum)>, TypeConflict>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let mut inferred = vec![];
+    match env.get_op(op_id) {
+        OpEnum::CombVariadic(op) => {
+            inferred.extend(same_type(env, [op.lhs].into_iter().chain(op.operands.iter().map(|&x| Some(x)))))
+        },
+        OpEnum::CombBinary(op) => inferred.extend(same_type(env, [op.lhs, op.op0, op.op1])),
+        OpEnum::CombUnary(op) => inferred.extend(same_type(env, [op.lhs, op.op])),
+        OpEnum::Assign(op) => inferred.extend(same_type(env, [op.lhs, op.rhs])),
+        OpEnum::CombMux2(op) => {
+            inferred.extend(op.cond.map(|cond| (cond, i1())));
+            inferred.extend(same_type(env, [op.lhs, op.op0, op.op1]));
+        },
+        OpEnum::CombICmp(op) => {
+            inferred.extend(op.lhs.map(|lhs| (lhs, i1())));
+            inferred.extend(same_type(env, [op.op0, op.op1]));
+        },
+        OpEnum::CombParity(op) => inferred.extend(op.lhs.map(|lhs| (lhs, i1()))),
+        OpEnum::CombConcat(op) => {
+            let mut widths = vec![];
+            for &operand in op.operands.iter() {
+                let Some(dtype) = dtype_of(env, operand) else {
+                    widths.push(None);
+                    continue;
+                };
+                let Some(width) = dtype.try_width() else {
+                    let message = format!(
+                        "type conflict on {} at {}: {} has no bit width",
+                        env.print_entity(operand),
+                        env.get_op(op_id).get_op_name(),
+                        dtype
+                    );
+                    let (op, entity, declared) = (op_id, operand, dtype);
+                    return Err(TypeConflict { op, entity, inferred: None, declared, message });
+                };
+                widths.push(Some(width));
+            }
+            let widths = widths.into_iter().collect::<Option<Vec<_>>>();
+            if let (Some(lhs), Some(widths)) = (op.lhs, widths) {
+                inferred.push((lhs, DataTypeEnum::UInt(UIntType(widths.into_iter().sum()))));
+            }
+        },
+        OpEnum::Select(op) => {
+            inferred.extend(op.conds.iter().map(|&cond| (cond, i1())));
+            inferred.extend(same_type(
+                env,
+                [op.lhs, op.default].into_iter().chain(op.values.iter().map(|&x| Some(x))),
+            ));
+        },
+        OpEnum::SeqCompReg(op) => {
+            inferred.extend(op.clk.map(|clk| (clk, i1())));
+            inferred.extend(op.reset.map(|reset| (reset, i1())));
+            inferred.extend(same_type(env, [op.output, op.input, op.reset_val]));
+        },
+        OpEnum::SeqRead(op) => {
+            if let (Some(rdata), Some(mem)) = (op.rdata, op.mem) {
+                if let Some(DataTypeEnum::SeqHlmem(SeqHlmemType(elem, _))) = dtype_of(env, mem) {
+                    inferred.push((rdata, *elem));
+                }
+            }
+        },
+        OpEnum::HwStructExtract(op) => {
+            if let (Some(lhs), Some(input)) = (op.lhs, op.struct_input) {
+                inferred.extend(struct_field(dtype_of(env, input), &op.field).map(|ty| (lhs, ty)));
+            }
+        },
+        OpEnum::HwStructInject(op) => {
+            if let (Some(new_value), Some(input)) = (op.new_value, op.struct_input) {
+                inferred
+                    .extend(struct_field(dtype_of(env, input), &op.field).map(|ty| (new_value, ty)));
+            }
+            inferred.extend(same_type(env, [op.lhs, op.struct_input]));
+        },
+        OpEnum::HwStructExplode(op) => {
+            if let Some(DataTypeEnum::Struct(StructType(fields))) =
+                op.struct_input.and_then(|input| dtype_of(env, input))
+            {
+                inferred.extend(zip_types(
+                    &op.outputs,
+                    fields.into_iter().map(|(_, dtype)| *dtype).collect(),
+                ));
+            }
+        },
+        OpEnum::HwArrayCreate(op) => {
+            let operands = same_type(env, op.operands.iter().map(|&x| Some(x)));
+            if let (Some(lhs), Some((_, elem))) = (op.lhs, operands.first()) {
+                inferred.push((
+                    lhs,
+                    DataTypeEnum::Array(ArrayType(Box::new(elem.to_owned()), op.operands.len())),
+                ));
+            }
+            inferred.extend(operands);
+        },
+        OpEnum::HwArrayGet(op) => {
+            if let (Some(lhs), Some(DataTypeEnum::Array(ArrayType(elem, _)))) =
+                (op.lhs, op.array.and_then(|array| dtype_of(env, array)))
+            {
+                inferred.push((lhs, *elem));
+            }
+        },
+        OpEnum::HwArrayConcat(op) => {
+            let arrays = op
+                .operands
+                .iter()
+                .map(|&operand| match dtype_of(env, operand) {
+                    Some(DataTypeEnum::Array(ArrayType(elem, size))) => Some((*elem, size)),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            if let (Some(lhs), Some(arrays)) = (op.lhs, arrays) {
+                if let Some((elem, _)) = arrays.first() {
+                    let size = arrays.iter().map(|(_, size)| size).sum();
+                    inferred.push((lhs, DataTypeEnum::Array(ArrayType(Box::new(elem.to_owned()), size))));
+                }
+            }
+        },
+        OpEnum::HwInstance(op) => {
+            if let Some(module) = as_module(env, utils::get_instance_target(env, op_id)) {
+                inferred.extend(zip_types(op.inputs(), port_types(module.arg_types())));
+                inferred.extend(zip_types(op.outputs(), port_types(module.output_types())));
+            }
+        },
+        OpEnum::HwModule(op) => {
+            // a module without a body has no arguments to type
+            if let Some(body) = op.body() {
+                let arguments = env.get_region_arguments(body);
+                inferred.extend(zip_types(&arguments, port_types(op.arg_types())));
+            }
+        },
+        OpEnum::HwInput(op) => {
+            if let Some(module) = as_module(env, utils::get_parent_op(env, op_id)) {
+                inferred.extend(zip_types(op.inputs(), port_types(module.arg_types())));
+            }
+        },
+        OpEnum::HwOutput(op) => {
+            if let Some(module) = as_module(env, utils::get_parent_op(env, op_id)) {
+                inferred.extend(zip_types(op.outputs(), port_types(module.output_types())));
+            }
+        },
+        _ => {},
+    }
+    Ok(inferred)
+}
+
+/// Fill in missing dtypes of the entities in `region` (nested regions included) until
+/// nothing changes, returning every place where an inferred dtype conflicts with a
+/// declared one. The arguments of `region` are typed by the op owning it.
+pub fn infer_types<E>(env: &mut E, region: RegionId) -> Vec<TypeConflict>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let ops = env
+        .get_region_use(region)
+        .into_iter()
+        .chain(utils::walk_region_ops(env, region))
+        .collect::<Vec<_>>();
+    let mut conflicts: Vec<TypeConflict> = vec![];
+
+    loop {
+        let mut changed = false;
+        for &op in ops.iter() {
+            let inferred = match infer_op_types(env, op) {
+                Ok(inferred) => inferred,
+                Err(conflict) => {
+                    if !conflicts.iter().any(|c| c.op == op && c.entity == conflict.entity) {
+                        conflicts.push(conflict);
+                    }
+                    continue;
+                },
+            };
+            for (entity, inferred) in inferred {
+                match dtype_of(env, entity) {
+                    None => {
+                        env.get_entity_entry(entity)
+                            .and_modify(|entity| entity.set_dtype(Some(inferred)));
+                        changed = true;
+                    },
+                    Some(declared) if declared != inferred => {
+                        if conflicts.iter().any(|c| c.op == op && c.entity == entity) {
+                            continue;
+                        }
+                        let message = format!(
+                            "type conflict on {} at {}: inferred {}, but declared {}",
+                            env.print_entity(entity),
+                            env.get_op(op).get_op_name(),
+                            inferred,
+                            declared
+                        );
+                        let inferred = Some(inferred);
+                        conflicts.push(TypeConflict { op, entity, inferred, declared, message });
+                    },
+                    _ => {},
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    conflicts
+}
@@ -0,0 +1,211 @@
+use irony::{Environ, IronyError, IronyResult, OpId};
+
+use crate::{utils, BoolAttr, EntityEnum, OpEnum};
+
+/// An `HwInstance` placed in `parent` that instantiates `target`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceEdge {
+    pub parent: OpId,
+    pub instance: OpId,
+    pub target: OpId,
+}
+
+/// The module hierarchy: one node per `HwModule` and one edge per `HwInstance`
+#[derive(Clone, Debug, Default)]
+pub struct InstanceGraph {
+    modules: Vec<OpId>,
+    tops: Vec<OpId>,
+    edges: Vec<InstanceEdge>,
+}
+
+/// find the `HwModule` which `op` is nested in
+pub fn get_parent_module<E>(env: &E, op: OpId) -> Option<OpId>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let mut current = utils::get_parent_op(env, op);
+    while let Some(parent) = current {
+        if let OpEnum::HwModule(_) = env.get_op(parent) {
+            return Some(parent);
+        }
+        current = utils::get_parent_op(env, parent);
+    }
+    None
+}
+
+impl InstanceGraph {
+    /// Fails on a module without a body, or an instance outside of any module or whose target
+    /// is not a module
+    pub fn new<E>(env: &E) -> IronyResult<Self>
+    where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+        let mut graph = Self::default();
+        let mut instances = vec![];
+
+        for op_id in env.get_ops_with_parent(None) {
+            let OpEnum::HwModule(module) = env.get_op(op_id) else {
+                continue;
+            };
+            graph.modules.push(op_id);
+            if let Some(EntityEnum::Module(entity)) = module.lhs().map(|lhs| env.get_entity(lhs)) {
+                if entity.top() == Some(&BoolAttr(true)) {
+                    graph.tops.push(op_id);
+                }
+            }
+            let Some(body) = module.body() else {
+                return Err(IronyError::failed(Some(op_id), "the module has no body"));
+            };
+            for op in utils::walk_region_ops(env, body) {
+                if let OpEnum::HwInstance(_) = env.get_op(op) {
+                    instances.push(op);
+                }
+            }
+        }
+
+        for instance in instances {
+            let Some(parent) = get_parent_module(env, instance) else {
+                let message = "an instance must be placed in a module";
+                return Err(IronyError::failed(Some(instance), message));
+            };
+            let target = utils::get_instance_target(env, instance)
+                .filter(|&target| matches!(env.get_op(target), OpEnum::HwModule(_)));
+            let Some(target) = target else {
+                let message = "the target of the instance cannot be found";
+                return Err(IronyError::failed(Some(instance), message));
+            };
+            graph.edges.push(InstanceEdge { parent, instance, target });
+        }
+
+        Ok(graph)
+    }
+
+    pub fn get_modules(&self) -> Vec<OpId> { self.modules.to_owned() }
+
+    pub fn get_edges(&self) -> Vec<InstanceEdge> { self.edges.to_owned() }
+
+    /// the instances placed in `module`
+    pub fn get_instances(&self, module: OpId) -> Vec<InstanceEdge> {
+        self.edges.iter().filter(|edge| edge.parent == module).copied().collect()
+    }
+
+    /// the instances of `module`
+    pub fn get_users(&self, module: OpId) -> Vec<InstanceEdge> {
+        self.edges.iter().filter(|edge| edge.target == module).copied().collect()
+    }
+
+    /// Modules marked by the `top` attribute, or the never instantiated modules if none
+    /// is marked.
+    pub fn get_top_modules(&self) -> Vec<OpId> {
+        if !self.tops.is_empty() {
+            return self.tops.to_owned();
+        }
+        self.modules
+            .iter()
+            .filter(|&&module| self.get_users(module).is_empty())
+            .copied()
+            .collect()
+    }
+
+    /// Every module after all the modules it instantiates. Modules on a recursive cycle
+    /// are visited once, in no particular order among themselves.
+    pub fn post_order(&self) -> Vec<OpId> {
+        let mut visited = irony::FxHashSet::default();
+        let mut order = vec![];
+
+        fn visit(
+            graph: &InstanceGraph, module: OpId, visited: &mut irony::FxHashSet<OpId>,
+            order: &mut Vec<OpId>,
+        ) {
+            if !visited.insert(module) {
+                return;
+            }
+            for edge in graph.get_instances(module) {
+                visit(graph, edge.target, visited, order);
+            }
+            order.push(module);
+        }
+
+        let roots = self.get_top_modules().into_iter().chain(self.modules.iter().copied());
+        for module in roots {
+            visit(self, module, &mut visited, &mut order);
+        }
+        order
+    }
+
+    /// A cycle of modules instantiating each other, the first module repeated at the end.
+    pub fn find_recursion(&self) -> Option<Vec<OpId>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Visiting,
+            Done,
+        }
+
+        fn visit(
+            graph: &InstanceGraph, module: OpId, marks: &mut irony::FxHashMap<OpId, Mark>,
+            stack: &mut Vec<OpId>,
+        ) -> Option<Vec<OpId>> {
+            match marks.get(&module) {
+                Some(Mark::Done) => return None,
+                Some(Mark::Visiting) => {
+                    let start = stack.iter().position(|&m| m == module).unwrap();
+                    let mut cycle = stack[start..].to_vec();
+                    cycle.push(module);
+                    return Some(cycle);
+                },
+                None => {},
+            }
+            marks.insert(module, Mark::Visiting);
+            stack.push(module);
+            for edge in graph.get_instances(module) {
+                if let Some(cycle) = visit(graph, edge.target, marks, stack) {
+                    return Some(cycle);
+                }
+            }
+            stack.pop();
+            marks.insert(module, Mark::Done);
+            None
+        }
+
+        let mut marks = irony::FxHashMap::default();
+        self.modules
+            .iter()
+            .find_map(|&module| visit(self, module, &mut marks, &mut vec![]))
+    }
+
+    pub fn is_recursive(&self) -> bool { self.find_recursion().is_some() }
+
+    /// Every chain of instances leading from a top module down to `module`, outermost
+    /// instance first. A top module itself has a single empty path. Fails on a recursive
+    /// hierarchy, whose paths are infinite.
+    pub fn instance_paths(&self, module: OpId) -> IronyResult<Vec<Vec<OpId>>> {
+        if let Some(cycle) = self.find_recursion() {
+            let message = "instance paths of a recursive hierarchy are infinite";
+            return Err(IronyError::failed(Some(cycle[0]), message));
+        }
+
+        fn paths_to(graph: &InstanceGraph, tops: &Vec<OpId>, module: OpId) -> Vec<Vec<OpId>> {
+            let mut paths = vec![];
+            if tops.contains(&module) {
+                paths.push(vec![]);
+            }
+            for edge in graph.get_users(module) {
+                for mut path in paths_to(graph, tops, edge.parent) {
+                    path.push(edge.instance);
+                    paths.push(path);
+                }
+            }
+            paths
+        }
+
+        Ok(paths_to(self, &self.get_top_modules(), module))
+    }
+
+    /// Modules that are not reachable from any top module
+    pub fn unused_modules(&self) -> Vec<OpId> {
+        let mut reachable = irony::FxHashSet::default();
+        let mut worklist = self.get_top_modules();
+        while let Some(module) = worklist.pop() {
+            if reachable.insert(module) {
+                worklist.extend(self.get_instances(module).iter().map(|edge| edge.target));
+            }
+        }
+        self.modules.iter().filter(|module| !reachable.contains(module)).copied().collect()
+    }
+}
@@ -0,0 +1,450 @@
+use irony::{Entity, EntityId, Environ, FxHashMap, IronyError, Loc, Op, OpId, RegionId};
+
+use crate::{
+    utils, ArrayAttr, Assign, AttributeEnum, CombVariadic, CombVariadicPredicate, ConstantAttr,
+    DataTypeEnum, EntityEnum, EventEval, HwConstant, HwModule, IdAttr, OpEnum, SeqCompReg,
+    StringAttr, TypeAttr, Wire,
+};
+
+/// A sequence or property op that cannot be turned into a monitor
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorError {
+    pub op: Option<OpId>,
+    pub message: String,
+}
+
+impl std::fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<MonitorError> for IronyError {
+    fn from(err: MonitorError) -> Self { IronyError::Failed { op: err.op, message: err.message } }
+}
+
+fn is_temporal(op: &OpEnum) -> bool {
+    matches!(op.get_dialect(), Some("sequence") | Some("property"))
+}
+
+struct MonitorBuilder<'env, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> {
+    env: &'env mut E,
+    clock: EntityId,
+    constants: [Option<EntityId>; 2],
+    /// the wire each event is evaluated to
+    events: FxHashMap<EntityId, EntityId>,
+    first: Option<EntityId>,
+    /// the location of the target being lowered, given to the new ops
+    loc: Option<Loc>,
+}
+
+impl<E: Environ<EntityT = EntityEnum, OpT = OpEnum>> MonitorBuilder<'_, E> {
+    fn error<T>(&self, op: Option<OpId>, message: String) -> Result<T, MonitorError> {
+        Err(MonitorError { op, message })
+    }
+
+    fn add_op(&mut self, op: OpEnum) -> OpId {
+        utils::add_op_at(self.env, op, self.loc.to_owned())
+    }
+
+    /// `entity`, defined for a previous target, is used for this one too
+    fn reuse(&mut self, entity: EntityId) -> EntityId {
+        utils::fuse_def_loc(self.env, entity, self.loc.to_owned());
+        entity
+    }
+
+    fn wire(&mut self) -> EntityId {
+        let dtype = Some(DataTypeEnum::UInt(1.into()));
+        self.env.add_entity(Wire::new(dtype, None, None, None).into())
+    }
+
+    fn constant(&mut self, value: bool) -> EntityId {
+        if let Some(constant) = self.constants[value as usize] {
+            return self.reuse(constant);
+        }
+        let constant = self.wire();
+        let attr = Some(ConstantAttr(vec![value]));
+        self.add_op(HwConstant::new(Some(constant), attr).into());
+        self.constants[value as usize] = Some(constant);
+        constant
+    }
+
+    fn variadic(&mut self, operands: Vec<EntityId>, predicate: CombVariadicPredicate) -> EntityId {
+        let lhs = self.wire();
+        self.add_op(CombVariadic::new(Some(lhs), operands, Some(predicate)).into());
+        lhs
+    }
+
+    fn and(&mut self, operands: Vec<EntityId>) -> EntityId {
+        match operands[..] {
+            [] => self.constant(true),
+            [operand] => operand,
+            _ => self.variadic(operands, CombVariadicPredicate::And),
+        }
+    }
+
+    fn or(&mut self, operands: Vec<EntityId>) -> EntityId {
+        match operands[..] {
+            [] => self.constant(false),
+            [operand] => operand,
+            _ => self.variadic(operands, CombVariadicPredicate::Or),
+        }
+    }
+
+    fn not(&mut self, operand: EntityId) -> EntityId {
+        let one = self.constant(true);
+        self.variadic(vec![operand, one], CombVariadicPredicate::Xor)
+    }
+
+    /// a register defining `output`, for feedback through wires made beforehand
+    fn reg_to(&mut self, output: EntityId, input: EntityId) {
+        let reg = SeqCompReg::new(Some(output), Some(input), Some(self.clock), None, None);
+        self.add_op(reg.into());
+    }
+
+    fn reg(&mut self, input: EntityId) -> EntityId {
+        let output = self.wire();
+        self.reg_to(output, input);
+        output
+    }
+
+    /// high in the first cycle only, registers starting at zero
+    fn first(&mut self) -> EntityId {
+        if let Some(first) = self.first {
+            return self.reuse(first);
+        }
+        let one = self.constant(true);
+        let started = self.reg(one);
+        let first = self.not(started);
+        self.first = Some(first);
+        first
+    }
+
+    /// high from the first cycle `go` is high on
+    fn sticky(&mut self, go: EntityId) -> EntityId {
+        let held = self.wire();
+        let sticky = self.or(vec![go, held]);
+        self.reg_to(held, sticky);
+        sticky
+    }
+
+    fn event(&mut self, event: EntityId) -> EntityId {
+        if let Some(&wire) = self.events.get(&event) {
+            return self.reuse(wire);
+        }
+        let wire = self.wire();
+        self.add_op(EventEval::new(Some(wire), Some(event)).into());
+        self.events.insert(event, wire);
+        wire
+    }
+
+    fn def(&self, entity: Option<EntityId>) -> Result<(EntityId, OpId), MonitorError> {
+        let Some(entity) = entity else {
+            return self.error(None, "an operand of a property is missing".into());
+        };
+        match self.env.get_defs(entity).first() {
+            Some(&def) => Ok((entity, def)),
+            None => self.error(None, format!("{} is not driven", self.env.print_entity(entity))),
+        }
+    }
+
+    /// the most cycles a match of `sequence` spans, `None` if unbounded
+    fn length(&self, sequence: EntityId) -> Result<Option<usize>, MonitorError> {
+        let (_, def) = self.def(Some(sequence))?;
+        match self.env.get_op(def) {
+            OpEnum::SqnFromEvent(_) => Ok(Some(1)),
+            OpEnum::SqnDelay(op) => {
+                let rhs = self.length(self.def(op.rhs())?.0)?;
+                Ok(op.ub().zip(rhs).map(|(IdAttr(ub), rhs)| ub + rhs))
+            },
+            OpEnum::SqnConcat(op) => {
+                let s0 = self.length(self.def(op.s0())?.0)?;
+                let s1 = self.length(self.def(op.s1())?.0)?;
+                Ok(s0.zip(s1).map(|(s0, s1)| s0 + s1 - 1))
+            },
+            op => self.error(Some(def), format!("`{}` is not a sequence", op.get_op_name())),
+        }
+    }
+
+    /// whether `property` is decided in the cycle it is checked from
+    fn is_immediate(&self, property: EntityId) -> Result<bool, MonitorError> {
+        let (_, def) = self.def(Some(property))?;
+        Ok(match self.env.get_op(def).to_owned() {
+            OpEnum::PrptFromSqn(op) => self.length(self.def(op.rhs())?.0)? == Some(1),
+            OpEnum::SqnFromEvent(_) => true,
+            OpEnum::PrptConjunction(op) => {
+                self.is_immediate(self.def(op.a())?.0)? && self.is_immediate(self.def(op.b())?.0)?
+            },
+            _ => false,
+        })
+    }
+
+    /// high in the cycles a match of `sequence` ends in, for matches starting when `go` is
+    /// high: a nondeterministic automaton whose states are the wires of the delays
+    fn matcher(&mut self, sequence: EntityId, go: EntityId) -> Result<EntityId, MonitorError> {
+        let (_, def) = self.def(Some(sequence))?;
+        match self.env.get_op(def).to_owned() {
+            OpEnum::SqnFromEvent(op) => {
+                let (event, _) = self.def(op.rhs())?;
+                let event = self.event(event);
+                Ok(self.and(vec![go, event]))
+            },
+            OpEnum::SqnDelay(op) => {
+                let (rhs, _) = self.def(op.rhs())?;
+                let lb = op.lb().map_or(0, |IdAttr(lb)| *lb);
+                // a shift register of the starts, the last `ub - lb + 1` taps starting `rhs`
+                let mut delayed = go;
+                for _ in 0..lb {
+                    delayed = self.reg(delayed);
+                }
+                let start = match op.ub() {
+                    Some(IdAttr(ub)) if *ub < lb => {
+                        return self.error(Some(def), format!("the delay [{}:{}] is empty", lb, ub));
+                    },
+                    Some(IdAttr(ub)) => {
+                        let mut taps = vec![delayed];
+                        for _ in lb..*ub {
+                            delayed = self.reg(delayed);
+                            taps.push(delayed);
+                        }
+                        self.or(taps)
+                    },
+                    None => self.sticky(delayed),
+                };
+                self.matcher(rhs, start)
+            },
+            OpEnum::SqnConcat(op) => {
+                let ((s0, _), (s1, _)) = (self.def(op.s0())?, self.def(op.s1())?);
+                let middle = self.matcher(s0, go)?;
+                self.matcher(s1, middle)
+            },
+            op => self.error(Some(def), format!("`{}` is not a sequence", op.get_op_name())),
+        }
+    }
+
+    /// High in the cycle a match of `sequence` started when `go` was high becomes
+    /// impossible. Starts of a sequence spanning at most `n` cycles are dealt round-robin
+    /// to `n` matchers, so that each one follows a single start at a time.
+    fn sequence_failer(
+        &mut self, sequence: EntityId, go: EntityId,
+    ) -> Result<EntityId, MonitorError> {
+        let length = match self.length(sequence)? {
+            None => return Ok(self.constant(false)),
+            Some(1) => {
+                let matched = self.matcher(sequence, go)?;
+                let unmatched = self.not(matched);
+                return Ok(self.and(vec![go, unmatched]));
+            },
+            Some(length) => length,
+        };
+
+        // a ring of one-hot phases, the first one taken in the first cycle
+        let ring = (0..length).map(|_| self.wire()).collect::<Vec<_>>();
+        let first = self.first();
+        let mut phases = vec![self.or(vec![first, ring[length - 1]])];
+        phases.extend(ring[..length - 1].iter().copied());
+        for (&held, &phase) in ring.iter().zip(phases.iter()) {
+            self.reg_to(held, phase);
+        }
+
+        let mut fails = vec![];
+        for (index, &phase) in phases.iter().enumerate() {
+            let start = self.and(vec![go, phase]);
+            let matched = self.matcher(sequence, start)?;
+            let (active_held, matched_held) = (self.wire(), self.wire());
+            let kept = self.not(phase);
+            let still_active = self.and(vec![active_held, kept]);
+            let active = self.or(vec![start, still_active]);
+            let still_matched = self.and(vec![matched_held, kept]);
+            let matched = self.or(vec![matched, still_matched]);
+            self.reg_to(active_held, active);
+            self.reg_to(matched_held, matched);
+            // the last cycle a match of this start can end in
+            let last = phases[(index + length - 1) % length];
+            let unmatched = self.not(matched);
+            fails.push(self.and(vec![active, unmatched, last]));
+        }
+        Ok(self.or(fails))
+    }
+
+    /// high in the cycle `property` checked from a cycle `go` is high in is violated, as
+    /// with [`check_properties`](crate::check_properties)
+    fn failer(&mut self, property: EntityId, go: EntityId) -> Result<EntityId, MonitorError> {
+        let (_, def) = self.def(Some(property))?;
+        match self.env.get_op(def).to_owned() {
+            OpEnum::PrptFromSqn(op) => self.sequence_failer(self.def(op.rhs())?.0, go),
+            OpEnum::SqnFromEvent(_) | OpEnum::SqnDelay(_) | OpEnum::SqnConcat(_) => {
+                self.sequence_failer(property, go)
+            },
+            OpEnum::PrptNexttime(op) => {
+                let next = self.reg(go);
+                self.failer(self.def(op.rhs())?.0, next)
+            },
+            OpEnum::PrptAlways(op) => {
+                let from_now_on = self.sticky(go);
+                self.failer(self.def(op.rhs())?.0, from_now_on)
+            },
+            OpEnum::PrptEventually(_) => Ok(self.constant(false)),
+            OpEnum::PrptUntil(op) => {
+                let ((a, _), (b, _)) = (self.def(op.a())?, self.def(op.b())?);
+                if !self.is_immediate(a)? || !self.is_immediate(b)? {
+                    let message = "the operands of `until` must be decided in a single cycle";
+                    return self.error(Some(def), message.into());
+                }
+                let always = self.constant(true);
+                let (a_fails, b_fails) = (self.failer(a, always)?, self.failer(b, always)?);
+                // waiting for `b` since a start
+                let held = self.wire();
+                let waiting = self.or(vec![go, held]);
+                let waiting = self.and(vec![waiting, b_fails]);
+                self.reg_to(held, waiting);
+                Ok(self.and(vec![waiting, a_fails]))
+            },
+            OpEnum::PrptConjunction(op) => {
+                let ((a, _), (b, _)) = (self.def(op.a())?, self.def(op.b())?);
+                let fails = vec![self.failer(a, go)?, self.failer(b, go)?];
+                Ok(self.or(fails))
+            },
+            OpEnum::PrptImplica(op) => {
+                let (mut a, a_def) = self.def(op.a())?;
+                if let OpEnum::PrptFromSqn(from) = self.env.get_op(a_def) {
+                    a = self.def(from.rhs())?.0;
+                }
+                let matched = self.matcher(a, go)?;
+                self.failer(self.def(op.b())?.0, matched)
+            },
+            op => self.error(Some(def), format!("`{}` is not a property", op.get_op_name())),
+        }
+    }
+}
+
+/// `fail`, or `fail_<n>` when a port of `hw_module` or a wire of its `body` is named so
+/// already
+fn fresh_output_name<E>(env: &E, hw_module: &HwModule, body: RegionId) -> String
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let body = env.get_region(body);
+    let ports = [hw_module.arg_names(), hw_module.output_names()];
+    let ports = ports.into_iter().flatten().flat_map(|ArrayAttr(ports)| ports.iter().cloned());
+    let wires = body.get_arguments().into_iter().chain(body.get_entity_children());
+    let wires = wires.filter_map(|entity| env.get_entity(entity).get_attr("name"));
+    let taken = ports
+        .chain(wires)
+        .filter_map(|name| match name {
+            AttributeEnum::StringAttr(StringAttr(name)) => Some(name),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    (0..)
+        .map(|n| if n == 0 { "fail".to_owned() } else { format!("fail_{}", n) })
+        .find(|name| !taken.contains(name))
+        .unwrap()
+}
+
+/// Compile the `property.synthesize` targets of an `HwModule` into a monitor circuit
+/// clocked by `clock`, and remove the sequence and property ops.
+///
+/// A sequence becomes an automaton of registers tracking its matches, delays being shift
+/// registers. Each target is checked from every cycle, and the new 1-bit output `fail`, or
+/// `fail_<n>` if a port or wire of the module has that name already, is high in the
+/// cycles one of them is violated, with the semantics of
+/// [`check_properties`](crate::check_properties) so liveness obligations never fail.
+/// Events are read through `event.eval`. The monitor registers rely on starting at zero.
+/// Nothing is changed on error.
+pub fn lower_monitors<E>(
+    env: &mut E, module: OpId, clock: EntityId,
+) -> Result<Option<EntityId>, MonitorError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let OpEnum::HwModule(hw_module) = env.get_op(module) else {
+        let message = "monitors are built in an HwModule".into();
+        return Err(MonitorError { op: Some(module), message });
+    };
+    let Some(body) = hw_module.body() else {
+        let message = "the module has no body".into();
+        return Err(MonitorError { op: Some(module), message });
+    };
+    let ops = utils::walk_region_ops(env, body);
+    let targets = ops
+        .iter()
+        .filter_map(|&op_id| match env.get_op(op_id) {
+            OpEnum::PrptSynth(op) => Some((op_id, op.property())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if targets.is_empty() {
+        return Ok(None);
+    }
+    let name = fresh_output_name(env, hw_module, body);
+
+    let mut transaction = env.transaction();
+    transaction.begin_region(Some(body));
+    let mut builder = MonitorBuilder {
+        env: &mut *transaction,
+        clock,
+        constants: [None, None],
+        events: FxHashMap::default(),
+        first: None,
+        loc: None,
+    };
+    let fails = targets
+        .iter()
+        .map(|&(op_id, property)| {
+            let Some(property) = property else {
+                return builder.error(Some(op_id), "no property is synthesized".into());
+            };
+            builder.loc = builder.env.get_op_loc(op_id);
+            let always = builder.constant(true);
+            builder.failer(property, always)
+        })
+        .collect::<Result<Vec<_>, _>>();
+    // the output fails for all the targets
+    let loc = Loc::fuse(targets.iter().map(|&(op_id, _)| builder.env.get_op_loc(op_id)));
+    builder.loc = loc.to_owned();
+    let fail = fails.map(|fails| builder.or(fails));
+    let output = fail.map(|fail| {
+        let dtype = Some(DataTypeEnum::UInt(1.into()));
+        let output = Wire::new(dtype, Some(name.as_str().into()), None, None);
+        let output = transaction.add_entity(output.into());
+        utils::add_op_at(&mut *transaction, Assign::new(Some(output), Some(fail)).into(), loc);
+        output
+    });
+    transaction.end_region();
+    let output = output?;
+
+    for op_id in ops.into_iter().rev() {
+        if is_temporal(transaction.get_op(op_id)) {
+            transaction.delete_op(op_id);
+        }
+    }
+
+    // `fail` becomes the last output, and the terminator stays last
+    let terminator = transaction.get_region(body).get_op_children().into_iter().find(|&op_id| {
+        matches!(transaction.get_op(op_id), OpEnum::HwOutput(_))
+    });
+    if let Some(terminator) = terminator {
+        transaction.get_op_entry(terminator).and_modify(|op| {
+            let mut outputs = op.get_uses()[0].1.to_owned();
+            outputs.push(Some(output));
+            op.set_uses(vec![("outputs".into(), outputs)]);
+        });
+        transaction.get_region_entry(body).and_modify(|region| {
+            region.op_children.retain(|&op_id| op_id != terminator);
+            region.op_children.push(terminator);
+        });
+    }
+    transaction.get_op_entry(module).and_modify(|op| {
+        let mut attrs = op.get_attrs();
+        for (key, attr) in attrs.iter_mut() {
+            let port = match key.as_str() {
+                "output_names" => StringAttr(name.to_owned()).into(),
+                "output_types" => TypeAttr(DataTypeEnum::UInt(1.into())).into(),
+                _ => continue,
+            };
+            if let AttributeEnum::ArrayAttr(ArrayAttr(ports)) = attr {
+                ports.push(port);
+            }
+        }
+        op.set_attrs(attrs);
+    });
+    transaction.commit();
+    Ok(Some(output))
+}
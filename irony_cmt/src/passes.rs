@@ -1,16 +1,18 @@
-use core::panic;
 use std::collections::HashSet;
 
-use irony::{Entity, Environ, Op, OpId, PassManagerTrait, PassTrait};
+use irony::{Entity, Environ, IronyError, IronyResult, Op, OpId, PassManagerTrait, PassTrait};
 
 
-use crate::{AttributeEnum, EntityEnum, OpEnum, StringAttr};
+use crate::{
+    flatten_module, infer_types, lower_events, lower_memories, lower_monitors, utils,
+    AttributeEnum, EntityEnum, OpEnum, StringAttr,
+};
 
 
 #[derive(Debug, Clone)]
 pub struct RenamePass;
 
-impl PassTrait<(), ()> for RenamePass {
+impl PassTrait<(), IronyError> for RenamePass {
     type EntityT = EntityEnum;
     type OpT = OpEnum;
 
@@ -22,7 +24,7 @@ impl PassTrait<(), ()> for RenamePass {
         }
     }
 
-    fn run_raw<E>(&self, env: &mut E, op: OpId) -> Result<(), ()>
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
     where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
         let region = env.get_op(op).get_regions()[0].1[0];
 
@@ -41,12 +43,11 @@ impl PassTrait<(), ()> for RenamePass {
                         })
                         .collect::<Vec<_>>();
                     for def in defs {
-                        let name = env.get_entity(def).get_attr("name").unwrap();
-                        let name = match name {
-                            AttributeEnum::StringAttr(StringAttr(name)) => name,
-                            _ => {
-                                panic!()
-                            },
+                        let Some(AttributeEnum::StringAttr(StringAttr(name))) =
+                            env.get_entity(def).get_attr("name")
+                        else {
+                            let message = format!("{} has no name", env.print_entity(def));
+                            return Err(IronyError::failed(Some(op_id), message));
                         };
 
                         let mut splits = name.split('_').collect::<Vec<_>>();
@@ -88,12 +89,204 @@ impl PassTrait<(), ()> for RenamePass {
     }
 }
 
+/// Fill in the missing dtypes of a module body, see [`infer_types`].
+///
+/// Fails with the first conflict if any inferred dtype conflicts with a declared one,
+/// each of them being emitted as an error diagnostic.
+#[derive(Debug, Clone)]
+pub struct TypeInferPass;
+
+impl PassTrait<(), IronyError> for TypeInferPass {
+    type EntityT = EntityEnum;
+    type OpT = OpEnum;
+
+    fn check_op<E>(&self, env: &E, op: OpId) -> bool
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        matches!(env.get_op(op), OpEnum::HwModule(_))
+    }
+
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        let region = env.get_op(op).get_regions()[0].1[0];
+        let conflicts = infer_types(env, region);
+        for conflict in conflicts.iter() {
+            env.emit_error(conflict.op, conflict.message.to_owned());
+        }
+        match conflicts.into_iter().next() {
+            Some(conflict) => Err(conflict.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Inline the instances of a module, see [`flatten_module`].
+#[derive(Debug, Clone)]
+pub struct FlattenPass {
+    pub recursive: bool,
+}
+
+impl PassTrait<(), IronyError> for FlattenPass {
+    type EntityT = EntityEnum;
+    type OpT = OpEnum;
+
+    fn check_op<E>(&self, env: &E, op: OpId) -> bool
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        matches!(env.get_op(op), OpEnum::HwModule(_))
+    }
+
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        flatten_module(env, op, self.recursive)
+    }
+}
+
+/// Canonicalize a module body, see [`irony::canonicalize`].
+#[derive(Debug, Clone)]
+pub struct CanonicalizePass;
+
+impl PassTrait<(), IronyError> for CanonicalizePass {
+    type EntityT = EntityEnum;
+    type OpT = OpEnum;
+
+    fn check_op<E>(&self, env: &E, op: OpId) -> bool
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        matches!(env.get_op(op), OpEnum::HwModule(_))
+    }
+
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        let region = env.get_op(op).get_regions()[0].1[0];
+        irony::canonicalize(env, region);
+        Ok(())
+    }
+}
+
+/// Reorder the ops of a module body and of its nested regions so that definitions come
+/// before uses, see [`irony::sort_topologically`]. Fails on a combinational cycle.
+#[derive(Debug, Clone)]
+pub struct TopologicalSortPass;
+
+impl PassTrait<(), IronyError> for TopologicalSortPass {
+    type EntityT = EntityEnum;
+    type OpT = OpEnum;
+
+    fn check_op<E>(&self, env: &E, op: OpId) -> bool
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        matches!(env.get_op(op), OpEnum::HwModule(_))
+    }
+
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        let body = env.get_op(op).get_regions()[0].1[0];
+        let mut regions = vec![body];
+        for op in env.walk_ops(Some(body)) {
+            let nested = env.get_op(op).get_regions().into_iter().flat_map(|(_, regions)| regions);
+            regions.extend(nested);
+        }
+        for region in regions {
+            irony::sort_topologically(env, region).map_err(|ops| {
+                IronyError::failed(ops.first().copied(), "the ops close a combinational cycle")
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Compile the `property.synthesize` targets of a module into a monitor clocked by the
+/// input port named `clock`, see [`lower_monitors`].
+#[derive(Debug, Clone)]
+pub struct MonitorLoweringPass {
+    pub clock: String,
+}
+
+impl Default for MonitorLoweringPass {
+    fn default() -> Self { Self { clock: "clk".into() } }
+}
+
+impl PassTrait<(), IronyError> for MonitorLoweringPass {
+    type EntityT = EntityEnum;
+    type OpT = OpEnum;
+
+    fn check_op<E>(&self, env: &E, op: OpId) -> bool
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        matches!(env.get_op(op), OpEnum::HwModule(_))
+    }
+
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        let body = env.get_op(op).get_regions()[0].1[0];
+        let clock = utils::get_input_ports(env, body).into_iter().find(|&port| {
+            matches!(
+                env.get_entity(port).get_attr("name"),
+                Some(AttributeEnum::StringAttr(StringAttr(name))) if *name == self.clock
+            )
+        });
+        let Some(clock) = clock else {
+            let message = format!("the module has no input port named {}", self.clock);
+            return Err(IronyError::failed(Some(op), message));
+        };
+        lower_monitors(env, op, clock)?;
+        Ok(())
+    }
+}
+
+/// Turn the events of a module into plain logic, see [`lower_events`].
+#[derive(Debug, Clone)]
+pub struct EventLoweringPass;
+
+impl PassTrait<(), IronyError> for EventLoweringPass {
+    type EntityT = EntityEnum;
+    type OpT = OpEnum;
+
+    fn check_op<E>(&self, env: &E, op: OpId) -> bool
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        matches!(env.get_op(op), OpEnum::HwModule(_))
+    }
+
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        Ok(lower_events(env, op)?)
+    }
+}
+
+/// Lower the memories of a module into registers or macros, see [`lower_memories`].
+#[derive(Debug, Clone)]
+pub struct MemoryLoweringPass {
+    pub max_register_bits: usize,
+}
+
+impl Default for MemoryLoweringPass {
+    fn default() -> Self { Self { max_register_bits: 1024 } }
+}
+
+impl PassTrait<(), IronyError> for MemoryLoweringPass {
+    type EntityT = EntityEnum;
+    type OpT = OpEnum;
+
+    fn check_op<E>(&self, env: &E, op: OpId) -> bool
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        matches!(env.get_op(op), OpEnum::HwModule(_))
+    }
+
+    fn run_raw<E>(&self, env: &mut E, op: OpId) -> IronyResult<()>
+    where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
+        Ok(lower_memories(env, op, self.max_register_bits)?)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum PassEnum {
     RenamePass(RenamePass),
+    TypeInferPass(TypeInferPass),
+    FlattenPass(FlattenPass),
+    CanonicalizePass(CanonicalizePass),
+    TopologicalSortPass(TopologicalSortPass),
+    MonitorLoweringPass(MonitorLoweringPass),
+    EventLoweringPass(EventLoweringPass),
+    MemoryLoweringPass(MemoryLoweringPass),
 }
 
-impl PassTrait<(), ()> for PassEnum {
+impl PassTrait<(), IronyError> for PassEnum {
     type EntityT = EntityEnum;
     type OpT = OpEnum;
 
@@ -101,13 +294,27 @@ impl PassTrait<(), ()> for PassEnum {
     where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
         match self {
             PassEnum::RenamePass(pass) => pass.check_op(env, op_id),
+            PassEnum::TypeInferPass(pass) => pass.check_op(env, op_id),
+            PassEnum::FlattenPass(pass) => pass.check_op(env, op_id),
+            PassEnum::CanonicalizePass(pass) => pass.check_op(env, op_id),
+            PassEnum::TopologicalSortPass(pass) => pass.check_op(env, op_id),
+            PassEnum::MonitorLoweringPass(pass) => pass.check_op(env, op_id),
+            PassEnum::EventLoweringPass(pass) => pass.check_op(env, op_id),
+            PassEnum::MemoryLoweringPass(pass) => pass.check_op(env, op_id),
         }
     }
 
-    fn run_raw<E>(&self, env: &mut E, op_id: irony::OpId) -> Result<(), ()>
+    fn run_raw<E>(&self, env: &mut E, op_id: irony::OpId) -> IronyResult<()>
     where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
         match self {
             PassEnum::RenamePass(pass) => pass.run_raw(env, op_id),
+            PassEnum::TypeInferPass(pass) => pass.run_raw(env, op_id),
+            PassEnum::FlattenPass(pass) => pass.run_raw(env, op_id),
+            PassEnum::CanonicalizePass(pass) => pass.run_raw(env, op_id),
+            PassEnum::TopologicalSortPass(pass) => pass.run_raw(env, op_id),
+            PassEnum::MonitorLoweringPass(pass) => pass.run_raw(env, op_id),
+            PassEnum::EventLoweringPass(pass) => pass.run_raw(env, op_id),
+            PassEnum::MemoryLoweringPass(pass) => pass.run_raw(env, op_id),
         }
     }
 }
@@ -118,7 +325,7 @@ pub struct PassManager {
     start_ops: Vec<Vec<OpId>>,
 }
 
-impl PassManagerTrait<(), ()> for PassManager {
+impl PassManagerTrait<(), IronyError> for PassManager {
     type EntityT = EntityEnum;
     type OpT = OpEnum;
     type PassT = PassEnum;
@@ -131,7 +338,7 @@ impl PassManagerTrait<(), ()> for PassManager {
         self.start_ops.append(&mut start_ops);
     }
 
-    fn run_passes<E>(&self, env: &mut E) -> Result<(), ()>
+    fn run_passes<E>(&self, env: &mut E) -> IronyResult<()>
     where E: Environ<EntityT = Self::EntityT, OpT = Self::OpT> {
         for (pass, op) in self.passes.iter().zip(self.start_ops.iter()) {
             for op in op.iter() {
@@ -0,0 +1,387 @@
+/// A literal of a [`SatSolver`]: twice the variable, plus one when negated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SatLit(pub u32);
+
+impl SatLit {
+    pub fn new(var: u32, negated: bool) -> Self { SatLit(var * 2 + negated as u32) }
+
+    pub fn var(&self) -> u32 { self.0 >> 1 }
+
+    pub fn is_negated(&self) -> bool { self.0 & 1 == 1 }
+}
+
+impl std::ops::Not for SatLit {
+    type Output = SatLit;
+
+    fn not(self) -> SatLit { SatLit(self.0 ^ 1) }
+}
+
+/// The unassigned variables by decreasing activity, ties broken by the lower variable.
+/// Variables assigned by propagation stay in the heap until they are popped.
+#[derive(Clone, Debug, Default)]
+struct VarOrder {
+    heap: Vec<usize>,
+    /// the position of each variable in the heap, if it is in there
+    positions: Vec<Option<usize>>,
+}
+
+impl VarOrder {
+    fn before(activity: &[f64], a: usize, b: usize) -> bool {
+        activity[a].total_cmp(&activity[b]).then(b.cmp(&a)).is_gt()
+    }
+
+    fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.positions.len() <= var {
+            self.positions.resize(var + 1, None);
+        }
+        if self.positions[var].is_none() {
+            self.positions[var] = Some(self.heap.len());
+            self.heap.push(var);
+            self.sift_up(self.heap.len() - 1, activity);
+        }
+    }
+
+    /// move `var` up after its activity grew
+    fn raise(&mut self, var: usize, activity: &[f64]) {
+        if let Some(position) = self.positions[var] {
+            self.sift_up(position, activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.positions[top] = None;
+        if last != top {
+            self.heap[0] = last;
+            self.positions[last] = Some(0);
+            self.sift_down(0, activity);
+        }
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut position: usize, activity: &[f64]) {
+        let var = self.heap[position];
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if !Self::before(activity, var, self.heap[parent]) {
+                break;
+            }
+            self.heap[position] = self.heap[parent];
+            self.positions[self.heap[position]] = Some(position);
+            position = parent;
+        }
+        self.heap[position] = var;
+        self.positions[var] = Some(position);
+    }
+
+    fn sift_down(&mut self, mut position: usize, activity: &[f64]) {
+        let var = self.heap[position];
+        loop {
+            let mut child = 2 * position + 1;
+            if child >= self.heap.len() {
+                break;
+            }
+            let right = child + 1;
+            let heap = &self.heap;
+            if right < heap.len() && Self::before(activity, heap[right], heap[child]) {
+                child = right;
+            }
+            if !Self::before(activity, self.heap[child], var) {
+                break;
+            }
+            self.heap[position] = self.heap[child];
+            self.positions[self.heap[position]] = Some(position);
+            position = child;
+        }
+        self.heap[position] = var;
+        self.positions[var] = Some(position);
+    }
+}
+
+/// A CDCL SAT solver: two watched literals, first-UIP clause learning, VSIDS branching
+/// with phase saving, and Luby restarts. Learnt clauses are kept for good, which is fine
+/// for the small problems of bounded model checking. Clauses can be added between calls
+/// to [`SatSolver::solve_with`], which solves under assumptions, so one solver serves a
+/// whole incremental search.
+#[derive(Clone, Debug, Default)]
+pub struct SatSolver {
+    clauses: Vec<Vec<SatLit>>,
+    /// the clauses watching each literal, checked once it becomes false
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<SatLit>,
+    /// where each decision level starts on the trail
+    trail_lim: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    increment: f64,
+    order: VarOrder,
+    phases: Vec<bool>,
+    /// set once the clauses are found unsatisfiable at level 0
+    unsat: bool,
+}
+
+impl SatSolver {
+    pub fn new() -> Self { Self { increment: 1.0, ..Self::default() } }
+
+    pub fn new_var(&mut self) -> u32 {
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.phases.push(false);
+        self.watches.extend([vec![], vec![]]);
+        self.order.insert(self.values.len() - 1, &self.activity);
+        self.values.len() as u32 - 1
+    }
+
+    pub fn var_count(&self) -> usize { self.values.len() }
+
+    fn value(&self, lit: SatLit) -> Option<bool> {
+        self.values[lit.var() as usize].map(|value| value != lit.is_negated())
+    }
+
+    fn assign(&mut self, lit: SatLit, reason: Option<usize>) {
+        let var = lit.var() as usize;
+        self.values[var] = Some(!lit.is_negated());
+        self.levels[var] = self.trail_lim.len();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Add a clause, which must only use variables made by `new_var`. Returns false when
+    /// the clauses became unsatisfiable.
+    pub fn add_clause(&mut self, lits: &[SatLit]) -> bool {
+        self.backtrack(0);
+        let mut clause = vec![];
+        for &lit in lits {
+            match self.value(lit) {
+                Some(true) => return true,
+                Some(false) => {},
+                None if clause.contains(&!lit) => return true,
+                None if !clause.contains(&lit) => clause.push(lit),
+                None => {},
+            }
+        }
+        match clause.len() {
+            0 => self.unsat = true,
+            1 => {
+                self.assign(clause[0], None);
+                self.unsat |= self.propagate().is_some();
+            },
+            _ => {
+                self.watches[clause[0].0 as usize].push(self.clauses.len());
+                self.watches[clause[1].0 as usize].push(self.clauses.len());
+                self.clauses.push(clause);
+            },
+        }
+        !self.unsat
+    }
+
+    /// propagate the assignments on the trail, returning the clause in conflict if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_lit = !self.trail[self.propagated];
+            self.propagated += 1;
+            let watching = std::mem::take(&mut self.watches[false_lit.0 as usize]);
+            let mut kept = vec![];
+            let mut conflict = None;
+            for (index, &clause_id) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[index..]);
+                    break;
+                }
+                let clause = &mut self.clauses[clause_id];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.values[first.var() as usize].map(|v| v != first.is_negated()) == Some(true)
+                {
+                    kept.push(clause_id);
+                    continue;
+                }
+                let values = &self.values;
+                let replacement = clause[2..].iter().position(|lit| {
+                    values[lit.var() as usize].map(|v| v != lit.is_negated()) != Some(false)
+                });
+                if let Some(position) = replacement {
+                    clause.swap(1, position + 2);
+                    let watch = clause[1].0 as usize;
+                    self.watches[watch].push(clause_id);
+                    continue;
+                }
+                kept.push(clause_id);
+                match self.value(first) {
+                    Some(false) => conflict = Some(clause_id),
+                    _ => self.assign(first, Some(clause_id)),
+                }
+            }
+            self.watches[false_lit.0 as usize] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.increment;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|activity| *activity *= 1e-100);
+            self.increment *= 1e-100;
+        }
+        self.order.raise(var, &self.activity);
+    }
+
+    /// the first-UIP clause learnt from `conflict`, its asserting literal first, and the
+    /// level to go back to
+    fn analyze(&mut self, conflict: usize) -> (Vec<SatLit>, usize) {
+        let level = self.trail_lim.len();
+        let mut seen = vec![false; self.values.len()];
+        let mut learnt = vec![SatLit(0)];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut uip = None;
+        loop {
+            let lits = self.clauses[clause].to_owned();
+            for lit in lits.into_iter().filter(|&lit| Some(lit) != uip) {
+                let var = lit.var() as usize;
+                if seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump(var);
+                if self.levels[var] == level {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var() as usize] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            pending -= 1;
+            if pending == 0 {
+                learnt[0] = !lit;
+                break;
+            }
+            uip = Some(lit);
+            clause = self.reasons[lit.var() as usize].expect("only decisions have no reason");
+        }
+        self.increment /= 0.95;
+
+        // the highest level of the other literals goes second, to be watched
+        let mut back = 0;
+        if let Some(position) =
+            (1..learnt.len()).max_by_key(|&i| self.levels[learnt[i].var() as usize])
+        {
+            learnt.swap(1, position);
+            back = self.levels[learnt[1].var() as usize];
+        }
+        (learnt, back)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        let Some(&start) = self.trail_lim.get(level) else {
+            return;
+        };
+        for lit in self.trail.drain(start..) {
+            let var = lit.var() as usize;
+            self.phases[var] = !lit.is_negated();
+            self.values[var] = None;
+            self.reasons[var] = None;
+            self.order.insert(var, &self.activity);
+        }
+        self.trail_lim.truncate(level);
+        self.propagated = self.propagated.min(start);
+    }
+
+    fn decide(&mut self) -> Option<SatLit> {
+        while let Some(var) = self.order.pop(&self.activity) {
+            if self.values[var].is_none() {
+                return Some(SatLit::new(var as u32, !self.phases[var]));
+            }
+        }
+        None
+    }
+
+    /// Search for an assignment satisfying every clause: the value of each variable if
+    /// there is one, `None` if the clauses are unsatisfiable.
+    pub fn solve(&mut self) -> Option<Vec<bool>> { self.solve_with(&[]) }
+
+    /// Search for an assignment satisfying every clause and making every literal of
+    /// `assumptions` true. The assumptions only hold for this call, and what is learnt
+    /// under them stays valid for the next ones.
+    pub fn solve_with(&mut self, assumptions: &[SatLit]) -> Option<Vec<bool>> {
+        if self.unsat {
+            return None;
+        }
+        let luby = |mut i: u64| {
+            // the i-th element (from 1) of 1, 1, 2, 1, 1, 2, 4, ...
+            loop {
+                let k = 64 - i.leading_zeros() as u64;
+                if i == (1 << k) - 1 {
+                    return 1u64 << (k - 1);
+                }
+                i -= (1 << (k - 1)) - 1;
+            }
+        };
+        let mut restarts = 1;
+        let mut conflicts = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_lim.is_empty() {
+                    self.unsat = true;
+                    return None;
+                }
+                conflicts += 1;
+                let (learnt, back) = self.analyze(conflict);
+                self.backtrack(back);
+                if learnt.len() == 1 {
+                    self.assign(learnt[0], None);
+                } else {
+                    let id = self.clauses.len();
+                    self.watches[learnt[0].0 as usize].push(id);
+                    self.watches[learnt[1].0 as usize].push(id);
+                    self.assign(learnt[0], Some(id));
+                    self.clauses.push(learnt);
+                }
+                continue;
+            }
+            if conflicts >= 100 * luby(restarts) {
+                restarts += 1;
+                conflicts = 0;
+                self.backtrack(0);
+                continue;
+            }
+            // the assumptions are decided first, one level each
+            if let Some(&assumption) = assumptions.get(self.trail_lim.len()) {
+                if self.value(assumption) == Some(false) {
+                    self.backtrack(0);
+                    return None;
+                }
+                self.trail_lim.push(self.trail.len());
+                if self.value(assumption).is_none() {
+                    self.assign(assumption, None);
+                }
+                continue;
+            }
+            let Some(decision) = self.decide() else {
+                let model = self.values.iter().map(|value| value.unwrap_or(false)).collect();
+                self.backtrack(0);
+                return Some(model);
+            };
+            self.trail_lim.push(self.trail.len());
+            self.assign(decision, None);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use irony::{IrSerde, IrValue, SerializeError};
+
+use crate::{
+    ArrayAttr, ArrayType, AttributeEnum, BoolAttr, CombBinaryPredicate, CombICmpPredicate,
+    CombUnaryPredicate, CombVariadicPredicate, ConstantAttr, DataTypeEnum, IdAttr, LocationAttr,
+    SeqHlmemType, StringAttr, StructType, SymbolRefAttr, TypeAttr, UArrayType, UIntType,
+};
+
+fn malformed<T>(msg: String) -> Result<T, SerializeError> { Err(SerializeError::Malformed(msg)) }
+
+/// predicates are stored by their printed name
+fn predicate_from_value<P: Clone>(
+    value: &IrValue, all: &[P], get_str: fn(&P) -> &'static str,
+) -> Result<P, SerializeError> {
+    let name = value.as_str()?;
+    match all.iter().find(|predicate| get_str(predicate) == name) {
+        Some(predicate) => Ok(predicate.to_owned()),
+        None => malformed(format!("unknown predicate `{}`", name)),
+    }
+}
+
+fn pair(value: &IrValue) -> Result<(&IrValue, &IrValue), SerializeError> {
+    match &value.as_list()?[..] {
+        [first, second] => Ok((first, second)),
+        _ => malformed(format!("expect a pair, found {:?}", value)),
+    }
+}
+
+impl IrSerde for UIntType {
+    fn to_ir_value(&self) -> IrValue { IrValue::UInt(self.0 as u64) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        Ok(UIntType(value.as_usize()?))
+    }
+}
+
+impl IrSerde for StructType {
+    fn to_ir_value(&self) -> IrValue {
+        IrValue::Map(self.0.iter().map(|(name, dtype)| (name.to_owned(), dtype.to_ir_value())).collect())
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        let fields = value
+            .as_map()?
+            .iter()
+            .map(|(name, dtype)| Ok((name.to_owned(), Box::new(DataTypeEnum::from_ir_value(dtype)?))))
+            .collect::<Result<_, SerializeError>>()?;
+        Ok(StructType(fields))
+    }
+}
+
+impl IrSerde for ArrayType {
+    fn to_ir_value(&self) -> IrValue {
+        IrValue::List(vec![self.0.to_ir_value(), IrValue::UInt(self.1 as u64)])
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        let (elem, size) = pair(value)?;
+        Ok(ArrayType(Box::new(DataTypeEnum::from_ir_value(elem)?), size.as_usize()?))
+    }
+}
+
+impl IrSerde for UArrayType {
+    fn to_ir_value(&self) -> IrValue {
+        IrValue::List(vec![self.0.to_ir_value(), IrValue::UInt(self.1 as u64)])
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        let (elem, size) = pair(value)?;
+        Ok(UArrayType(Box::new(DataTypeEnum::from_ir_value(elem)?), size.as_usize()?))
+    }
+}
+
+impl IrSerde for SeqHlmemType {
+    fn to_ir_value(&self) -> IrValue {
+        let dims = self.1.iter().map(|&dim| IrValue::UInt(dim as u64)).collect();
+        IrValue::List(vec![self.0.to_ir_value(), IrValue::List(dims)])
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        let (elem, dims) = pair(value)?;
+        let dims = dims.as_list()?.iter().map(|dim| dim.as_usize()).collect::<Result<_, _>>()?;
+        Ok(SeqHlmemType(Box::new(DataTypeEnum::from_ir_value(elem)?), dims))
+    }
+}
+
+impl IrSerde for CombVariadicPredicate {
+    fn to_ir_value(&self) -> IrValue { IrValue::Str(self.get_str().to_owned()) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        use CombVariadicPredicate::*;
+        predicate_from_value(value, &[Add, Mul, And, Or, Xor], Self::get_str)
+    }
+}
+
+impl IrSerde for CombUnaryPredicate {
+    fn to_ir_value(&self) -> IrValue { IrValue::Str(self.get_str().to_owned()) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        use CombUnaryPredicate::*;
+        predicate_from_value(value, &[Not, Neg], Self::get_str)
+    }
+}
+
+impl IrSerde for CombBinaryPredicate {
+    fn to_ir_value(&self) -> IrValue { IrValue::Str(self.get_str().to_owned()) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        use CombBinaryPredicate::*;
+        predicate_from_value(value, &[DivU, DivS, ModU, ModS, Shl, ShrU, ShrS, Sub], Self::get_str)
+    }
+}
+
+impl IrSerde for CombICmpPredicate {
+    fn to_ir_value(&self) -> IrValue { IrValue::Str(self.get_str().to_owned()) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        use CombICmpPredicate::*;
+        predicate_from_value(
+            value,
+            &[EQ, NE, SLT, SLE, SGT, SGE, ULT, ULE, UGT, UGE, CEQ, CNE, WEQ, WNE],
+            Self::get_str,
+        )
+    }
+}
+
+impl IrSerde for StringAttr {
+    fn to_ir_value(&self) -> IrValue { IrValue::Str(self.0.to_owned()) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        Ok(StringAttr(value.as_str()?.to_owned()))
+    }
+}
+
+impl IrSerde for SymbolRefAttr {
+    fn to_ir_value(&self) -> IrValue { IrValue::Str(self.0.to_owned()) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        Ok(SymbolRefAttr(value.as_str()?.to_owned()))
+    }
+}
+
+impl IrSerde for BoolAttr {
+    fn to_ir_value(&self) -> IrValue { IrValue::Bool(self.0) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        Ok(BoolAttr(value.as_bool()?))
+    }
+}
+
+impl IrSerde for IdAttr {
+    fn to_ir_value(&self) -> IrValue { IrValue::UInt(self.0 as u64) }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        Ok(IdAttr(value.as_usize()?))
+    }
+}
+
+impl IrSerde for TypeAttr {
+    fn to_ir_value(&self) -> IrValue { self.0.to_ir_value() }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        Ok(TypeAttr(DataTypeEnum::from_ir_value(value)?))
+    }
+}
+
+/// bits are written as a string of `0` and `1`, lowest bit first
+impl IrSerde for ConstantAttr {
+    fn to_ir_value(&self) -> IrValue {
+        IrValue::Str(self.0.iter().map(|&bit| if bit { '1' } else { '0' }).collect())
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        let bits = value
+            .as_str()?
+            .chars()
+            .map(|c| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                _ => malformed(format!("`{}` is not a bit", c)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ConstantAttr(bits))
+    }
+}
+
+impl IrSerde for ArrayAttr {
+    fn to_ir_value(&self) -> IrValue {
+        IrValue::List(self.0.iter().map(|attr| attr.to_ir_value()).collect())
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        let attrs = value.as_list()?.iter().map(AttributeEnum::from_ir_value).collect::<Result<_, _>>()?;
+        Ok(ArrayAttr(attrs))
+    }
+}
+
+impl IrSerde for LocationAttr {
+    fn to_ir_value(&self) -> IrValue {
+        IrValue::Map(vec![
+            ("file".to_owned(), IrValue::Str(self.file.to_owned())),
+            ("line".to_owned(), IrValue::UInt(self.line as u64)),
+            ("column".to_owned(), IrValue::UInt(self.column as u64)),
+        ])
+    }
+
+    fn from_ir_value(value: &IrValue) -> Result<Self, SerializeError> {
+        Ok(LocationAttr {
+            file: value.field("file")?.as_str()?.to_owned(),
+            line: value.field("line")?.as_uint()? as u32,
+            column: value.field("column")?.as_uint()? as u32,
+        })
+    }
+}
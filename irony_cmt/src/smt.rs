@@ -0,0 +1,440 @@
+use std::io::Write;
+
+use irony::{Entity, EntityId, Environ, FxHashMap, FxHashSet, IronyError, Op, OpId};
+
+use crate::{
+    utils, AttributeEnum, CombBinaryPredicate, CombICmpPredicate, CombUnaryPredicate,
+    CombVariadicPredicate, ConstantAttr, EntityEnum, OpEnum, StringAttr,
+};
+
+/// An op or entity of a module that has no SMT-LIB2 counterpart
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmtError {
+    pub op: Option<OpId>,
+    pub message: String,
+}
+
+impl std::fmt::Display for SmtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<SmtError> for IronyError {
+    fn from(err: SmtError) -> Self { IronyError::Failed { op: err.op, message: err.message } }
+}
+
+/// `op` applied to `terms` two at a time from the left, as SMT-LIB2 only defines the
+/// binary forms of most bit-vector operators
+fn fold(op: &str, terms: Vec<String>) -> String {
+    let mut terms = terms.into_iter();
+    let first = terms.next().unwrap_or_default();
+    terms.fold(first, |acc, term| format!("({} {} {})", op, acc, term))
+}
+
+fn bool_to_bv(term: String) -> String { format!("(ite {} #b1 #b0)", term) }
+
+struct SmtExporter<'env, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> {
+    env: &'env E,
+    module: String,
+    lines: Vec<String>,
+    defined: FxHashSet<EntityId>,
+    /// entities whose definition is being built, to catch combinational loops
+    pending: FxHashSet<EntityId>,
+    /// the names given to a `|module_n name|` function so far
+    names: FxHashMap<String, EntityId>,
+}
+
+impl<E: Environ<EntityT = EntityEnum, OpT = OpEnum>> SmtExporter<'_, E> {
+    fn error<T>(&self, op: Option<OpId>, message: String) -> Result<T, SmtError> {
+        Err(SmtError { op, message })
+    }
+
+    fn width(&self, entity: EntityId) -> Result<usize, SmtError> {
+        match self.env.get_entity(entity).get_dtype().and_then(|dtype| dtype.try_width()) {
+            Some(width) if width > 0 => Ok(width),
+            _ => {
+                let name = self.env.print_entity(entity);
+                self.error(None, format!("{} is not a bit-vector of a known width", name))
+            },
+        }
+    }
+
+    fn name(&self, entity: EntityId) -> Option<String> {
+        match self.env.get_entity(entity).get_attr("name") {
+            Some(AttributeEnum::StringAttr(StringAttr(name))) => Some(name.replace('|', "_")),
+            _ => None,
+        }
+    }
+
+    fn sort(&self, entity: EntityId) -> Result<String, SmtError> {
+        Ok(format!("(_ BitVec {})", self.width(entity)?))
+    }
+
+    fn function(&self, entity: EntityId) -> String { format!("|{}#{}|", self.module, entity.0) }
+
+    /// the value of `entity` in `state`
+    fn term_in(&self, entity: EntityId, state: &str) -> String {
+        format!("({} {})", self.function(entity), state)
+    }
+
+    /// `|module_n name|` for named entities, the way a solver front end finds them
+    fn alias(&mut self, entity: EntityId) -> Result<(), SmtError> {
+        let Some(name) = self.name(entity) else {
+            return Ok(());
+        };
+        if self.names.contains_key(&name) {
+            return Ok(());
+        }
+        self.names.insert(name.to_owned(), entity);
+        let (sort, term) = (self.sort(entity)?, self.term_in(entity, "state"));
+        let module = self.module.to_owned();
+        self.lines.push(format!(
+            "(define-fun |{}_n {}| ((state |{}_s|)) {} {})",
+            module, name, module, sort, term
+        ));
+        Ok(())
+    }
+
+    /// declare the state held by an input port or a register
+    fn declare(&mut self, entity: EntityId, kind: &str) -> Result<(), SmtError> {
+        let width = self.width(entity)?;
+        let name = self.name(entity).unwrap_or_else(|| format!("_{}", entity.0));
+        self.lines.push(format!("; yosys-smt2-{} {} {}", kind, name, width));
+        self.lines.push(format!(
+            "(declare-fun {} (|{}_s|) {})",
+            self.function(entity),
+            self.module,
+            self.sort(entity)?
+        ));
+        self.defined.insert(entity);
+        self.alias(entity)
+    }
+
+    /// the value of `entity` in `state`, defining it and the entities it depends on first
+    fn term(&mut self, entity: Option<EntityId>, op: OpId) -> Result<String, SmtError> {
+        let Some(entity) = entity else {
+            return self.error(Some(op), format!("an operand of op {} is missing", op.0));
+        };
+        if self.defined.contains(&entity) {
+            return Ok(self.term_in(entity, "state"));
+        }
+        if !self.pending.insert(entity) {
+            let name = self.env.print_entity(entity);
+            return self.error(Some(op), format!("{} is on a combinational loop", name));
+        }
+        let Some(&def) = self.env.get_defs(entity).first() else {
+            let name = self.env.print_entity(entity);
+            return self.error(Some(op), format!("{} is not driven", name));
+        };
+        let body = self.define(def, entity)?;
+        self.pending.remove(&entity);
+        self.lines.push(format!(
+            "(define-fun {} ((state |{}_s|)) {} {})",
+            self.function(entity),
+            self.module,
+            self.sort(entity)?,
+            body
+        ));
+        self.defined.insert(entity);
+        self.alias(entity)?;
+        Ok(self.term_in(entity, "state"))
+    }
+
+    fn terms(&mut self, entities: &[EntityId], op: OpId) -> Result<Vec<String>, SmtError> {
+        entities.iter().map(|&entity| self.term(Some(entity), op)).collect()
+    }
+
+    /// the term `op` gives to `lhs`
+    fn define(&mut self, op_id: OpId, lhs: EntityId) -> Result<String, SmtError> {
+        let env = self.env;
+        let width = self.width(lhs)?;
+        let term = match env.get_op(op_id) {
+            OpEnum::HwConstant(op) => {
+                let value = match op.value() {
+                    Some(ConstantAttr(value)) => value.to_owned(),
+                    None => vec![],
+                };
+                let bits = (0..width).rev().map(|i| match value.get(i) {
+                    Some(true) => '1',
+                    _ => '0',
+                });
+                format!("#b{}", bits.collect::<String>())
+            },
+            OpEnum::Assign(op) => self.term(op.rhs(), op_id)?,
+            OpEnum::HwBitCast(op) => self.term(op.rhs(), op_id)?,
+            OpEnum::CombUnary(op) => {
+                let operand = self.term(op.op(), op_id)?;
+                match op.predicate() {
+                    Some(CombUnaryPredicate::Neg) => format!("(bvneg {})", operand),
+                    _ => format!("(bvnot {})", operand),
+                }
+            },
+            OpEnum::CombVariadic(op) => {
+                let operands = self.terms(op.operands(), op_id)?;
+                let name = match op.predicate() {
+                    Some(CombVariadicPredicate::Add) => "bvadd",
+                    Some(CombVariadicPredicate::Mul) => "bvmul",
+                    Some(CombVariadicPredicate::And) => "bvand",
+                    Some(CombVariadicPredicate::Or) => "bvor",
+                    Some(CombVariadicPredicate::Xor) | None => "bvxor",
+                };
+                fold(name, operands)
+            },
+            OpEnum::CombBinary(op) => {
+                let (a, b) = (self.term(op.op0(), op_id)?, self.term(op.op1(), op_id)?);
+                let name = match op.predicate() {
+                    Some(CombBinaryPredicate::DivU) => "bvudiv",
+                    Some(CombBinaryPredicate::DivS) => "bvsdiv",
+                    Some(CombBinaryPredicate::ModU) => "bvurem",
+                    Some(CombBinaryPredicate::ModS) => "bvsrem",
+                    Some(CombBinaryPredicate::Shl) => "bvshl",
+                    Some(CombBinaryPredicate::ShrU) => "bvlshr",
+                    Some(CombBinaryPredicate::ShrS) => "bvashr",
+                    Some(CombBinaryPredicate::Sub) => "bvsub",
+                    None => return self.error(Some(op_id), "a binary op needs a predicate".into()),
+                };
+                format!("({} {} {})", name, a, b)
+            },
+            OpEnum::CombICmp(op) => {
+                let (a, b) = (self.term(op.op0(), op_id)?, self.term(op.op1(), op_id)?);
+                use CombICmpPredicate::*;
+                let name = match op.predicate() {
+                    Some(EQ | CEQ | WEQ) => "=",
+                    Some(NE | CNE | WNE) => "distinct",
+                    Some(ULT) => "bvult",
+                    Some(ULE) => "bvule",
+                    Some(UGT) => "bvugt",
+                    Some(UGE) => "bvuge",
+                    Some(SLT) => "bvslt",
+                    Some(SLE) => "bvsle",
+                    Some(SGT) => "bvsgt",
+                    Some(SGE) => "bvsge",
+                    None => return self.error(Some(op_id), "a comparison needs a predicate".into()),
+                };
+                bool_to_bv(format!("({} {} {})", name, a, b))
+            },
+            OpEnum::CombMux2(op) => {
+                let cond = self.term(op.cond(), op_id)?;
+                let (then, other) = (self.term(op.op0(), op_id)?, self.term(op.op1(), op_id)?);
+                format!("(ite (= {} #b1) {} {})", cond, then, other)
+            },
+            OpEnum::CombExtract(op) => {
+                let (Some(input), Some(low)) = (op.input(), op.low()) else {
+                    let message = "an extract needs an input and a low bit".into();
+                    return self.error(Some(op_id), message);
+                };
+                // shift in the wider of the two widths, so that no bit of `low` is lost
+                let (input_width, low_width) = (self.width(input)?, self.width(low)?);
+                let common = input_width.max(low_width);
+                let extend = |term: String, width: usize| match width < common {
+                    true => format!("((_ zero_extend {}) {})", common - width, term),
+                    false => term,
+                };
+                let input = extend(self.term(Some(input), op_id)?, input_width);
+                let low = extend(self.term(Some(low), op_id)?, low_width);
+                format!("((_ extract {} 0) (bvlshr {} {}))", width - 1, input, low)
+            },
+            OpEnum::CombConcat(op) => fold("concat", self.terms(op.operands(), op_id)?),
+            OpEnum::CombReplicate(op) => {
+                let Some(rhs) = op.rhs() else {
+                    return self.error(Some(op_id), "a replicate needs an operand".into());
+                };
+                let times = width / self.width(rhs)?;
+                format!("((_ repeat {}) {})", times, self.term(Some(rhs), op_id)?)
+            },
+            OpEnum::CombParity(op) => {
+                let Some(rhs) = op.rhs() else {
+                    return self.error(Some(op_id), "a parity needs an operand".into());
+                };
+                let operand = self.term(Some(rhs), op_id)?;
+                let bits = (0..self.width(rhs)?)
+                    .map(|i| format!("((_ extract {} {}) {})", i, i, operand))
+                    .collect();
+                fold("bvxor", bits)
+            },
+            OpEnum::HwInstance(_) => {
+                return self.error(Some(op_id), "instances must be flattened first".into())
+            },
+            op => {
+                let name = op.get_op_name();
+                return self.error(Some(op_id), format!("`{}` has no SMT-LIB2 counterpart", name));
+            },
+        };
+        Ok(term)
+    }
+}
+
+/// Export an `HwModule` as SMT-LIB2 bit-vector terms, in the style of yosys' `write_smt2`.
+///
+/// The module state is a sort `|top_s|`; input ports and registers are declared as
+/// functions of it, every other entity gets a `define-fun` computing it from the state,
+/// and named entities are also reachable as `|top_n name|`. The state is tied to the next
+/// one by the transition relation `|top_t|`, where each register takes its input, or its
+/// reset value while the reset is high. Registers start unconstrained, so `|top_i|` is
+/// true. The module must be flattened.
+pub fn export_smt2<E>(env: &E, module: OpId) -> Result<String, SmtError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let OpEnum::HwModule(op) = env.get_op(module) else {
+        let message = "only modules can be exported".into();
+        return Err(SmtError { op: Some(module), message });
+    };
+    let name = match op.name() {
+        Some(StringAttr(name)) => name.replace('|', "_"),
+        None => format!("_{}", module.0),
+    };
+    let Some(body) = op.body() else {
+        return Err(SmtError { op: Some(module), message: "the module has no body".into() });
+    };
+    let mut exporter = SmtExporter {
+        env,
+        module: name.to_owned(),
+        lines: vec![
+            format!("; yosys-smt2-module {}", name),
+            format!("(declare-sort |{}_s| 0)", name),
+        ],
+        defined: FxHashSet::default(),
+        pending: FxHashSet::default(),
+        names: FxHashMap::default(),
+    };
+
+    for port in utils::get_input_ports(env, body) {
+        exporter.declare(port, "input")?;
+    }
+    let ops = utils::walk_region_ops(env, body);
+    let registers = ops
+        .iter()
+        .filter_map(|&op_id| match env.get_op(op_id) {
+            OpEnum::SeqCompReg(reg) => Some((op_id, reg.output())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for &(op_id, output) in registers.iter() {
+        let Some(output) = output else {
+            return exporter.error(Some(op_id), "a register defines nothing".into());
+        };
+        exporter.declare(output, "register")?;
+    }
+
+    // every def gets a term, even the ones no output depends on
+    for &op_id in ops.iter() {
+        match env.get_op(op_id) {
+            OpEnum::SeqCompReg(_) | OpEnum::HwInput(_) | OpEnum::HwOutput(_) => {},
+            op => {
+                let defs = op.get_defs().into_iter().flat_map(|(_, defs)| defs).flatten();
+                for def in defs.collect::<Vec<_>>() {
+                    exporter.term(Some(def), op_id)?;
+                }
+            },
+        }
+    }
+    for &op_id in ops.iter() {
+        if let OpEnum::HwOutput(output) = env.get_op(op_id) {
+            for &entity in output.outputs() {
+                exporter.term(Some(entity), op_id)?;
+                let width = exporter.width(entity)?;
+                let name = exporter.name(entity).unwrap_or_else(|| format!("_{}", entity.0));
+                exporter.lines.push(format!("; yosys-smt2-output {} {}", name, width));
+            }
+        }
+    }
+
+    let mut next_states = vec![];
+    for (op_id, output) in registers {
+        let OpEnum::SeqCompReg(reg) = env.get_op(op_id) else { unreachable!() };
+        let mut next = exporter.term(reg.input(), op_id)?;
+        if let (Some(reset), Some(reset_val)) = (reg.reset(), reg.reset_val()) {
+            let reset = exporter.term(Some(reset), op_id)?;
+            let reset_val = exporter.term(Some(reset_val), op_id)?;
+            next = format!("(ite (= {} #b1) {} {})", reset, reset_val, next);
+        }
+        let output = exporter.term_in(output.unwrap(), "next_state");
+        next_states.push(format!("(= {} {})", next, output));
+    }
+    let transition = match next_states.len() {
+        0 => "true".to_owned(),
+        1 => next_states.remove(0),
+        _ => format!("(and {})", next_states.join(" ")),
+    };
+
+    let state = format!("((state |{}_s|))", name);
+    let mut lines = exporter.lines;
+    for (suffix, term) in [("a", "true"), ("u", "true"), ("i", "true"), ("h", "true")] {
+        lines.push(format!("(define-fun |{}_{}| {} Bool {})", name, suffix, state, term));
+    }
+    lines.push(format!(
+        "(define-fun |{}_t| ((state |{}_s|) (next_state |{}_s|)) Bool {})",
+        name, name, name, transition
+    ));
+    lines.push(format!("; yosys-smt2-topmod {}", name));
+    Ok(lines.join("\n") + "\n")
+}
+
+/// A bounded query on a module exported by [`export_smt2`]: `steps + 1` states from an
+/// initial one, each tied to the next by the transition relation, and whether the 1-bit
+/// entity named `bad` is high in any of them. `sat` means `bad` can be reached.
+pub fn smt2_bmc_query(module: &str, steps: usize, bad: &str) -> String {
+    let mut lines = vec![];
+    for step in 0..=steps {
+        lines.push(format!("(declare-const s{} |{}_s|)", step, module));
+        lines.push(format!("(assert (|{}_h| s{}))", module, step));
+        lines.push(format!("(assert (|{}_u| s{}))", module, step));
+    }
+    lines.push(format!("(assert (|{}_i| s0))", module));
+    for step in 0..steps {
+        lines.push(format!("(assert (|{}_t| s{} s{}))", module, step, step + 1));
+    }
+    let reached = (0..=steps)
+        .map(|step| format!("(= (|{}_n {}| s{}) #b1)", module, bad, step))
+        .collect::<Vec<_>>();
+    lines.push(format!("(assert (or {}))", reached.join(" ")));
+    lines.push("(check-sat)".into());
+    lines.join("\n") + "\n"
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtResult {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+/// A solver binary reading SMT-LIB2 from its standard input
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmtSolver {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl SmtSolver {
+    pub fn new(program: impl Into<String>, args: Vec<String>) -> Self {
+        Self { program: program.into(), args }
+    }
+
+    pub fn z3() -> Self { Self::new("z3", vec!["-in".into()]) }
+
+    pub fn bitwuzla() -> Self { Self::new("bitwuzla", vec![]) }
+
+    /// run the solver on `script` and return what it printed
+    pub fn run(&self, script: &str) -> std::io::Result<String> {
+        let mut child = std::process::Command::new(&self.program)
+            .args(&self.args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+        child.stdin.take().unwrap().write_all(script.as_bytes())?;
+        let output = child.wait_with_output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// the answer to the first `check-sat` of `script`
+    pub fn check(&self, script: &str) -> std::io::Result<SmtResult> {
+        let output = self.run(script)?;
+        let answer = output.lines().map(str::trim).find(|line| !line.is_empty());
+        Ok(match answer {
+            Some("sat") => SmtResult::Sat,
+            Some("unsat") => SmtResult::Unsat,
+            _ => SmtResult::Unknown,
+        })
+    }
+}
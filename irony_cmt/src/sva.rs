@@ -0,0 +1,213 @@
+use irony::{Entity, EntityId, Environ, IronyError, Op, OpId, RegionId};
+
+use crate::{AttributeEnum, EntityEnum, IdAttr, OpEnum, StringAttr};
+
+/// A sequence or property op that cannot be written as SystemVerilog Assertions
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvaError {
+    pub op: Option<OpId>,
+    pub message: String,
+}
+
+impl std::fmt::Display for SvaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<SvaError> for IronyError {
+    fn from(err: SvaError) -> Self { IronyError::Failed { op: err.op, message: err.message } }
+}
+
+/// An emitted expression, and whether it needs no parentheses as an operand
+struct Sva(String, bool);
+
+impl Sva {
+    fn operand(self) -> String {
+        match self {
+            Sva(expr, true) => expr,
+            Sva(expr, false) => format!("({})", expr),
+        }
+    }
+}
+
+struct SvaEmitter<'env, E: Environ<EntityT = EntityEnum, OpT = OpEnum>> {
+    env: &'env E,
+    lines: Vec<String>,
+}
+
+impl<E: Environ<EntityT = EntityEnum, OpT = OpEnum>> SvaEmitter<'_, E> {
+    fn error<T>(&self, op: Option<OpId>, message: String) -> Result<T, SvaError> {
+        Err(SvaError { op, message })
+    }
+
+    fn name(&self, entity: EntityId) -> Option<String> {
+        match self.env.get_entity(entity).get_attr("name") {
+            Some(AttributeEnum::StringAttr(StringAttr(name))) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn def(&self, entity: Option<EntityId>) -> Result<(EntityId, OpId), SvaError> {
+        let Some(entity) = entity else {
+            return self.error(None, "an operand of a property is missing".into());
+        };
+        match self.env.get_defs(entity).first() {
+            Some(&def) => Ok((entity, def)),
+            None => self.error(None, format!("{} is not driven", self.env.print_entity(entity))),
+        }
+    }
+
+    /// the signal an event stands for: the wire it is made from, or the event itself
+    fn signal(&self, event: EntityId) -> Result<String, SvaError> {
+        let from = self.env.get_defs(event).into_iter().find_map(|def| match self.env.get_op(def) {
+            OpEnum::EventFrom(op) => op.rhs(),
+            _ => None,
+        });
+        let entity = from.unwrap_or(event);
+        match self.name(entity) {
+            Some(name) => Ok(name),
+            None => {
+                let message = format!("{} has no name to refer to", self.env.print_entity(entity));
+                self.error(None, message)
+            },
+        }
+    }
+
+    /// the clocking event of an assertion: a wire or an event made from one ticks on its
+    /// rising edge, any other event when it is triggered
+    fn clocking(&self, clock: EntityId) -> Result<String, SvaError> {
+        let from_wire = match self.env.get_entity(clock) {
+            EntityEnum::Wire(_) => true,
+            _ => self
+                .env
+                .get_defs(clock)
+                .into_iter()
+                .any(|def| matches!(self.env.get_op(def), OpEnum::EventFrom(_))),
+        };
+        let signal = self.signal(clock)?;
+        Ok(if from_wire { format!("@(posedge {})", signal) } else { format!("@({})", signal) })
+    }
+
+    fn sequence(&self, sequence: EntityId) -> Result<Sva, SvaError> {
+        let (_, def) = self.def(Some(sequence))?;
+        match self.env.get_op(def) {
+            OpEnum::SqnFromEvent(op) => Ok(Sva(self.signal(self.def(op.rhs())?.0)?, true)),
+            OpEnum::SqnDelay(op) => {
+                let rhs = self.sequence(self.def(op.rhs())?.0)?.operand();
+                let lb = op.lb().map_or(0, |IdAttr(lb)| *lb);
+                let delay = match op.ub() {
+                    Some(IdAttr(ub)) if *ub == lb => format!("##{}", lb),
+                    Some(IdAttr(ub)) => format!("##[{}:{}]", lb, ub),
+                    None => format!("##[{}:$]", lb),
+                };
+                Ok(Sva(format!("{} {}", delay, rhs), false))
+            },
+            OpEnum::SqnConcat(op) => {
+                // the second sequence starts in the cycle the first one ends, which is
+                // `##0` unless it starts with a delay of its own
+                let s0 = self.sequence(self.def(op.s0())?.0)?.operand();
+                let (s1, s1_def) = self.def(op.s1())?;
+                let s1 = match self.env.get_op(s1_def) {
+                    OpEnum::SqnDelay(_) => self.sequence(s1)?.0,
+                    _ => format!("##0 {}", self.sequence(s1)?.operand()),
+                };
+                Ok(Sva(format!("{} {}", s0, s1), false))
+            },
+            op => self.error(Some(def), format!("`{}` is not a sequence", op.get_op_name())),
+        }
+    }
+
+    fn property(&self, property: EntityId) -> Result<Sva, SvaError> {
+        let (_, def) = self.def(Some(property))?;
+        let unary = |keyword: &str, rhs: Option<EntityId>| -> Result<Sva, SvaError> {
+            let rhs = self.property(self.def(rhs)?.0)?.operand();
+            Ok(Sva(format!("{} {}", keyword, rhs), false))
+        };
+        let binary = |keyword: &str, a: Option<EntityId>, b: Option<EntityId>| {
+            let a = self.property(self.def(a)?.0)?.operand();
+            let b = self.property(self.def(b)?.0)?.operand();
+            Ok(Sva(format!("{} {} {}", a, keyword, b), false))
+        };
+        match self.env.get_op(def) {
+            OpEnum::PrptFromSqn(op) => self.sequence(self.def(op.rhs())?.0),
+            OpEnum::SqnFromEvent(_) | OpEnum::SqnDelay(_) | OpEnum::SqnConcat(_) => {
+                self.sequence(property)
+            },
+            OpEnum::PrptNexttime(op) => unary("nexttime", op.rhs()),
+            OpEnum::PrptAlways(op) => unary("always", op.rhs()),
+            OpEnum::PrptEventually(op) => unary("s_eventually", op.rhs()),
+            OpEnum::PrptUntil(op) => binary("until", op.a(), op.b()),
+            OpEnum::PrptConjunction(op) => binary("and", op.a(), op.b()),
+            OpEnum::PrptImplica(op) => {
+                // the antecedent of `|->` must be a sequence
+                let (mut a, a_def) = self.def(op.a())?;
+                if let OpEnum::PrptFromSqn(from) = self.env.get_op(a_def) {
+                    a = self.def(from.rhs())?.0;
+                }
+                let a = self.sequence(a)?.operand();
+                // `|->` binds looser than the sequence operators
+                let (b, b_def) = self.def(op.b())?;
+                let b = match self.env.get_op(b_def).get_dialect() {
+                    Some("sequence") => self.sequence(b)?.0,
+                    _ => self.property(b)?.operand(),
+                };
+                Ok(Sva(format!("{} |-> {}", a, b), false))
+            },
+            op => self.error(Some(def), format!("`{}` is not a property", op.get_op_name())),
+        }
+    }
+
+    /// emit the targets of `region`, an `event.block` clocking the ones inside it
+    fn region(&mut self, region: RegionId, clock: Option<EntityId>) -> Result<(), SvaError> {
+        for op_id in self.env.get_region(region).get_op_children() {
+            let op = self.env.get_op(op_id);
+            let clock = match op {
+                OpEnum::EventBlockDef(block) => block.event().or(clock),
+                _ => clock,
+            };
+            if let OpEnum::PrptSynth(synth) = op {
+                let Some(clock) = clock else {
+                    return self.error(Some(op_id), "the property has no clock".into());
+                };
+                let (property, _) = self.def(synth.property())?;
+                let label = self.name(property).map(|name| format!("{}: ", name));
+                let body = self.property(property)?.0;
+                let clocking = self.clocking(clock)?;
+                self.lines.push(format!(
+                    "{}assert property ({} {});",
+                    label.unwrap_or_default(),
+                    clocking,
+                    body
+                ));
+            }
+            for (_, regions) in op.get_regions() {
+                for region in regions {
+                    self.region(region, clock)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the `property.synthesize` targets of an `HwModule` as SystemVerilog Assertions,
+/// one `assert property` per line, labelled with the name of the property if it has one.
+///
+/// A target is clocked by the event of the `event.block` it is in, or else by `clock`;
+/// a wire or an event made from a wire is sampled on the rising edge. Sequences follow
+/// the `ltl` dialect of CIRCT, so `sequence.concat` overlaps its operands as `##0` does.
+/// Events and wires are referred to by name.
+pub fn emit_sva<E>(env: &E, module: OpId, clock: Option<EntityId>) -> Result<String, SvaError>
+where E: Environ<EntityT = EntityEnum, OpT = OpEnum> {
+    let OpEnum::HwModule(op) = env.get_op(module) else {
+        let message = "only the properties of a module can be emitted".into();
+        return Err(SvaError { op: Some(module), message });
+    };
+    let Some(body) = op.body() else {
+        return Err(SvaError { op: Some(module), message: "the module has no body".into() });
+    };
+    let mut emitter = SvaEmitter { env, lines: vec![] };
+    emitter.region(body, clock)?;
+    Ok(emitter.lines.into_iter().map(|line| line + "\n").collect())
+}
@@ -31,7 +31,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("a".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -65,7 +65,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(1.into())),
                     Some("clk".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -74,7 +74,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("a".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -83,7 +83,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("b".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -92,7 +92,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("c".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -101,7 +101,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("d".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -110,7 +110,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("e".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -119,7 +119,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(1.into())),
                     Some("cond".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -128,7 +128,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("h".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -137,7 +137,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("h_reg".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -148,7 +148,8 @@ mod hw_test {
                 HwInstance::new(
                     vec![b],
                     vec![a],
-                    Some(IdAttr(module_pass.id() as usize)),
+                    None,
+                    Some("pass".into()),
                     Some(StringAttr("pass_inst".into())),
                 )
                 .into(),
@@ -160,7 +161,7 @@ mod hw_test {
             cmt.add_op(
                 CombVariadic::new(
                     Some(d),
-                    vec![b, c].into(),
+                    vec![b, c],
                     Some(CombVariadicPredicate::Add),
                 )
                 .into(),
@@ -186,7 +187,7 @@ mod hw_test {
     }
 
     #[test]
-    pub fn print_test() -> Result<(), ()> {
+    pub fn print_test() -> irony::IronyResult<()> {
         let (mut cmt, ..) = create();
 
         let no_parent = cmt
@@ -226,7 +227,7 @@ mod hw_test {
                 Some("top".into()),
                 Some(true.into()),
                 Some(true.into()),
-                Some(LocationAttr(Location::caller().to_owned())),
+                Some(Location::caller().into()),
             )
             .into(),
         );
@@ -256,7 +257,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("a".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -265,7 +266,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("b".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -287,7 +288,7 @@ mod hw_test {
                 Some("Pass".into()),
                 None,
                 Some(true.into()),
-                Some(LocationAttr(Location::caller().to_owned())),
+                Some(Location::caller().into()),
             )
             .into(),
         );
@@ -311,7 +312,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("a".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -328,7 +329,7 @@ mod hw_test {
                 Some("top".into()),
                 Some(true.into()),
                 Some(true.into()),
-                Some(LocationAttr(Location::caller().to_owned())),
+                Some(Location::caller().into()),
             )
             .into(),
         );
@@ -352,7 +353,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("a".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -361,7 +362,7 @@ mod hw_test {
                     Some(DataTypeEnum::UInt(8.into())),
                     Some("b".into()),
                     Some(true.into()),
-                    Some(LocationAttr(Location::caller().to_owned())),
+                    Some(Location::caller().into()),
                 )
                 .into(),
             );
@@ -373,7 +374,8 @@ mod hw_test {
                 HwInstance::new(
                     vec![b],
                     vec![a],
-                    Some(IdAttr(module_pass.id() as usize)),
+                    Some(IdAttr(module_pass.id())),
+                    None,
                     Some(StringAttr("pass_inst".into())),
                 )
                 .into(),
@@ -383,3 +385,2560 @@ mod hw_test {
         });
     }
 }
+
+mod constraint_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    fn wire(cmt: &mut CmtEnv, name: &str, width: usize) -> EntityId {
+        cmt.add_entity(Wire::new(Some(DataTypeEnum::UInt(width.into())), Some(name.into()), None, None).into())
+    }
+
+    /// constraints get the uses and the defs of an op in this order, which they were not
+    /// given before: a check on the operands looked at the results and the reverse
+    #[test]
+    pub fn argument_order_test() {
+        let mut cmt = CmtEnv::default();
+        let [a, b, c, x, y] = [("a", 8), ("b", 4), ("c", 1), ("x", 8), ("y", 16)].map(|(name, width)| wire(&mut cmt, name, width));
+
+        // the operands of `icmp` must share a type, whatever its result is
+        let mismatched = cmt.add_op(CombICmp::new(Some(c), Some(a), Some(b), Some(CombICmpPredicate::EQ)).into());
+        let matched = cmt.add_op(CombICmp::new(Some(c), Some(a), Some(x), Some(CombICmpPredicate::EQ)).into());
+        assert!(!cmt.verify_op(mismatched));
+        assert!(cmt.verify_op(matched));
+
+        // the inputs of an instance are checked against the module arguments, and its
+        // outputs against the module outputs
+        let body = cmt.add_region(Region::new(true));
+        cmt.add_op(
+            HwModule::new(
+                None,
+                Some(StringAttr("widen".into())),
+                Some(vec![StringAttr("i".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(8.into()))].into()),
+                Some(vec![StringAttr("o".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(16.into()))].into()),
+                Some(body),
+            )
+            .into(),
+        );
+        cmt.begin_region(Some(body));
+        let (i, o) = (wire(&mut cmt, "i", 8), wire(&mut cmt, "o", 16));
+        cmt.add_op(HwInput::new(vec![i]).into());
+        cmt.add_op(HwOutput::new(vec![o]).into());
+        cmt.end_region();
+        let instance = |inputs, outputs| {
+            HwInstance::new(outputs, inputs, None, Some("widen".into()), Some(StringAttr("u".into()))).into()
+        };
+        let good = cmt.add_op(instance(vec![x], vec![y]));
+        let swapped = cmt.add_op(instance(vec![y], vec![x]));
+        assert!(cmt.verify_op(good));
+        assert!(!cmt.verify_op(swapped));
+    }
+}
+
+mod infer_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    fn wire(cmt: &mut CmtEnv, name: &str, dtype: Option<DataTypeEnum>) -> EntityId {
+        cmt.add_entity(Wire::new(dtype, Some(name.into()), None, None).into())
+    }
+
+    #[test]
+    pub fn infer_widths_test() {
+        let mut cmt = CmtEnv::default();
+        let module = cmt.add_entity(Module::new(None, Some("top".into()), None, None, None).into());
+        let body = cmt.add_region(Region::new(true));
+        cmt.add_op(
+            HwModule::new(
+                Some(module),
+                Some(StringAttr("top".into())),
+                Some(vec![StringAttr("a".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(8.into()))].into()),
+                Some(vec![StringAttr("d".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(16.into()))].into()),
+                Some(body),
+            )
+            .into(),
+        );
+
+        cmt.begin_region(Some(body));
+        let a = wire(&mut cmt, "a", None);
+        let b = wire(&mut cmt, "b", None);
+        let c = wire(&mut cmt, "c", None);
+        let d = wire(&mut cmt, "d", None);
+        cmt.add_op(HwInput::new(vec![a]).into());
+        cmt.add_op(
+            CombVariadic::new(Some(b), vec![a, a], Some(CombVariadicPredicate::Add)).into(),
+        );
+        cmt.add_op(CombICmp::new(Some(c), Some(a), Some(b), Some(CombICmpPredicate::EQ)).into());
+        cmt.add_op(CombConcat::new(Some(d), vec![a, b]).into());
+        cmt.add_op(HwOutput::new(vec![d]).into());
+        cmt.end_region();
+
+        let conflicts = infer_types(&mut cmt, body);
+        assert!(conflicts.is_empty());
+
+        let widths = [a, b, c, d]
+            .iter()
+            .map(|wire| cmt.get_entity(*wire).get_dtype().unwrap().width())
+            .collect::<Vec<_>>();
+        assert_eq!(widths, vec![8, 8, 1, 16]);
+
+        cmt.get_entity_entry(c)
+            .and_modify(|entity| entity.set_dtype(Some(DataTypeEnum::UInt(4.into()))));
+        let conflicts = infer_types(&mut cmt, body);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].entity, c);
+
+        // the conflicts are reported as diagnostics, the first one failing the pass
+        let module = cmt.get_region_use(body).unwrap();
+        let failed = TypeInferPass.run_raw(&mut cmt, module).unwrap_err();
+        assert_eq!(failed, conflicts[0].to_owned().into());
+        let diagnostics = cmt.get_diagnostics().take_unhandled();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, conflicts[0].message);
+    }
+
+    #[test]
+    pub fn concat_without_width_test() {
+        let mut cmt = CmtEnv::default();
+        let body = cmt.add_region(Region::new(true));
+        let memory = DataTypeEnum::SeqHlmem(SeqHlmemType(Box::new(DataTypeEnum::UInt(8.into())), vec![4]));
+        cmt.begin_region(Some(body));
+        let a = wire(&mut cmt, "a", Some(DataTypeEnum::UInt(8.into())));
+        let m = wire(&mut cmt, "m", Some(memory.to_owned()));
+        let d = wire(&mut cmt, "d", None);
+        let concat = cmt.add_op(CombConcat::new(Some(d), vec![a, m]).into());
+        cmt.end_region();
+
+        let conflicts = infer_types(&mut cmt, body);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].op, conflicts[0].entity), (concat, m));
+        assert_eq!((conflicts[0].inferred.to_owned(), conflicts[0].declared.to_owned()), (None, memory));
+        assert_eq!(cmt.get_entity(d).get_dtype(), None);
+    }
+
+    #[test]
+    pub fn no_body_test() {
+        // a module without a body has no arguments to type
+        let mut cmt = CmtEnv::default();
+        let module = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        assert_eq!(infer_op_types(&cmt, module), Ok(vec![]));
+    }
+}
+
+mod instance_graph_test {
+    use irony::{Environ, IronyError, Op, Region};
+
+    use crate::*;
+
+    #[test]
+    pub fn hierarchy_test() {
+        let (cmt, _, top_def) = super::hw_test::create();
+        let graph = InstanceGraph::new(&cmt).unwrap();
+
+        let pass_def = graph.get_modules().into_iter().find(|&m| m != top_def).unwrap();
+        let instance = graph.get_instances(top_def)[0].instance;
+
+        assert_eq!(graph.get_top_modules(), vec![top_def]);
+        assert_eq!(graph.post_order(), vec![pass_def, top_def]);
+        assert!(!graph.is_recursive());
+        assert_eq!(graph.instance_paths(pass_def), Ok(vec![vec![instance]]));
+        assert!(graph.unused_modules().is_empty());
+    }
+
+    /// a module without ports instantiating each of `targets`
+    fn module(cmt: &mut CmtEnv, name: &str, targets: &[&str]) -> OpId {
+        let body = cmt.add_region(Region::new(true));
+        let module = HwModule::new(None, Some(name.into()), None, None, None, None, Some(body));
+        let module = cmt.add_op(module.into());
+        cmt.with_region(Some(body), |cmt| {
+            for (i, &target) in targets.iter().enumerate() {
+                let name = StringAttr(format!("{}_{}", target, i));
+                let target = Some(target.into());
+                cmt.add_op(HwInstance::new(vec![], vec![], None, target, Some(name)).into());
+            }
+        });
+        module
+    }
+
+    #[test]
+    pub fn recursive_test() {
+        let mut cmt = CmtEnv::default();
+        let top = module(&mut cmt, "top", &["a"]);
+        let a = module(&mut cmt, "a", &["b"]);
+        let b = module(&mut cmt, "b", &["a"]);
+        let graph = InstanceGraph::new(&cmt).unwrap();
+
+        assert_eq!(graph.get_top_modules(), vec![top]);
+        assert_eq!(graph.find_recursion(), Some(vec![a, b, a]));
+        let mut order = graph.post_order();
+        order.sort_by_key(|module| module.0);
+        assert_eq!(order, vec![top, a, b]);
+
+        let failed = graph.instance_paths(b).unwrap_err();
+        assert!(matches!(failed, IronyError::Failed { op: Some(op), .. } if op == a));
+        assert_eq!(failed.to_string(), "instance paths of a recursive hierarchy are infinite");
+    }
+
+    #[test]
+    pub fn missing_target_test() {
+        let mut cmt = CmtEnv::default();
+        module(&mut cmt, "top", &["a"]);
+        let failed = InstanceGraph::new(&cmt).unwrap_err();
+        let IronyError::Failed { op: Some(instance), .. } = failed else { panic!("{:?}", failed) };
+        assert!(matches!(cmt.get_op(instance), OpEnum::HwInstance(_)));
+        assert_eq!(failed.to_string(), "the target of the instance cannot be found");
+
+        // an entity defined by an op other than a module is no target either
+        let wire = cmt.add_entity(Wire::new(None, Some("w".into()), None, None).into());
+        cmt.add_op(HwConstant::new(Some(wire), Some(ConstantAttr(vec![true]))).into());
+        let target = vec![("target_id".into(), IdAttr(wire.0).into())];
+        cmt.get_op_entry(instance).and_modify(|op| op.set_attrs(target));
+        assert!(InstanceGraph::new(&cmt).is_err());
+
+        // nor is a module without a body walked
+        let mut cmt = CmtEnv::default();
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let failed = InstanceGraph::new(&cmt).unwrap_err();
+        assert_eq!(failed, IronyError::failed(Some(ext), "the module has no body"));
+    }
+
+    #[test]
+    pub fn shared_module_test() {
+        let mut cmt = CmtEnv::default();
+        let top = module(&mut cmt, "top", &["mid", "leaf"]);
+        let mid = module(&mut cmt, "mid", &["leaf", "leaf"]);
+        let leaf = module(&mut cmt, "leaf", &[]);
+        let graph = InstanceGraph::new(&cmt).unwrap();
+
+        let instances =
+            |module| graph.get_instances(module).iter().map(|e| e.instance).collect::<Vec<_>>();
+        let (in_top, in_mid) = (instances(top), instances(mid));
+        assert_eq!(graph.get_users(leaf).len(), 3);
+        assert_eq!(graph.post_order(), vec![leaf, mid, top]);
+        assert!(graph.unused_modules().is_empty());
+
+        let paths = graph.instance_paths(leaf).unwrap();
+        assert_eq!(paths.len(), 3);
+        for path in [vec![in_top[1]], vec![in_top[0], in_mid[0]], vec![in_top[0], in_mid[1]]] {
+            assert!(paths.contains(&path), "missing {:?}", path);
+        }
+        assert_eq!(graph.instance_paths(mid), Ok(vec![vec![in_top[0]]]));
+    }
+}
+
+mod flatten_test {
+    use irony::Environ;
+
+    use crate::*;
+
+    #[test]
+    pub fn flatten_top_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+
+        assert_eq!(flatten_top(&mut cmt), Ok(top_def));
+
+        let graph = InstanceGraph::new(&cmt).unwrap();
+        assert_eq!(graph.get_modules(), vec![top_def]);
+        assert!(graph.get_edges().is_empty());
+
+        // the pass-through module is gone, `b` is replaced by the top input `a`
+        let body = cmt.get_op(top_def).get_regions()[0].1[0];
+        let add = cmt
+            .get_region(body)
+            .get_op_children()
+            .into_iter()
+            .find_map(|op| match cmt.get_op(op) {
+                OpEnum::CombVariadic(add) => Some(add),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(cmt.print_entity(add.operands[0]), "%a");
+        let printed = cmt.print_op(top_def);
+        assert!(printed.starts_with("hw.module @top(%a: i8) -> (b: i8) {"));
+        assert!(printed.contains("%d = comb.add %a, %c : i8"));
+        assert!(!printed.contains("hw.instance"));
+    }
+
+    #[test]
+    pub fn missing_target_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        let instance = InstanceGraph::new(&cmt).unwrap().get_instances(top_def)[0].instance;
+        cmt.get_op_entry(instance).and_modify(|op| {
+            let mut attrs = op.get_attrs();
+            attrs.retain(|(name, _)| name != "target" && name != "target_id");
+            attrs.push(("target".into(), SymbolRefAttr("missing".into()).into()));
+            op.set_attrs(attrs);
+        });
+        let json = cmt.to_json();
+
+        let failed = flatten_module(&mut cmt, top_def, false).unwrap_err();
+        assert!(matches!(failed, irony::IronyError::Failed { op: Some(op), .. } if op == instance));
+        assert_eq!(failed.to_string(), "the target of the instance cannot be found");
+        assert_eq!(cmt.to_json(), json);
+    }
+}
+
+mod clone_test {
+    use irony::Environ;
+
+    use crate::*;
+
+    #[test]
+    pub fn clone_op_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        let pass_def = InstanceGraph::new(&cmt).unwrap().get_instances(top_def)[0].target;
+
+        let mapping = cmt.clone_op(top_def, None);
+        let cloned = mapping.lookup_op(top_def).unwrap();
+        assert_ne!(cloned, top_def);
+        assert!(mapping.get_entity_map().iter().all(|(old, new)| old != new));
+        assert_eq!(cmt.print_op(cloned), cmt.print_op(top_def));
+
+        let mut other = CmtEnv::default();
+        let mapping = cmt.clone_op_into(pass_def, &mut other, None);
+        let cloned = mapping.lookup_op(pass_def).unwrap();
+        assert_eq!(other.print_op(cloned), cmt.print_op(pass_def));
+    }
+}
+
+mod serialize_test {
+    use irony::Environ;
+
+    use crate::*;
+
+    #[test]
+    pub fn round_trip_test() {
+        let (cmt, _, top_def) = super::hw_test::create();
+
+        let json = cmt.to_json();
+        let from_json = CmtEnv::from_json(&json).unwrap();
+        assert_eq!(from_json.print_op(top_def), cmt.print_op(top_def));
+        assert_eq!(from_json.to_json(), json);
+
+        let binary = cmt.to_binary();
+        assert!(binary.len() < json.len());
+        let mut from_binary = CmtEnv::from_binary(&binary).unwrap();
+        assert_eq!(from_binary.print_op(top_def), cmt.print_op(top_def));
+
+        // the loaded environment keeps working
+        assert_eq!(flatten_top(&mut from_binary), Ok(top_def));
+
+        let version = format!("\"version\": {}", irony::FORMAT_VERSION);
+        let newer = json.replacen(&version, "\"version\": 99", 1);
+        assert_eq!(
+            CmtEnv::from_json(&newer).err(),
+            Some(irony::IronyError::Serialize(irony::SerializeError::Version(99)))
+        );
+    }
+
+    #[test]
+    pub fn hash_after_load_test() {
+        let mut cmt = CmtEnv::default();
+        let u8 = || Some(DataTypeEnum::UInt(8.into()));
+        let constant = |cmt: &mut CmtEnv, name: &str, value: bool| {
+            let lhs = cmt.add_entity(Wire::new(u8(), Some(name.into()), None, None).into());
+            cmt.add_op(HwConstant::new(Some(lhs), Some(ConstantAttr(vec![value; 8]))).into())
+        };
+        let one = constant(&mut cmt, "one", true);
+        assert_eq!(cmt.hash_op(one), Some(one));
+
+        let mut loaded = CmtEnv::from_binary(&cmt.to_binary()).unwrap();
+        assert_eq!(loaded.hash_op(one), Some(one));
+        let again = constant(&mut loaded, "again", true);
+        assert_eq!(loaded.hash_op(again), Some(one));
+        let zero = constant(&mut loaded, "zero", false);
+        assert_eq!(loaded.hash_op(zero), Some(zero));
+        assert_eq!(loaded.op_table.len(), 2);
+
+
+        // the entities are written first
+        let duplicate = loaded.to_json().replacen("\"id\": 2", "\"id\": 0", 1);
+        assert_eq!(
+            CmtEnv::from_json(&duplicate).err(),
+            Some(irony::IronyError::Serialize(irony::SerializeError::Malformed(
+                "duplicate id 0".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    pub fn truncated_binary_test() {
+        // a string whose length overflows the position
+        let mut binary = b"IRNY".to_vec();
+        binary.extend([irony::FORMAT_VERSION as u8, 4]);
+        binary.extend([0xff; 9]);
+        binary.push(0x01);
+        assert_eq!(
+            CmtEnv::from_binary(&binary).err(),
+            Some(irony::IronyError::Serialize(irony::SerializeError::Syntax(
+                "unexpected end of input".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    pub fn nesting_test() {
+        let too_deep = Some(irony::SerializeError::Syntax("nesting too deep".to_owned()));
+        assert_eq!(irony::IrValue::from_json(&"[".repeat(1_000_000)).err(), too_deep);
+        assert_eq!(irony::IrValue::from_bytes(&[5, 1].repeat(1_000_000)).err(), too_deep);
+
+        // values nested just below the limit still load
+        let nested = "[".repeat(255) + &"]".repeat(255);
+        assert!(irony::IrValue::from_json(&nested).is_ok());
+    }
+
+    #[test]
+    pub fn renamed_attr_test() {
+        let mut cmt = CmtEnv::default();
+        let dtype = DataTypeEnum::Array(ArrayType(Box::new(DataTypeEnum::UInt(2.into())), 2));
+        let lhs = cmt.add_entity(Wire::new(Some(dtype), Some("lhs".into()), None, None).into());
+        let values = vec![ConstantAttr(vec![true, false]), ConstantAttr(vec![false, true])];
+        let constant = cmt.add_op(HwAggregateConstant::new(Some(lhs), Some(values.into())).into());
+        let json = cmt.to_json();
+
+        // files written before version 6 name the values `attrs`
+        let version = format!("\"version\": {}", irony::FORMAT_VERSION);
+        let old =
+            json.replacen(&version, "\"version\": 5", 1).replacen("\"values\"", "\"attrs\"", 1);
+        assert_ne!(old, json);
+        let loaded = CmtEnv::from_json(&old).unwrap();
+        assert_eq!(loaded.print_op(constant), cmt.print_op(constant));
+        assert_eq!(loaded.to_json(), json);
+    }
+}
+
+mod dot_test {
+    use irony::Environ;
+
+    use crate::*;
+
+    #[test]
+    pub fn export_dot_test() {
+        let (cmt, _, top_def) = super::hw_test::create();
+
+        let dot = export_dot(&cmt, &DotOptions { color_by_dialect: true, ..Default::default() });
+        assert!(dot.starts_with("digraph irony {"));
+        assert!(dot.contains("label=\"hw.module %top: body\""));
+        assert!(dot.contains("[label=\"%d: i8\"]"));
+        assert!(dot.contains("[label=\"comb.mux\", fillcolor=\"#b2df8a\"];"));
+        assert!(dot.contains("[label=\"seq.compreg\", fillcolor=\"#fdbf6f\"];"));
+        assert!(dot.contains("[label=\"%cond: i1\"];"));
+        assert_eq!(dot.matches("subgraph cluster_region").count(), 2);
+        assert_eq!(dot.matches(" -> ").count(), 13);
+        assert!(dot.ends_with("}\n"));
+
+        // the cone of `c` reaches the output register but not the instance
+        let body = cmt.get_op(top_def).get_regions()[0].1[0];
+        let c = cmt
+            .get_entities_with_parent(Some(body))
+            .into_iter()
+            .find(|&entity| cmt.print_entity(entity) == "%c")
+            .unwrap();
+        let cone = export_dot(&cmt, &DotOptions { cone: Some(c), ..Default::default() });
+        assert!(cone.contains("hw.constant") && cone.contains("seq.compreg"));
+        assert!(!cone.contains("hw.instance"));
+    }
+}
+
+mod region_argument_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    /// a pass-through module whose port is a region argument, and a top instantiating it
+    pub fn create() -> (CmtEnv, OpId, OpId) {
+        let mut cmt = CmtEnv::default();
+        let i8 = || Some(DataTypeEnum::UInt(8.into()));
+
+        let module_pass =
+            cmt.add_entity(Module::new(None, Some("Pass".into()), None, None, None).into());
+        let pass_body = cmt.add_region(Region::new(true));
+        let pass_def = cmt.add_op(
+            HwModule::new(
+                Some(module_pass),
+                Some(StringAttr("pass".into())),
+                Some(vec![StringAttr("a".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(8.into()))].into()),
+                Some(vec![StringAttr("b".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(8.into()))].into()),
+                Some(pass_body),
+            )
+            .into(),
+        );
+        let a = cmt.add_region_argument(pass_body, Wire::new(None, Some("a".into()), None, None).into());
+        cmt.with_region(Some(pass_body), |cmt| {
+            let b = cmt.add_entity(Wire::new(i8(), Some("b".into()), None, None).into());
+            cmt.add_op(CombUnary::new(Some(b), Some(a), Some(CombUnaryPredicate::Not)).into());
+            cmt.add_op(HwOutput::new(vec![b]).into());
+        });
+
+        let module_top = cmt.add_entity(
+            Module::new(None, Some("top".into()), Some(true.into()), None, None).into(),
+        );
+        let top_body = cmt.add_region(Region::new(true));
+        let top_def = cmt.add_op(
+            HwModule::new(
+                Some(module_top),
+                Some(StringAttr("top".into())),
+                Some(vec![StringAttr("x".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(8.into()))].into()),
+                Some(vec![StringAttr("y".into())].into()),
+                Some(vec![TypeAttr(DataTypeEnum::UInt(8.into()))].into()),
+                Some(top_body),
+            )
+            .into(),
+        );
+        let x = cmt.add_region_argument(top_body, Wire::new(i8(), Some("x".into()), None, None).into());
+        cmt.with_region(Some(top_body), |cmt| {
+            let y = cmt.add_entity(Wire::new(i8(), Some("y".into()), None, None).into());
+            cmt.add_op(
+                HwInstance::new(
+                    vec![y],
+                    vec![x],
+                    None,
+                    Some("pass".into()),
+                    Some(StringAttr("pass_inst".into())),
+                )
+                .into(),
+            );
+            cmt.add_op(HwOutput::new(vec![y]).into());
+        });
+        (cmt, pass_def, top_def)
+    }
+
+    #[test]
+    pub fn module_arguments_test() {
+        let (mut cmt, pass_def, top_def) = create();
+        let pass_body = cmt.get_op(pass_def).get_regions()[0].1[0];
+        let a = cmt.get_region_arguments(pass_body)[0];
+        assert_eq!(cmt.get_argument_owner(a), Some(pass_body));
+        assert_eq!(cmt.print_region_arguments(pass_body), "%a");
+
+        // the argument gets its dtype from the module signature
+        assert!(infer_types(&mut cmt, pass_body).is_empty());
+        assert_eq!(cmt.get_entity(a).get_dtype(), Some(DataTypeEnum::UInt(8.into())));
+        assert!(cmt.verify_op(pass_def) && cmt.verify_op(top_def));
+
+        let loaded = CmtEnv::from_json(&cmt.to_json()).unwrap();
+        assert_eq!(loaded.get_region_arguments(pass_body), vec![a]);
+
+        let mapping = cmt.clone_op(pass_def, None);
+        let cloned_body = mapping.lookup_region(pass_body).unwrap();
+        assert_eq!(cmt.get_region_arguments(cloned_body), vec![mapping.lookup_entity(a).unwrap()]);
+
+        // inlining substitutes the argument by the instance input
+        assert_eq!(flatten_module(&mut cmt, top_def, true), Ok(()));
+        let top_body = cmt.get_op(top_def).get_regions()[0].1[0];
+        let not = cmt
+            .get_region(top_body)
+            .get_op_children()
+            .into_iter()
+            .find_map(|op| match cmt.get_op(op) {
+                OpEnum::CombUnary(not) => Some(not),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(not.op, Some(cmt.get_region_arguments(top_body)[0]));
+    }
+}
+
+mod symbol_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    #[test]
+    pub fn symbol_table_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        let instance = InstanceGraph::new(&cmt).unwrap().get_instances(top_def)[0].instance;
+        let pass_def = cmt.lookup_symbol(None, "pass").unwrap();
+
+        assert_eq!(cmt.get_op(top_def).get_symbol(), Some(irony::Symbol::new("top".into())));
+        assert_eq!(cmt.resolve_symbol(instance, "pass"), Some(pass_def));
+        assert_eq!(cmt.get_symbol_uses(pass_def), vec![instance]);
+        assert!(cmt.find_duplicate_symbols(None).is_empty());
+        assert!(cmt.print_op(instance).contains("@pass("));
+
+        assert_eq!(
+            cmt.rename_symbol(pass_def, "top"),
+            Err(irony::IronyError::DuplicateSymbol("top".into()))
+        );
+        assert_eq!(cmt.rename_symbol(pass_def, "buffer"), Ok(()));
+        assert!(cmt.print_op(instance).contains("@buffer("));
+        assert!(cmt.verify_op(instance));
+
+        // an instance moved to another environment still finds its module by name
+        let mut other = CmtEnv::default();
+        cmt.clone_op_into(pass_def, &mut other, None);
+        let mapping = cmt.clone_op_into(top_def, &mut other, None);
+        let cloned = mapping.lookup_op(top_def).unwrap();
+        assert_eq!(InstanceGraph::new(&other).unwrap().get_instances(cloned).len(), 1);
+
+        let copy = cmt.clone_op(pass_def, None).lookup_op(pass_def).unwrap();
+        assert_eq!(cmt.find_duplicate_symbols(None), vec!["buffer".to_owned()]);
+        cmt.get_diagnostics().take_unhandled();
+        assert!(!cmt.verify());
+        let duplicates = cmt
+            .get_diagnostics()
+            .take_unhandled()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.message == "the symbol `buffer` is already declared")
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].loc, cmt.get_op_loc(copy));
+
+        // symbols in a nested symbol table shadow the top level ones
+        let scope = cmt.add_region(Region::new(true).with_symbol_table());
+        let mapping = cmt.clone_op(pass_def, Some(scope));
+        let nested = mapping.lookup_op(pass_def).unwrap();
+        assert_eq!(cmt.get_symbol_scope(nested), Some(scope));
+        assert_eq!(cmt.resolve_symbol(nested, "buffer"), Some(nested));
+        assert_eq!(cmt.resolve_symbol(instance, "buffer"), cmt.lookup_symbol(None, "buffer"));
+
+        // an instance in the nested table targets the module declared there
+        let top_copy = cmt.clone_op(top_def, Some(scope));
+        let nested_instance = top_copy.lookup_op(instance).unwrap();
+        assert_eq!(utils::get_instance_target(&cmt, nested_instance), Some(nested));
+        assert_eq!(utils::get_instance_target(&cmt, instance), cmt.lookup_symbol(None, "buffer"));
+    }
+
+    #[test]
+    pub fn unresolved_symbol_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        let instance = InstanceGraph::new(&cmt).unwrap().get_instances(top_def)[0].instance;
+        let pass_def = cmt.lookup_symbol(None, "pass").unwrap();
+        assert_eq!(cmt.find_unresolved_symbols(instance), Vec::<String>::new());
+
+        cmt.delete_op(pass_def);
+        assert_eq!(cmt.find_unresolved_symbols(instance), vec!["pass".to_owned()]);
+        assert!(!cmt.verify_op(instance));
+        cmt.get_diagnostics().take_unhandled();
+        assert!(!cmt.verify());
+        let messages = cmt
+            .get_diagnostics()
+            .take_unhandled()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.loc == cmt.get_op_loc(instance))
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["unresolved symbol `@pass`".to_owned()]);
+    }
+}
+
+mod transaction_test {
+    use irony::Environ;
+
+    use crate::*;
+
+    #[test]
+    pub fn rollback_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        let json = cmt.to_json();
+
+        // dropping the guard rolls the flattening back
+        {
+            let mut transaction = cmt.transaction();
+            assert_eq!(flatten_top(&mut *transaction), Ok(top_def));
+            assert_eq!(InstanceGraph::new(&*transaction).unwrap().get_modules(), vec![top_def]);
+        }
+        assert_eq!(cmt.to_json(), json);
+        assert_eq!(InstanceGraph::new(&cmt).unwrap().get_modules().len(), 2);
+
+        // a failed inner transaction leaves the outer one alone
+        let mut transaction = cmt.transaction();
+        let pass_def = transaction.lookup_symbol(None, "pass").unwrap();
+        assert_eq!(transaction.rename_symbol(pass_def, "buffer"), Ok(()));
+        let failed: irony::IronyResult<()> = transaction.try_transaction(|cmt| {
+            flatten_top(cmt)?;
+            Err(irony::IronyError::failed(None, "undo"))
+        });
+        assert_eq!(failed, Err(irony::IronyError::failed(None, "undo")));
+        assert_eq!(transaction.lookup_symbol(None, "buffer"), Some(pass_def));
+        transaction.rollback();
+        assert_eq!(cmt.to_json(), json);
+
+        // committed changes stay, and new ids do not collide with the old ones
+        let transaction = cmt.transaction();
+        transaction.commit();
+        assert_eq!(cmt.try_transaction(flatten_top), Ok(top_def));
+        assert_ne!(cmt.to_json(), json);
+        let mapping = cmt.clone_op(top_def, None);
+        assert!(mapping.get_op_map().iter().all(|(old, new)| old != new));
+    }
+
+    #[test]
+    pub fn nested_rollback_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        let json = cmt.to_json();
+        let order = cmt.get_op_ids();
+
+        // ops added before and after an inner transaction removed one are all undone
+        let mut transaction = cmt.transaction();
+        transaction.add_op(HwOutput::new(vec![]).into());
+        let inner: irony::IronyResult<()> = transaction.try_transaction(|cmt| {
+            cmt.delete_op(order[0]);
+            cmt.add_op(HwOutput::new(vec![]).into());
+            Ok(())
+        });
+        assert_eq!(inner, Ok(()));
+        transaction.add_op(HwOutput::new(vec![]).into());
+        transaction.get_op_entry(top_def).and_modify(|op| op.set_loc(None));
+        transaction.rollback();
+        assert_eq!(cmt.get_op_ids(), order);
+        assert_eq!(cmt.to_json(), json);
+    }
+}
+
+mod delete_test {
+    use irony::{DanglingRef, Environ, IronyError};
+
+    use crate::*;
+
+    #[test]
+    pub fn safe_delete_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        assert!(cmt.find_dangling_refs().is_empty());
+
+        let body = cmt.get_op(top_def).get_regions()[0].1[0];
+        let find = |cmt: &CmtEnv, name: &str| {
+            cmt.get_region(body)
+                .get_op_children()
+                .into_iter()
+                .find(|&op| cmt.get_op(op).get_op_name() == name)
+                .unwrap()
+        };
+        let constant = find(&cmt, "hw.constant");
+        let add = find(&cmt, "comb.variadic");
+        let c = cmt.get_op(constant).get_defs()[0].1[0].unwrap();
+
+        assert_eq!(
+            cmt.try_delete_op(constant),
+            Err(IronyError::InUse { entity: c, users: vec![add] })
+        );
+        assert_eq!(cmt.try_delete_entity(c), Err(IronyError::InUse { entity: c, users: vec![add] }));
+
+        // the unchecked deletion leaves the add with a dangling use
+        {
+            let mut transaction = cmt.transaction();
+            transaction.delete_op(constant);
+            assert!(!transaction.get_region(body).op_children.contains(&constant));
+            assert!(!transaction.get_region(body).entity_children.contains(&c));
+            assert_eq!(transaction.find_dangling_refs(), vec![DanglingRef::Use { op: add, entity: c }]);
+        }
+
+        let deleted = cmt.delete_op_cascade(constant);
+        assert_eq!(deleted[..2], [constant, add]);
+        assert!(cmt.find_dangling_refs().is_empty());
+        // everything downstream of the constant goes, up to the output
+        let remaining = cmt.get_region(body).get_op_children();
+        assert_eq!(remaining, vec![find(&cmt, "hw.input"), find(&cmt, "hw.instance")]);
+        assert_eq!(deleted.len(), 7);
+
+        // the pass module is referenced by name only, its body goes with it
+        let pass_def = cmt.lookup_symbol(None, "pass").unwrap();
+        let pass_body = cmt.get_op(pass_def).get_regions()[0].1[0];
+        assert_eq!(cmt.try_delete_op(pass_def), Ok(()));
+        assert!(!cmt.contains_region(pass_body));
+        assert!(cmt.find_dangling_refs().is_empty());
+    }
+}
+
+mod error_test {
+    use irony::{EntityId, Environ, IronyError, OpId, RegionId};
+
+    use crate::*;
+
+    #[test]
+    pub fn try_get_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+        assert!(cmt.try_get_op(top_def).is_ok());
+        assert_eq!(cmt.try_get_op(OpId(99)).err(), Some(IronyError::OpNotFound(OpId(99))));
+        assert_eq!(
+            cmt.try_get_entity(EntityId(99)).err(),
+            Some(IronyError::EntityNotFound(EntityId(99)))
+        );
+        assert_eq!(
+            cmt.try_get_region(RegionId(99)).err(),
+            Some(IronyError::RegionNotFound(RegionId(99)))
+        );
+
+        let constant = cmt
+            .get_op_ids()
+            .into_iter()
+            .find(|&op| cmt.get_op(op).get_op_name() == "hw.constant")
+            .unwrap();
+        let err = cmt.try_delete_op(constant).unwrap_err();
+        assert!(err.to_string().starts_with("entity"));
+    }
+
+    #[test]
+    #[should_panic(expected = "op 99 is not in the table")]
+    pub fn get_missing_op_test() {
+        let (cmt, ..) = super::hw_test::create();
+        cmt.get_op(OpId(99));
+    }
+}
+
+mod location_test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use irony::{Diagnostic, Environ, Loc, Severity};
+
+    use crate::*;
+
+    #[test]
+    pub fn op_location_test() {
+        let (mut cmt, pass_def, top_def) = super::region_argument_test::create();
+        let find = |cmt: &CmtEnv, module: OpId, name: &str| {
+            let body = cmt.get_op(module).get_regions()[0].1[0];
+            cmt.get_region(body)
+                .get_op_children()
+                .into_iter()
+                .find(|&op| cmt.get_op(op).get_op_name() == name)
+                .unwrap()
+        };
+
+        // ops are located where they were added
+        let not = find(&cmt, pass_def, "temporary.comb_unary");
+        let not_loc = cmt.get_op_loc(not).unwrap();
+        assert!(not_loc.to_string().starts_with(file!()));
+        assert!(cmt.get_op_ids().into_iter().all(|op| cmt.get_op_loc(op).is_some()));
+
+        let parsed = Loc::name("pass_inst", Some(Loc::file_line_col("top.sv", 3, 5)));
+        let instance = find(&cmt, top_def, "hw.instance");
+        cmt.set_op_loc(instance, Some(parsed.to_owned()));
+        assert_eq!(cmt.get_op_loc(instance).unwrap().to_string(), "\"pass_inst\"(top.sv:3:5)");
+
+        // clones keep the location, inlined ops are located at the call site
+        let cloned = cmt.clone_op(pass_def, None).lookup_op(pass_def).unwrap();
+        assert_eq!(cmt.get_op_loc(cloned), cmt.get_op_loc(pass_def));
+        let json = cmt.to_json();
+        assert_eq!(CmtEnv::from_json(&json).unwrap().to_json(), json);
+        assert_eq!(flatten_module(&mut cmt, top_def, false), Ok(()));
+        let inlined = find(&cmt, top_def, "temporary.comb_unary");
+        assert_eq!(cmt.get_op_loc(inlined), Some(Loc::call_site(not_loc.to_owned(), parsed)));
+
+        assert_eq!(
+            Loc::fuse([Some(not_loc.to_owned()), None, Some(Loc::Fused(vec![not_loc.to_owned()]))]),
+            Some(not_loc)
+        );
+        assert_eq!(Loc::fuse([None]), None);
+    }
+
+    #[test]
+    pub fn diagnostic_test() {
+        let (cmt, _, top_def) = super::hw_test::create();
+
+        // the ports declared by the top module miss `clk`
+        assert!(!cmt.verify());
+        assert_eq!(cmt.get_diagnostics().error_count(), 1);
+        let unhandled = cmt.get_diagnostics().take_unhandled();
+        assert_eq!(unhandled.len(), 1);
+        assert_eq!(unhandled[0].loc, cmt.get_op_loc(top_def));
+        assert!(unhandled[0].to_string().starts_with(file!()));
+
+        cmt.emit_warning(top_def, "unused output");
+        let unhandled = cmt.get_diagnostics().take_unhandled();
+        assert_eq!(unhandled[0].severity, Severity::Warning);
+        assert!(unhandled[0].to_string().ends_with(": warning: unused output"));
+
+        // the latest handler goes first, and passes on what it does not take
+        let errors = Rc::new(RefCell::new(vec![]));
+        let collected = errors.clone();
+        cmt.get_diagnostics().add_stderr_handler();
+        let handler = cmt.get_diagnostics().add_handler(move |diagnostic| {
+            if diagnostic.severity != Severity::Error {
+                return false;
+            }
+            collected.borrow_mut().push(diagnostic.to_owned());
+            true
+        });
+        cmt.emit_error(top_def, "bad module");
+        cmt.emit_diagnostic(
+            Diagnostic::error("no location").with_note("see here", cmt.get_op_loc(top_def)),
+        );
+        cmt.emit_warning(top_def, "printed");
+        assert_eq!(errors.borrow().len(), 2);
+        assert_eq!(errors.borrow()[0].loc, cmt.get_op_loc(top_def));
+        assert!(errors.borrow()[1].to_string().starts_with("error: no location\n  "));
+        assert_eq!(cmt.get_diagnostics().error_count(), 2);
+        assert!(cmt.get_diagnostics().get_unhandled().is_empty());
+
+        cmt.get_diagnostics().remove_handler(handler);
+        cmt.emit_error(top_def, "printed too");
+        assert_eq!(errors.borrow().len(), 2);
+    }
+}
+
+mod accessor_test {
+    use irony::Environ;
+
+    use crate::*;
+
+    #[test]
+    pub fn typed_accessor_test() {
+        let (mut cmt, _, top_def) = super::hw_test::create();
+
+        let OpEnum::HwModule(module) = cmt.get_op(top_def).to_owned() else { panic!() };
+        assert_eq!(module.name(), Some(&StringAttr("top".into())));
+        assert_eq!(module.arg_names().map(|names| names.0.len()), Some(1));
+        let body = module.body().unwrap();
+
+        let instance = InstanceGraph::new(&cmt).unwrap().get_instances(top_def)[0].instance;
+        let OpEnum::HwInstance(mut inst) = cmt.get_op(instance).to_owned() else { panic!() };
+        assert_eq!(inst.target(), Some(&SymbolRefAttr("pass".into())));
+        assert_eq!(inst.outputs().len(), 1);
+        inst.set_name(Some(StringAttr("renamed".into())));
+        inst.set_inputs(vec![]);
+        assert_eq!(inst.name(), Some(&StringAttr("renamed".into())));
+        assert!(inst.inputs().is_empty());
+
+        let lhs = module.lhs().unwrap();
+        let EntityEnum::Module(mut entity) = cmt.get_entity(lhs).to_owned() else { panic!() };
+        assert_eq!(entity.top(), Some(&BoolAttr(true)));
+        entity.set_top(None);
+        assert_eq!(entity.top(), None);
+        assert_eq!(entity.name(), Some(&StringAttr("top".into())));
+
+        assert!(cmt.get_region(body).get_op_children().contains(&instance));
+        cmt.get_op_entry(instance).and_modify(|op| *op = inst.into());
+        assert!(cmt.print_op(instance).contains("\"renamed\""));
+    }
+}
+
+mod proc_macro_test {
+    use irony::{Environ, Op, Region};
+
+    use self::dialect::*;
+    use crate::{BoolAttr, DataTypeEnum, SameType, StringAttr};
+
+    /// a dialect defined with the attribute macros instead of `op_def!` and friends
+    mod dialect {
+        use irony::preclude::*;
+
+        use crate::{AttributeEnum, BoolAttr, DataTypeEnum, SameType, StringAttr};
+
+        /// the dialect has no passes, this serves as both the pass and its manager
+        #[derive(Default, Debug, Clone)]
+        pub struct NoPasses;
+
+        impl PassTrait<(), IronyError> for NoPasses {
+            type EntityT = MiniEntity;
+            type OpT = MiniOp;
+
+            fn check_op<E>(&self, _: &E, _: OpId) -> bool
+            where E: Environ<EntityT = MiniEntity, OpT = MiniOp> {
+                false
+            }
+
+            fn run_raw<E>(&self, _: &mut E, _: OpId) -> IronyResult<()>
+            where E: Environ<EntityT = MiniEntity, OpT = MiniOp> {
+                Ok(())
+            }
+        }
+
+        impl PassManagerTrait<(), IronyError> for NoPasses {
+            type EntityT = MiniEntity;
+            type OpT = MiniOp;
+            type PassT = NoPasses;
+
+            fn add_passes(&mut self, _: Vec<NoPasses>, _: Vec<Vec<OpId>>) {}
+
+            fn run_passes<E>(&self, _: &mut E) -> IronyResult<()>
+            where E: Environ<EntityT = MiniEntity, OpT = MiniOp> {
+                Ok(())
+            }
+        }
+
+        #[irony_macros::entity(data_type = DataTypeEnum, attr = AttributeEnum)]
+        pub struct Value {
+            name: StringAttr,
+        }
+
+        #[irony_macros::entity_enum(data_type = DataTypeEnum, attr = AttributeEnum)]
+        pub enum MiniEntity {
+            Value(Value),
+        }
+
+        #[irony_macros::constraint(
+            data_type = DataTypeEnum,
+            attr = AttributeEnum,
+            verify = |_, _, a: Vec<(String, Vec<Option<EntityId>>)>, b: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                a.iter().chain(b.iter()).any(|(name, v)| name == "operands" && v.len() > 1)
+            }
+        )]
+        pub struct TwoOperands;
+
+        #[irony_macros::constraint_enum(data_type = DataTypeEnum, attr = AttributeEnum)]
+        pub enum MiniConstraint {
+            SameType(SameType),
+            TwoOperands(TwoOperands),
+        }
+
+        #[irony_macros::op(
+            data_type = DataTypeEnum,
+            attr = AttributeEnum,
+            constraint = MiniConstraint,
+            dialect = "mini",
+            name = "module",
+            symbol = name,
+            print = |env: &E, attrs: Vec<(String, AttributeEnum)>, _, _, regions: Vec<(String, Vec<RegionId>)>| {
+                format!("mini.module {} {}", attrs[0].1, env.print_region(regions[0].1[0]))
+            }
+        )]
+        pub struct Block {
+            #[attr]
+            name: StringAttr,
+            #[region]
+            body: RegionId,
+        }
+
+        #[irony_macros::op(
+            data_type = DataTypeEnum,
+            attr = AttributeEnum,
+            constraint = MiniConstraint,
+            dialect = "mini",
+            print = |env: &E, attrs: Vec<(String, AttributeEnum)>, _, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                format!("{} = mini.const {}", env.print_entity(defs[0].1[0].unwrap()), attrs[0].1)
+            }
+        )]
+        pub struct Const {
+            #[def]
+            lhs: EntityId,
+            #[attr(hash)]
+            value: BoolAttr,
+        }
+
+        #[irony_macros::op(
+            data_type = DataTypeEnum,
+            attr = AttributeEnum,
+            constraint = MiniConstraint,
+            dialect = "mini",
+            traits = [Pure, Commutative, SameOperandsAndResultType],
+            constraints = [TwoOperands.into()],
+            print = |env: &E, _, uses: Vec<(String, Vec<Option<EntityId>>)>, defs: Vec<(String, Vec<Option<EntityId>>)>, _| {
+                let operands = uses[0].1.iter().map(|x| env.print_entity(x.unwrap())).collect::<Vec<_>>();
+                format!("{} = mini.and {}", env.print_entity(defs[0].1[0].unwrap()), operands.join(", "))
+            }
+        )]
+        pub struct And {
+            #[def]
+            lhs: EntityId,
+            #[uses]
+            operands: Vec<EntityId>,
+        }
+
+        #[irony_macros::op_enum(data_type = DataTypeEnum, attr = AttributeEnum, constraint = MiniConstraint)]
+        pub enum MiniOp {
+            Block(Block),
+            Const(Const),
+            And(And),
+        }
+
+        #[irony_macros::environ(
+            data_type = DataTypeEnum,
+            attr = AttributeEnum,
+            entity = MiniEntity,
+            op = MiniOp,
+            constraint = MiniConstraint,
+            pm = NoPasses
+        )]
+        pub struct MiniEnv;
+    }
+
+    #[test]
+    pub fn proc_macro_dialect_test() {
+        let mut env = MiniEnv::default();
+        let i1 = || Some(DataTypeEnum::UInt(1.into()));
+
+        let body = env.add_region(Region::new(true));
+        let block = env.add_op(Block::new(Some(StringAttr("top".into())), Some(body)).into());
+        env.with_region(Some(body), |env| {
+            let a = env.add_entity(Value::new(i1(), Some("a".into())).into());
+            let b = env.add_entity(Value::new(i1(), Some("b".into())).into());
+            let c = env.add_entity(Value::new(i1(), None).into());
+            env.add_op(Const::new(Some(a), Some(BoolAttr(true))).into());
+            env.add_op(Const::new(Some(b), Some(BoolAttr(false))).into());
+            env.add_op(And::new(Some(c), vec![a, b]).into());
+        });
+        let [a, b, c] = env.get_region(body).get_entity_children()[..] else { panic!() };
+        let and = env.get_region(body).get_op_children()[2];
+
+        assert_eq!(env.lookup_symbol(None, "top"), Some(block));
+        assert_eq!(env.get_uses(a), vec![and]);
+        assert!(env.verify_op(and));
+
+        let MiniOp::And(op) = env.get_op(and).to_owned() else { panic!() };
+        assert_eq!(op.lhs(), Some(c));
+        assert_eq!(op.operands(), &[a, b]);
+        // the trait adds the constraint it implies
+        assert_eq!(op.get_constraints(), vec![TwoOperands.into(), SameType::new().into()]);
+        assert!(op.is_pure() && op.is_commutative());
+        let parts = (op.get_defs(), op.get_uses(), op.get_attrs(), op.get_regions());
+        assert_eq!(op.get_op_name(), "mini.and");
+        let rebuilt = MiniOp::from_parts("mini.and", parts.0, parts.1, parts.2, parts.3).unwrap();
+        assert_eq!(rebuilt.get_uses(), op.get_uses());
+        assert_eq!(MiniOp::from_parts("And", vec![], vec![], vec![], vec![]), None);
+        assert_eq!(env.get_registry().qualify("Block"), Some("mini.module"));
+
+        let MiniEntity::Value(value) = env.get_entity(a).to_owned();
+        assert_eq!(value.name(), Some(&StringAttr("a".into())));
+
+        let printed = env.print_op(block);
+        assert!(printed.contains("mini.const"), "{}", printed);
+        assert!(printed.contains("mini.and"), "{}", printed);
+        let from_json = MiniEnv::from_json(&env.to_json()).unwrap();
+        assert_eq!(from_json.print_op(block), printed);
+    }
+}
+
+mod op_trait_test {
+    use irony::{Environ, Op, OpTrait, Region};
+
+    use crate::*;
+
+    #[test]
+    pub fn canonicalize_test() {
+        let mut cmt = CmtEnv::default();
+        let u8 = || Some(DataTypeEnum::UInt(8.into()));
+        let wire = |name: &str| Wire::new(u8(), Some(name.into()), None, None).into();
+
+        let outside = cmt.add_entity(wire("outside"));
+        let body = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        cmt.with_region(Some(body), |cmt| {
+            let (a, b) = (cmt.add_entity(wire("a")), cmt.add_entity(wire("b")));
+            let (x, y, z) = (cmt.add_entity(wire("x")), cmt.add_entity(wire("y")), cmt.add_entity(wire("z")));
+            let c = cmt.add_entity(wire("c"));
+            cmt.add_op(HwInput::new(vec![a, b]).into());
+            cmt.add_op(CombVariadic::new(Some(x), vec![a, b], Some(CombVariadicPredicate::And)).into());
+            cmt.add_op(CombVariadic::new(Some(y), vec![b, a], Some(CombVariadicPredicate::And)).into());
+            cmt.add_op(CombVariadic::new(Some(z), vec![y, x], Some(CombVariadicPredicate::Or)).into());
+            cmt.add_op(HwConstant::new(Some(c), Some(ConstantAttr(vec![true; 8]))).into());
+            cmt.add_op(HwOutput::new(vec![z]).into());
+        });
+        let ops = cmt.get_region(body).get_op_children();
+        let (and, or, output) = (ops[1], ops[3], ops[5]);
+
+        assert!(cmt.get_op(and).is_pure() && cmt.get_op(and).is_commutative());
+        assert!(cmt.get_op(output).is_terminator() && !cmt.get_op(output).is_pure());
+        assert!(cmt.get_op(module).is_isolated_from_above());
+        assert_eq!(cmt.verify_op_traits(module), Ok(()));
+        assert_eq!(cmt.get_op(and).get_constraints(), vec![SameType::new().into()]);
+        assert!(cmt.verify_op(and));
+
+        assert!(irony::canonicalize(&mut cmt, body) > 0);
+        let ops = cmt.get_region(body).get_op_children();
+        assert_eq!(ops, vec![ops[0], and, or, output]);
+        let x = cmt.get_op(and).get_defs()[0].1[0].unwrap();
+        assert_eq!(cmt.get_op(or).get_uses()[0].1, vec![Some(x), Some(x)]);
+        assert_eq!(irony::canonicalize(&mut cmt, body), 0);
+
+        // a dead chain goes at once, the last op first
+        cmt.with_region(Some(body), |cmt| {
+            let [p, q, r] = ["p", "q", "r"].map(|name| cmt.add_entity(wire(name)));
+            for (lhs, operand) in [(p, x), (q, p), (r, q)] {
+                let not = CombUnary::new(Some(lhs), Some(operand), Some(CombUnaryPredicate::Not));
+                cmt.add_op(not.into());
+            }
+        });
+        assert_eq!(irony::dce(&mut cmt, body), 3);
+        assert_eq!(cmt.get_region(body).get_op_children(), vec![ops[0], and, or, output]);
+
+        // an op after the terminator, and a use of an entity from outside the module
+        let extra = cmt.add_op(Assign::new(None, Some(outside)).into());
+        cmt.get_op_entry(extra).and_modify(|op| op.set_parent(Some(body)));
+        cmt.get_region_entry(body).and_modify(|region| region.add_op_child(extra));
+        assert_eq!(cmt.verify_op_traits(output), Err(OpTrait::Terminator));
+        assert_eq!(cmt.verify_op_traits(module), Err(OpTrait::IsolatedFromAbove));
+        assert!(!cmt.verify());
+
+        // the defs and uses of an op with `SameOperandsAndResultType` share their dtype
+        let i4 = Some(DataTypeEnum::UInt(4.into()));
+        let narrow = cmt.add_entity(Wire::new(i4, None, None, None).into());
+        let not = CombUnary::new(Some(narrow), Some(x), Some(CombUnaryPredicate::Not));
+        let not = cmt.add_op(not.into());
+        assert_eq!(cmt.verify_op_traits(not), Ok(()));
+        assert!(!cmt.verify_op_constraints(not) && !cmt.verify_op(not));
+    }
+}
+
+mod dialect_test {
+    use irony::{Dialect, Environ, Op};
+
+    use crate::*;
+
+    #[test]
+    pub fn registry_test() {
+        let (cmt, _, top_def) = super::hw_test::create();
+        let registry = cmt.get_registry();
+        assert_eq!(
+            registry.get_dialects(),
+            vec!["event", "sequence", "property", "temporary", "hw", "comb", "seq"]
+        );
+        assert!(registry.get_ops(None).is_empty());
+        assert_eq!(registry.get_ops(Some("comb")).len(), CombDialect::get_ops().len());
+        assert_eq!(registry.get_all_ops().len(), 49);
+
+        // the op enum is composed of the dialects in order
+        let mut ops = EventDialect::get_ops();
+        ops.extend(SequenceDialect::get_ops());
+        ops.extend(PropertyDialect::get_ops());
+        ops.extend(TemporaryDialect::get_ops());
+        ops.extend(HwDialect::get_ops());
+        ops.extend(CombDialect::get_ops());
+        ops.extend(SeqDialect::get_ops());
+        assert_eq!(registry.get_all_ops(), ops.as_slice());
+        assert!(std::ptr::eq(HwInput::op_info(), HwInput::op_info()));
+
+        // names are derived from the type unless given in the dialect
+        assert_eq!(HwStructExtract::op_info().name, "hw.struct_extract");
+        assert_eq!(registry.get_op("hw.wire").unwrap().type_name, "Assign");
+        assert_eq!(registry.qualify("CombICmp"), Some("comb.icmp"));
+        assert_eq!(SeqDialect::get_name(), "seq");
+
+        let top = cmt.get_op(top_def);
+        assert_eq!((top.get_op_name(), top.get_dialect()), ("hw.module".to_owned(), Some("hw")));
+        assert!(cmt.print_op(top_def).starts_with("hw.module"));
+
+        // files written before qualified names load with the type names mapped
+        let json = cmt.to_json();
+        let mut old = json.replacen(
+            &format!("\"version\": {}", irony::FORMAT_VERSION),
+            "\"version\": 4",
+            1,
+        );
+        for info in registry.get_all_ops() {
+            old = old.replace(&format!("\"{}\"", info.name), &format!("\"{}\"", info.type_name));
+        }
+        assert_ne!(old, json);
+        assert_eq!(CmtEnv::from_json(&old).unwrap().to_json(), json);
+    }
+}
+
+mod comb_loop_test {
+    use irony::{Environ, Op};
+
+    use crate::*;
+
+    #[test]
+    pub fn comb_loop_test() {
+        let (mut cmt, pass_def, top_def) = super::region_argument_test::create();
+        let analysis = CombLoopAnalysis::new(&cmt).unwrap();
+        assert_eq!(analysis.get_port_deps(pass_def), Some(&vec![vec![0]]));
+        assert_eq!(analysis.get_port_deps(top_def), Some(&vec![vec![0]]));
+        assert!(analysis.find_all_cyclic_components(&cmt).unwrap().is_empty());
+
+        let body = cmt.get_op(top_def).get_regions()[0].1[0];
+        let x = cmt.get_region_arguments(body)[0];
+        cmt.with_region(Some(body), |cmt| {
+            let wire = |cmt: &mut CmtEnv, name: &str| {
+                cmt.add_entity(Wire::new(None, Some(name.into()), None, None).into())
+            };
+            let (p, q) = (wire(cmt, "p"), wire(cmt, "q"));
+            cmt.add_op(CombVariadic::new(Some(p), vec![q, x], Some(CombVariadicPredicate::And)).into());
+            cmt.add_op(CombUnary::new(Some(q), Some(p), Some(CombUnaryPredicate::Not)).into());
+
+            // broken by the register
+            let (r, s) = (wire(cmt, "r"), wire(cmt, "s"));
+            cmt.add_op(SeqCompReg::new(Some(r), Some(s), Some(x), None, None).into());
+            cmt.add_op(CombUnary::new(Some(s), Some(r), Some(CombUnaryPredicate::Not)).into());
+
+            // through the pass-through module
+            let w = wire(cmt, "w");
+            let instance = HwInstance::new(vec![w], vec![w], None, Some("pass".into()), None);
+            cmt.add_op(instance.into());
+        });
+
+        let loops = CombLoopAnalysis::new(&cmt).unwrap().find_cyclic_components(&cmt, top_def).unwrap();
+        let reports = loops.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            reports,
+            vec!["combinational loop in `top`: %p -> %q -> %p", "combinational loop in `top`: %w -> %w"]
+        );
+        assert_eq!(loops[1].entities.len(), 2);
+        assert_eq!(loops[0].component.len(), 2);
+        let analysis = CombLoopAnalysis::new(&cmt).unwrap();
+        assert!(analysis.find_cyclic_components(&cmt, pass_def).unwrap().is_empty());
+
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let failed = analysis.find_cyclic_components(&cmt, ext).unwrap_err();
+        assert_eq!(failed.to_string(), "the module has no body");
+    }
+
+    #[test]
+    pub fn long_loop_test() {
+        let (mut cmt, _, top_def) = super::region_argument_test::create();
+        let body = cmt.get_op(top_def).get_regions()[0].1[0];
+        let count = 20_000;
+        cmt.with_region(Some(body), |cmt| {
+            let wire = |i| Wire::new(None, Some(format!("w{}", i).into()), None, None).into();
+            let wires = (0..count).map(|i| cmt.add_entity(wire(i))).collect::<Vec<_>>();
+            for i in 0..count {
+                let (lhs, rhs) = (Some(wires[i]), Some(wires[(i + 1) % count]));
+                cmt.add_op(CombUnary::new(lhs, rhs, Some(CombUnaryPredicate::Not)).into());
+            }
+        });
+
+        let loops = CombLoopAnalysis::new(&cmt).unwrap().find_cyclic_components(&cmt, top_def).unwrap();
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].component.len(), count);
+        assert_eq!(loops[0].entities.len(), count + 1);
+    }
+}
+
+mod topological_sort_test {
+    use irony::{Environ, PassTrait, Region, VerifyOptions};
+
+    use crate::*;
+
+    #[test]
+    pub fn sort_test() {
+        let mut cmt = CmtEnv::default();
+        let u8 = || Some(DataTypeEnum::UInt(8.into()));
+        let wire = |name: &str| Wire::new(u8(), Some(name.into()), None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        cmt.with_region(Some(body), |cmt| {
+            let (p, q) = (cmt.add_entity(wire("p")), cmt.add_entity(wire("q")));
+            let (r, s) = (cmt.add_entity(wire("r")), cmt.add_entity(wire("s")));
+            cmt.add_op(HwOutput::new(vec![q]).into());
+            cmt.add_op(CombUnary::new(Some(q), Some(p), Some(CombUnaryPredicate::Not)).into());
+            cmt.add_op(HwConstant::new(Some(p), Some(ConstantAttr(vec![true; 8]))).into());
+            // the register feedback needs no reordering
+            cmt.add_op(SeqCompReg::new(Some(r), Some(s), Some(p), None, None).into());
+            cmt.add_op(CombUnary::new(Some(s), Some(r), Some(CombUnaryPredicate::Not)).into());
+        });
+        let ops = cmt.get_region(body).get_op_children();
+        let q = cmt.get_region(body).get_entity_children()[1];
+        assert_eq!(cmt.verify_def_before_use(ops[0]), Err(q));
+        assert_eq!(cmt.verify_def_before_use(ops[3]), Ok(()));
+        let count_early_uses = |cmt: &CmtEnv| {
+            cmt.verify_with(&VerifyOptions { def_before_use: true });
+            let diagnostics = cmt.get_diagnostics().take_unhandled();
+            diagnostics.iter().filter(|d| d.to_string().contains("before its definition")).count()
+        };
+        assert_eq!(count_early_uses(&cmt), 2);
+
+        assert_eq!(TopologicalSortPass.run_on(&mut cmt, module), Ok(()));
+        assert_eq!(cmt.get_region(body).get_op_children(), vec![ops[2], ops[1], ops[3], ops[4], ops[0]]);
+        assert_eq!(count_early_uses(&cmt), 0);
+
+        // the reordering is undone with the transaction
+        cmt.get_region_entry(body).and_modify(|region| region.op_children.reverse());
+        let reversed = cmt.get_region(body).get_op_children();
+        {
+            let mut transaction = cmt.transaction();
+            assert_eq!(irony::sort_topologically(&mut *transaction, body), Ok(()));
+        }
+        assert_eq!(cmt.get_region(body).get_op_children(), reversed);
+
+        // a combinational cycle cannot be ordered
+        let s = cmt.get_region(body).get_entity_children()[3];
+        cmt.get_op_entry(ops[1]).and_modify(|op| op.set_uses(vec![("op".into(), vec![Some(s)])]));
+        cmt.get_op_entry(ops[4]).and_modify(|op| op.set_uses(vec![("op".into(), vec![Some(q)])]));
+        let order = cmt.get_region(body).get_op_children();
+        assert_eq!(irony::sort_topologically(&mut cmt, body), Err(vec![ops[0], ops[4], ops[1]]));
+        assert_eq!(cmt.get_region(body).get_op_children(), order);
+        let failed = TopologicalSortPass.run_on(&mut cmt, module);
+        assert!(matches!(failed, Err(irony::IronyError::Failed { op: Some(op), .. }) if op == ops[0]));
+    }
+}
+
+mod aig_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    #[test]
+    pub fn bitblast_test() {
+        let mut cmt = CmtEnv::default();
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let wire = |name: &str, width| Wire::new(uint(width), Some(name.into()), None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        cmt.with_region(Some(body), |cmt| {
+            let (a, b, rst) = (cmt.add_entity(wire("a", 8)), cmt.add_entity(wire("b", 8)), cmt.add_entity(wire("rst", 1)));
+            cmt.add_op(HwInput::new(vec![a, b, rst]).into());
+            let names = ["sum", "diff", "lt", "min", "shr", "quot", "rem", "cnt", "inc", "one", "zero"];
+            let [sum, diff, lt, min, shr, quot, rem, cnt, inc, one, zero] =
+                names.map(|name| cmt.add_entity(wire(name, if name == "lt" { 1 } else { 8 })));
+            cmt.add_op(CombVariadic::new(Some(sum), vec![a, b], Some(CombVariadicPredicate::Add)).into());
+            cmt.add_op(CombBinary::new(Some(diff), Some(a), Some(b), Some(CombBinaryPredicate::Sub)).into());
+            cmt.add_op(CombICmp::new(Some(lt), Some(a), Some(b), Some(CombICmpPredicate::SLT)).into());
+            cmt.add_op(CombMux2::new(Some(min), Some(lt), Some(a), Some(b)).into());
+            cmt.add_op(CombBinary::new(Some(shr), Some(a), Some(b), Some(CombBinaryPredicate::ShrU)).into());
+            cmt.add_op(CombBinary::new(Some(quot), Some(a), Some(b), Some(CombBinaryPredicate::DivU)).into());
+            cmt.add_op(CombBinary::new(Some(rem), Some(a), Some(b), Some(CombBinaryPredicate::ModS)).into());
+            // a counter cleared by `rst`
+            let mut bits = vec![false; 8];
+            cmt.add_op(HwConstant::new(Some(zero), Some(ConstantAttr(bits.to_owned()))).into());
+            bits[0] = true;
+            cmt.add_op(HwConstant::new(Some(one), Some(ConstantAttr(bits))).into());
+            cmt.add_op(CombVariadic::new(Some(inc), vec![cnt, one], Some(CombVariadicPredicate::Add)).into());
+            cmt.add_op(SeqCompReg::new(Some(cnt), Some(inc), None, Some(rst), Some(zero)).into());
+            cmt.add_op(HwOutput::new(vec![sum, diff, lt, min, shr, quot, rem, cnt]).into());
+        });
+
+        let aig = bitblast_module(&cmt, module).unwrap();
+        assert_eq!((aig.inputs.len(), aig.latches.len(), aig.outputs.len()), (17, 8, 57));
+        assert_eq!(aig.inputs[0].name, "a[0]");
+        assert_eq!(aig.outputs[16].name, "lt");
+
+        let to_bits = |value: u8| (0..8).map(|i| value >> i & 1 == 1).collect::<Vec<_>>();
+        let from_bits = |bits: &[bool]| bits.iter().rev().fold(0u8, |acc, &bit| acc << 1 | bit as u8);
+        for (a, b) in [(7u8, 3u8), (200, 13), (3, 200), (0x80, 0xff), (42, 0), (5, 9)] {
+            let inputs = [to_bits(a), to_bits(b), vec![false]].concat();
+            let (outputs, _) = aig.simulate(&inputs, &to_bits(0));
+            let (sa, sb) = (a as i8, b as i8);
+            let quot = a.checked_div(b).unwrap_or(0xff);
+            let rem = if b == 0 { a } else { sa.wrapping_rem(sb) as u8 };
+            assert_eq!(from_bits(&outputs[0..8]), a.wrapping_add(b));
+            assert_eq!(from_bits(&outputs[8..16]), a.wrapping_sub(b));
+            assert_eq!(outputs[16], sa < sb);
+            assert_eq!(from_bits(&outputs[17..25]), if sa < sb { a } else { b });
+            assert_eq!(from_bits(&outputs[25..33]), a.checked_shr(b as u32).unwrap_or(0));
+            assert_eq!((from_bits(&outputs[33..41]), from_bits(&outputs[41..49])), (quot, rem));
+        }
+
+        let (outputs, next) = aig.simulate(&[vec![false; 16], vec![false]].concat(), &to_bits(41));
+        assert_eq!((from_bits(&outputs[49..57]), from_bits(&next)), (41, 42));
+        let (_, next) = aig.simulate(&[vec![false; 16], vec![true]].concat(), &to_bits(41));
+        assert_eq!(from_bits(&next), 0);
+
+        let ascii = aig.to_aiger_ascii();
+        assert!(ascii.starts_with("aag "));
+        assert!(ascii.contains("\ni0 a[0]\n") && ascii.contains("\nl7 cnt[7]\n"));
+        let binary = aig.to_aiger_binary();
+        assert!(binary.starts_with(b"aig "));
+        let header = |text: &[u8]| {
+            let line = text.split(|&c| c == b'\n').next().unwrap();
+            String::from_utf8_lossy(&line[4..]).into_owned()
+        };
+        assert_eq!(header(ascii.as_bytes()), header(&binary));
+
+        // both files read back to the same gates, which compute what the AIG does
+        let path = std::env::temp_dir().join(format!("irony_bitblast_{}.aag", std::process::id()));
+        std::fs::write(&path, &ascii).unwrap();
+        let from_ascii = read_aiger(&std::fs::read(&path).unwrap());
+        std::fs::write(&path, &binary).unwrap();
+        let from_binary = read_aiger(&std::fs::read(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(from_ascii, from_binary);
+        assert_eq!((from_ascii.inputs, from_ascii.latches.len()), (17, 8));
+        for (a, b, rst, cnt) in [(7u8, 3u8, false, 41u8), (200, 13, true, 9), (0x80, 0xff, false, 255)] {
+            let inputs = [to_bits(a), to_bits(b), vec![rst]].concat();
+            let expected = aig.simulate(&inputs, &to_bits(cnt));
+            assert_eq!(eval_aiger(&from_ascii, &inputs, &to_bits(cnt)), expected);
+        }
+    }
+
+    /// An AIGER file read back: the latches as their next literal and init, the outputs,
+    /// and the and gates as their literal and the larger and smaller operands
+    #[derive(Debug, PartialEq)]
+    struct Aiger {
+        inputs: usize,
+        latches: Vec<(u32, bool)>,
+        outputs: Vec<u32>,
+        ands: Vec<(u32, u32, u32)>,
+    }
+
+    fn read_aiger(file: &[u8]) -> Aiger {
+        let line = |pos: &mut usize| {
+            let end = *pos + file[*pos..].iter().position(|&c| c == b'\n').unwrap();
+            let text = std::str::from_utf8(&file[*pos..end]).unwrap();
+            *pos = end + 1;
+            text.split(' ').map(str::to_owned).collect::<Vec<_>>()
+        };
+        let delta = |pos: &mut usize| {
+            let mut value = 0;
+            for shift in (0..).step_by(7) {
+                let byte = file[*pos];
+                *pos += 1;
+                value |= ((byte & 0x7f) as u32) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            value
+        };
+        let num = |field: &String| field.parse::<u32>().unwrap();
+
+        let mut pos = 0;
+        let header = line(&mut pos);
+        let ascii = header[0] == "aag";
+        let [i, l, o, a] = [2, 3, 4, 5].map(|index| num(&header[index]) as usize);
+        if ascii {
+            (0..i).for_each(|_| assert_eq!(line(&mut pos).len(), 1));
+        }
+        let latches = (0..l)
+            .map(|_| {
+                let fields = line(&mut pos);
+                let fields = if ascii { &fields[1..] } else { &fields[..] };
+                (num(&fields[0]), fields.get(1).is_some_and(|init| init == "1"))
+            })
+            .collect();
+        let outputs = (0..o).map(|_| num(&line(&mut pos)[0])).collect();
+        let ands = (0..a)
+            .map(|index| match ascii {
+                true => {
+                    let fields = line(&mut pos);
+                    let (x, y) = (num(&fields[1]), num(&fields[2]));
+                    (num(&fields[0]), x.max(y), x.min(y))
+                },
+                false => {
+                    let lhs = 2 * (i + l + index + 1) as u32;
+                    let rhs0 = lhs - delta(&mut pos);
+                    (lhs, rhs0, rhs0 - delta(&mut pos))
+                },
+            })
+            .collect();
+        Aiger { inputs: i, latches, outputs, ands }
+    }
+
+    /// the outputs and next latch values of one cycle, as `Aig::simulate` gives them
+    fn eval_aiger(aiger: &Aiger, inputs: &[bool], state: &[bool]) -> (Vec<bool>, Vec<bool>) {
+        let mut values = vec![false; 1 + inputs.len() + state.len() + aiger.ands.len()];
+        for (value, &bit) in values[1..].iter_mut().zip(inputs.iter().chain(state)) {
+            *value = bit;
+        }
+        let value = |values: &[bool], lit: u32| values[lit as usize / 2] ^ (lit & 1 == 1);
+        for &(lhs, x, y) in aiger.ands.iter() {
+            values[lhs as usize / 2] = value(&values, x) && value(&values, y);
+        }
+        let outputs = aiger.outputs.iter().map(|&lit| value(&values, lit)).collect();
+        let next = aiger.latches.iter().map(|&(lit, _)| value(&values, lit)).collect();
+        (outputs, next)
+    }
+
+    /// a module with inputs `a`, `b` of 8 bits and `s` of 3 bits, the ops built by `body`
+    /// and the entities it returns as outputs
+    fn create<F>(body: F) -> (CmtEnv, OpId)
+    where F: Fn(&mut CmtEnv, [EntityId; 3]) -> Vec<EntityId> {
+        let mut cmt = CmtEnv::default();
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let region = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(region))
+                .into(),
+        );
+        cmt.with_region(Some(region), |cmt| {
+            let inputs = [("a", 8), ("b", 8), ("s", 3)].map(|(name, width)| {
+                cmt.add_entity(Wire::new(uint(width), Some(name.into()), None, None).into())
+            });
+            cmt.add_op(HwInput::new(inputs.to_vec()).into());
+            let outputs = body(cmt, inputs);
+            cmt.add_op(HwOutput::new(outputs).into());
+        });
+        (cmt, module)
+    }
+
+    #[test]
+    pub fn ops_test() {
+        let (cmt, module) = create(|cmt, [a, b, s]| {
+            let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+            let names = [("prod", 8), ("sra", 8), ("sdiv", 8), ("ext", 4), ("cat", 11), ("rep", 6)];
+            let [prod, sra, sdiv, ext, cat, rep] = names.map(|(name, width)| {
+                cmt.add_entity(Wire::new(uint(width), Some(name.into()), None, None).into())
+            });
+            let par = cmt.add_entity(Wire::new(uint(1), Some("par".into()), None, None).into());
+            let scaled = cmt.add_entity(Wire::new(uint(3), Some("scaled".into()), None, None).into());
+            cmt.add_op(CombVariadic::new(Some(prod), vec![a, b], Some(CombVariadicPredicate::Mul)).into());
+            // an operand wider than the product so far
+            cmt.add_op(CombVariadic::new(Some(scaled), vec![s, a], Some(CombVariadicPredicate::Mul)).into());
+            cmt.add_op(CombBinary::new(Some(sra), Some(a), Some(b), Some(CombBinaryPredicate::ShrS)).into());
+            cmt.add_op(CombBinary::new(Some(sdiv), Some(a), Some(b), Some(CombBinaryPredicate::DivS)).into());
+            cmt.add_op(CombExtract::new(Some(ext), Some(a), Some(s)).into());
+            cmt.add_op(CombConcat::new(Some(cat), vec![s, a]).into());
+            cmt.add_op(CombReplicate::new(Some(rep), Some(s)).into());
+            cmt.add_op(CombParity::new(Some(par), Some(a)).into());
+            vec![prod, sra, sdiv, ext, cat, rep, par, scaled]
+        });
+        let aig = bitblast_module(&cmt, module).unwrap();
+        assert_eq!((aig.inputs.len(), aig.outputs.len()), (19, 49));
+
+        let to_bits = |value: u8, width: usize| (0..width).map(|i| value >> i & 1 == 1).collect::<Vec<_>>();
+        let from_bits = |bits: &[bool]| bits.iter().rev().fold(0u16, |acc, &bit| acc << 1 | bit as u16);
+        for (a, b, s) in [(7u8, 3u8, 0u8), (200, 13, 5), (3, 200, 7), (0x80, 0xff, 2), (0x81, 9, 1), (5, 2, 4)] {
+            let inputs = [to_bits(a, 8), to_bits(b, 8), to_bits(s, 3)].concat();
+            let (outputs, _) = aig.simulate(&inputs, &[]);
+            let (sa, sb) = (a as i8, b as i8);
+            let sra = sa.checked_shr(b as u32).unwrap_or(if sa < 0 { -1 } else { 0 });
+            assert_eq!(from_bits(&outputs[0..8]), a.wrapping_mul(b) as u16);
+            assert_eq!(from_bits(&outputs[8..16]), sra as u8 as u16);
+            assert_eq!(from_bits(&outputs[16..24]), sa.wrapping_div(sb) as u8 as u16);
+            assert_eq!(from_bits(&outputs[24..28]), (a >> s & 0xf) as u16);
+            assert_eq!(from_bits(&outputs[28..39]), (s as u16) << 8 | a as u16);
+            assert_eq!(from_bits(&outputs[39..45]), (s | s << 3) as u16);
+            assert_eq!(outputs[45], a.count_ones() % 2 == 1);
+            assert_eq!(from_bits(&outputs[46..49]), (s.wrapping_mul(a) & 7) as u16);
+        }
+    }
+
+    #[test]
+    pub fn error_test() {
+        // a failed operand is not taken for a loop by the next entity using it
+        let (cmt, module) = create(|cmt, _| {
+            let uint = || Some(DataTypeEnum::UInt(8.into()));
+            let [u, w, v] = ["u", "w", "v"]
+                .map(|name| cmt.add_entity(Wire::new(uint(), Some(name.into()), None, None).into()));
+            cmt.add_op(CombUnary::new(Some(w), Some(u), Some(CombUnaryPredicate::Not)).into());
+            cmt.add_op(CombUnary::new(Some(v), Some(w), Some(CombUnaryPredicate::Not)).into());
+            vec![]
+        });
+        let mut blaster = crate::bitblast::blast_module(&cmt, module).unwrap();
+        let body = cmt.get_op(module).get_regions()[0].1[0];
+        let named = |name: &str| {
+            let mut entities = cmt.get_entities_with_parent(Some(body)).into_iter();
+            entities.find(|&entity| cmt.print_entity(entity) == name)
+        };
+        for name in ["%w", "%v", "%w"] {
+            let failed = blaster.get(named(name), module).unwrap_err();
+            assert_eq!(failed.message, "%u is not driven");
+        }
+
+        // values without a bit width cannot be turned into gates
+        let (cmt, module) = create(|cmt, [a, ..]| {
+            let dtype = DataTypeEnum::UArray(UArrayType(Box::new(DataTypeEnum::UInt(8.into())), 2));
+            let mem = cmt.add_entity(Wire::new(Some(dtype), Some("mem".into()), None, None).into());
+            cmt.add_op(Assign::new(Some(mem), Some(a)).into());
+            vec![mem]
+        });
+        let failed = bitblast_module(&cmt, module).unwrap_err();
+        assert_eq!(failed.message, "%mem has no bit width");
+
+        let mut cmt = CmtEnv::default();
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let failed = bitblast_module(&cmt, ext).unwrap_err();
+        assert_eq!((failed.op, failed.message.as_str()), (Some(ext), "the module has no body"));
+    }
+}
+
+mod smt_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    /// a 4-bit counter `cnt` reset to three, with `hit` high when it is three and `never`
+    /// high when it equals its own increment
+    fn counter(cmt: &mut CmtEnv) -> OpId {
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let wire = |name: &str, width| Wire::new(uint(width), Some(name.into()), None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        cmt.with_region(Some(body), |cmt| {
+            let rst = cmt.add_entity(wire("rst", 1));
+            cmt.add_op(HwInput::new(vec![rst]).into());
+            let [cnt, inc, one, three, done] =
+                ["cnt", "inc", "one", "three", "done"].map(|name| cmt.add_entity(wire(name, 4)));
+            let [hit, never] = ["hit", "never"].map(|name| cmt.add_entity(wire(name, 1)));
+            cmt.add_op(HwConstant::new(Some(one), Some(ConstantAttr(vec![true]))).into());
+            cmt.add_op(HwConstant::new(Some(three), Some(ConstantAttr(vec![true, true]))).into());
+            cmt.add_op(CombVariadic::new(Some(inc), vec![cnt, one], Some(CombVariadicPredicate::Add)).into());
+            cmt.add_op(SeqCompReg::new(Some(cnt), Some(inc), None, Some(rst), Some(three)).into());
+            cmt.add_op(CombICmp::new(Some(hit), Some(cnt), Some(three), Some(CombICmpPredicate::EQ)).into());
+            cmt.add_op(CombICmp::new(Some(never), Some(cnt), Some(inc), Some(CombICmpPredicate::EQ)).into());
+            cmt.add_op(CombConcat::new(Some(done), vec![hit, hit, hit, hit]).into());
+            cmt.add_op(HwOutput::new(vec![hit, never]).into());
+        });
+        module
+    }
+
+    #[test]
+    pub fn export_test() {
+        let mut cmt = CmtEnv::default();
+        let module = counter(&mut cmt);
+
+        let smt = export_smt2(&cmt, module).unwrap();
+        let lines = smt.lines().collect::<Vec<_>>();
+        assert_eq!(lines[..2], ["; yosys-smt2-module top", "(declare-sort |top_s| 0)"]);
+        for line in ["; yosys-smt2-input rst 1", "; yosys-smt2-register cnt 4", "; yosys-smt2-output hit 1"] {
+            assert!(lines.contains(&line), "missing {}", line);
+        }
+
+        // terms are numbered by entity ids, so they are spelled by the names `|top_n name|`
+        // gives them, and `name'` in the next state
+        let mut names = vec![];
+        for line in lines.iter() {
+            if let Some(rest) = line.strip_prefix("(define-fun |top_n ") {
+                let (name, rest) = rest.split_once('|').unwrap();
+                let term = rest.rsplit_once(" (").unwrap().1.strip_suffix(" state))").unwrap();
+                names.push((term.to_owned(), name.to_owned()));
+            }
+        }
+        let spell = |body: &str| {
+            names.iter().fold(body.to_owned(), |body, (term, name)| {
+                body.replace(&format!("({} state)", term), name)
+                    .replace(&format!("({} next_state)", term), &format!("{}'", name))
+            })
+        };
+        let term = |name: &str| names.iter().find(|(_, x)| x == name).unwrap().0.to_owned();
+        let declared = |name: &str| format!("(declare-fun {} (|top_s|) (_ BitVec ", term(name));
+        let defined = |name: &str| {
+            let prefix = format!("(define-fun {} ((state |top_s|)) (_ BitVec ", term(name));
+            let line = lines.iter().find_map(|line| line.strip_prefix(&prefix)).unwrap();
+            let (width, body) = line.strip_suffix(')').unwrap().split_once(") ").unwrap();
+            (width.parse::<usize>().unwrap(), spell(body))
+        };
+
+        assert!(lines.contains(&(declared("rst") + "1))").as_str()));
+        assert!(lines.contains(&(declared("cnt") + "4))").as_str()));
+        assert_eq!(defined("one"), (4, "#b0001".into()));
+        assert_eq!(defined("three"), (4, "#b0011".into()));
+        assert_eq!(defined("inc"), (4, "(bvadd cnt one)".into()));
+        assert_eq!(defined("hit"), (1, "(ite (= cnt three) #b1 #b0)".into()));
+        assert_eq!(defined("done"), (4, "(concat (concat (concat hit hit) hit) hit)".into()));
+        let transition = lines
+            .iter()
+            .find_map(|line| line.strip_prefix("(define-fun |top_t| ((state |top_s|) (next_state |top_s|)) Bool "))
+            .unwrap();
+        assert_eq!(spell(transition.strip_suffix(')').unwrap()), "(= (ite (= rst #b1) three inc) cnt')");
+
+        // terms are declared or defined before they are used
+        let mut known = vec![];
+        for &line in lines.iter() {
+            let mut terms = line.match_indices("|top#").map(|(at, _)| {
+                let end = at + 1 + line[at + 1..].find('|').unwrap();
+                &line[at..=end]
+            });
+            if line.starts_with("(declare-fun |top#") || line.starts_with("(define-fun |top#") {
+                known.push(terms.next().unwrap());
+            }
+            for term in terms {
+                assert!(known.contains(&term), "{} is used before it is defined", term);
+            }
+        }
+
+        let query = smt2_bmc_query("top", 2, "hit");
+        assert!(query.contains("(assert (|top_t| s1 s2))"));
+        assert!(query.ends_with("(check-sat)\n"));
+
+        let body = cmt.get_op(module).get_regions()[0].1[0];
+        cmt.with_region(Some(body), |cmt| {
+            let uint = Some(DataTypeEnum::UInt(4.into()));
+            let wire = |name: &str| Wire::new(uint.to_owned(), Some(name.into()), None, None).into();
+            let (p, q) = (cmt.add_entity(wire("p")), cmt.add_entity(wire("q")));
+            cmt.add_op(CombUnary::new(Some(p), Some(q), Some(CombUnaryPredicate::Not)).into());
+            cmt.add_op(CombUnary::new(Some(q), Some(p), Some(CombUnaryPredicate::Neg)).into());
+        });
+        let error = export_smt2(&cmt, module).unwrap_err();
+        assert_eq!(error.to_string(), "%p is on a combinational loop");
+    }
+
+    #[test]
+    pub fn memory_test() {
+        let mut cmt = CmtEnv::default();
+        let module = counter(&mut cmt);
+        let body = cmt.get_op(module).get_regions()[0].1[0];
+        cmt.with_region(Some(body), |cmt| {
+            let clk = cmt.add_entity(Wire::new(Some(DataTypeEnum::UInt(1.into())), None, None, None).into());
+            let dtype = DataTypeEnum::SeqHlmem(SeqHlmemType(Box::new(DataTypeEnum::UInt(8.into())), vec![4]));
+            let mem = cmt.add_entity(Wire::new(Some(dtype), Some("mem".into()), None, None).into());
+            cmt.add_op(SeqHlmem::new(Some(mem), Some(clk), None).into());
+        });
+        let error = export_smt2(&cmt, module).unwrap_err();
+        assert!(error.to_string().ends_with("is not a bit-vector of a known width"), "{}", error);
+
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let error = export_smt2(&cmt, ext).unwrap_err();
+        assert_eq!((error.op, error.to_string()), (Some(ext), "the module has no body".into()));
+    }
+
+    #[test]
+    #[ignore = "needs z3 on the PATH"]
+    pub fn solver_test() {
+        let mut cmt = CmtEnv::default();
+        let module = counter(&mut cmt);
+        let smt = export_smt2(&cmt, module).unwrap();
+
+        let solver = SmtSolver::z3();
+        for steps in [0, 2] {
+            let check = |bad| solver.check(&(smt.to_owned() + &smt2_bmc_query("top", steps, bad))).unwrap();
+            assert_eq!(check("hit"), SmtResult::Sat);
+            assert_eq!(check("never"), SmtResult::Unsat);
+        }
+    }
+}
+
+mod bmc_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    #[test]
+    pub fn sat_test() {
+        // three pigeons do not fit in two holes
+        let mut solver = SatSolver::new();
+        let holes = (0..3).map(|_| [solver.new_var(), solver.new_var()]).collect::<Vec<_>>();
+        for pigeon in holes.iter() {
+            solver.add_clause(&[SatLit::new(pigeon[0], false), SatLit::new(pigeon[1], false)]);
+        }
+        for (i, j) in [(0, 1), (0, 2), (1, 2)] {
+            for (&a, &b) in holes[i].iter().zip(holes[j].iter()) {
+                solver.add_clause(&[SatLit::new(a, true), SatLit::new(b, true)]);
+            }
+        }
+        assert_eq!(solver.solve(), None);
+
+        // random 3-SAT instances agree with trying every assignment
+        let mut seed = 0x2545f491u64;
+        let mut random = |n: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        for _ in 0..200 {
+            let clauses = (0..40)
+                .map(|_| (0..3).map(|_| SatLit::new(random(10) as u32, random(2) == 1)).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let satisfies = |model: &[bool]| {
+                clauses.iter().all(|c| c.iter().any(|lit| model[lit.var() as usize] != lit.is_negated()))
+            };
+            let mut solver = SatSolver::new();
+            for _ in 0..10 {
+                solver.new_var();
+            }
+            for clause in clauses.iter() {
+                solver.add_clause(clause);
+            }
+            let expected = (0..1 << 10).any(|bits: u32| satisfies(&(0..10).map(|i| bits >> i & 1 == 1).collect::<Vec<_>>()));
+            match solver.solve() {
+                Some(model) => assert!(satisfies(&model)),
+                None => assert!(!expected),
+            }
+
+            // the same solver under assumptions, which do not outlive the call
+            let assumptions = [0, 1].map(|_| SatLit::new(random(10) as u32, random(2) == 1));
+            let assumed =
+                |model: &[bool]| assumptions.iter().all(|lit| model[lit.var() as usize] != lit.is_negated());
+            let expected_assumed = (0..1 << 10).any(|bits: u32| {
+                let model = (0..10).map(|i| bits >> i & 1 == 1).collect::<Vec<_>>();
+                satisfies(&model) && assumed(&model)
+            });
+            match solver.solve_with(&assumptions) {
+                Some(model) => assert!(satisfies(&model) && assumed(&model)),
+                None => assert!(!expected_assumed),
+            }
+            assert_eq!(solver.solve().is_some(), expected);
+        }
+    }
+
+    #[test]
+    pub fn bmc_test() {
+        let mut cmt = CmtEnv::default();
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let wire = |name: &str, width| Wire::new(uint(width), Some(name.into()), None, None).into();
+        let event = |name: &str| Event::new(None, Some(name.into()), None, None).into();
+        let sqn = || Sqn::new(None, None, None, None).into();
+        let prpt = || Prpt::new(None, None, None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        cmt.with_region(Some(body), |cmt| {
+            // a counter, and `small` while it is below 5
+            let [cnt, inc, one, five] = ["cnt", "inc", "one", "five"].map(|name| cmt.add_entity(wire(name, 4)));
+            let small = cmt.add_entity(wire("small", 1));
+            cmt.add_op(HwConstant::new(Some(one), Some(ConstantAttr(vec![true]))).into());
+            cmt.add_op(HwConstant::new(Some(five), Some(ConstantAttr(vec![true, false, true]))).into());
+            cmt.add_op(CombVariadic::new(Some(inc), vec![cnt, one], Some(CombVariadicPredicate::Add)).into());
+            cmt.add_op(SeqCompReg::new(Some(cnt), Some(inc), None, None, None).into());
+            cmt.add_op(CombICmp::new(Some(small), Some(cnt), Some(five), Some(CombICmpPredicate::ULT)).into());
+
+            // a free request, acknowledged one cycle later
+            let (req, ack) = (cmt.add_entity(event("req")), cmt.add_entity(event("ack")));
+            let (req_wire, ack_wire) = (cmt.add_entity(wire("req_wire", 1)), cmt.add_entity(wire("ack_wire", 1)));
+            cmt.add_op(EventDef::new(Some(req)).into());
+            cmt.add_op(EventEval::new(Some(req_wire), Some(req)).into());
+            cmt.add_op(SeqCompReg::new(Some(ack_wire), Some(req_wire), None, None, None).into());
+            cmt.add_op(EventFrom::new(Some(ack), Some(ack_wire)).into());
+
+            // always small
+            let is_small = cmt.add_entity(event("is_small"));
+            cmt.add_op(EventFrom::new(Some(is_small), Some(small)).into());
+            let [s, p, always_small] = [sqn(), prpt(), prpt()].map(|e| cmt.add_entity(e));
+            cmt.add_op(SqnFromEvent::new(Some(s), Some(is_small)).into());
+            cmt.add_op(PrptFromSqn::new(Some(p), Some(s)).into());
+            cmt.add_op(PrptAlways::new(Some(always_small), Some(p)).into());
+
+            // req |-> ##delay ack
+            let mut implications = vec![];
+            for delay in [1, 2] {
+                let [req_s, ack_s, delayed] = [sqn(), sqn(), sqn()].map(|e| cmt.add_entity(e));
+                let [antecedent, implication] = [prpt(), prpt()].map(|e| cmt.add_entity(e));
+                cmt.add_op(SqnFromEvent::new(Some(req_s), Some(req)).into());
+                cmt.add_op(SqnFromEvent::new(Some(ack_s), Some(ack)).into());
+                let bound = Some(IdAttr(delay));
+                cmt.add_op(SqnDelay::new(Some(delayed), Some(ack_s), bound.to_owned(), bound).into());
+                cmt.add_op(PrptFromSqn::new(Some(antecedent), Some(req_s)).into());
+                cmt.add_op(PrptImplica::new(Some(implication), Some(antecedent), Some(delayed)).into());
+                implications.push(implication);
+            }
+
+            for property in [always_small, implications[0], implications[1]] {
+                cmt.add_op(PrptSynth::new(Some(property)).into());
+            }
+        });
+        let ops = cmt.get_region(body).get_op_children();
+        let targets = ops.into_iter().filter(|&op| matches!(cmt.get_op(op), OpEnum::PrptSynth(_)));
+        let targets = targets.collect::<Vec<_>>();
+
+        let reports = check_properties(&cmt, module, 6).unwrap();
+        assert_eq!(reports.iter().map(|report| report.target).collect::<Vec<_>>(), targets);
+
+        let BmcResult::Fail { depth, trace } = &reports[0].result else { panic!("expected a failure") };
+        assert_eq!(*depth, 5);
+        let cnt = trace.get_signal("cnt").unwrap();
+        assert_eq!((0..=5).map(|cycle| cnt.get_value(cycle).unwrap()).collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5]);
+        assert!(trace.to_string().contains("small    1 1 1 1 1 0\n"));
+
+        assert_eq!(reports[1].result, BmcResult::Pass { bound: 6 });
+
+        // the request is not repeated in the next cycle
+        let BmcResult::Fail { depth, trace } = &reports[2].result else { panic!("expected a failure") };
+        assert_eq!(*depth, 2);
+        let req = trace.get_signal("req_wire").unwrap();
+        assert_eq!((req.get_value(0), req.get_value(1)), (Some(1), Some(0)));
+    }
+}
+
+mod monitor_test {
+    use irony::{Environ, PassTrait, Region};
+
+    use crate::*;
+
+    /// a counter, a request acknowledged one cycle later, and the `target`-th property
+    fn build(target: usize) -> (CmtEnv, OpId) {
+        let mut cmt = CmtEnv::default();
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let wire = |name: &str, width| Wire::new(uint(width), Some(name.into()), None, None).into();
+        let event = |name: &str| Event::new(None, Some(name.into()), None, None).into();
+        let sqn = || Sqn::new(None, None, None, None).into();
+        let prpt = || Prpt::new(None, None, None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        cmt.with_region(Some(body), |cmt| {
+            let clk = cmt.add_entity(wire("clk", 1));
+            cmt.add_op(HwInput::new(vec![clk]).into());
+            let [cnt, inc, one, five] = ["cnt", "inc", "one", "five"].map(|name| cmt.add_entity(wire(name, 4)));
+            let small = cmt.add_entity(wire("small", 1));
+            cmt.add_op(HwConstant::new(Some(one), Some(ConstantAttr(vec![true]))).into());
+            cmt.add_op(HwConstant::new(Some(five), Some(ConstantAttr(vec![true, false, true]))).into());
+            cmt.add_op(CombVariadic::new(Some(inc), vec![cnt, one], Some(CombVariadicPredicate::Add)).into());
+            cmt.add_op(SeqCompReg::new(Some(cnt), Some(inc), Some(clk), None, None).into());
+            cmt.add_op(CombICmp::new(Some(small), Some(cnt), Some(five), Some(CombICmpPredicate::ULT)).into());
+
+            let (req, ack) = (cmt.add_entity(event("req")), cmt.add_entity(event("ack")));
+            let (req_wire, ack_wire) = (cmt.add_entity(wire("req_wire", 1)), cmt.add_entity(wire("ack_wire", 1)));
+            cmt.add_op(EventDef::new(Some(req)).into());
+            cmt.add_op(EventEval::new(Some(req_wire), Some(req)).into());
+            cmt.add_op(SeqCompReg::new(Some(ack_wire), Some(req_wire), Some(clk), None, None).into());
+            cmt.add_op(EventFrom::new(Some(ack), Some(ack_wire)).into());
+            let is_small = cmt.add_entity(event("is_small"));
+            cmt.add_op(EventFrom::new(Some(is_small), Some(small)).into());
+
+            let [small_s, req_s, ack_s, delayed] = [sqn(), sqn(), sqn(), sqn()].map(|e| cmt.add_entity(e));
+            cmt.add_op(SqnFromEvent::new(Some(small_s), Some(is_small)).into());
+            cmt.add_op(SqnFromEvent::new(Some(req_s), Some(req)).into());
+            cmt.add_op(SqnFromEvent::new(Some(ack_s), Some(ack)).into());
+            let [small_p, req_p, property] = [prpt(), prpt(), prpt()].map(|e| cmt.add_entity(e));
+            cmt.add_op(PrptFromSqn::new(Some(small_p), Some(small_s)).into());
+            cmt.add_op(PrptFromSqn::new(Some(req_p), Some(req_s)).into());
+            match target {
+                // always small
+                0 => {
+                    cmt.add_op(PrptAlways::new(Some(property), Some(small_p)).into());
+                },
+                // req |-> ##delay ack
+                delay @ (1 | 2) => {
+                    let bound = Some(IdAttr(delay));
+                    cmt.add_op(SqnDelay::new(Some(delayed), Some(ack_s), bound.to_owned(), bound).into());
+                    cmt.add_op(PrptImplica::new(Some(property), Some(req_p), Some(delayed)).into());
+                },
+                // req ##1 ack, once, spanning two cycles
+                _ => {
+                    let bound = Some(IdAttr(1));
+                    let concat = cmt.add_entity(sqn());
+                    cmt.add_op(SqnDelay::new(Some(delayed), Some(ack_s), bound.to_owned(), bound).into());
+                    cmt.add_op(SqnConcat::new(Some(concat), Some(req_s), Some(delayed)).into());
+                    cmt.add_op(PrptFromSqn::new(Some(property), Some(concat)).into());
+                },
+            }
+            cmt.add_op(PrptSynth::new(Some(property)).into());
+            cmt.add_op(HwOutput::new(vec![]).into());
+        });
+        (cmt, module)
+    }
+
+    /// the `fail` output of the lowered monitor over the cycles of `reqs`
+    fn simulate(cmt: &CmtEnv, module: OpId, reqs: &[bool]) -> Vec<bool> {
+        let aig = bitblast_module(cmt, module).unwrap();
+        assert_eq!(aig.outputs.iter().map(|output| output.name.as_str()).collect::<Vec<_>>(), ["fail"]);
+        let req = aig.inputs.iter().position(|input| input.name == "req").unwrap();
+        let mut state = vec![false; aig.latches.len()];
+        let mut fails = vec![];
+        for &value in reqs {
+            let mut inputs = vec![false; aig.inputs.len()];
+            inputs[req] = value;
+            let (outputs, next) = aig.simulate(&inputs, &state);
+            fails.push(outputs[0]);
+            state = next;
+        }
+        fails
+    }
+
+    #[test]
+    pub fn lower_test() {
+        let cycles = |fails: Vec<bool>| (0..fails.len()).filter(|&i| fails[i]).collect::<Vec<_>>();
+        let (mut lowered, module) = build(0);
+        let clk = lowered.get_region(lowered.get_op(module).get_regions()[0].1[0]).get_op_children()[0];
+        let clk = lowered.get_op(clk).get_defs()[0].1[0].unwrap();
+        let body = lowered.get_op(module).get_regions()[0].1[0];
+        let synth = lowered.get_region(body).get_op_children().into_iter().find(|&op| {
+            matches!(lowered.get_op(op), OpEnum::PrptSynth(_))
+        });
+        let synth_loc = Some(irony::Loc::file_line_col("top.sv", 9, 1));
+        lowered.set_op_loc(synth.unwrap(), synth_loc.to_owned());
+        let fail = lower_monitors(&mut lowered, module, clk).unwrap().unwrap();
+        assert_eq!(cycles(simulate(&lowered, module, &[false; 8])), [5, 6, 7]);
+
+        // the monitor is located at the target
+        assert_eq!(lowered.get_op_loc(lowered.get_defs(fail)[0]), synth_loc);
+        let ops = lowered.get_region(body).get_op_children();
+        let locs = ops.iter().map(|&op| lowered.get_op_loc(op).unwrap().to_string());
+        assert!(locs.into_iter().all(|loc| !loc.contains("monitor.rs")));
+
+        // no sequence or property op is left, and the output is the last op
+        assert!(ops.iter().all(|&op| !matches!(lowered.get_op(op).get_dialect(), Some("sequence" | "property"))));
+        assert!(matches!(lowered.get_op(*ops.last().unwrap()), OpEnum::HwOutput(_)));
+
+        // a request only in the first cycle is acknowledged one cycle later, and the sequence
+        // checked from the cycles after it fails as soon as it spans two cycles
+        let reqs = [true, false, false, false, false];
+        for (target, expected) in [(1, vec![]), (2, vec![2]), (3, vec![2, 3, 4])] {
+            let (mut cmt, module) = build(target);
+            assert_eq!(MonitorLoweringPass::default().run_raw(&mut cmt, module), Ok(()));
+            assert_eq!(cycles(simulate(&cmt, module, &reqs)), expected);
+        }
+
+        // overlapping matches are followed at once
+        let (mut cmt, module) = build(3);
+        MonitorLoweringPass::default().run_raw(&mut cmt, module).unwrap();
+        assert_eq!(cycles(simulate(&cmt, module, &[true; 6])), []);
+        assert_eq!(cycles(simulate(&cmt, module, &[false, true, true, false, false])), [1, 4]);
+
+        // an output port named `fail` keeps its name
+        let (mut cmt, module) = build(0);
+        cmt.get_op_entry(module).and_modify(|op| {
+            let OpEnum::HwModule(op) = op else { unreachable!() };
+            op.set_output_names(Some(ArrayAttr(vec![StringAttr("fail".into()).into()])));
+            let bit = TypeAttr(DataTypeEnum::UInt(1.into()));
+            op.set_output_types(Some(ArrayAttr(vec![bit.into()])));
+        });
+        let fail = lower_monitors(&mut cmt, module, clk).unwrap().unwrap();
+        assert_eq!(cmt.print_entity(fail), "%fail_1");
+        let OpEnum::HwModule(op) = cmt.get_op(module) else { unreachable!() };
+        let names = ["fail", "fail_1"].map(|name| AttributeEnum::StringAttr(StringAttr(name.into())));
+        assert_eq!(op.output_names().unwrap().0, names);
+
+        // without a clock nothing changes
+        let (mut cmt, module) = build(0);
+        let pass = MonitorLoweringPass { clock: "clock".into() };
+        let failed = pass.run_raw(&mut cmt, module).unwrap_err();
+        assert_eq!(failed.to_string(), "the module has no input port named clock");
+
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let failed = lower_monitors(&mut cmt, ext, clk).unwrap_err();
+        assert_eq!(failed.to_string(), "the module has no body");
+    }
+}
+
+mod sva_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    #[test]
+    pub fn emit_test() {
+        let mut cmt = CmtEnv::default();
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let wire = |name: &str| Wire::new(uint(1), Some(name.into()), None, None).into();
+        let event = |name: Option<&str>| Event::new(None, name.map(|name| name.into()), None, None).into();
+        let sqn = || Sqn::new(None, None, None, None).into();
+        let prpt = |name: Option<&str>| Prpt::new(None, name.map(|name| name.into()), None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let block = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        cmt.with_region(Some(body), |cmt| {
+            let [clk, req_wire, ack_wire] = ["clk", "req_wire", "ack_wire"].map(|name| cmt.add_entity(wire(name)));
+            cmt.add_op(HwInput::new(vec![clk, req_wire, ack_wire]).into());
+            let [tick, req, ack] = [None, None, None].map(|name| cmt.add_entity(event(name)));
+            cmt.add_op(EventFrom::new(Some(tick), Some(clk)).into());
+            cmt.add_op(EventFrom::new(Some(req), Some(req_wire)).into());
+            cmt.add_op(EventFrom::new(Some(ack), Some(ack_wire)).into());
+            let start = cmt.add_entity(event(Some("start")));
+            cmt.add_op(EventDef::new(Some(start)).into());
+
+            let [req_s, ack_s, start_s, delayed, ranged, open, concat] = [(); 7].map(|_| cmt.add_entity(sqn()));
+            cmt.add_op(SqnFromEvent::new(Some(req_s), Some(req)).into());
+            cmt.add_op(SqnFromEvent::new(Some(ack_s), Some(ack)).into());
+            cmt.add_op(SqnFromEvent::new(Some(start_s), Some(start)).into());
+            cmt.add_op(SqnDelay::new(Some(delayed), Some(ack_s), Some(IdAttr(2)), Some(IdAttr(2))).into());
+            cmt.add_op(SqnDelay::new(Some(ranged), Some(ack_s), Some(IdAttr(1)), Some(IdAttr(3))).into());
+            cmt.add_op(SqnDelay::new(Some(open), Some(req_s), Some(IdAttr(1)), None).into());
+            cmt.add_op(SqnConcat::new(Some(concat), Some(req_s), Some(ranged)).into());
+
+            let [req_p, ack_p, handshake, next, always, eventually, until, both] =
+                [None, None, Some("handshake"), None, None, None, None, None].map(|name| cmt.add_entity(prpt(name)));
+            cmt.add_op(PrptFromSqn::new(Some(req_p), Some(req_s)).into());
+            cmt.add_op(PrptFromSqn::new(Some(ack_p), Some(ack_s)).into());
+            cmt.add_op(PrptImplica::new(Some(handshake), Some(req_p), Some(delayed)).into());
+            cmt.add_op(PrptNexttime::new(Some(next), Some(ack_p)).into());
+            cmt.add_op(PrptAlways::new(Some(always), Some(handshake)).into());
+            cmt.add_op(PrptEventually::new(Some(eventually), Some(open)).into());
+            cmt.add_op(PrptUntil::new(Some(until), Some(req_p), Some(ack_p)).into());
+            cmt.add_op(PrptConjunction::new(Some(both), Some(next), Some(until)).into());
+
+            for property in [always, eventually, both] {
+                cmt.add_op(PrptSynth::new(Some(property)).into());
+            }
+            cmt.add_op(EventBlockDef::new(Some(start), Some(block)).into());
+            cmt.with_region(Some(block), |cmt| {
+                cmt.add_op(PrptSynth::new(Some(concat)).into());
+            });
+        });
+
+        let clk = cmt.get_region(body).get_op_children()[1];
+        let tick = cmt.get_op(clk).get_defs()[0].1[0];
+        assert_eq!(
+            emit_sva(&cmt, module, tick).unwrap(),
+            [
+                "assert property (@(posedge clk) always (req_wire |-> ##2 ack_wire));",
+                "assert property (@(posedge clk) s_eventually (##[1:$] req_wire));",
+                "assert property (@(posedge clk) (nexttime ack_wire) and (req_wire until ack_wire));",
+                "assert property (@(start) req_wire ##[1:3] ack_wire);",
+                "",
+            ]
+            .join("\n")
+        );
+
+        // the labelled target, and a target with no clock
+        let handshake = cmt.get_region(body).get_op_children().into_iter().find_map(|op| match cmt.get_op(op) {
+            OpEnum::PrptImplica(op) => op.rst(),
+            _ => None,
+        });
+        cmt.with_region(Some(body), |cmt| {
+            cmt.add_op(PrptSynth::new(handshake).into());
+        });
+        let sva = emit_sva(&cmt, module, tick).unwrap();
+        assert!(sva.ends_with("handshake: assert property (@(posedge clk) req_wire |-> ##2 ack_wire);\n"));
+        assert!(emit_sva(&cmt, module, None).is_err());
+
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let error = emit_sva(&cmt, ext, tick).unwrap_err();
+        assert_eq!((error.op, error.message.as_str()), (Some(ext), "the module has no body"));
+    }
+}
+
+mod event_test {
+    use irony::{Environ, PassTrait, Region};
+
+    use crate::*;
+
+    #[test]
+    pub fn graph_test() {
+        let mut cmt = CmtEnv::default();
+        let event = |name: Option<&str>| Event::new(None, name.map(|name| name.into()), None, None).into();
+        let wire = || Wire::new(Some(DataTypeEnum::UInt(1.into())), Some("a".into()), None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let [first, second] = [(); 2].map(|_| cmt.add_region(Region::new(false)));
+        let module =
+            cmt.add_op(HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into());
+        cmt.begin_region(Some(body));
+        let a = cmt.add_entity(wire());
+        cmt.add_op(HwInput::new(vec![a]).into());
+        let [root, hit, free, other] = [None, None, Some("free"), None].map(|name| cmt.add_entity(event(name)));
+        for event in [root, free, other] {
+            cmt.add_op(EventDef::new(Some(event)).into());
+        }
+        cmt.add_op(EventFrom::new(Some(hit), Some(a)).into());
+        let unions = [hit, free].map(|son| cmt.add_op(EventUnion::new(Some(root), Some(son)).into()));
+        let else_of = cmt.add_op(EventElseOf::new(Some(other), Some(hit)).into());
+        let blocks = [(root, first), (other, second)]
+            .map(|(event, region)| cmt.add_op(EventBlockDef::new(Some(event), Some(region)).into()));
+        cmt.end_region();
+
+        // an event made in the first block is out of the scope of the second one
+        cmt.begin_region(Some(first));
+        let inner = cmt.add_entity(event(None));
+        cmt.add_op(EventDef::new(Some(inner)).into());
+        cmt.end_region();
+        cmt.begin_region(Some(second));
+        let eval = cmt.add_entity(wire());
+        cmt.add_op(EventEval::new(Some(eval), Some(inner)).into());
+        cmt.end_region();
+
+        let graph = EventGraph::new(&cmt, module);
+        assert_eq!(graph.get_roots(), [root, other, inner]);
+        assert_eq!(graph.get_sons(root), [hit, free]);
+        assert_eq!((graph.get_father(hit), graph.get_depth(hit)), (Some(root), 1));
+        assert!(graph.is_sub_event(free, root) && !graph.is_sub_event(root, free));
+        assert_eq!(graph.get_else_of(other), [hit]);
+        assert_eq!(graph.get_blocks(other), [blocks[1]]);
+        assert!(graph.is_forest());
+        assert!(unions.iter().chain([&else_of, &blocks[0]]).all(|&op| cmt.verify_op(op)));
+        assert!(!cmt.verify_op(blocks[1]));
+
+        // a second father, then a cycle
+        cmt.begin_region(Some(body));
+        let union = cmt.add_op(EventUnion::new(Some(other), Some(hit)).into());
+        cmt.end_region();
+        assert!(!cmt.verify_op(union) && !cmt.verify_op(unions[0]));
+        assert!(!EventGraph::new(&cmt, module).is_forest());
+        cmt.get_op_entry(union).and_modify(|op| {
+            op.set_uses(vec![("father".into(), vec![Some(hit)]), ("son".into(), vec![Some(root)])])
+        });
+        assert!(!cmt.verify_op(union));
+        assert_eq!(EventGraph::new(&cmt, module).find_cycle(), Some(vec![root, hit]));
+    }
+
+    #[test]
+    pub fn lower_test() {
+        let mut cmt = CmtEnv::default();
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let wire = |name: &str, width| Wire::new(uint(width), Some(name.into()), None, None).into();
+        let event = |name: Option<&str>| Event::new(None, name.map(|name| name.into()), None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let [count, mark] = [(); 2].map(|_| cmt.add_region(Region::new(false)));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        let [a, cnt, out, fired] = [("a", 1), ("cnt", 4), ("out", 1), ("fired", 1)].map(|(name, width)| cmt.add_entity(wire(name, width)));
+        cmt.with_region(Some(body), |cmt| {
+            cmt.add_op(HwInput::new(vec![a]).into());
+            // `root` fires with `a` or the free `b`, `other` when `root` does but `a` does not
+            let [root, hit, b, other] = [None, None, Some("b"), None].map(|name| cmt.add_entity(event(name)));
+            for event in [root, b, other] {
+                cmt.add_op(EventDef::new(Some(event)).into());
+            }
+            cmt.add_op(EventFrom::new(Some(hit), Some(a)).into());
+            cmt.add_op(EventUnion::new(Some(root), Some(hit)).into());
+            cmt.add_op(EventUnion::new(Some(root), Some(b)).into());
+            cmt.add_op(EventElseOf::new(Some(other), Some(hit)).into());
+            let eval = cmt.add_op(EventEval::new(Some(fired), Some(b)).into());
+            cmt.set_op_loc(eval, Some(irony::Loc::file_line_col("top.sv", 7, 1)));
+            cmt.add_op(EventBlockDef::new(Some(root), Some(count)).into());
+            cmt.add_op(EventBlockDef::new(Some(other), Some(mark)).into());
+            cmt.add_op(HwOutput::new(vec![cnt, out, fired]).into());
+        });
+        cmt.with_region(Some(count), |cmt| {
+            let [inc, one] = ["inc", "one"].map(|name| cmt.add_entity(wire(name, 4)));
+            cmt.add_op(HwConstant::new(Some(one), Some(ConstantAttr(vec![true, false, false, false]))).into());
+            cmt.add_op(CombVariadic::new(Some(inc), vec![cnt, one], Some(CombVariadicPredicate::Add)).into());
+            cmt.add_op(SeqCompReg::new(Some(cnt), Some(inc), None, None, None).into());
+        });
+        cmt.with_region(Some(mark), |cmt| {
+            let high = cmt.add_entity(wire("high", 1));
+            cmt.add_op(HwConstant::new(Some(high), Some(ConstantAttr(vec![true]))).into());
+            cmt.add_op(Assign::new(Some(out), Some(high)).into());
+        });
+
+        assert_eq!(EventLoweringPass.run_raw(&mut cmt, module), Ok(()));
+        let ops = utils::walk_region_ops(&cmt, body);
+        // the lowered ops are located at the event ops
+        let eval_loc = Some(irony::Loc::file_line_col("top.sv", 7, 1));
+        assert_eq!(cmt.get_op_loc(cmt.get_defs(fired)[0]), eval_loc);
+        let locs = ops.iter().map(|&op| cmt.get_op_loc(op).unwrap().to_string());
+        assert!(locs.into_iter().all(|loc| !loc.contains("events.rs")));
+        assert!(ops.iter().all(|&op| cmt.get_op(op).get_dialect() != Some("event")));
+        assert!(ops.iter().all(|&op| cmt.verify_def_before_use(op).is_ok()));
+        assert!(matches!(cmt.get_op(*ops.last().unwrap()), OpEnum::HwOutput(_)));
+
+        let aig = bitblast_module(&cmt, module).unwrap();
+        assert_eq!(aig.inputs.iter().map(|input| input.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        let mut state = vec![false; aig.latches.len()];
+        let mut trace = vec![];
+        for (a, b) in [(true, false), (false, false), (false, true), (true, true), (false, false), (false, false)] {
+            let (outputs, next) = aig.simulate(&[a, b], &state);
+            let cnt = outputs[..4].iter().rev().fold(0, |acc, &bit| acc << 1 | bit as u8);
+            trace.push((cnt, outputs[4], outputs[5]));
+            state = next;
+        }
+        assert_eq!(
+            trace,
+            [(0, false, false), (1, false, false), (1, true, true), (2, false, true), (3, false, false), (3, false, false)]
+        );
+
+        // nothing is left to lower, and a sequence must be lowered first
+        assert_eq!(lower_events(&mut cmt, module), Ok(()));
+        cmt.with_region(Some(body), |cmt| {
+            let [e, s] = [cmt.add_entity(event(None)), cmt.add_entity(Sqn::new(None, None, None, None).into())];
+            cmt.add_op(EventFrom::new(Some(e), Some(a)).into());
+            cmt.add_op(SqnFromEvent::new(Some(s), Some(e)).into());
+        });
+        assert!(lower_events(&mut cmt, module).is_err());
+    }
+
+    #[test]
+    pub fn no_width_test() {
+        // a wire assigned in a block without a bit width has no zero to keep otherwise
+        let mut cmt = CmtEnv::default();
+        let body = cmt.add_region(Region::new(true));
+        let block = cmt.add_region(Region::new(false));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        let dtype = DataTypeEnum::UArray(UArrayType(Box::new(DataTypeEnum::UInt(8.into())), 2));
+        let [mem, src] = ["mem", "src"]
+            .map(|name| cmt.add_entity(Wire::new(Some(dtype.to_owned()), Some(name.into()), None, None).into()));
+        cmt.with_region(Some(body), |cmt| {
+            cmt.add_op(HwInput::new(vec![src]).into());
+            let event = cmt.add_entity(Event::new(None, Some("e".into()), None, None).into());
+            cmt.add_op(EventDef::new(Some(event)).into());
+            cmt.add_op(EventBlockDef::new(Some(event), Some(block)).into());
+            cmt.add_op(HwOutput::new(vec![mem]).into());
+        });
+        cmt.with_region(Some(block), |cmt| {
+            cmt.add_op(Assign::new(Some(mem), Some(src)).into());
+        });
+        let error = lower_events(&mut cmt, module).unwrap_err();
+        assert_eq!(error.to_string(), "%mem has no bit width");
+    }
+
+    #[test]
+    pub fn no_body_test() {
+        let mut cmt = CmtEnv::default();
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let error = lower_events(&mut cmt, ext).unwrap_err();
+        assert_eq!((error.op, error.message.as_str()), (Some(ext), "the module has no body"));
+    }
+}
+
+mod hlmem_test {
+    use irony::{Environ, Region};
+
+    use crate::*;
+
+    /// a 2x2 memory of 4-bit words with a write and a read port of latency 1
+    fn create() -> (CmtEnv, OpId, RegionId, EntityId) {
+        let mut cmt = CmtEnv::default();
+        let uint = |width: usize| Some(DataTypeEnum::UInt(width.into()));
+        let wire = |name: &str, width| Wire::new(uint(width), Some(name.into()), None, None).into();
+
+        let body = cmt.add_region(Region::new(true));
+        let module = cmt.add_op(
+            HwModule::new(None, Some(StringAttr("top".into())), None, None, None, None, Some(body)).into(),
+        );
+        let ports = [("clk", 1), ("we", 1), ("wa0", 1), ("wa1", 1), ("wd", 4), ("re", 1), ("ra0", 1), ("ra1", 1)];
+        let [clk, we, wa0, wa1, wd, re, ra0, ra1] = ports.map(|(name, width)| cmt.add_entity(wire(name, width)));
+        let rd = cmt.add_entity(wire("rd", 4));
+        cmt.with_region(Some(body), |cmt| {
+            cmt.add_op(HwInput::new(vec![clk, we, wa0, wa1, wd, re, ra0, ra1]).into());
+            let dtype = Some(DataTypeEnum::SeqHlmem(SeqHlmemType(Box::new(DataTypeEnum::UInt(4.into())), vec![2, 2])));
+            let mem = cmt.add_entity(Wire::new(dtype, Some("mem".into()), None, None).into());
+            cmt.add_op(SeqHlmem::new(Some(mem), Some(clk), None).into());
+            cmt.add_op(SeqWrite::new(Some(mem), Some(we), Some(wd), vec![wa0, wa1], Some(1usize.into())).into());
+            cmt.add_op(SeqRead::new(Some(rd), Some(mem), Some(re), vec![ra0, ra1], Some(1usize.into())).into());
+            cmt.add_op(HwOutput::new(vec![rd]).into());
+        });
+        (cmt, module, body, rd)
+    }
+
+    #[test]
+    pub fn print_test() {
+        let (cmt, _, body, _) = create();
+        let printed = cmt.print_region(body);
+        assert!(printed.contains("seq.hlmem"));
+        assert!(printed.contains("!seq.hlmem<2x2xi4>"));
+        assert!(printed.contains("rden"));
+        assert!(printed.contains("wren"));
+        assert!(printed.contains("{latency = 1}"));
+    }
+
+    #[test]
+    pub fn register_test() {
+        let (mut cmt, module, body, _) = create();
+        assert_eq!(MemoryLoweringPass::default().run_raw(&mut cmt, module), Ok(()));
+        let ops = utils::walk_region_ops(&cmt, body);
+        assert!(ops.iter().all(|&op| {
+            !matches!(cmt.get_op(op), OpEnum::SeqHlmem(_) | OpEnum::SeqRead(_) | OpEnum::SeqWrite(_))
+        }));
+        assert!(ops.iter().all(|&op| cmt.verify_def_before_use(op).is_ok()));
+        assert!(matches!(cmt.get_op(*ops.last().unwrap()), OpEnum::HwOutput(_)));
+
+        let aig = bitblast_module(&cmt, module).unwrap();
+        let mut state = vec![false; aig.latches.len()];
+        let mut trace = vec![];
+        // (write enable, write address, data, read enable, read address)
+        let cycles = [
+            (true, [true, false], 5, false, [false, false]),
+            (true, [false, true], 9, true, [true, false]),
+            (false, [false, false], 0, true, [false, true]),
+            (false, [false, false], 0, false, [false, false]),
+            (false, [false, false], 0, true, [true, true]),
+            (false, [false, false], 0, false, [false, false]),
+        ];
+        for (we, wa, wd, re, ra) in cycles {
+            let mut inputs = vec![false, we, wa[0], wa[1]];
+            inputs.extend((0..4).map(|bit| wd >> bit & 1 == 1));
+            inputs.extend([re, ra[0], ra[1]]);
+            let (outputs, next) = aig.simulate(&inputs, &state);
+            trace.push(outputs.iter().rev().fold(0, |acc, &bit| acc << 1 | bit as u8));
+            state = next;
+        }
+        assert_eq!(trace, [0, 0, 5, 9, 9, 0]);
+    }
+
+    #[test]
+    pub fn macro_test() {
+        let (mut cmt, module, body, rd) = create();
+        assert_eq!(lower_memories(&mut cmt, module, 15), Ok(()));
+        let memory = cmt.lookup_symbol(None, "hlmem_2x2xi4_r1_w1").unwrap();
+        let OpEnum::HwModule(memory) = cmt.get_op(memory) else { panic!() };
+        let names = |attr: Option<&ArrayAttr>| {
+            attr.unwrap().0.iter().map(|name| format!("{}", name)).collect::<Vec<_>>().join(", ")
+        };
+        assert_eq!(names(memory.arg_names()), "clk, r0_en, r0_addr, w0_en, w0_addr, w0_data");
+        assert_eq!(names(memory.output_names()), "r0_data");
+
+        let instance = cmt.get_defs(rd)[0];
+        assert!(matches!(cmt.get_op(instance), OpEnum::HwInstance(_)));
+        assert_eq!(cmt.get_op(instance).get_parent(), Some(body));
+        assert!(cmt.print_op(instance).contains("@hlmem_2x2xi4_r1_w1("));
+        assert!(InstanceGraph::new(&cmt).unwrap().get_instances(module).len() == 1);
+
+        // a write must take a cycle
+        let (mut cmt, module, body, _) = create();
+        let write = utils::walk_region_ops(&cmt, body)
+            .into_iter()
+            .find(|&op| matches!(cmt.get_op(op), OpEnum::SeqWrite(_)))
+            .unwrap();
+        cmt.get_op_entry(write).and_modify(|op| op.set_attrs(vec![("latency".into(), IdAttr(0).into())]));
+        assert_eq!(lower_memories(&mut cmt, module, 1024).map_err(|error| error.op), Err(Some(write)));
+
+        // the name of the macro must not belong to another module
+        let (mut cmt, module, body, _) = create();
+        let other = cmt.add_region(Region::new(true));
+        let name = Some(StringAttr("hlmem_2x2xi4_r1_w1".into()));
+        cmt.add_op(HwModule::new(None, name, None, None, None, None, Some(other)).into());
+        let hlmem = utils::walk_region_ops(&cmt, body)
+            .into_iter()
+            .find(|&op| matches!(cmt.get_op(op), OpEnum::SeqHlmem(_)))
+            .unwrap();
+        let error = lower_memories(&mut cmt, module, 0).unwrap_err();
+        assert_eq!(error.op, Some(hlmem));
+        assert!(error.message.contains("not a memory macro"));
+    }
+
+    #[test]
+    pub fn location_test() {
+        let sv = |line| Some(irony::Loc::file_line_col("mem.sv", line, 1));
+        for max_register_bits in [1024, 0] {
+            let (mut cmt, module, body, rd) = create();
+            let ops = utils::walk_region_ops(&cmt, body);
+            for (line, &op) in ops.iter().enumerate() {
+                cmt.set_op_loc(op, sv(line as u32));
+            }
+            let read = ops.iter().position(|&op| matches!(cmt.get_op(op), OpEnum::SeqRead(_)));
+            let read_loc = sv(read.unwrap() as u32);
+            assert_eq!(lower_memories(&mut cmt, module, max_register_bits), Ok(()));
+
+            // the lowered ops are located at the memory and its ports
+            let ops = utils::walk_region_ops(&cmt, body);
+            let locs = ops.iter().map(|&op| cmt.get_op_loc(op).unwrap().to_string());
+            assert!(locs.into_iter().all(|loc| loc.contains("mem.sv") && !loc.contains(".rs")));
+            let def = cmt.get_defs(rd)[0];
+            match cmt.get_op(def) {
+                OpEnum::Assign(_) => assert_eq!(cmt.get_op_loc(def), read_loc),
+                _ => assert!(cmt.get_op_loc(def).unwrap().to_string().contains("mem.sv:3:1")),
+            }
+        }
+    }
+
+    #[test]
+    pub fn unnamed_test() {
+        let (mut cmt, module, body, _) = create();
+        let entities = cmt.get_entities_with_parent(None);
+        let clk = entities.into_iter().find(|&entity| cmt.print_entity(entity) == "%clk");
+        assert!(clk.is_some());
+        cmt.with_region(Some(body), |cmt| {
+            for _ in 0..2 {
+                let dtype = Some(DataTypeEnum::SeqHlmem(SeqHlmemType(
+                    Box::new(DataTypeEnum::UInt(4.into())),
+                    vec![2],
+                )));
+                let mem = cmt.add_entity(Wire::new(dtype, None, None, None).into());
+                cmt.add_op(SeqHlmem::new(Some(mem), clk, None).into());
+            }
+        });
+        assert_eq!(lower_memories(&mut cmt, module, 0), Ok(()));
+        let instances = utils::walk_region_ops(&cmt, body)
+            .into_iter()
+            .filter(|&op| matches!(cmt.get_op(op), OpEnum::HwInstance(_)))
+            .map(|op| cmt.print_op(op))
+            .collect::<Vec<_>>();
+        assert_eq!(instances.len(), 3);
+        for name in ["\"mem\"", "\"mem_1\"", "\"mem_2\""] {
+            assert_eq!(instances.iter().filter(|instance| instance.contains(name)).count(), 1);
+        }
+    }
+
+    #[test]
+    pub fn address_width_test() {
+        // an address without a bit width cannot be decoded
+        let (mut cmt, module, body, _) = create();
+        let named = |cmt: &CmtEnv, name: &str| {
+            cmt.get_entity_ids().into_iter().find(|&entity| cmt.print_entity(entity) == name).unwrap()
+        };
+        let [mem, re] = ["%mem", "%re"].map(|name| named(&cmt, name));
+        let other = cmt.add_entity(Wire::new(Some(DataTypeEnum::UInt(4.into())), None, None, None).into());
+        cmt.with_region(Some(body), |cmt| {
+            cmt.add_op(SeqRead::new(Some(other), Some(mem), Some(re), vec![mem, mem], Some(1usize.into())).into());
+        });
+        let error = lower_memories(&mut cmt, module, 1024).unwrap_err();
+        assert_eq!(error.message, "%mem has no bit width");
+    }
+
+    #[test]
+    pub fn no_body_test() {
+        let mut cmt = CmtEnv::default();
+        let ext = cmt.add_op(HwModule::new(None, Some(StringAttr("ext".into())), None, None, None, None, None).into());
+        let error = lower_memories(&mut cmt, ext, 0).unwrap_err();
+        assert_eq!((error.op, error.message.as_str()), (Some(ext), "the module has no body"));
+    }
+
+    #[test]
+    pub fn missing_port_test() {
+        let mut cmt = CmtEnv::default();
+        let read = cmt.add_op(SeqRead::new(None, None, None, vec![], None).into());
+        let write = cmt.add_op(SeqWrite::new(None, None, None, vec![], None).into());
+        assert!(cmt.print_op(read).contains("seq.read"));
+        assert!(cmt.print_op(write).contains("seq.write"));
+    }
+}
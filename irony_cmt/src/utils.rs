@@ -1,6 +1,6 @@
 use irony::{Entity, EntityId, Environ, Op};
 
-use crate::{ArrayAttr, AttributeEnum, DataTypeEnum, TypeAttr};
+use crate::{ArrayAttr, AttributeEnum, DataTypeEnum, HwInput, HwOutput, SymbolRefAttr, TypeAttr};
 
 pub fn extract_attrs_for_region<E, EntityT, F, G>(
     env: &E, region_id: irony::RegionId, op_name: &str, f: F, g: G,
@@ -38,6 +38,23 @@ where
     Some(ArrayAttr(input_types).into())
 }
 
+/// The input ports of a module body: its region arguments, or the defs of the `HwInput`
+/// op for bodies built without arguments.
+pub fn get_input_ports<E: irony::Environ>(env: &E, region_id: irony::RegionId) -> Vec<EntityId> {
+    let region = env.get_region(region_id);
+    if !region.arguments.is_empty() {
+        return region.get_arguments();
+    }
+    let input = HwInput::op_info().name.as_str();
+    region
+        .op_children
+        .iter()
+        .map(|&op_id| env.get_op(op_id))
+        .find(|op| op.get_op_name() == input)
+        .map(|op| op.get_defs().into_iter().flat_map(|(_, defs)| defs).flatten().collect())
+        .unwrap_or_default()
+}
+
 pub fn extract_input_names<E, EntityT>(
     env: &E, region_id: irony::RegionId,
 ) -> Option<AttributeEnum>
@@ -45,17 +62,11 @@ where
     E: irony::Environ<EntityT = EntityT>,
     EntityT: Entity<DataTypeT = DataTypeEnum, AttributeT = AttributeEnum>,
 {
-    extract_attrs_for_region(
-        env,
-        region_id,
-        "HwInput",
-        |op: &<E as Environ>::OpT| op.get_defs(),
-        |env: &E, x: &EntityId| {
-            let name = irony::utils::extract_vec(&env.get_entity(*x).get_attrs(), "name")
-                .unwrap();
-            name
-        },
-    )
+    let names = get_input_ports(env, region_id)
+        .into_iter()
+        .map(|x| irony::utils::extract_vec(&env.get_entity(x).get_attrs(), "name").unwrap())
+        .collect::<Vec<AttributeEnum>>();
+    Some(ArrayAttr(names).into())
 }
 
 pub fn extract_input_types<E, EntityT>(
@@ -65,17 +76,11 @@ where
     E: irony::Environ<EntityT = EntityT>,
     EntityT: Entity<DataTypeT = DataTypeEnum, AttributeT = AttributeEnum>,
 {
-    extract_attrs_for_region(
-        env,
-        region_id,
-        "HwInput",
-        |op: &<E as Environ>::OpT| op.get_defs(),
-        |env: &E, x: &EntityId| {
-            let input = env.get_entity(*x);
-            let type_attr = TypeAttr(input.get_dtype().unwrap());
-            AttributeEnum::TypeAttr(type_attr)
-        },
-    )
+    let types = get_input_ports(env, region_id)
+        .into_iter()
+        .map(|x| TypeAttr(env.get_entity(x).get_dtype().unwrap()).into())
+        .collect::<Vec<AttributeEnum>>();
+    Some(ArrayAttr(types).into())
 }
 
 pub fn extract_output_types<E, EntityT>(
@@ -88,7 +93,7 @@ where
     extract_attrs_for_region(
         env,
         region_id,
-        "HwOutput",
+        &HwOutput::op_info().name,
         |op: &<E as Environ>::OpT| op.get_uses(),
         |env: &E, x: &EntityId| {
             let input = env.get_entity(*x);
@@ -120,3 +125,77 @@ where
         .into(),
     )
 }
+
+/// Find the `HwModule` op `instance` refers to, by its `target` symbol resolved from the
+/// instance, or else by the module entity in `target_id`
+pub fn get_instance_target<E>(env: &E, instance: irony::OpId) -> Option<irony::OpId>
+where E: irony::Environ<EntityT = crate::EntityEnum, OpT = crate::OpEnum> {
+    let crate::OpEnum::HwInstance(inst) = env.get_op(instance) else {
+        return None;
+    };
+    match inst.target() {
+        Some(SymbolRefAttr(target)) => env.resolve_symbol(instance, target),
+        None => lookup_instance_target(env, &inst.get_attrs()),
+    }
+}
+
+/// Find the `HwModule` op an instance with `attrs` refers to, for the printer and the
+/// constraints which only see the attributes: a `target` symbol is looked up at the top
+/// level
+pub fn lookup_instance_target<E, EntityT>(
+    env: &E, attrs: &Vec<(String, AttributeEnum)>,
+) -> Option<irony::OpId>
+where
+    E: irony::Environ<EntityT = EntityT>,
+    EntityT: Entity<DataTypeT = DataTypeEnum, AttributeT = AttributeEnum>,
+{
+    if let Some(AttributeEnum::SymbolRefAttr(SymbolRefAttr(target))) =
+        irony::utils::extract_vec(attrs, "target")
+    {
+        return env.lookup_symbol(None, &target);
+    }
+    let Some(AttributeEnum::IdAttr(target_id)) =
+        irony::utils::extract_vec(attrs, "target_id")
+    else {
+        return None;
+    };
+    env.get_entity(EntityId(target_id.0)).get_defs(env).first().copied()
+}
+
+/// Add `op` located at `loc`, the location of what it is lowered from, rather than at the
+/// code lowering it
+pub fn add_op_at<E: irony::Environ>(
+    env: &mut E, op: E::OpT, loc: Option<irony::Loc>,
+) -> irony::OpId {
+    let op = env.add_op(op);
+    env.set_op_loc(op, loc);
+    op
+}
+
+/// fuse `loc` into the location of the ops defining `entity`, when it is shared by several
+/// lowered ops
+pub fn fuse_def_loc<E: irony::Environ>(env: &mut E, entity: EntityId, loc: Option<irony::Loc>) {
+    for op in env.get_defs(entity) {
+        let fused = irony::Loc::fuse([env.get_op_loc(op), loc.to_owned()]);
+        env.set_op_loc(op, fused);
+    }
+}
+
+/// find the op owning the region which `op` is placed in
+pub fn get_parent_op<E: irony::Environ>(env: &E, op: irony::OpId) -> Option<irony::OpId> {
+    env.get_op(op).get_parent().and_then(|region| env.get_region_use(region))
+}
+
+/// collect the ops in `region` and its nested regions, in pre-order
+pub fn walk_region_ops<E: irony::Environ>(env: &E, region: irony::RegionId) -> Vec<irony::OpId> {
+    let mut ops = vec![];
+    for op in env.get_region(region).get_op_children() {
+        ops.push(op);
+        for (_, regions) in env.get_op(op).get_regions() {
+            for region in regions {
+                ops.extend(walk_region_ops(env, region));
+            }
+        }
+    }
+    ops
+}
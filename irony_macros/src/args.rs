@@ -0,0 +1,127 @@
+use proc_macro2::{Span, TokenStream};
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Expr, Ident, LitStr, Token, Type};
+
+/// what an argument of the form `key = value` expects as its value
+#[derive(Clone, Copy)]
+pub(crate) enum Kind {
+    Type,
+    Ident,
+    Str,
+    Expr,
+    ExprList,
+    IdentList,
+}
+
+pub(crate) enum Value {
+    Type(Type),
+    Ident(Ident),
+    Str(LitStr),
+    Expr(Expr),
+    ExprList(Vec<Expr>),
+    IdentList(Vec<Ident>),
+}
+
+/// The `key = value, ...` arguments of an attribute macro, checked against the keys it accepts
+pub(crate) struct Args {
+    values: Vec<(Ident, Value)>,
+}
+
+impl Args {
+    pub(crate) fn parse(tokens: TokenStream, spec: &[(&str, Kind)]) -> syn::Result<Self> {
+        let parser = |input: ParseStream| {
+            let mut values: Vec<(Ident, Value)> = vec![];
+            while !input.is_empty() {
+                let key: Ident = input.parse()?;
+                let Some((_, kind)) = spec.iter().find(|(name, _)| key == name) else {
+                    let expected =
+                        spec.iter().map(|(name, _)| format!("`{}`", name)).collect::<Vec<_>>();
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("unknown argument `{}`, expected one of {}", key, expected.join(", ")),
+                    ));
+                };
+                if values.iter().any(|(name, _)| name == &key) {
+                    return Err(syn::Error::new(key.span(), format!("duplicate argument `{}`", key)));
+                }
+                input.parse::<Token![=]>()?;
+                let value = match kind {
+                    Kind::Type => Value::Type(input.parse()?),
+                    Kind::Ident => Value::Ident(input.parse()?),
+                    Kind::Str => Value::Str(input.parse()?),
+                    Kind::Expr => Value::Expr(input.parse()?),
+                    Kind::ExprList => {
+                        let content;
+                        bracketed!(content in input);
+                        let exprs = Punctuated::<Expr, Token![,]>::parse_terminated(&content)?;
+                        Value::ExprList(exprs.into_iter().collect())
+                    },
+                    Kind::IdentList => {
+                        let content;
+                        bracketed!(content in input);
+                        let idents = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+                        Value::IdentList(idents.into_iter().collect())
+                    },
+                };
+                values.push((key, value));
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+            }
+            Ok(Args { values })
+        };
+        parser.parse2(tokens)
+    }
+
+    fn take(&mut self, key: &str) -> Option<Value> {
+        let index = self.values.iter().position(|(name, _)| name == key)?;
+        Some(self.values.remove(index).1)
+    }
+
+    fn missing(key: &str) -> syn::Error {
+        syn::Error::new(Span::call_site(), format!("missing argument `{} = ...`", key))
+    }
+
+    pub(crate) fn ty(&mut self, key: &str) -> syn::Result<Type> {
+        match self.take(key) {
+            Some(Value::Type(ty)) => Ok(ty),
+            _ => Err(Self::missing(key)),
+        }
+    }
+
+    pub(crate) fn expr(&mut self, key: &str) -> syn::Result<Expr> {
+        match self.take(key) {
+            Some(Value::Expr(expr)) => Ok(expr),
+            _ => Err(Self::missing(key)),
+        }
+    }
+
+    pub(crate) fn opt_ident(&mut self, key: &str) -> Option<Ident> {
+        match self.take(key) {
+            Some(Value::Ident(ident)) => Some(ident),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn opt_str(&mut self, key: &str) -> Option<LitStr> {
+        match self.take(key) {
+            Some(Value::Str(lit)) => Some(lit),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn expr_list(&mut self, key: &str) -> Vec<Expr> {
+        match self.take(key) {
+            Some(Value::ExprList(exprs)) => exprs,
+            _ => vec![],
+        }
+    }
+
+    pub(crate) fn ident_list(&mut self, key: &str) -> Vec<Ident> {
+        match self.take(key) {
+            Some(Value::IdentList(idents)) => idents,
+            _ => vec![],
+        }
+    }
+}
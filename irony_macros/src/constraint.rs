@@ -0,0 +1,104 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Fields, ItemEnum, ItemStruct};
+
+use crate::args::{Args, Kind};
+use crate::util;
+
+pub(crate) fn expand_constraint(args: TokenStream, item: ItemStruct) -> syn::Result<TokenStream> {
+    let mut args = Args::parse(
+        args,
+        &[("data_type", Kind::Type), ("attr", Kind::Type), ("verify", Kind::Expr)],
+    )?;
+    let data_ty = args.ty("data_type")?;
+    let attr_ty = args.ty("attr")?;
+    let verify = args.expr("verify")?;
+
+    util::reject_generics(&item.generics, "a constraint")?;
+    if !matches!(item.fields, Fields::Unit) {
+        return Err(syn::Error::new_spanned(
+            &item.fields,
+            "a constraint is a unit struct, checked by its `verify` closure",
+        ));
+    }
+
+    let name = &item.ident;
+    let vis = &item.vis;
+    let struct_attrs = &item.attrs;
+
+    Ok(quote! {
+        #(#struct_attrs)*
+        #[derive(Default, Clone, Debug, PartialEq, Hash)]
+        #vis struct #name;
+
+        impl irony::ConstraintTrait for #name {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+
+            fn verify<'env, E, EntityT: irony::Entity>(
+                &self,
+                env: &'env E,
+                attrs: Vec<(String, Self::AttributeT)>,
+                uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                regions: Vec<(String, Vec<irony::RegionId>)>,
+            ) -> bool
+            where
+                E: irony::Environ<EntityT = EntityT>,
+                EntityT: irony::Entity<DataTypeT = Self::DataTypeT, AttributeT = Self::AttributeT>,
+            {
+                let f = #verify;
+                f(env, attrs, uses, defs, regions)
+            }
+        }
+    })
+}
+
+pub(crate) fn expand_constraint_enum(args: TokenStream, item: ItemEnum) -> syn::Result<TokenStream> {
+    let mut args = Args::parse(args, &[("data_type", Kind::Type), ("attr", Kind::Type)])?;
+    let data_ty = args.ty("data_type")?;
+    let attr_ty = args.ty("attr")?;
+
+    let (idents, tys): (Vec<_>, Vec<_>) = util::enum_variants(&item)?.into_iter().unzip();
+    let name = &item.ident;
+    let vis = &item.vis;
+    let enum_attrs = &item.attrs;
+
+    Ok(quote! {
+        #(#enum_attrs)*
+        #[derive(Clone, Debug, PartialEq, Hash)]
+        #vis enum #name {
+            #(#idents(#tys)),*
+        }
+
+        impl irony::ConstraintTrait for #name {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+
+            fn verify<'env, E, EntityT: irony::Entity>(
+                &self,
+                env: &'env E,
+                attrs: Vec<(String, Self::AttributeT)>,
+                uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                regions: Vec<(String, Vec<irony::RegionId>)>,
+            ) -> bool
+            where
+                E: irony::Environ<EntityT = EntityT>,
+                EntityT: irony::Entity<DataTypeT = Self::DataTypeT, AttributeT = Self::AttributeT>,
+            {
+                match self {
+                    #(Self::#idents(inner) => inner.verify(env, attrs, uses, defs, regions),)*
+                }
+            }
+        }
+
+        #(
+            impl From<#tys> for #name {
+                fn from(constraint: #tys) -> Self {
+                    Self::#idents(constraint)
+                }
+            }
+        )*
+    })
+}
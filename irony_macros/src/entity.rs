@@ -0,0 +1,286 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Fields, ItemEnum, ItemStruct};
+
+use crate::args::{Args, Kind};
+use crate::util;
+
+/// fields generated for every entity, which the spec cannot declare again
+const RESERVED: &[&str] = &["id", "parent", "dtype"];
+
+pub(crate) fn expand_entity(args: TokenStream, item: ItemStruct) -> syn::Result<TokenStream> {
+    let mut args = Args::parse(args, &[("data_type", Kind::Type), ("attr", Kind::Type)])?;
+    let data_ty = args.ty("data_type")?;
+    let attr_ty = args.ty("attr")?;
+
+    util::reject_generics(&item.generics, "an entity")?;
+    let fields = match &item.fields {
+        Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+        Fields::Unit => vec![],
+        Fields::Unnamed(_) => {
+            return Err(syn::Error::new_spanned(&item.fields, "an entity must have named fields"))
+        },
+    };
+    for field in fields.iter() {
+        let name = field.ident.as_ref().expect("named field");
+        if RESERVED.contains(&name.to_string().as_str()) {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!("`{}` is generated for every entity and cannot be declared", name),
+            ));
+        }
+    }
+
+    let name = &item.ident;
+    let name_str = name.to_string();
+    let vis = &item.vis;
+    let struct_attrs = &item.attrs;
+    let attrs = fields.iter().map(|f| &f.attrs).collect::<Vec<_>>();
+    let names = fields.iter().map(|f| f.ident.to_owned().unwrap()).collect::<Vec<_>>();
+    let keys = names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+    let setters = names.iter().map(|name| format_ident!("set_{}", name)).collect::<Vec<_>>();
+    let tys = fields
+        .iter()
+        .map(|f| util::unwrap_generic(&f.ty, "Option").unwrap_or(&f.ty))
+        .collect::<Vec<_>>();
+
+    Ok(quote! {
+        #(#struct_attrs)*
+        #[derive(Clone, Debug, PartialEq, Hash)]
+        #vis struct #name {
+            pub id: usize,
+            pub parent: Option<irony::RegionId>,
+            pub dtype: Option<#data_ty>,
+            #(#(#attrs)* pub #names: Option<#tys>,)*
+        }
+
+        impl irony::Entity for #name {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+
+            fn get_defs<E: irony::Environ>(&self, env: &E) -> Vec<irony::OpId> {
+                env.get_defs(self.as_id())
+            }
+
+            fn get_uses<E: irony::Environ>(&self, env: &E) -> Vec<irony::OpId> {
+                env.get_uses(self.as_id())
+            }
+
+            fn get_dtype(&self) -> Option<Self::DataTypeT> {
+                self.dtype.to_owned()
+            }
+
+            fn set_dtype(&mut self, dtype: Option<Self::DataTypeT>) {
+                self.dtype = dtype
+            }
+
+            fn get_entity_name(&self) -> String {
+                String::from(#name_str)
+            }
+
+            fn from_parts(
+                name: &str, dtype: Option<Self::DataTypeT>, attrs: Vec<(String, Self::AttributeT)>,
+            ) -> Option<Self> {
+                if name != #name_str {
+                    return None;
+                }
+                let mut entity = Self::const_new(dtype);
+                entity.set_attrs(attrs);
+                Some(entity)
+            }
+
+            fn as_id(&self) -> irony::EntityId {
+                irony::EntityId(self.id)
+            }
+
+            fn get_parent(&self) -> Option<irony::RegionId> {
+                self.parent
+            }
+
+            fn set_parent(&mut self, parent: Option<irony::RegionId>) {
+                self.parent = parent
+            }
+
+            fn get_attrs(&self) -> Vec<(String, Self::AttributeT)> {
+                let mut attrs = vec![];
+                #(
+                    if let Some(attr) = self.#names.to_owned() {
+                        attrs.push((String::from(#keys), attr.into()))
+                    }
+                )*
+                attrs
+            }
+
+            #[allow(unused_variables)]
+            fn set_attrs(&mut self, attrs: Vec<(String, Self::AttributeT)>) {
+                #(
+                    if let Some((_, attr)) = attrs.iter().find(|(name, _)| name == #keys) {
+                        self.#names = Some(attr.to_owned().into())
+                    }
+                )*
+            }
+        }
+
+        impl irony::Id for #name {
+            fn id(&self) -> usize {
+                self.id
+            }
+
+            fn set_id(&mut self, id: usize) {
+                self.id = id
+            }
+        }
+
+        impl #name {
+            pub const fn const_new(dtype: Option<#data_ty>) -> Self {
+                Self { id: 0, dtype, parent: None, #(#names: None,)* }
+            }
+
+            pub fn new(dtype: Option<#data_ty>, #(#names: Option<#tys>),*) -> Self {
+                Self { id: 0, dtype, parent: None, #(#names,)* }
+            }
+
+            #(
+                pub fn #names(&self) -> Option<&#tys> {
+                    self.#names.as_ref()
+                }
+
+                pub fn #setters(&mut self, #names: Option<#tys>) {
+                    self.#names = #names;
+                }
+            )*
+        }
+    })
+}
+
+pub(crate) fn expand_entity_enum(args: TokenStream, item: ItemEnum) -> syn::Result<TokenStream> {
+    let mut args = Args::parse(args, &[("data_type", Kind::Type), ("attr", Kind::Type)])?;
+    let data_ty = args.ty("data_type")?;
+    let attr_ty = args.ty("attr")?;
+
+    let variants = util::enum_variants(&item)?;
+    let names = variants.iter().map(|(_, ty)| util::type_name(ty)).collect::<syn::Result<Vec<_>>>()?;
+    let (idents, tys): (Vec<_>, Vec<_>) = variants.into_iter().unzip();
+
+    let name = &item.ident;
+    let name_str = name.to_string();
+    let vis = &item.vis;
+    let enum_attrs = &item.attrs;
+
+    let forward = |call: TokenStream| {
+        quote! {
+            match self {
+                #(Self::#idents(inner) => inner.#call,)*
+            }
+        }
+    };
+    let get_defs = forward(quote!(get_defs(env)));
+    let get_uses = forward(quote!(get_uses(env)));
+    let get_dtype = forward(quote!(get_dtype()));
+    let set_dtype = forward(quote!(set_dtype(dtype)));
+    let get_entity_name = forward(quote!(get_entity_name()));
+    let as_id = forward(quote!(as_id()));
+    let get_parent = forward(quote!(get_parent()));
+    let set_parent = forward(quote!(set_parent(parent)));
+    let get_attrs = forward(quote!(get_attrs()));
+    let set_attrs = forward(quote!(set_attrs(attrs)));
+    let id = forward(quote!(id()));
+    let set_id = forward(quote!(set_id(id)));
+
+    let into_variant = idents.iter().zip(tys.iter()).map(|(ident, ty)| {
+        let message = format!("expected a `{}` of `{}`", ident, name_str);
+        quote! {
+            impl From<#name> for #ty {
+                fn from(entity: #name) -> Self {
+                    match entity {
+                        #name::#ident(inner) => inner,
+                        #[allow(unreachable_patterns)]
+                        _ => panic!(#message),
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#enum_attrs)*
+        #[derive(Clone, Debug, PartialEq, Hash)]
+        #vis enum #name {
+            #(#idents(#tys)),*
+        }
+
+        impl irony::Entity for #name {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+
+            fn get_defs<E: irony::Environ>(&self, env: &E) -> Vec<irony::OpId> {
+                #get_defs
+            }
+
+            fn get_uses<E: irony::Environ>(&self, env: &E) -> Vec<irony::OpId> {
+                #get_uses
+            }
+
+            fn get_dtype(&self) -> Option<Self::DataTypeT> {
+                #get_dtype
+            }
+
+            fn set_dtype(&mut self, dtype: Option<Self::DataTypeT>) {
+                #set_dtype
+            }
+
+            fn get_entity_name(&self) -> String {
+                #get_entity_name
+            }
+
+            fn from_parts(
+                name: &str, dtype: Option<Self::DataTypeT>, attrs: Vec<(String, Self::AttributeT)>,
+            ) -> Option<Self> {
+                match name {
+                    #(#names => <#tys as irony::Entity>::from_parts(name, dtype, attrs).map(Self::#idents),)*
+                    _ => None,
+                }
+            }
+
+            fn as_id(&self) -> irony::EntityId {
+                #as_id
+            }
+
+            fn get_parent(&self) -> Option<irony::RegionId> {
+                #get_parent
+            }
+
+            fn set_parent(&mut self, parent: Option<irony::RegionId>) {
+                #set_parent
+            }
+
+            fn get_attrs(&self) -> Vec<(String, Self::AttributeT)> {
+                #get_attrs
+            }
+
+            fn set_attrs(&mut self, attrs: Vec<(String, Self::AttributeT)>) {
+                #set_attrs
+            }
+        }
+
+        impl irony::Id for #name {
+            fn id(&self) -> usize {
+                #id
+            }
+
+            fn set_id(&mut self, id: usize) {
+                #set_id
+            }
+        }
+
+        #(
+            impl From<#tys> for #name {
+                fn from(entity: #tys) -> Self {
+                    Self::#idents(entity)
+                }
+            }
+        )*
+
+        #(#into_variant)*
+    })
+}
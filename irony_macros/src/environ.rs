@@ -0,0 +1,78 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Fields, ItemStruct};
+
+use crate::args::{Args, Kind};
+use crate::util;
+
+/// fields generated for every environment, which the spec cannot declare again
+const RESERVED: &[&str] = &[
+    "op_table",
+    "entity_table",
+    "region_table",
+    "parent_stack",
+    "pass_manager",
+    "hasher",
+    "op_hash_table",
+    "diagnostics",
+];
+
+/// The tables and the `Environ` impl are the ones of `irony::environ_def!`, which needs no
+/// unstable feature, so the spec is checked here and then handed over to it.
+pub(crate) fn expand_environ(args: TokenStream, item: ItemStruct) -> syn::Result<TokenStream> {
+    let mut args = Args::parse(
+        args,
+        &[
+            ("data_type", Kind::Type),
+            ("attr", Kind::Type),
+            ("entity", Kind::Type),
+            ("op", Kind::Type),
+            ("constraint", Kind::Type),
+            ("pm", Kind::Type),
+        ],
+    )?;
+    let data_ty = args.ty("data_type")?;
+    let attr_ty = args.ty("attr")?;
+    let entity_ty = args.ty("entity")?;
+    let op_ty = args.ty("op")?;
+    let constraint_ty = args.ty("constraint")?;
+    let pm_ty = args.ty("pm")?;
+
+    util::reject_generics(&item.generics, "an environment")?;
+    if let Some(attr) = item.attrs.first() {
+        return Err(syn::Error::new_spanned(attr, "an environment takes no other attributes"));
+    }
+    let fields = match &item.fields {
+        Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+        Fields::Unit => vec![],
+        Fields::Unnamed(_) => {
+            return Err(syn::Error::new_spanned(&item.fields, "an environment must have named fields"))
+        },
+    };
+    for field in fields.iter() {
+        let name = field.ident.as_ref().expect("named field");
+        if RESERVED.contains(&name.to_string().as_str()) {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!("`{}` is generated for every environment and cannot be declared", name),
+            ));
+        }
+        if let Some(attr) = field.attrs.first() {
+            return Err(syn::Error::new_spanned(attr, "fields of an environment take no attributes"));
+        }
+    }
+
+    let name = &item.ident;
+    let vis = fields.iter().map(|f| &f.vis);
+    let names = fields.iter().map(|f| &f.ident);
+    let tys = fields.iter().map(|f| &f.ty);
+
+    Ok(quote! {
+        irony::environ_def! {
+            [data_type = #data_ty, attr = #attr_ty, entity = #entity_ty, op = #op_ty, constraint = #constraint_ty, pm = #pm_ty]
+            struct #name {
+                #(#vis #names: #tys),*
+            }
+        }
+    })
+}
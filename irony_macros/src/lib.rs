@@ -0,0 +1,96 @@
+//! Attribute macros defining the ops, entities, constraints and environment of a dialect.
+//!
+//! They generate the same items and trait impls as `irony::op_def!`, `irony::entity_def!`,
+//! `irony::constraint_def!` and `irony::environ_def!`, but check the spec first and report
+//! mistakes at the offending token, and they need no unstable feature. `irony::op_def!`
+//! hands every op over to `op`, so the two cannot disagree on what an op is.
+//!
+//! ```ignore
+//! #[irony_macros::op(data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum,
+//!     constraints = [SameType::new().into()], print = |env: &E, _, _, _, _| { .. })]
+//! pub struct CombAdd {
+//!     #[def]
+//!     lhs: EntityId,
+//!     #[uses]
+//!     operands: Vec<EntityId>,
+//!     #[attr(hash)]
+//!     name: StringAttr,
+//! }
+//!
+//! #[irony_macros::op_enum(data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum)]
+//! pub enum OpEnum {
+//!     CombAdd(CombAdd),
+//! }
+//! ```
+
+mod args;
+mod constraint;
+mod entity;
+mod environ;
+mod op;
+mod util;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, ItemEnum, ItemStruct};
+
+fn finish(result: syn::Result<proc_macro2::TokenStream>) -> TokenStream {
+    result.unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Define an op from a struct whose fields are marked `#[def]`, `#[uses]`, `#[attr]`
+/// (`#[attr(hash)]` to take part in the op hash) or `#[region]`.
+///
+/// Defs and uses are `EntityId`, regions are `RegionId`, or a `Vec` of them when variadic;
+/// an attribute field has the type of the attribute. Takes `data_type`, `attr`,
+/// `constraint` and `print`, and optionally `constraints = [..]`, `symbol = field`,
+/// `traits = [..]` naming variants of `irony::OpTrait`, and `dialect = "comb"` with
+/// `name = "add"` to register the op as `comb.add`.
+#[proc_macro_attribute]
+pub fn op(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemStruct);
+    finish(op::expand_op(args.into(), item))
+}
+
+/// Collect ops into the enum the environment stores, one `Name(Name)` variant per op.
+#[proc_macro_attribute]
+pub fn op_enum(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemEnum);
+    finish(op::expand_op_enum(args.into(), item))
+}
+
+/// Define an entity from a struct whose fields are its attributes. Takes `data_type` and `attr`.
+#[proc_macro_attribute]
+pub fn entity(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemStruct);
+    finish(entity::expand_entity(args.into(), item))
+}
+
+/// Collect entities into the enum the environment stores, one `Name(Name)` variant per entity.
+#[proc_macro_attribute]
+pub fn entity_enum(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemEnum);
+    finish(entity::expand_entity_enum(args.into(), item))
+}
+
+/// Define a constraint from a unit struct and its `verify` closure. Takes `data_type`,
+/// `attr` and `verify`.
+#[proc_macro_attribute]
+pub fn constraint(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemStruct);
+    finish(constraint::expand_constraint(args.into(), item))
+}
+
+/// Collect constraints into the enum the ops hold, one `Name(Type)` variant per constraint.
+#[proc_macro_attribute]
+pub fn constraint_enum(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemEnum);
+    finish(constraint::expand_constraint_enum(args.into(), item))
+}
+
+/// Define the environment of a dialect, with extra fields if any. Takes `data_type`,
+/// `attr`, `entity`, `op`, `constraint` and `pm`.
+#[proc_macro_attribute]
+pub fn environ(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemStruct);
+    finish(environ::expand_environ(args.into(), item))
+}
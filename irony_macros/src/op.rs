@@ -0,0 +1,787 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Attribute, Field, Fields, Ident, ItemEnum, ItemStruct, LitStr, Meta, Type};
+
+use crate::args::{Args, Kind};
+use crate::util;
+
+/// fields generated for every op, which the spec cannot declare again
+const RESERVED: &[&str] = &["id", "op_name", "constraints", "parent", "loc", "printer"];
+
+/// the variants of `irony::OpTrait`
+const OP_TRAITS: &[&str] = &[
+    "Pure",
+    "Commutative",
+    "Terminator",
+    "IsolatedFromAbove",
+    "HasSideEffects",
+    "SameOperandsAndResultType",
+    "Sequential",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Def,
+    Use,
+    Attr,
+    Region,
+}
+
+struct OpField {
+    attrs: Vec<Attribute>,
+    name: Ident,
+    role: Role,
+    variadic: bool,
+    /// the attribute type, for `#[attr]` fields
+    ty: Type,
+    hash: bool,
+}
+
+impl OpField {
+    fn key(&self) -> String { self.name.to_string() }
+
+    fn stored_ty(&self) -> TokenStream {
+        let ty = &self.ty;
+        match (self.role, self.variadic) {
+            (Role::Def | Role::Use, false) => quote!(Option<irony::EntityId>),
+            (Role::Def | Role::Use, true) => quote!(Vec<irony::EntityId>),
+            (Role::Region, false) => quote!(Option<irony::RegionId>),
+            (Role::Region, true) => quote!(Vec<irony::RegionId>),
+            (Role::Attr, _) => quote!(Option<#ty>),
+        }
+    }
+}
+
+/// whether an id field holds a single id or a variadic list of them
+fn arity(ty: &Type, id: &str, role: &str) -> syn::Result<bool> {
+    if util::is_named(ty, id) || util::unwrap_generic(ty, "Option").is_some_and(|x| util::is_named(x, id)) {
+        return Ok(false);
+    }
+    if util::unwrap_generic(ty, "Vec").is_some_and(|x| util::is_named(x, id)) {
+        return Ok(true);
+    }
+    Err(syn::Error::new_spanned(
+        ty,
+        format!("a {} must be `{1}`, `Option<{1}>` or `Vec<{1}>`", role, id),
+    ))
+}
+
+fn parse_field(field: &Field) -> syn::Result<OpField> {
+    let name = field.ident.to_owned().expect("named field");
+    if RESERVED.contains(&name.to_string().as_str()) {
+        return Err(syn::Error::new_spanned(
+            &name,
+            format!("`{}` is generated for every op and cannot be declared", name),
+        ));
+    }
+
+    let mut role = None;
+    let mut hash = false;
+    let mut attrs = vec![];
+    for attr in field.attrs.iter() {
+        let this = if attr.path().is_ident("def") {
+            Role::Def
+        } else if attr.path().is_ident("uses") {
+            Role::Use
+        } else if attr.path().is_ident("attr") {
+            Role::Attr
+        } else if attr.path().is_ident("region") {
+            Role::Region
+        } else {
+            attrs.push(attr.to_owned());
+            continue;
+        };
+        if role.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "a field takes only one of `#[def]`, `#[uses]`, `#[attr]` and `#[region]`",
+            ));
+        }
+        match (&attr.meta, this) {
+            (Meta::Path(_), _) => {},
+            (Meta::List(list), Role::Attr) => list.parse_nested_meta(|meta| {
+                if meta.path.is_ident("hash") {
+                    hash = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `hash`"))
+                }
+            })?,
+            (_, Role::Attr) => {
+                return Err(syn::Error::new_spanned(attr, "expected `#[attr]` or `#[attr(hash)]`"))
+            },
+            _ => {
+                attr.meta.require_path_only()?;
+            },
+        }
+        role = Some(this);
+    }
+
+    let Some(role) = role else {
+        return Err(syn::Error::new_spanned(
+            field,
+            format!("field `{}` needs one of `#[def]`, `#[uses]`, `#[attr]` or `#[region]`", name),
+        ));
+    };
+    let (variadic, ty) = match role {
+        Role::Def => (arity(&field.ty, "EntityId", "def")?, field.ty.to_owned()),
+        Role::Use => (arity(&field.ty, "EntityId", "use")?, field.ty.to_owned()),
+        Role::Region => (arity(&field.ty, "RegionId", "region")?, field.ty.to_owned()),
+        Role::Attr => {
+            (false, util::unwrap_generic(&field.ty, "Option").unwrap_or(&field.ty).to_owned())
+        },
+    };
+    Ok(OpField { attrs, name, role, variadic, ty, hash })
+}
+
+fn lit_option(lit: Option<LitStr>) -> TokenStream {
+    match lit {
+        Some(lit) => quote!(Some(#lit)),
+        None => quote!(None),
+    }
+}
+
+pub(crate) fn expand_op(args: TokenStream, item: ItemStruct) -> syn::Result<TokenStream> {
+    let mut args = Args::parse(
+        args,
+        &[
+            ("data_type", Kind::Type),
+            ("attr", Kind::Type),
+            ("constraint", Kind::Type),
+            ("constraints", Kind::ExprList),
+            ("symbol", Kind::Ident),
+            ("traits", Kind::IdentList),
+            ("dialect", Kind::Str),
+            ("name", Kind::Str),
+            ("print", Kind::Expr),
+        ],
+    )?;
+    let data_ty = args.ty("data_type")?;
+    let attr_ty = args.ty("attr")?;
+    let constraint_ty = args.ty("constraint")?;
+    let constraints = args.expr_list("constraints");
+    let symbol = args.opt_ident("symbol");
+    let traits = args.ident_list("traits");
+    let dialect = lit_option(args.opt_str("dialect"));
+    let mnemonic = lit_option(args.opt_str("name"));
+    let print = args.expr("print")?;
+    for op_trait in traits.iter() {
+        if !OP_TRAITS.contains(&op_trait.to_string().as_str()) {
+            return Err(syn::Error::new_spanned(
+                op_trait,
+                format!("unknown op trait `{}`, expected one of {}", op_trait, OP_TRAITS.join(", ")),
+            ));
+        }
+    }
+
+    util::reject_generics(&item.generics, "an op")?;
+    let fields = match &item.fields {
+        Fields::Named(fields) => fields.named.iter().map(parse_field).collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unit => vec![],
+        Fields::Unnamed(_) => {
+            return Err(syn::Error::new_spanned(&item.fields, "an op must have named fields"))
+        },
+    };
+    if let Some(symbol) = symbol.as_ref() {
+        if !fields.iter().any(|f| f.role == Role::Attr && &f.name == symbol) {
+            return Err(syn::Error::new_spanned(
+                symbol,
+                format!("the symbol `{}` must name an `#[attr]` field", symbol),
+            ));
+        }
+    }
+
+    let name = &item.ident;
+    let name_str = name.to_string();
+    let vis = &item.vis;
+    let struct_attrs = &item.attrs;
+    let printer = format_ident!("{}Printer", name);
+    let of = |role: Role| fields.iter().filter(move |f| f.role == role);
+
+    let field_decls = fields.iter().map(|f| {
+        let (attrs, name, ty) = (&f.attrs, &f.name, f.stored_ty());
+        quote!(#(#attrs)* pub #name: #ty,)
+    });
+
+    let get_ids = |role: Role| {
+        of(role).map(|f| {
+            let (name, key) = (&f.name, f.key());
+            if f.variadic {
+                quote!((String::from(#key), self.#name.iter().map(|&x| Some(x)).collect()))
+            } else {
+                quote!((String::from(#key), vec![self.#name]))
+            }
+        })
+    };
+    let set_ids = |role: Role, parts: Ident| {
+        of(role)
+            .map(|f| {
+                let (name, key) = (&f.name, f.key());
+                if f.variadic {
+                    quote! {
+                        self.#name = #parts.iter().find(|(k, _)| k == #key)
+                            .map(|(_, v)| v.iter().flatten().copied().collect()).unwrap_or_default();
+                    }
+                } else {
+                    quote! {
+                        self.#name = #parts.iter().find(|(k, _)| k == #key)
+                            .and_then(|(_, v)| v.first().copied().flatten());
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let get_defs = get_ids(Role::Def);
+    let get_uses = get_ids(Role::Use);
+    let set_defs = set_ids(Role::Def, format_ident!("defs"));
+    let set_uses = set_ids(Role::Use, format_ident!("uses"));
+
+    let get_attrs = of(Role::Attr).map(|f| {
+        let (name, key) = (&f.name, f.key());
+        quote! {
+            if let Some(attr) = self.#name.to_owned() {
+                (String::from(#key), attr.into())
+            } else {
+                (String::from("none"), <#attr_ty>::None)
+            }
+        }
+    });
+    let set_attrs = of(Role::Attr).map(|f| {
+        let (name, key) = (&f.name, f.key());
+        quote! {
+            self.#name = attrs.iter().find(|(k, _)| k == #key).map(|(_, v)| v.to_owned().into());
+        }
+    });
+
+    let get_regions = of(Role::Region).map(|f| {
+        let (name, key) = (&f.name, f.key());
+        if f.variadic {
+            quote!((String::from(#key), self.#name.to_owned()))
+        } else {
+            let message = format!("region `{}` of `{}` is not set", key, name_str);
+            quote!((String::from(#key), vec![self.#name.expect(#message)]))
+        }
+    });
+    let set_regions = of(Role::Region).map(|f| {
+        let (name, key) = (&f.name, f.key());
+        if f.variadic {
+            quote! {
+                self.#name = regions.iter().find(|(k, _)| k == #key)
+                    .map(|(_, v)| v.to_owned()).unwrap_or_default();
+            }
+        } else {
+            quote! {
+                self.#name = regions.iter().find(|(k, _)| k == #key).and_then(|(_, v)| v.first().copied());
+            }
+        }
+    });
+
+    let (get_symbol, set_symbol) = match symbol.as_ref() {
+        Some(symbol) => (
+            quote!(self.#symbol.as_ref().map(|name| irony::Symbol::new(name.to_string()))),
+            quote!(self.#symbol = Some(symbol.get_name().to_owned().into());),
+        ),
+        None => {
+            let message = format!("{} does not declare a symbol", name_str);
+            (quote!(None), quote!(let _ = symbol; panic!(#message);))
+        },
+    };
+
+    let hash_fields = fields.iter().map(|f| {
+        let name = &f.name;
+        match (f.role, f.variadic) {
+            (Role::Def | Role::Use, false) => quote! {
+                if let Some(entity) = self.#name {
+                    reducer.reduce_entity(entity).hash(&mut *env.get_hasher());
+                }
+            },
+            (Role::Def | Role::Use, true) => quote! {
+                for &entity in self.#name.iter() {
+                    reducer.reduce_entity(entity).hash(&mut *env.get_hasher());
+                }
+            },
+            (Role::Attr, _) if f.hash => quote!(self.#name.hash(&mut *env.get_hasher());),
+            (Role::Attr, _) => quote!(),
+            (Role::Region, false) => quote! {
+                if let Some(region) = self.#name {
+                    env.hash_region(region, reducer);
+                }
+            },
+            (Role::Region, true) => quote! {
+                for &region in self.#name.iter() {
+                    env.hash_region(region, reducer);
+                }
+            },
+        }
+    });
+
+    let field_names = fields.iter().map(|f| &f.name).collect::<Vec<_>>();
+    let new_params = fields.iter().map(|f| {
+        let (name, ty) = (&f.name, f.stored_ty());
+        quote!(#name: #ty)
+    });
+    let empty_fields = fields.iter().map(|f| {
+        let name = &f.name;
+        if f.variadic {
+            quote!(#name: vec![])
+        } else {
+            quote!(#name: None)
+        }
+    });
+
+    let accessors = fields.iter().map(|f| {
+        let (name, stored) = (&f.name, f.stored_ty());
+        let setter = format_ident!("set_{}", name);
+        let getter = match (f.role, f.variadic) {
+            (Role::Def | Role::Use, false) => quote!(pub fn #name(&self) -> Option<irony::EntityId> { self.#name }),
+            (Role::Def | Role::Use, true) => quote!(pub fn #name(&self) -> &[irony::EntityId] { &self.#name }),
+            (Role::Region, false) => quote!(pub fn #name(&self) -> Option<irony::RegionId> { self.#name }),
+            (Role::Region, true) => quote!(pub fn #name(&self) -> &[irony::RegionId] { &self.#name }),
+            (Role::Attr, _) => {
+                let ty = &f.ty;
+                quote!(pub fn #name(&self) -> Option<&#ty> { self.#name.as_ref() })
+            },
+        };
+        if f.role == Role::Region {
+            return getter;
+        }
+        quote! {
+            #getter
+
+            pub fn #setter(&mut self, #name: #stored) {
+                self.#name = #name;
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#struct_attrs)*
+        #[derive(PartialEq, Debug, Clone)]
+        #vis struct #name {
+            pub id: usize,
+            pub op_name: String,
+            #(#field_decls)*
+            pub constraints: Vec<#constraint_ty>,
+            pub parent: Option<irony::RegionId>,
+            pub loc: Option<irony::Loc>,
+            pub printer: #printer,
+        }
+
+        impl irony::Id for #name {
+            fn id(&self) -> usize {
+                self.id
+            }
+
+            fn set_id(&mut self, id: usize) {
+                self.id = id;
+            }
+        }
+
+        #[allow(unused_variables)]
+        impl irony::Op for #name {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+            type ConstraintT = #constraint_ty;
+            type PrinterT = #printer;
+
+            fn get_defs(&self) -> Vec<(String, Vec<Option<irony::EntityId>>)> {
+                vec![#(#get_defs),*]
+            }
+
+            fn set_defs(&mut self, defs: Vec<(String, Vec<Option<irony::EntityId>>)>) {
+                #(#set_defs)*
+            }
+
+            fn get_uses(&self) -> Vec<(String, Vec<Option<irony::EntityId>>)> {
+                vec![#(#get_uses),*]
+            }
+
+            fn set_uses(&mut self, uses: Vec<(String, Vec<Option<irony::EntityId>>)>) {
+                #(#set_uses)*
+            }
+
+            fn get_attrs(&self) -> Vec<(String, Self::AttributeT)> {
+                vec![#(#get_attrs),*]
+            }
+
+            fn set_attrs(&mut self, attrs: Vec<(String, Self::AttributeT)>) {
+                #(#set_attrs)*
+            }
+
+            fn get_constraints(&self) -> Vec<Self::ConstraintT> {
+                let mut constraints = self.constraints.clone();
+                #(constraints.extend(irony::trait_constraint!(#traits));)*
+                constraints
+            }
+
+            fn uses(&self, entity: irony::EntityId) -> bool {
+                self.get_uses().iter().flat_map(|(_, v)| v.iter()).any(|&x| x == Some(entity))
+            }
+
+            fn defs(&self, entity: irony::EntityId) -> bool {
+                self.get_defs().iter().flat_map(|(_, v)| v.iter()).any(|&x| x == Some(entity))
+            }
+
+            fn get_parent(&self) -> Option<irony::RegionId> {
+                self.parent
+            }
+
+            fn set_parent(&mut self, parent: Option<irony::RegionId>) {
+                self.parent = parent;
+            }
+
+            fn get_regions(&self) -> Vec<(String, Vec<irony::RegionId>)> {
+                vec![#(#get_regions),*]
+            }
+
+            fn set_regions(&mut self, regions: Vec<(String, Vec<irony::RegionId>)>) {
+                #(#set_regions)*
+            }
+
+            fn use_region(&self, region: irony::RegionId) -> bool {
+                self.get_regions().iter().any(|(_, v)| v.contains(&region))
+            }
+
+            fn get_op_name(&self) -> String {
+                self.op_name.clone()
+            }
+
+            fn get_symbol(&self) -> Option<irony::Symbol> {
+                #get_symbol
+            }
+
+            fn set_symbol(&mut self, symbol: irony::Symbol) {
+                #set_symbol
+            }
+
+            fn get_loc(&self) -> Option<irony::Loc> {
+                self.loc.to_owned()
+            }
+
+            fn set_loc(&mut self, loc: Option<irony::Loc>) {
+                self.loc = loc;
+            }
+
+            fn get_traits(&self) -> Vec<irony::OpTrait> {
+                vec![#(irony::OpTrait::#traits),*]
+            }
+
+            fn get_registered_ops() -> Vec<irony::OpInfo> {
+                vec![Self::op_info().clone()]
+            }
+
+            fn get_dialect(&self) -> Option<&'static str> {
+                Self::op_info().dialect
+            }
+
+            fn from_parts(
+                name: &str,
+                defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                attrs: Vec<(String, Self::AttributeT)>,
+                regions: Vec<(String, Vec<irony::RegionId>)>,
+            ) -> Option<Self> {
+                if name != Self::op_info().name {
+                    return None;
+                }
+                let mut op = Self {
+                    id: 0,
+                    op_name: Self::op_info().name.clone(),
+                    #(#empty_fields,)*
+                    constraints: vec![#(#constraints),*],
+                    parent: None,
+                    loc: None,
+                    printer: #printer,
+                };
+                op.set_defs(defs);
+                op.set_uses(uses);
+                op.set_attrs(attrs);
+                op.set_regions(regions);
+                Some(op)
+            }
+
+            fn get_printer(&self) -> Self::PrinterT {
+                self.printer.clone()
+            }
+
+            fn hash_with_reducer(&self, env: &impl irony::Environ, reducer: &mut impl irony::ReducerTrait) {
+                use std::hash::Hash;
+                self.op_name.hash(&mut *env.get_hasher());
+                #(#hash_fields)*
+            }
+        }
+
+        impl #name {
+            /// the dialect and qualified name of the op
+            pub fn op_info() -> &'static irony::OpInfo {
+                static INFO: std::sync::OnceLock<irony::OpInfo> = std::sync::OnceLock::new();
+                INFO.get_or_init(|| irony::OpInfo::new(#dialect, #name_str, #mnemonic))
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            pub fn new(#(#new_params),*) -> Self {
+                Self {
+                    id: 0,
+                    op_name: Self::op_info().name.clone(),
+                    #(#field_names,)*
+                    constraints: vec![#(#constraints),*],
+                    parent: None,
+                    loc: None,
+                    printer: #printer,
+                }
+            }
+
+            #(#accessors)*
+        }
+
+        #[derive(Clone, Debug, PartialEq, Hash)]
+        #vis struct #printer;
+
+        impl irony::OpPrinterTrait for #printer {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+
+            fn print<'env, E, EntityT: irony::Entity>(
+                &self,
+                env: &'env E,
+                attrs: Vec<(String, Self::AttributeT)>,
+                uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                regions: Vec<(String, Vec<irony::RegionId>)>,
+            ) -> String
+            where
+                E: irony::Environ<EntityT = EntityT, AttributeT = Self::AttributeT>,
+                EntityT: irony::Entity<DataTypeT = Self::DataTypeT, AttributeT = Self::AttributeT>,
+            {
+                let f = #print;
+                f(env, attrs, uses, defs, regions)
+            }
+        }
+    })
+}
+
+pub(crate) fn expand_op_enum(args: TokenStream, item: ItemEnum) -> syn::Result<TokenStream> {
+    let mut args = Args::parse(
+        args,
+        &[("data_type", Kind::Type), ("attr", Kind::Type), ("constraint", Kind::Type)],
+    )?;
+    let data_ty = args.ty("data_type")?;
+    let attr_ty = args.ty("attr")?;
+    let constraint_ty = args.ty("constraint")?;
+
+    let variants = util::enum_variants(&item)?;
+    let names = variants.iter().map(|(_, ty)| util::type_name(ty)).collect::<syn::Result<Vec<_>>>()?;
+    let (idents, tys): (Vec<_>, Vec<_>) = variants.into_iter().unzip();
+
+    let name = &item.ident;
+    let vis = &item.vis;
+    let enum_attrs = &item.attrs;
+    let printer = format_ident!("{}Printer", name);
+
+    let forward = |call: TokenStream| {
+        quote! {
+            match self {
+                #(Self::#idents(inner) => inner.#call,)*
+            }
+        }
+    };
+    let id = forward(quote!(id()));
+    let set_id = forward(quote!(set_id(id)));
+    let get_defs = forward(quote!(get_defs()));
+    let set_defs = forward(quote!(set_defs(defs)));
+    let get_uses = forward(quote!(get_uses()));
+    let set_uses = forward(quote!(set_uses(uses)));
+    let get_attrs = forward(quote!(get_attrs()));
+    let set_attrs = forward(quote!(set_attrs(attrs)));
+    let get_constraints = forward(quote!(get_constraints()));
+    let uses = forward(quote!(uses(entity)));
+    let defs = forward(quote!(defs(entity)));
+    let get_parent = forward(quote!(get_parent()));
+    let set_parent = forward(quote!(set_parent(parent)));
+    let get_regions = forward(quote!(get_regions()));
+    let set_regions = forward(quote!(set_regions(regions)));
+    let use_region = forward(quote!(use_region(region)));
+    let get_op_name = forward(quote!(get_op_name()));
+    let get_symbol = forward(quote!(get_symbol()));
+    let set_symbol = forward(quote!(set_symbol(symbol)));
+    let get_loc = forward(quote!(get_loc()));
+    let set_loc = forward(quote!(set_loc(loc)));
+    let get_traits = forward(quote!(get_traits()));
+    let get_dialect = forward(quote!(get_dialect()));
+    let hash_with_reducer = forward(quote!(hash_with_reducer(env, reducer)));
+
+    Ok(quote! {
+        #(#enum_attrs)*
+        #[derive(PartialEq, Debug, Clone)]
+        #vis enum #name {
+            #(#idents(#tys)),*
+        }
+
+        #(
+            impl From<#tys> for #name {
+                fn from(op: #tys) -> Self {
+                    Self::#idents(op)
+                }
+            }
+        )*
+
+        impl irony::Id for #name {
+            fn id(&self) -> usize {
+                #id
+            }
+
+            fn set_id(&mut self, id: usize) {
+                #set_id
+            }
+        }
+
+        impl irony::Op for #name {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+            type ConstraintT = #constraint_ty;
+            type PrinterT = #printer;
+
+            fn get_defs(&self) -> Vec<(String, Vec<Option<irony::EntityId>>)> {
+                #get_defs
+            }
+
+            fn set_defs(&mut self, defs: Vec<(String, Vec<Option<irony::EntityId>>)>) {
+                #set_defs
+            }
+
+            fn get_uses(&self) -> Vec<(String, Vec<Option<irony::EntityId>>)> {
+                #get_uses
+            }
+
+            fn set_uses(&mut self, uses: Vec<(String, Vec<Option<irony::EntityId>>)>) {
+                #set_uses
+            }
+
+            fn get_attrs(&self) -> Vec<(String, Self::AttributeT)> {
+                #get_attrs
+            }
+
+            fn set_attrs(&mut self, attrs: Vec<(String, Self::AttributeT)>) {
+                #set_attrs
+            }
+
+            fn get_constraints(&self) -> Vec<Self::ConstraintT> {
+                #get_constraints
+            }
+
+            fn uses(&self, entity: irony::EntityId) -> bool {
+                #uses
+            }
+
+            fn defs(&self, entity: irony::EntityId) -> bool {
+                #defs
+            }
+
+            fn get_parent(&self) -> Option<irony::RegionId> {
+                #get_parent
+            }
+
+            fn set_parent(&mut self, parent: Option<irony::RegionId>) {
+                #set_parent
+            }
+
+            fn get_regions(&self) -> Vec<(String, Vec<irony::RegionId>)> {
+                #get_regions
+            }
+
+            fn set_regions(&mut self, regions: Vec<(String, Vec<irony::RegionId>)>) {
+                #set_regions
+            }
+
+            fn use_region(&self, region: irony::RegionId) -> bool {
+                #use_region
+            }
+
+            fn get_op_name(&self) -> String {
+                #get_op_name
+            }
+
+            fn get_symbol(&self) -> Option<irony::Symbol> {
+                #get_symbol
+            }
+
+            fn set_symbol(&mut self, symbol: irony::Symbol) {
+                #set_symbol
+            }
+
+            fn get_loc(&self) -> Option<irony::Loc> {
+                #get_loc
+            }
+
+            fn set_loc(&mut self, loc: Option<irony::Loc>) {
+                #set_loc
+            }
+
+            fn get_traits(&self) -> Vec<irony::OpTrait> {
+                #get_traits
+            }
+
+            fn get_dialect(&self) -> Option<&'static str> {
+                #get_dialect
+            }
+
+            fn get_registered_ops() -> Vec<irony::OpInfo> {
+                let mut ops = vec![];
+                #(ops.extend(<#tys as irony::Op>::get_registered_ops());)*
+                ops
+            }
+
+            fn from_parts(
+                name: &str,
+                defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                attrs: Vec<(String, Self::AttributeT)>,
+                regions: Vec<(String, Vec<irony::RegionId>)>,
+            ) -> Option<Self> {
+                static OPS: std::sync::OnceLock<Vec<irony::OpInfo>> = std::sync::OnceLock::new();
+                let ops = OPS.get_or_init(<Self as irony::Op>::get_registered_ops);
+                match ops.iter().find(|info| info.name == name)?.type_name {
+                    #(#names => <#tys as irony::Op>::from_parts(name, defs, uses, attrs, regions).map(Self::#idents),)*
+                    _ => None,
+                }
+            }
+
+            fn get_printer(&self) -> Self::PrinterT {
+                match self {
+                    #(Self::#idents(inner) => #printer::#idents(inner.get_printer()),)*
+                }
+            }
+
+            fn hash_with_reducer(&self, env: &impl irony::Environ, reducer: &mut impl irony::ReducerTrait) {
+                #hash_with_reducer
+            }
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        #vis enum #printer {
+            #(#idents(<#tys as irony::Op>::PrinterT)),*
+        }
+
+        impl irony::OpPrinterTrait for #printer {
+            type DataTypeT = #data_ty;
+            type AttributeT = #attr_ty;
+
+            fn print<'env, E, EntityT: irony::Entity>(
+                &self,
+                env: &'env E,
+                attrs: Vec<(String, Self::AttributeT)>,
+                uses: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                defs: Vec<(String, Vec<Option<irony::EntityId>>)>,
+                regions: Vec<(String, Vec<irony::RegionId>)>,
+            ) -> String
+            where
+                E: irony::Environ<EntityT = EntityT, AttributeT = Self::AttributeT>,
+                EntityT: irony::Entity<DataTypeT = Self::DataTypeT, AttributeT = Self::AttributeT>,
+            {
+                match self {
+                    #(Self::#idents(inner) => inner.print(env, attrs, uses, defs, regions),)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,64 @@
+use syn::{Fields, GenericArgument, Generics, Ident, ItemEnum, PathArguments, Type};
+
+/// the last segment of a plain type path, with its generic arguments
+fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+        _ => None,
+    }
+}
+
+/// whether `ty` is the type named `name`, e.g. `EntityId` or `irony::EntityId`
+pub(crate) fn is_named(ty: &Type, name: &str) -> bool {
+    last_segment(ty).is_some_and(|seg| seg.ident == name && seg.arguments.is_none())
+}
+
+/// `T` if `ty` is `wrapper<T>`, e.g. `Option<T>` or `Vec<T>`
+pub(crate) fn unwrap_generic<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let seg = last_segment(ty)?;
+    if seg.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(inner)) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+pub(crate) fn reject_generics(generics: &Generics, what: &str) -> syn::Result<()> {
+    if generics.params.is_empty() && generics.where_clause.is_none() {
+        Ok(())
+    } else {
+        Err(syn::Error::new_spanned(generics, format!("{} cannot be generic", what)))
+    }
+}
+
+/// The variants of an enum collecting the types of a dialect, each written `Name(Type)`
+pub(crate) fn enum_variants(item: &ItemEnum) -> syn::Result<Vec<(Ident, Type)>> {
+    reject_generics(&item.generics, "the enum")?;
+    if item.variants.is_empty() {
+        return Err(syn::Error::new_spanned(&item.ident, "the enum needs at least one variant"));
+    }
+    item.variants
+        .iter()
+        .map(|variant| match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Ok((variant.ident.to_owned(), fields.unnamed[0].ty.to_owned()))
+            },
+            _ => Err(syn::Error::new_spanned(
+                variant,
+                format!("expected a variant of the form `{0}({0})`", variant.ident),
+            )),
+        })
+        .collect()
+}
+
+/// the op or entity name a variant type stands for, i.e. its last path segment
+pub(crate) fn type_name(ty: &Type) -> syn::Result<String> {
+    last_segment(ty)
+        .map(|seg| seg.ident.to_string())
+        .ok_or_else(|| syn::Error::new_spanned(ty, "expected the name of a type"))
+}
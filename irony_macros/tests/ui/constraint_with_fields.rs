@@ -0,0 +1,6 @@
+#[irony_macros::constraint(data_type = DataTypeEnum, attr = AttributeEnum, verify = |_, _, _, _, _| true)]
+pub struct SameType {
+    strict: bool,
+}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[irony_macros::entity(data_type = DataTypeEnum, attr = AttributeEnum)]
+pub struct Wire(StringAttr);
+
+fn main() {}
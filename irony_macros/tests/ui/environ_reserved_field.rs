@@ -0,0 +1,7 @@
+#[irony_macros::environ(data_type = DataTypeEnum, attr = AttributeEnum, entity = EntityEnum,
+    op = OpEnum, constraint = ConstraintEnum, pm = PassManager)]
+pub struct CmtEnv {
+    op_table: usize,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+#[irony_macros::op(data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum,
+    symbol = lhs, print = |_, _, _, _, _| String::new())]
+pub struct HwModule {
+    #[def]
+    lhs: EntityId,
+    #[attr]
+    name: StringAttr,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[irony_macros::op(data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum)]
+pub struct CombAdd {
+    #[def]
+    lhs: EntityId,
+}
+
+fn main() {}
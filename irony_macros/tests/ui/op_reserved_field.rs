@@ -0,0 +1,8 @@
+#[irony_macros::op(data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum,
+    print = |_, _, _, _, _| String::new())]
+pub struct CombAdd {
+    #[attr]
+    loc: StringAttr,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+#[irony_macros::op(data_type = DataTypeEnum, attr = AttributeEnum, constraint = ConstraintEnum,
+    traits = [Pure, Idempotent], print = |_, _, _, _, _| String::new())]
+pub struct CombAdd {
+    #[def]
+    lhs: EntityId,
+}
+
+fn main() {}